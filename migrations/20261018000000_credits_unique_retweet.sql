@@ -0,0 +1,22 @@
+-- One credit per retweet. `RetweetCreditService::award_credit` inserts with
+-- `ON CONFLICT (user_id, twitter_post_id) DO NOTHING`, which needs this constraint.
+--
+-- Duplicates awarded before it existed are removed first, keeping one row per
+-- retweet: a used one if any, so a spent credit stays spent, else the oldest.
+-- Apply in a single transaction, e.g. `psql --single-transaction -f <file>`.
+
+LOCK TABLE credits IN SHARE ROW EXCLUSIVE MODE;
+
+DELETE FROM credits
+WHERE id IN (
+    SELECT id
+    FROM (
+        SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id, twitter_post_id ORDER BY is_used DESC, id) AS rank
+        FROM credits
+        WHERE twitter_post_id IS NOT NULL
+    ) ranked
+    WHERE rank > 1
+);
+
+ALTER TABLE credits
+    ADD CONSTRAINT credits_user_id_twitter_post_id_key UNIQUE (user_id, twitter_post_id);
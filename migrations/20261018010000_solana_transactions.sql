@@ -0,0 +1,21 @@
+-- Outgoing Solana transactions tracked by `TransactionManager`. A row is written
+-- before the first broadcast and updated on every resubmission, so a restart can
+-- pick up whatever was left in flight.
+
+CREATE TABLE solana_transactions (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    purpose TEXT NOT NULL,
+    reference TEXT,
+    signature TEXT NOT NULL UNIQUE,
+    message TEXT NOT NULL,
+    last_valid_block_height BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'sent',
+    attempts INT NOT NULL,
+    slot BIGINT,
+    error TEXT
+);
+
+-- Sells and payouts look their transactions up by what they belong to.
+CREATE INDEX solana_transactions_purpose_reference_idx ON solana_transactions (purpose, reference);
@@ -0,0 +1,20 @@
+-- Tokens the agent bought, one row per mint. Databases from before the portfolio
+-- service have the table without some of these columns; their rows get an empty
+-- name and pool, and the next buy of the token sets its pool.
+
+CREATE TABLE IF NOT EXISTS tokens (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    address TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    name TEXT NOT NULL,
+    pool_address TEXT NOT NULL
+);
+
+ALTER TABLE tokens
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS name TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS pool_address TEXT NOT NULL DEFAULT '';
+
+-- `PositionManager` upserts tokens with `ON CONFLICT (address)`.
+CREATE UNIQUE INDEX IF NOT EXISTS tokens_address_key ON tokens (address);
@@ -0,0 +1,42 @@
+-- Positions managed by `PositionManager`. A row is reserved before its buy is sent,
+-- and its id is the `trade_position_id` of the position's trades.
+
+CREATE TABLE positions (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    chat_uuid TEXT NOT NULL,
+    pool_address TEXT NOT NULL,
+    token_id INT REFERENCES tokens (id),
+    status TEXT NOT NULL DEFAULT 'pending',
+    remaining_quantity DOUBLE PRECISION,
+    closing_started_at TIMESTAMP,
+    closing_reason TEXT
+);
+
+CREATE INDEX positions_status_idx ON positions (status);
+
+-- Positions bought before this table existed are taken over from their `open` trade.
+-- Sells used to sell everything, so a position with a `closed` trade is closed.
+INSERT INTO positions (id, created_at, updated_at, chat_uuid, pool_address, token_id, status, remaining_quantity)
+SELECT DISTINCT ON (o.trade_position_id)
+       o.trade_position_id, o.created_at, o.created_at, o.chat_uuid, t.pool_address, o.token_id,
+       CASE WHEN closed.trade_position_id IS NULL THEN 'open' ELSE 'closed' END,
+       CASE WHEN closed.trade_position_id IS NULL THEN o.quote_token_quantity ELSE 0 END
+FROM trades o
+JOIN tokens t ON t.id = o.token_id
+LEFT JOIN (SELECT DISTINCT trade_position_id FROM trades WHERE trade_type = 'closed') closed
+    ON closed.trade_position_id = o.trade_position_id
+WHERE o.trade_type = 'open'
+ORDER BY o.trade_position_id, o.id;
+
+-- New positions must not reuse a `trade_position_id` already in `trades`.
+SELECT setval(
+    pg_get_serial_sequence('positions', 'id'),
+    GREATEST((SELECT MAX(trade_position_id) FROM trades), (SELECT MAX(id) FROM positions), 0) + 1,
+    false
+);
+
+-- Positions read their trades by `trade_position_id`; a stale sell is matched to its trade by signature.
+CREATE INDEX IF NOT EXISTS trades_trade_position_id_idx ON trades (trade_position_id);
+CREATE INDEX IF NOT EXISTS trades_tx_id_idx ON trades (tx_id);
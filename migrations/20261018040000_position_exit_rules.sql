@@ -0,0 +1,21 @@
+-- Exit rules of single positions, overriding the `EXIT_*` settings, and the trailing
+-- stop peak the exit engine keeps per position.
+
+CREATE TABLE position_exit_rules (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    trade_position_id INT NOT NULL UNIQUE REFERENCES positions (id),
+    take_profit_pct DOUBLE PRECISION,
+    stop_loss_pct DOUBLE PRECISION,
+    trailing_stop_pct DOUBLE PRECISION,
+    max_hold_minutes BIGINT,
+    take_profit_disabled BOOLEAN NOT NULL DEFAULT false,
+    stop_loss_disabled BOOLEAN NOT NULL DEFAULT false,
+    trailing_stop_disabled BOOLEAN NOT NULL DEFAULT false,
+    max_hold_disabled BOOLEAN NOT NULL DEFAULT false,
+    peak_value_sol DOUBLE PRECISION
+);
+
+-- Rule that triggered a sell, on `closed` trades; older sells have none.
+ALTER TABLE trades ADD COLUMN exit_reason TEXT;
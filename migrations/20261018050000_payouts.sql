@@ -0,0 +1,26 @@
+-- Profit shares paid to shillers by `PayoutService`. The row is written before any
+-- transfer, and the unique `trade_position_id` keeps a position from being paid twice.
+
+CREATE TABLE payouts (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    trade_position_id INT NOT NULL UNIQUE REFERENCES positions (id),
+    trade_id INT NOT NULL REFERENCES trades (id),
+    chat_uuid TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    pnl_sol DOUBLE PRECISION NOT NULL,
+    share_pct DOUBLE PRECISION NOT NULL,
+    amount_lamports BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    solana_transaction_id INT REFERENCES solana_transactions (id),
+    signature TEXT,
+    error TEXT
+);
+
+CREATE INDEX payouts_wallet_idx ON payouts (wallet, created_at DESC);
+CREATE INDEX payouts_status_idx ON payouts (status);
+
+-- Trades are no longer tied to a payment.
+ALTER TABLE trades ALTER COLUMN payment_id DROP NOT NULL;
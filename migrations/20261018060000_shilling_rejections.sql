@@ -0,0 +1,13 @@
+-- Shilled tokens the agent turned down, listed by the shilling statistics.
+
+CREATE TABLE shilling_rejections (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    chat_uuid TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    pool_address TEXT,
+    source TEXT NOT NULL,
+    reason TEXT NOT NULL
+);
+
+CREATE INDEX shilling_rejections_created_at_idx ON shilling_rejections (created_at DESC, id DESC);
@@ -0,0 +1,6 @@
+-- A balance snapshot is still recorded when SOL cannot be priced, without its USD value.
+
+ALTER TABLE agent_balance_changes ALTER COLUMN amount DROP NOT NULL;
+
+-- Balance buckets are read by time range.
+CREATE INDEX IF NOT EXISTS agent_balance_changes_created_at_idx ON agent_balance_changes (created_at, id);
@@ -0,0 +1,26 @@
+-- Recurring jobs shared by every scheduler instance, and the history of their runs.
+
+CREATE TABLE scheduled_jobs (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    name TEXT NOT NULL UNIQUE,
+    schedule TEXT NOT NULL,
+    paused BOOLEAN NOT NULL DEFAULT false,
+    next_run_at TIMESTAMP
+);
+
+CREATE TABLE job_runs (
+    id SERIAL PRIMARY KEY,
+    job_name TEXT NOT NULL,
+    trigger TEXT NOT NULL,
+    status TEXT NOT NULL,
+    instance_id TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP,
+    duration_ms BIGINT,
+    output TEXT,
+    error TEXT
+);
+
+CREATE INDEX job_runs_job_name_idx ON job_runs (job_name, started_at DESC);
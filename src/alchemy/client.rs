@@ -1,7 +1,7 @@
 use reqwest::Client;
 use serde_json::json;
 use chrono::{DateTime, Utc};
-use crate::alchemy::models::{HistoricalPriceBySymbol, HistoricalPriceByAddress, Transfer, Balance};
+use crate::alchemy::models::{HistoricalPriceBySymbol, HistoricalPriceByAddress, Transfer, Balance, TokenPricesResponse};
 
 /// Alchemy API client for fetching blockchain data.
 pub struct AlchemyClient {
@@ -55,6 +55,28 @@ impl AlchemyClient {
         Ok(data)
     }
 
+    /// Fetch current prices by symbol.
+    pub async fn get_token_prices_by_symbol(&self, symbols: &[&str]) -> Result<TokenPricesResponse, reqwest::Error> {
+        let url = format!("{}/prices/v1/{}/tokens/by-symbol", self.base_url, self.api_key);
+        let query: Vec<(&str, &str)> = symbols.iter().map(|symbol| ("symbols", *symbol)).collect();
+
+        let response = self.client.get(&url).query(&query).send().await?.error_for_status()?;
+        let data = response.json::<TokenPricesResponse>().await?;
+        Ok(data)
+    }
+
+    /// Fetch current prices by token address.
+    pub async fn get_token_prices_by_address(&self, addresses: &[&str], network: &str) -> Result<TokenPricesResponse, reqwest::Error> {
+        let url = format!("{}/prices/v1/{}/tokens/by-address", self.base_url, self.api_key);
+        let body = json!({
+            "addresses": addresses.iter().map(|address| json!({ "network": network, "address": address })).collect::<Vec<_>>(),
+        });
+
+        let response = self.client.post(&url).json(&body).send().await?.error_for_status()?;
+        let data = response.json::<TokenPricesResponse>().await?;
+        Ok(data)
+    }
+
     /// Fetch token transfers for a given wallet.
     pub async fn get_transfers(&self, wallet: &str, chain: &str, incoming: bool) -> Result<Vec<Transfer>, reqwest::Error> {
         let address_key = if incoming { "toAddress" } else { "fromAddress" };
@@ -4,5 +4,5 @@ pub mod models;
 
 
 pub use client::AlchemyClient;
-pub use models::{HistoricalPriceBySymbol, HistoricalPriceByAddress, HistoricalPrice, Transfer, Balance};
+pub use models::{HistoricalPriceBySymbol, HistoricalPriceByAddress, HistoricalPrice, Transfer, Balance, TokenPrice, TokenPrices};
 pub use langchain::describe_alchemy_api;
@@ -60,3 +60,29 @@ pub struct Balance {
     pub value: i64,
     pub error: Option<String>,
 }
+
+/// A current price quote of a token.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPrice {
+    pub currency: String,
+    pub value: String,
+    pub last_updated_at: DateTime<Utc>,
+}
+
+/// Current prices of one token, looked up by symbol or by address.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenPrices {
+    pub symbol: Option<String>,
+    pub address: Option<String>,
+    pub network: Option<String>,
+    #[serde(default)]
+    pub prices: Vec<TokenPrice>,
+    pub error: Option<String>,
+}
+
+/// Response of the current price endpoints.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenPricesResponse {
+    pub data: Vec<TokenPrices>,
+}
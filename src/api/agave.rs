@@ -6,7 +6,7 @@ async fn get_agave_balance(path: web::Path<String>) -> impl Responder {
     let address = path.into_inner();
     let agave_client = AgaveClient::new();
 
-    match agave_client.get_balance(&address) {
+    match agave_client.get_balance(&address).await {
         Ok(balance) => HttpResponse::Ok().json(serde_json::json!({ "balance": balance })),
         Err(e) => HttpResponse::InternalServerError().json(e),
     }
@@ -4,9 +4,20 @@ use sqlx::PgPool;
 use uuid::Uuid;
 use chrono::Utc;
 use crate::core::db::get_db_pool;
-use crate::utils::redis::RedisClient;
+use crate::core::config::RedisSettings;
+use crate::core::errors::ChatErrors;
+use crate::api::auth::extract_user_from_access_token;
+use crate::api::general::{refund_user_credit, reserve_user_credit};
+use crate::llm::agent::{AgentEvent, AgentOutcome};
+use crate::llm::llm_service::answer_users_msg;
+use crate::llm::provider::LlmProvider;
+use crate::models::base::ConversationStatus;
+use crate::utils::redis::{RedisChatHistory, RedisChatMessage, RedisClient};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::error;
 use anyhow::Result;
 
 
@@ -26,6 +37,55 @@ pub struct ChatCreate {
 }
 
 
+#[derive(Serialize, Deserialize)]
+pub struct ChatMessageRequest {
+    pub message: String,
+}
+
+
+#[derive(Serialize)]
+pub struct ChatMessageResponse {
+    pub reply: String,
+    pub status: ConversationStatus,
+    pub token_entity: Option<serde_json::Value>,
+}
+
+
+/// Fetch a chat and make sure it belongs to the given wallet
+pub async fn get_owned_chat(pool: &PgPool, chat_uuid: &str, wallet: &str) -> Result<Chat> {
+    let row = sqlx::query!(
+        "SELECT c.id, c.uuid, c.user_id, c.created_at, c.state, u.wallet
+         FROM chats c JOIN users u ON u.id = c.user_id
+         WHERE c.uuid = $1 AND c.state != 'deleted'",
+        chat_uuid
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ChatErrors::ChatNotFound)?;
+
+    if row.wallet != wallet {
+        return Err(ChatErrors::UserNotOwner.into());
+    }
+
+    Ok(Chat {
+        id: row.id,
+        uuid: row.uuid,
+        user_id: row.user_id,
+        created_at: row.created_at,
+        state: row.state,
+    })
+}
+
+
+fn chat_error_response(e: &anyhow::Error) -> HttpResponse {
+    match e.downcast_ref::<ChatErrors>() {
+        Some(ChatErrors::ChatNotFound) => HttpResponse::NotFound().body(e.to_string()),
+        Some(ChatErrors::UserNotOwner) => HttpResponse::Forbidden().body(e.to_string()),
+        _ => HttpResponse::InternalServerError().body("Error retrieving chat"),
+    }
+}
+
+
 #[get("/chats")]
 pub async fn get_all_chats(
     pool: web::Data<PgPool>,
@@ -155,11 +215,378 @@ pub async fn update_chat(
 }
 
 
+/// Authorized chat turn, ready to be answered by the agent
+struct PreparedChatTurn {
+    wallet: String,
+    chat: Chat,
+    history: RedisChatHistory,
+    history_messages: Vec<RedisChatMessage>,
+    /// Credit reserved for a shilling decision; without one the agent only discusses.
+    credit_id: Option<i32>,
+}
+
+
+impl PreparedChatTurn {
+    fn is_shilling_allowed(&self) -> bool {
+        self.credit_id.is_some()
+    }
+}
+
+
+async fn prepare_chat_turn(
+    req: HttpRequest,
+    chat_uuid: &str,
+    pool: &web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+) -> Result<PreparedChatTurn, HttpResponse> {
+    let user = extract_user_from_access_token(req, redis_client, pool.clone())
+        .await
+        .map_err(|_| HttpResponse::Unauthorized().body("Not authorized"))?;
+
+    let chat = get_owned_chat(pool.get_ref(), chat_uuid, &user.wallet)
+        .await
+        .map_err(|e| chat_error_response(&e))?;
+
+    let history = RedisChatHistory::new_redis_chat(chat.uuid.clone(), &RedisSettings::new_redis().redis_url)
+        .await
+        .map_err(|_| HttpResponse::InternalServerError().body("Failed to connect to chat history"))?;
+    let history_messages = history
+        .base_messages()
+        .await
+        .map_err(|_| HttpResponse::InternalServerError().body("Failed to load chat history"))?;
+
+    // Reserved before the agent runs, so two concurrent messages cannot buy on one credit.
+    let credit_id = reserve_user_credit(pool.get_ref(), chat.user_id)
+        .await
+        .map_err(|_| HttpResponse::InternalServerError().body("Failed to check credits"))?;
+
+    Ok(PreparedChatTurn { wallet: user.wallet, chat, history, history_messages, credit_id })
+}
+
+
+/// A credit pays for one shilling decision, whichever way it went; otherwise it is given back.
+async fn settle_credit(pool: &PgPool, turn: &PreparedChatTurn, status: Option<&ConversationStatus>) {
+    let Some(credit_id) = turn.credit_id else {
+        return;
+    };
+    if matches!(status, Some(ConversationStatus::Approve | ConversationStatus::Reject)) {
+        return;
+    }
+    if let Err(e) = refund_user_credit(pool, credit_id).await {
+        error!("Failed to refund credit {} of user {}: {:?}", credit_id, turn.chat.user_id, e);
+    }
+}
+
+
+/// Settle the credit and append both turns to the Redis history
+async fn finish_chat_turn(pool: &PgPool, turn: &PreparedChatTurn, message: &str, outcome: &AgentOutcome) {
+    settle_credit(pool, turn, Some(&outcome.status)).await;
+
+    if turn.history.add_message("user", message).await.is_err()
+        || turn.history.add_message("assistant", &outcome.content).await.is_err()
+    {
+        error!("Failed to persist history of chat {}", turn.chat.uuid);
+    }
+}
+
+
+/// Run the agent on a prepared turn and settle it; the credit is refunded when the agent fails
+async fn answer_chat_turn(
+    provider: &dyn LlmProvider,
+    pool: &PgPool,
+    turn: &PreparedChatTurn,
+    message: &str,
+    events: Option<&mpsc::UnboundedSender<AgentEvent>>,
+) -> Result<AgentOutcome> {
+    let result = answer_users_msg(
+        provider,
+        pool,
+        &turn.chat.uuid,
+        message,
+        &turn.wallet,
+        &turn.history_messages,
+        turn.is_shilling_allowed(),
+        events,
+    )
+    .await;
+
+    match &result {
+        Ok(outcome) => finish_chat_turn(pool, turn, message, outcome).await,
+        Err(e) => {
+            error!("Failed to answer message in chat {}: {:?}", turn.chat.uuid, e);
+            settle_credit(pool, turn, None).await;
+        }
+    }
+    result
+}
+
+
+/// Streamed variant of `answer_chat_turn`, closing the stream with a `final` or `error` event
+async fn stream_chat_turn(
+    provider: &dyn LlmProvider,
+    pool: &PgPool,
+    turn: &PreparedChatTurn,
+    message: &str,
+    tx: &mpsc::UnboundedSender<AgentEvent>,
+) {
+    let event = match answer_chat_turn(provider, pool, turn, message, Some(tx)).await {
+        Ok(outcome) => AgentEvent::Final { content: outcome.content, status: outcome.status, token_entity: outcome.aux_data },
+        Err(_) => AgentEvent::Error { message: "Failed to process message".to_string() },
+    };
+    let _ = tx.send(event);
+}
+
+
+/// One server-sent event frame
+fn sse_frame(event: &AgentEvent) -> web::Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    web::Bytes::from(format!("event: {}\ndata: {}\n\n", event.name(), data))
+}
+
+
+#[post("/chats/{chat_uuid}/messages")]
+pub async fn send_chat_message(
+    req: HttpRequest,
+    chat_uuid: web::Path<String>,
+    pool: web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+    provider: web::Data<Arc<dyn LlmProvider>>,
+    body: web::Json<ChatMessageRequest>,
+) -> impl Responder {
+    let turn = match prepare_chat_turn(req, &chat_uuid, &pool, redis_client).await {
+        Ok(turn) => turn,
+        Err(response) => return response,
+    };
+
+    let outcome = match answer_chat_turn(provider.get_ref().as_ref(), pool.get_ref(), &turn, &body.message, None).await {
+        Ok(outcome) => outcome,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to process message"),
+    };
+
+    HttpResponse::Ok().json(ChatMessageResponse {
+        reply: outcome.content,
+        status: outcome.status,
+        token_entity: outcome.aux_data,
+    })
+}
+
+
+/// Same as `send_chat_message`, but streams the reply as server-sent events:
+/// `delta` for content chunks, `tool_call_started`/`tool_call_finished` for tool progress,
+/// and a closing `final` (or `error`) event carrying the full reply and `ConversationStatus`.
+#[post("/chats/{chat_uuid}/messages/stream")]
+pub async fn stream_chat_message(
+    req: HttpRequest,
+    chat_uuid: web::Path<String>,
+    pool: web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+    provider: web::Data<Arc<dyn LlmProvider>>,
+    body: web::Json<ChatMessageRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let turn = match prepare_chat_turn(req, &chat_uuid, &pool, redis_client).await {
+        Ok(turn) => turn,
+        Err(response) => return response,
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel::<AgentEvent>();
+    let pool = pool.get_ref().clone();
+    let provider = provider.get_ref().clone();
+
+    actix_web::rt::spawn(async move {
+        stream_chat_turn(provider.as_ref(), &pool, &turn, &body.message, &tx).await;
+    });
+
+    let stream = UnboundedReceiverStream::new(rx).map(|event| Ok::<_, actix_web::Error>(sse_frame(&event)));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all_chats)
         .service(get_user_chats)
         .service(create_chat)
         .service(get_chat_by_uuid)
         .service(delete_chat)
-        .service(update_chat);
+        .service(update_chat)
+        .service(send_chat_message)
+        .service(stream_chat_message);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_fixtures::create_user_tables;
+    use crate::llm::provider::MockProvider;
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    /// Nothing listens here, so history writes fail and are only logged.
+    const UNREACHABLE_REDIS: &str = "redis://127.0.0.1:1";
+
+    async fn setup(pool: &PgPool) {
+        create_user_tables(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO users (wallet) VALUES ('wallet-a'), ('wallet-b');
+            INSERT INTO chats (uuid, user_id) VALUES ('chat-a', 1);
+            INSERT INTO chats (uuid, user_id, state) VALUES ('chat-deleted', 1, 'deleted');
+            INSERT INTO credits (user_id, twitter_post_id) VALUES (1, 'post-1');
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn prepared_turn(pool: &PgPool, redis_url: &str, session_id: &str) -> PreparedChatTurn {
+        let chat = get_owned_chat(pool, "chat-a", "wallet-a").await.unwrap();
+        let credit_id = reserve_user_credit(pool, chat.user_id).await.unwrap();
+        let history = RedisChatHistory::new_redis_chat(session_id.to_string(), redis_url).await.unwrap();
+        PreparedChatTurn { wallet: "wallet-a".to_string(), chat, history, history_messages: vec![], credit_id }
+    }
+
+    async fn is_used(pool: &PgPool, credit_id: i32) -> bool {
+        sqlx::query_scalar::<_, bool>("SELECT is_used FROM credits WHERE id = $1")
+            .bind(credit_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn only_the_owner_gets_the_chat(pool: PgPool) {
+        setup(&pool).await;
+
+        assert_eq!(get_owned_chat(&pool, "chat-a", "wallet-a").await.unwrap().user_id, 1);
+
+        let not_owner = get_owned_chat(&pool, "chat-a", "wallet-b").await.unwrap_err();
+        assert!(matches!(not_owner.downcast_ref::<ChatErrors>(), Some(ChatErrors::UserNotOwner)));
+        assert_eq!(chat_error_response(&not_owner).status(), StatusCode::FORBIDDEN);
+
+        for chat_uuid in ["chat-deleted", "chat-missing"] {
+            let missing = get_owned_chat(&pool, chat_uuid, "wallet-a").await.unwrap_err();
+            assert_eq!(chat_error_response(&missing).status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reserves_the_only_credit_once(pool: PgPool) {
+        setup(&pool).await;
+
+        let credit_id = reserve_user_credit(&pool, 1).await.unwrap();
+        assert!(credit_id.is_some());
+        assert!(is_used(&pool, credit_id.unwrap()).await);
+        assert_eq!(reserve_user_credit(&pool, 1).await.unwrap(), None);
+        assert_eq!(reserve_user_credit(&pool, 2).await.unwrap(), None);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn refunds_the_credit_when_the_agent_only_discusses(pool: PgPool) {
+        setup(&pool).await;
+        let turn = prepared_turn(&pool, UNREACHABLE_REDIS, "chat-a").await;
+        let credit_id = turn.credit_id.unwrap();
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::text_reply("Which token do you mean?")]);
+
+        let outcome = answer_chat_turn(&provider, &pool, &turn, "gm", None).await.unwrap();
+
+        assert_eq!(outcome.status, ConversationStatus::Discuss);
+        assert!(!is_used(&pool, credit_id).await);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn refunds_the_credit_when_the_agent_fails(pool: PgPool) {
+        setup(&pool).await;
+        let turn = prepared_turn(&pool, UNREACHABLE_REDIS, "chat-a").await;
+        let credit_id = turn.credit_id.unwrap();
+        let provider = MockProvider::new_mock_provider(vec![]);
+
+        assert!(answer_chat_turn(&provider, &pool, &turn, "gm", None).await.is_err());
+        assert!(!is_used(&pool, credit_id).await);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn keeps_the_credit_spent_on_a_decision(pool: PgPool) {
+        setup(&pool).await;
+        let turn = prepared_turn(&pool, UNREACHABLE_REDIS, "chat-a").await;
+        let credit_id = turn.credit_id.unwrap();
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::tool_call_reply(
+            "call_1",
+            "rejectShilling",
+            json!({"explanation": "Too little liquidity."}),
+        )]);
+
+        let outcome = answer_chat_turn(&provider, &pool, &turn, "buy this", None).await.unwrap();
+
+        assert_eq!(outcome.status, ConversationStatus::Reject);
+        assert_eq!(outcome.content, "Too little liquidity.");
+        assert!(is_used(&pool, credit_id).await);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn streams_deltas_and_closes_with_the_final_reply(pool: PgPool) {
+        setup(&pool).await;
+        let turn = prepared_turn(&pool, UNREACHABLE_REDIS, "chat-a").await;
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::text_reply("gm!")]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        stream_chat_turn(&provider, &pool, &turn, "gm", &tx).await;
+        drop(tx);
+
+        let mut frames = vec![];
+        while let Some(event) = rx.recv().await {
+            frames.push(String::from_utf8(sse_frame(&event).to_vec()).unwrap());
+        }
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], "event: delta\ndata: {\"type\":\"delta\",\"content\":\"gm!\"}\n\n");
+        assert!(frames[1].starts_with("event: final\ndata: "));
+        assert!(frames[1].contains("\"content\":\"gm!\""));
+        assert!(frames[1].contains("\"status\":\"Discuss\""));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn streams_an_error_event_when_the_agent_fails(pool: PgPool) {
+        setup(&pool).await;
+        let turn = prepared_turn(&pool, UNREACHABLE_REDIS, "chat-a").await;
+        let provider = MockProvider::new_mock_provider(vec![]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        stream_chat_turn(&provider, &pool, &turn, "gm", &tx).await;
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.name(), "error");
+        assert!(!is_used(&pool, turn.credit_id.unwrap()).await);
+    }
+
+    #[sqlx::test(migrations = false)]
+    #[ignore = "requires a Redis server at REDIS_URL"]
+    async fn appends_both_turns_to_the_redis_history(pool: PgPool) {
+        setup(&pool).await;
+        let redis_url = RedisSettings::new_redis().redis_url;
+        let session_id = format!("chat-test-{}", Uuid::new_v4());
+        let turn = prepared_turn(&pool, &redis_url, &session_id).await;
+        turn.history.add_message("user", "earlier").await.unwrap();
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::text_reply("gm!")]);
+
+        answer_chat_turn(&provider, &pool, &turn, "gm", None).await.unwrap();
+
+        let messages = turn.history.base_messages().await.unwrap();
+        let messages: Vec<(String, String)> = messages.into_iter().map(|m| (m.role, m.content)).collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("user".to_string(), "earlier".to_string()),
+                ("user".to_string(), "gm".to_string()),
+                ("assistant".to_string(), "gm!".to_string()),
+            ]
+        );
+
+        let mut conn = redis::Client::open(redis_url).unwrap().get_multiplexed_async_connection().await.unwrap();
+        redis::cmd("DEL").arg(&session_id).query_async::<()>(&mut conn).await.unwrap();
+    }
 }
@@ -1,4 +1,4 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
 use serde::{Serialize, Deserialize};
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -7,6 +7,15 @@ use crate::core::db::get_db_pool;
 use crate::utils::redis::RedisClient;
 use anyhow::Result;
 use chrono::Utc;
+use tracing::error;
+use crate::balance_history::BalanceRecorder;
+use crate::credits::RetweetCreditService;
+use crate::llm::provider::LlmProvider;
+use crate::core::config::SchedulerSettings;
+use crate::positions::{sell_all_positions, PositionManager};
+use crate::api::scheduler::is_admin;
+use crate::scheduler::{CheckRetwittsJob, JobLease, LogAgentBalanceJob, Scheduler, SellTokensJob};
+use crate::utils::error::{CreditError, SchedulerError, TwitterError};
 
 
 #[derive(Serialize, Deserialize)]
@@ -16,39 +25,39 @@ pub struct ConnectTwitter {
 }
 
 
-#[derive(Serialize, Deserialize)]
-pub struct Credit {
-    pub user_id: i32,
-    pub twitter_post_id: String,
-    pub is_used: bool,
-}
-
-
-pub async fn create_users_retwitt(
-    pool: &PgPool,
-    user_id: i32,
-    twitter_post_id: String,
-) -> Result<Credit> {
-    let credit = sqlx::query_as!(
-        Credit,
-        "INSERT INTO credits (user_id, twitter_post_id, is_used) VALUES ($1, $2, false) RETURNING user_id, twitter_post_id, is_used",
-        user_id,
-        twitter_post_id
+pub async fn has_user_available_credits(pool: &PgPool, user_id: i32) -> Result<bool> {
+    let credit = sqlx::query!(
+        "SELECT id FROM credits WHERE user_id = $1 AND is_used = false LIMIT 1",
+        user_id
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
-    Ok(credit)
+    Ok(credit.is_some())
 }
 
 
-pub async fn has_user_available_credits(pool: &PgPool, user_id: i32) -> Result<bool> {
+/// Marks one unused credit of the user as used and returns its id, or `None` when the user has
+/// none left. Concurrent callers never get the same credit.
+pub async fn reserve_user_credit(pool: &PgPool, user_id: i32) -> Result<Option<i32>> {
     let credit = sqlx::query!(
-        "SELECT id FROM credits WHERE user_id = $1 AND is_used = false LIMIT 1",
+        "UPDATE credits SET is_used = true
+         WHERE id = (SELECT id FROM credits WHERE user_id = $1 AND is_used = false ORDER BY id LIMIT 1 FOR UPDATE SKIP LOCKED)
+         AND is_used = false
+         RETURNING id",
         user_id
     )
     .fetch_optional(pool)
     .await?;
-    Ok(credit.is_some())
+    Ok(credit.map(|credit| credit.id))
+}
+
+
+/// Gives a reserved credit back.
+pub async fn refund_user_credit(pool: &PgPool, credit_id: i32) -> Result<()> {
+    sqlx::query!("UPDATE credits SET is_used = false WHERE id = $1", credit_id)
+        .execute(pool)
+        .await?;
+    Ok(())
 }
 
 
@@ -60,18 +69,6 @@ pub async fn get_all_twitter_users(pool: &PgPool) -> Result<Vec<String>> {
 }
 
 
-pub async fn check_existing_retwitted_post(pool: &PgPool, user_id: i32, post_id: String) -> Result<bool> {
-    let retweet = sqlx::query!(
-        "SELECT id FROM credits WHERE user_id = $1 AND twitter_post_id = $2 LIMIT 1",
-        user_id,
-        post_id
-    )
-    .fetch_optional(pool)
-    .await?;
-    Ok(retweet.is_some())
-}
-
-
 pub async fn update_user(pool: &PgPool, wallet: String, twitter_id: String) -> Result<()> {
     sqlx::query!(
         "UPDATE users SET twitter_id = $1 WHERE wallet = $2",
@@ -84,25 +81,129 @@ pub async fn update_user(pool: &PgPool, wallet: String, twitter_id: String) -> R
 }
 
 
+/// Awards a credit for every new retweet of the agent's recent posts and reports them, under
+/// the lease of the scheduled `check_retwitts` job; admin only, 409 while a check is already
+/// running, 429 with `Retry-After` when Twitter rate limits the first request.
 #[post("/check_retwitts")]
-pub async fn check_retwitts(pool: web::Data<PgPool>) -> impl Responder {
-    let users = get_all_twitter_users(pool.get_ref()).await;
-    match users {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(_) => HttpResponse::InternalServerError().body("Error retrieving users"),
+pub async fn check_retwitts(
+    req: HttpRequest,
+    scheduler: web::Data<Arc<Scheduler>>,
+    pool: web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
     }
+    let service = match RetweetCreditService::from_settings(pool.get_ref().clone()) {
+        Ok(service) => service,
+        Err(e) => {
+            error!("Failed to initialize Twitter client: {}", e);
+            return HttpResponse::InternalServerError().body("Error initializing Twitter client");
+        }
+    };
+    let redis = redis_client.lock().await.clone();
+    let lease = match JobLease::acquire(&redis, CheckRetwittsJob::NAME, SchedulerSettings::new_scheduler().lease_ttl_secs).await {
+        Ok(lease) => lease,
+        Err(SchedulerError::LeaseHeld(_)) => return HttpResponse::Conflict().body("Retweets are already being checked"),
+        Err(e) => {
+            error!("Failed to take retweet check lease: {}", e);
+            return HttpResponse::InternalServerError().body("Error taking retweet check lease");
+        }
+    };
+
+    let response = match service.award_retweet_credits().await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(CreditError::Twitter(TwitterError::RateLimited(retry_after_secs))) => HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", retry_after_secs.to_string()))
+            .body("Twitter rate limit reached, retry later"),
+        Err(e) => {
+            error!("Failed to award retweet credits: {}", e);
+            HttpResponse::InternalServerError().body("Error checking retweets")
+        }
+    };
+    lease.release().await;
+    response
 }
 
 
+/// Sells every open position and records the closed trades, under the lease of the
+/// scheduled `sell_tokens` job; admin only, 409 while a sell is already running.
 #[post("/sell_tokens")]
-pub async fn sell_tokens(pool: web::Data<PgPool>) -> impl Responder {
-    HttpResponse::Ok().body("Sell tokens process started")
+pub async fn sell_tokens(
+    req: HttpRequest,
+    scheduler: web::Data<Arc<Scheduler>>,
+    pool: web::Data<PgPool>,
+    provider: web::Data<Arc<dyn LlmProvider>>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    let redis = redis_client.lock().await.clone();
+    let lease = match JobLease::acquire(&redis, SellTokensJob::NAME, SchedulerSettings::new_scheduler().lease_ttl_secs).await {
+        Ok(lease) => lease,
+        Err(SchedulerError::LeaseHeld(_)) => return HttpResponse::Conflict().body("Tokens are already being sold"),
+        Err(e) => {
+            error!("Failed to take sell lease: {}", e);
+            return HttpResponse::InternalServerError().body("Error taking sell lease");
+        }
+    };
+
+    let response = match PositionManager::from_settings(pool.get_ref().clone()).await {
+        Ok(positions) => match sell_all_positions(provider.get_ref().as_ref(), &positions, pool.get_ref()).await {
+            Ok(results) => HttpResponse::Ok().json(results),
+            Err(e) => {
+                error!("Failed to load open positions: {}", e);
+                HttpResponse::InternalServerError().body("Error retrieving open positions")
+            }
+        },
+        Err(e) => {
+            error!("Failed to initialize position manager: {}", e);
+            HttpResponse::InternalServerError().body("Error initializing Solana driver")
+        }
+    };
+    lease.release().await;
+    response
 }
 
 
+/// Records a snapshot of the agent balance for the balance charts, under the lease of the
+/// scheduled `log_agent_balance` job; admin only, 409 while a snapshot is already being taken.
 #[post("/log_agent_balance")]
-pub async fn log_agent_balance(pool: web::Data<PgPool>) -> impl Responder {
-    HttpResponse::Ok().body("Agent balance logged")
+pub async fn log_agent_balance(
+    req: HttpRequest,
+    scheduler: web::Data<Arc<Scheduler>>,
+    pool: web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    let redis = redis_client.lock().await.clone();
+    let lease = match JobLease::acquire(&redis, LogAgentBalanceJob::NAME, SchedulerSettings::new_scheduler().lease_ttl_secs).await {
+        Ok(lease) => lease,
+        Err(SchedulerError::LeaseHeld(_)) => return HttpResponse::Conflict().body("Agent balance is already being logged"),
+        Err(e) => {
+            error!("Failed to take balance lease: {}", e);
+            return HttpResponse::InternalServerError().body("Error taking balance lease");
+        }
+    };
+
+    let response = match BalanceRecorder::shared(pool.get_ref().clone()).await {
+        Ok(recorder) => match recorder.record_snapshot().await {
+            Ok(snapshot) => HttpResponse::Ok().json(snapshot),
+            Err(e) => {
+                error!("Failed to log agent balance: {}", e);
+                HttpResponse::InternalServerError().body("Error logging agent balance")
+            }
+        },
+        Err(e) => {
+            error!("Failed to initialize balance recorder: {}", e);
+            HttpResponse::InternalServerError().body("Error logging agent balance")
+        }
+    };
+    lease.release().await;
+    response
 }
 
 
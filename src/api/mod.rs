@@ -1,5 +1,9 @@
 pub mod auth;
 pub mod chats;
 pub mod general;
+pub mod payouts;
+pub mod portfolio;
+pub mod positions;
+pub mod scheduler;
 pub mod statistics;
 pub mod agave;
\ No newline at end of file
@@ -0,0 +1,53 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::error;
+use crate::api::auth::extract_user_from_access_token;
+use crate::payouts::PayoutService;
+use crate::utils::paginated_response::{default_page_number, default_page_size, page_bounds, PaginatedResponse};
+use crate::utils::redis::RedisClient;
+
+
+#[derive(Deserialize)]
+pub struct PayoutHistoryQuery {
+    #[serde(default = "default_page_number")]
+    pub page_number: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+
+/// Profit shares owed or sent to the signed-in user, newest first.
+#[get("/payouts")]
+pub async fn get_user_payouts(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis_client: web::Data<Arc<Mutex<RedisClient>>>,
+    query: web::Query<PayoutHistoryQuery>,
+) -> impl Responder {
+    let user = match extract_user_from_access_token(req, redis_client, pool.clone()).await {
+        Ok(user) => user,
+        Err(_) => return HttpResponse::Unauthorized().body("Not authorized"),
+    };
+
+    let (page_number, page_size) = page_bounds(query.page_number, query.page_size);
+    match PayoutService::from_settings(pool.get_ref().clone())
+        .payouts_for_wallet(&user.wallet, page_number, page_size)
+        .await
+    {
+        Ok((payouts, total)) => {
+            HttpResponse::Ok().json(PaginatedResponse::new_paginated_response(page_size, page_number, total, payouts))
+        }
+        Err(e) => {
+            error!("Failed to load payouts of {}: {}", user.wallet, e);
+            HttpResponse::InternalServerError().body("Error retrieving payouts")
+        }
+    }
+}
+
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_user_payouts);
+}
@@ -0,0 +1,28 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use sqlx::PgPool;
+use tracing::error;
+use crate::portfolio::Portfolio;
+
+
+#[get("/portfolio")]
+pub async fn get_portfolio(pool: web::Data<PgPool>) -> impl Responder {
+    let portfolio = match Portfolio::for_agent(pool.get_ref().clone()).await {
+        Ok(portfolio) => portfolio,
+        Err(e) => {
+            error!("Failed to load agent wallet: {}", e);
+            return HttpResponse::InternalServerError().body("Error loading agent wallet");
+        }
+    };
+    match portfolio.snapshot().await {
+        Ok(snapshot) => HttpResponse::Ok().json(snapshot),
+        Err(e) => {
+            error!("Failed to read portfolio: {}", e);
+            HttpResponse::InternalServerError().body("Error retrieving portfolio")
+        }
+    }
+}
+
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_portfolio);
+}
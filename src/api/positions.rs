@@ -0,0 +1,86 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::error;
+use crate::api::scheduler::is_admin;
+use crate::core::config::ExitSettings;
+use crate::positions::service::{self, get_exit_state, set_exit_rules};
+use crate::positions::{ExitRuleOverrides, ExitRules, Position};
+use crate::scheduler::Scheduler;
+use crate::utils::error::PositionError;
+
+
+#[derive(Serialize)]
+pub struct OpenPositionResponse {
+    #[serde(flatten)]
+    pub position: Position,
+    /// Rules set for this position only.
+    pub overrides: ExitRuleOverrides,
+    /// Rules the exit engine applies, global ones included.
+    pub exit_rules: ExitRules,
+    pub peak_value_sol: Option<f64>,
+}
+
+
+#[get("/positions")]
+pub async fn get_open_positions(pool: web::Data<PgPool>) -> impl Responder {
+    let open_positions = match service::get_open_positions(pool.get_ref()).await {
+        Ok(open_positions) => open_positions,
+        Err(e) => {
+            error!("Failed to load open positions: {}", e);
+            return HttpResponse::InternalServerError().body("Error retrieving open positions");
+        }
+    };
+
+    let global = ExitRules::from_settings(&ExitSettings::new_exit());
+    let mut response = Vec::with_capacity(open_positions.len());
+    for position in open_positions {
+        let state = match get_exit_state(pool.get_ref(), position.trade_position_id).await {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to load exit rules of position {}: {}", position.trade_position_id, e);
+                return HttpResponse::InternalServerError().body("Error retrieving exit rules");
+            }
+        };
+        response.push(OpenPositionResponse {
+            position,
+            exit_rules: global.with_overrides(&state.overrides),
+            overrides: state.overrides,
+            peak_value_sol: state.peak_value_sol,
+        });
+    }
+    HttpResponse::Ok().json(response)
+}
+
+
+/// Sets the exit rules of one position; rules left out fall back to the `EXIT_*` settings
+/// and rules set to `null` are turned off. Admin only.
+#[post("/positions/{trade_position_id}/exit_rules")]
+pub async fn set_position_exit_rules(
+    req: HttpRequest,
+    scheduler: web::Data<Arc<Scheduler>>,
+    pool: web::Data<PgPool>,
+    trade_position_id: web::Path<i32>,
+    rules: web::Json<ExitRuleOverrides>,
+) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    match set_exit_rules(pool.get_ref(), *trade_position_id, &rules).await {
+        Ok(()) => HttpResponse::Ok().json(rules.into_inner()),
+        Err(e @ PositionError::InvalidExitRule(_)) => HttpResponse::BadRequest().body(e.to_string()),
+        Err(e @ PositionError::NotFound(_)) => HttpResponse::NotFound().body(e.to_string()),
+        Err(e @ PositionError::AlreadyClosed(_)) => HttpResponse::Conflict().body(e.to_string()),
+        Err(e) => {
+            error!("Failed to set exit rules of position {}: {}", trade_position_id, e);
+            HttpResponse::InternalServerError().body("Error saving exit rules")
+        }
+    }
+}
+
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_open_positions)
+        .service(set_position_exit_rules);
+}
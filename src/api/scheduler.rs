@@ -0,0 +1,119 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use crate::scheduler::Scheduler;
+use crate::utils::error::SchedulerError;
+use crate::utils::paginated_response::{default_page_number, default_page_size, page_bounds, PaginatedResponse};
+
+
+#[derive(Deserialize)]
+pub struct JobRunsQuery {
+    #[serde(default = "default_page_number")]
+    pub page_number: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+
+/// Whether the request carries the `ADMIN_API_KEY` in `X-Admin-Key`.
+pub(crate) fn is_admin(req: &HttpRequest, scheduler: &Scheduler) -> bool {
+    let admin_api_key = &scheduler.settings().admin_api_key;
+    !admin_api_key.is_empty()
+        && req
+            .headers()
+            .get("X-Admin-Key")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == admin_api_key.expose())
+}
+
+
+fn scheduler_error_response(e: SchedulerError) -> HttpResponse {
+    match e {
+        SchedulerError::UnknownJob(name) => HttpResponse::NotFound().body(format!("Unknown job {}", name)),
+        SchedulerError::LeaseHeld(name) => HttpResponse::Conflict().body(format!("Job {} is already running", name)),
+        e => {
+            error!("Scheduler request failed: {}", e);
+            HttpResponse::InternalServerError().body("Error accessing scheduled jobs")
+        }
+    }
+}
+
+
+/// Declared jobs with their schedule, paused state, next run and latest run.
+#[get("/admin/jobs")]
+pub async fn get_jobs(req: HttpRequest, scheduler: web::Data<Arc<Scheduler>>) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    match scheduler.jobs().await {
+        Ok(jobs) => HttpResponse::Ok().json(jobs),
+        Err(e) => scheduler_error_response(e),
+    }
+}
+
+
+/// Run history of one job, newest first.
+#[get("/admin/jobs/{job_name}/runs")]
+pub async fn get_job_runs(
+    req: HttpRequest,
+    scheduler: web::Data<Arc<Scheduler>>,
+    job_name: web::Path<String>,
+    query: web::Query<JobRunsQuery>,
+) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    let (page_number, page_size) = page_bounds(query.page_number, query.page_size);
+    match scheduler.runs(&job_name, page_number, page_size).await {
+        Ok((runs, total)) => HttpResponse::Ok().json(PaginatedResponse::new_paginated_response(page_size, page_number, total, runs)),
+        Err(e) => scheduler_error_response(e),
+    }
+}
+
+
+/// Stops scheduled runs of a job on every instance; it can still be triggered.
+#[post("/admin/jobs/{job_name}/pause")]
+pub async fn pause_job(req: HttpRequest, scheduler: web::Data<Arc<Scheduler>>, job_name: web::Path<String>) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    match scheduler.set_paused(&job_name, true).await {
+        Ok(job) => HttpResponse::Ok().json(job),
+        Err(e) => scheduler_error_response(e),
+    }
+}
+
+
+#[post("/admin/jobs/{job_name}/resume")]
+pub async fn resume_job(req: HttpRequest, scheduler: web::Data<Arc<Scheduler>>, job_name: web::Path<String>) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    match scheduler.set_paused(&job_name, false).await {
+        Ok(job) => HttpResponse::Ok().json(job),
+        Err(e) => scheduler_error_response(e),
+    }
+}
+
+
+/// Runs a job now and returns the recorded run; 409 if it is already running.
+#[post("/admin/jobs/{job_name}/trigger")]
+pub async fn trigger_job(req: HttpRequest, scheduler: web::Data<Arc<Scheduler>>, job_name: web::Path<String>) -> impl Responder {
+    if !is_admin(&req, &scheduler) {
+        return HttpResponse::Unauthorized().body("Not authorized");
+    }
+    match scheduler.trigger(&job_name).await {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(e) => scheduler_error_response(e),
+    }
+}
+
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_jobs)
+        .service(get_job_runs)
+        .service(pause_job)
+        .service(resume_job)
+        .service(trigger_job);
+}
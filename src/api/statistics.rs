@@ -3,7 +3,15 @@ use serde::{Serialize, Deserialize};
 use sqlx::PgPool;
 use chrono::{DateTime, Utc};
 use crate::core::db::get_db_pool;
+use crate::pnl::{compute_pnl, PnlReport};
+use crate::balance_history::BalanceGranularity;
+use crate::statistics::{ShillingStatistics, StatisticsService};
+use crate::utils::error::{BalanceHistoryError, StatisticsError};
+use crate::utils::paginated_response::{default_page_number, default_page_size, page_bounds, PaginatedResponse};
+use crate::utils::price_oracle::PriceOracle;
+use crate::utils::solana_rpc::shared_solana_rpc;
 use anyhow::Result;
+use tracing::{error, warn};
 
 
 pub const SOL_IMAGE_URL: &str = "https://img-v1.raydium.io/icon/So11111111111111111111111111111111111111112.png";
@@ -22,12 +30,13 @@ pub struct UsersCountResponse {
 
 
 #[derive(Serialize, Deserialize)]
-pub struct ShillingStatisticsResponse {
-    pub result: serde_json::Value,
+pub struct ShillingStatisticsResponse<T> {
+    pub action: ShillingStatisticsAction,
+    pub result: T,
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ShillingStatisticsAction {
     DashboardInfo,
     TotalPaid,
@@ -46,6 +55,18 @@ pub enum ShillingStatisticsAction {
 }
 
 
+#[derive(Deserialize)]
+pub struct ShillingStatisticsQuery {
+    pub action: ShillingStatisticsAction,
+    #[serde(default = "default_page_number")]
+    pub page_number: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+    /// IANA timezone the agent balance buckets are aligned to.
+    pub timezone: Option<String>,
+}
+
+
 pub async fn get_unique_users_count(pool: &PgPool) -> Result<i32> {
     let count = sqlx::query!("SELECT COUNT(id) as count FROM users")
         .fetch_one(pool)
@@ -80,41 +101,47 @@ pub async fn get_count_of_trades(pool: &PgPool) -> Result<i32> {
 }
 
 
+pub async fn get_count_of_closed_trades(pool: &PgPool) -> Result<i32> {
+    let count = sqlx::query!("SELECT COUNT(id) as count FROM trades WHERE trade_type = 'closed'")
+        .fetch_one(pool)
+        .await?
+        .count
+        .unwrap_or(0);
+    Ok(count)
+}
+
+
+/// Realized PnL of all sells, fees included, in SOL.
 pub async fn get_total_pnl(pool: &PgPool) -> Result<f64> {
-    let result = sqlx::query!(
-        "SELECT SUM(closed.baseTokenQuantity - open.baseTokenQuantity) as total_pnl
-         FROM trades open
-         JOIN trades closed ON open.trade_position_id = closed.trade_position_id
-         WHERE open.trade_type = 'open' AND closed.trade_type = 'closed'"
-    )
-    .fetch_one(pool)
-    .await?
-    .total_pnl
-    .unwrap_or(0.0);
-    Ok(result)
+    Ok(compute_pnl(pool, None).await?.realized_pnl_sol)
 }
 
 
+/// Realized PnL and transaction of the best and the worst sell, in SOL.
 pub async fn get_max_min_pnl(pool: &PgPool) -> Result<(f64, f64, Option<String>, Option<String>)> {
-    let row = sqlx::query!(
-        "WITH pnl_calc AS (
-            SELECT (closed.baseTokenQuantity - open.baseTokenQuantity) AS pnl, closed.tx_id, t.symbol
-            FROM trades open
-            JOIN trades closed ON open.trade_position_id = closed.trade_position_id
-            JOIN tokens t ON closed.token_id = t.id
-            WHERE open.trade_type = 'open' AND closed.trade_type = 'closed'
-        )
-        SELECT 
-            (SELECT pnl FROM pnl_calc ORDER BY pnl DESC LIMIT 1) AS max_pnl,
-            (SELECT tx_id FROM pnl_calc ORDER BY pnl DESC LIMIT 1) AS max_tx_id,
-            (SELECT pnl FROM pnl_calc ORDER BY pnl ASC LIMIT 1) AS min_pnl,
-            (SELECT tx_id FROM pnl_calc ORDER BY pnl ASC LIMIT 1) AS min_tx_id
-        "
-    )
-    .fetch_one(pool)
-    .await?;
+    let report = compute_pnl(pool, None).await?;
+    let (max_pnl, max_tx_id) = report.best_sale.as_ref().map(|sale| (sale.pnl_sol, Some(sale.tx_id.clone()))).unwrap_or((0.0, None));
+    let (min_pnl, min_tx_id) = report.worst_sale.as_ref().map(|sale| (sale.pnl_sol, Some(sale.tx_id.clone()))).unwrap_or((0.0, None));
+    Ok((max_pnl, min_pnl, max_tx_id, min_tx_id))
+}
 
-    Ok((row.max_pnl.unwrap_or(0.0), row.min_pnl.unwrap_or(0.0), row.max_tx_id, row.min_tx_id))
+
+/// Realized and unrealized PnL, with open lots marked to market.
+pub async fn get_pnl_report(pool: &PgPool) -> Result<PnlReport> {
+    let oracle = PriceOracle::new_price_oracle(shared_solana_rpc());
+    Ok(compute_pnl(pool, Some(&oracle)).await?)
+}
+
+
+/// Current SOL price in USD, or `None` if no price source is available.
+pub async fn get_sol_price_usd() -> Option<f64> {
+    match PriceOracle::new_price_oracle(shared_solana_rpc()).sol_usd().await {
+        Ok(price) => Some(price.price_usd),
+        Err(e) => {
+            warn!("Failed to price SOL: {}", e);
+            None
+        }
+    }
 }
 
 
@@ -138,8 +165,14 @@ pub async fn get_messages_count_by_user_action(pool: web::Data<PgPool>, user_id:
 
 #[get("/total_pnl")]
 pub async fn get_total_pnl_route(pool: web::Data<PgPool>) -> impl Responder {
-    match get_total_pnl(pool.get_ref()).await {
-        Ok(total_pnl) => HttpResponse::Ok().json(serde_json::json!({ "total_pnl": total_pnl })),
+    match get_pnl_report(pool.get_ref()).await {
+        Ok(report) => HttpResponse::Ok().json(serde_json::json!({
+            "total_pnl": report.realized_pnl_sol,
+            "total_pnl_usd": report.realized_pnl_usd,
+            "unrealized_pnl": report.unrealized_pnl_sol,
+            "unrealized_pnl_usd": report.unrealized_pnl_usd,
+            "fees": report.fees_sol
+        })),
         Err(_) => HttpResponse::InternalServerError().body("Error retrieving total PnL"),
     }
 }
@@ -148,18 +181,275 @@ pub async fn get_total_pnl_route(pool: web::Data<PgPool>) -> impl Responder {
 #[get("/max_min_pnl")]
 pub async fn get_max_min_pnl_route(pool: web::Data<PgPool>) -> impl Responder {
     match get_max_min_pnl(pool.get_ref()).await {
-        Ok((max_pnl, min_pnl, max_tx_id, min_tx_id)) => HttpResponse::Ok().json(serde_json::json!({
-            "max": { "pnl": max_pnl, "tx_id": max_tx_id },
-            "min": { "pnl": min_pnl, "tx_id": min_tx_id }
-        })),
+        Ok((max_pnl, min_pnl, max_tx_id, min_tx_id)) => {
+            let sol_price = get_sol_price_usd().await;
+            HttpResponse::Ok().json(serde_json::json!({
+                "max": { "pnl": max_pnl, "pnl_usd": sol_price.map(|price| max_pnl * price), "tx_id": max_tx_id },
+                "min": { "pnl": min_pnl, "pnl_usd": sol_price.map(|price| min_pnl * price), "tx_id": min_tx_id }
+            }))
+        }
         Err(_) => HttpResponse::InternalServerError().body("Error retrieving max/min PnL"),
     }
 }
 
 
+/// Per-token lots and PnL, with the best and worst realized sell.
+#[get("/pnl")]
+pub async fn get_pnl_route(pool: web::Data<PgPool>) -> impl Responder {
+    match get_pnl_report(pool.get_ref()).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(_) => HttpResponse::InternalServerError().body("Error retrieving PnL"),
+    }
+}
+
+
+fn shilling_statistics_response<T: Serialize>(action: ShillingStatisticsAction, result: Result<T, StatisticsError>) -> HttpResponse {
+    match result {
+        Ok(result) => HttpResponse::Ok().json(ShillingStatisticsResponse { action, result }),
+        Err(StatisticsError::BalanceHistory(BalanceHistoryError::InvalidTimezone(timezone))) => {
+            HttpResponse::BadRequest().body(format!("Unknown timezone {}", timezone))
+        }
+        Err(e) => {
+            error!("Failed to compute {:?} statistics: {}", action, e);
+            HttpResponse::InternalServerError().body("Error retrieving shilling statistics")
+        }
+    }
+}
+
+
+fn shilling_statistics_page<T: Serialize>(
+    action: ShillingStatisticsAction,
+    page_number: usize,
+    page_size: usize,
+    result: Result<(Vec<T>, usize), StatisticsError>,
+) -> HttpResponse {
+    let page = result.map(|(data, total)| PaginatedResponse::new_paginated_response(page_size, page_number, total, data));
+    shilling_statistics_response(action, page)
+}
+
+
+/// Answers the query from `service`, calling the method of its action.
+async fn shilling_statistics(service: &dyn ShillingStatistics, query: &ShillingStatisticsQuery) -> HttpResponse {
+    let action = query.action;
+    let (page_number, page_size) = page_bounds(query.page_number, query.page_size);
+    let timezone = query.timezone.as_deref();
+
+    match action {
+        ShillingStatisticsAction::DashboardInfo => shilling_statistics_response(action, service.dashboard_info().await),
+        ShillingStatisticsAction::TotalPaid => shilling_statistics_response(action, service.total_paid().await),
+        ShillingStatisticsAction::MaxMinTradePnL => shilling_statistics_response(action, service.max_min_trade_pnl().await),
+        ShillingStatisticsAction::CurrentBalance => shilling_statistics_response(action, service.current_balance().await),
+        ShillingStatisticsAction::Assets => shilling_statistics_response(action, service.assets().await),
+        ShillingStatisticsAction::Transactions => {
+            shilling_statistics_page(action, page_number, page_size, service.transactions(page_number, page_size).await)
+        }
+        ShillingStatisticsAction::Rejections => {
+            shilling_statistics_page(action, page_number, page_size, service.rejections(page_number, page_size).await)
+        }
+        ShillingStatisticsAction::Trades => {
+            shilling_statistics_page(action, page_number, page_size, service.trades(page_number, page_size).await)
+        }
+        ShillingStatisticsAction::TokensApproved => {
+            shilling_statistics_page(action, page_number, page_size, service.tokens_approved(page_number, page_size).await)
+        }
+        ShillingStatisticsAction::AgentBalanceByMinutes => {
+            shilling_statistics_response(action, service.agent_balance(BalanceGranularity::Minute, timezone).await)
+        }
+        ShillingStatisticsAction::AgentBalanceByDay => {
+            shilling_statistics_response(action, service.agent_balance(BalanceGranularity::Day, timezone).await)
+        }
+        ShillingStatisticsAction::AgentBalanceByWeek => {
+            shilling_statistics_response(action, service.agent_balance(BalanceGranularity::Week, timezone).await)
+        }
+        ShillingStatisticsAction::AgentBalanceByMonth => {
+            shilling_statistics_response(action, service.agent_balance(BalanceGranularity::Month, timezone).await)
+        }
+        ShillingStatisticsAction::AgentBalanceByYear => {
+            shilling_statistics_response(action, service.agent_balance(BalanceGranularity::Year, timezone).await)
+        }
+    }
+}
+
+
+/// Dashboard data for one `ShillingStatisticsAction`; list actions take `page_number` and `page_size`.
+#[get("/statistics/shilling")]
+pub async fn get_shilling_statistics(
+    pool: web::Data<PgPool>,
+    query: web::Query<ShillingStatisticsQuery>,
+) -> impl Responder {
+    let service = StatisticsService::from_settings(pool.get_ref().clone());
+    shilling_statistics(&service, &query).await
+}
+
+
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all_users_count)
         .service(get_messages_count_by_user_action)
         .service(get_total_pnl_route)
-        .service(get_max_min_pnl_route);
+        .service(get_max_min_pnl_route)
+        .service(get_pnl_route)
+        .service(get_shilling_statistics);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use async_trait::async_trait;
+    use serde_json::Value;
+    use std::sync::Mutex;
+    use crate::balance_history::BalanceCandle;
+    use crate::portfolio::PortfolioSnapshot;
+    use crate::statistics::{
+        ApprovedToken, CurrentBalance, DashboardInfo, MaxMinTradePnl, RejectionItem, TotalPaid, TradeItem, TransactionItem,
+    };
+
+    const LIST_TOTAL: usize = 45;
+
+    /// Records which method each query reached and answers with fixed, empty data.
+    #[derive(Default)]
+    struct StubStatistics {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl StubStatistics {
+        fn call(&self, name: String) {
+            self.calls.lock().unwrap().push(name);
+        }
+
+        fn page<T>(&self, name: &str, page_number: usize, page_size: usize) -> Result<(Vec<T>, usize), StatisticsError> {
+            self.call(format!("{}:{}:{}", name, page_number, page_size));
+            Ok((vec![], LIST_TOTAL))
+        }
+    }
+
+    #[async_trait]
+    impl ShillingStatistics for StubStatistics {
+        async fn dashboard_info(&self) -> Result<DashboardInfo, StatisticsError> {
+            self.call("dashboard_info".to_string());
+            Ok(DashboardInfo {
+                total_users: 1,
+                positions_opened: 0,
+                positions_closed: 0,
+                open_positions: 0,
+                rejections: 0,
+                realized_pnl_sol: 0.0,
+                total_paid_sol: 0.0,
+                balance_sol: None,
+                sol_price_usd: None,
+            })
+        }
+
+        async fn total_paid(&self) -> Result<TotalPaid, StatisticsError> {
+            self.call("total_paid".to_string());
+            Ok(TotalPaid { payouts_sent: 0, total_paid_sol: 0.0, total_paid_usd: None })
+        }
+
+        async fn max_min_trade_pnl(&self) -> Result<MaxMinTradePnl, StatisticsError> {
+            self.call("max_min_trade_pnl".to_string());
+            Ok(MaxMinTradePnl { max: None, min: None })
+        }
+
+        async fn current_balance(&self) -> Result<CurrentBalance, StatisticsError> {
+            self.call("current_balance".to_string());
+            Ok(CurrentBalance { wallet: "agent".to_string(), balance_sol: 1.0, sol_price_usd: None, balance_usd: None })
+        }
+
+        async fn agent_balance(&self, granularity: BalanceGranularity, timezone: Option<&str>) -> Result<Vec<BalanceCandle>, StatisticsError> {
+            self.call(format!("agent_balance:{}", granularity.as_date_trunc()));
+            match timezone {
+                Some("Mars/Olympus") => Err(BalanceHistoryError::InvalidTimezone("Mars/Olympus".to_string()).into()),
+                _ => Ok(vec![]),
+            }
+        }
+
+        async fn assets(&self) -> Result<PortfolioSnapshot, StatisticsError> {
+            self.call("assets".to_string());
+            Ok(PortfolioSnapshot {
+                wallet: "agent".to_string(),
+                sol_balance: 1.0,
+                sol_price_usd: None,
+                sol_balance_usd: None,
+                total_value_usd: None,
+                tokens: vec![],
+            })
+        }
+
+        async fn transactions(&self, page_number: usize, page_size: usize) -> Result<(Vec<TransactionItem>, usize), StatisticsError> {
+            self.page("transactions", page_number, page_size)
+        }
+
+        async fn rejections(&self, page_number: usize, page_size: usize) -> Result<(Vec<RejectionItem>, usize), StatisticsError> {
+            self.page("rejections", page_number, page_size)
+        }
+
+        async fn trades(&self, page_number: usize, page_size: usize) -> Result<(Vec<TradeItem>, usize), StatisticsError> {
+            self.page("trades", page_number, page_size)
+        }
+
+        async fn tokens_approved(&self, page_number: usize, page_size: usize) -> Result<(Vec<ApprovedToken>, usize), StatisticsError> {
+            self.page("tokens_approved", page_number, page_size)
+        }
+    }
+
+    fn query(action: ShillingStatisticsAction, timezone: Option<&str>) -> ShillingStatisticsQuery {
+        ShillingStatisticsQuery { action, page_number: 2, page_size: 500, timezone: timezone.map(str::to_string) }
+    }
+
+    async fn body(response: HttpResponse) -> Value {
+        let bytes = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn dispatches_each_action_to_its_statistics() {
+        use ShillingStatisticsAction as Action;
+        let cases = [
+            (Action::DashboardInfo, "dashboard_info"),
+            (Action::TotalPaid, "total_paid"),
+            (Action::MaxMinTradePnL, "max_min_trade_pnl"),
+            (Action::CurrentBalance, "current_balance"),
+            (Action::Assets, "assets"),
+            (Action::Transactions, "transactions:2:100"),
+            (Action::Rejections, "rejections:2:100"),
+            (Action::Trades, "trades:2:100"),
+            (Action::TokensApproved, "tokens_approved:2:100"),
+            (Action::AgentBalanceByMinutes, "agent_balance:minute"),
+            (Action::AgentBalanceByDay, "agent_balance:day"),
+            (Action::AgentBalanceByWeek, "agent_balance:week"),
+            (Action::AgentBalanceByMonth, "agent_balance:month"),
+            (Action::AgentBalanceByYear, "agent_balance:year"),
+        ];
+
+        for (action, call) in cases {
+            let stub = StubStatistics::default();
+            let response = shilling_statistics(&stub, &query(action, None)).await;
+
+            assert_eq!(response.status(), StatusCode::OK, "{:?}", action);
+            assert_eq!(*stub.calls.lock().unwrap(), vec![call.to_string()]);
+            let body = body(response).await;
+            assert_eq!(body["action"], serde_json::to_value(action).unwrap());
+        }
+    }
+
+    #[actix_web::test]
+    async fn pages_list_actions_with_the_clamped_bounds() {
+        let stub = StubStatistics::default();
+        let body = body(shilling_statistics(&stub, &query(ShillingStatisticsAction::Trades, None)).await).await;
+
+        assert_eq!(body["result"]["page_number"], 2);
+        assert_eq!(body["result"]["page_size"], 100);
+        assert_eq!(body["result"]["total_items"], LIST_TOTAL);
+        assert_eq!(body["result"]["total_pages"], 1);
+        assert_eq!(body["result"]["data"], Value::Array(vec![]));
+    }
+
+    #[actix_web::test]
+    async fn refuses_an_unknown_timezone() {
+        let stub = StubStatistics::default();
+        let response = shilling_statistics(&stub, &query(ShillingStatisticsAction::AgentBalanceByDay, Some("Mars/Olympus"))).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,5 @@
+pub mod models;
+pub mod service;
+
+pub use models::{BalanceCandle, BalanceGranularity, BalanceSnapshot, Ohlc};
+pub use service::{balance_candles, fill_gaps, BalanceRecorder};
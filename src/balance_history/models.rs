@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// One recorded agent balance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub balance_sol: f64,
+    pub balance_usd: Option<f64>,
+}
+
+/// Size of the buckets of a balance chart.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceGranularity {
+    Minute,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl BalanceGranularity {
+    /// Field name understood by Postgres `date_trunc`.
+    pub fn as_date_trunc(&self) -> &'static str {
+        match self {
+            BalanceGranularity::Minute => "minute",
+            BalanceGranularity::Day => "day",
+            BalanceGranularity::Week => "week",
+            BalanceGranularity::Month => "month",
+            BalanceGranularity::Year => "year",
+        }
+    }
+
+    /// Distance between two bucket starts, as a Postgres interval.
+    pub fn as_interval(&self) -> &'static str {
+        match self {
+            BalanceGranularity::Minute => "1 minute",
+            BalanceGranularity::Day => "1 day",
+            BalanceGranularity::Week => "1 week",
+            BalanceGranularity::Month => "1 month",
+            BalanceGranularity::Year => "1 year",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ohlc {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Ohlc {
+    /// A bucket without snapshots, flat at the previous close.
+    pub fn flat(value: f64) -> Self {
+        Self { open: value, high: value, low: value, close: value }
+    }
+}
+
+/// Agent balance over one bucket.
+///
+/// Buckets without snapshots are carried forward from the previous close and have
+/// `samples == 0`; buckets before the first snapshot have no values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceCandle {
+    /// Start of the bucket in the requested timezone, as local time.
+    pub bucket: NaiveDateTime,
+    /// Start of the bucket in UTC.
+    pub bucket_utc: NaiveDateTime,
+    pub sol: Option<Ohlc>,
+    /// `None` also when no snapshot up to this bucket could be priced.
+    pub usd: Option<Ohlc>,
+    pub samples: i64,
+}
+
+/// Bucket aggregates as read from the database, before gap filling.
+#[derive(Debug, Clone)]
+pub struct BucketAggregate {
+    pub bucket: NaiveDateTime,
+    pub bucket_utc: NaiveDateTime,
+    pub sol: Option<Ohlc>,
+    pub usd: Option<Ohlc>,
+    pub samples: i64,
+}
@@ -0,0 +1,222 @@
+use solana_sdk::native_token::lamports_to_sol;
+use sqlx::PgPool;
+use std::error::Error;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::balance_history::models::{BalanceCandle, BalanceGranularity, BalanceSnapshot, BucketAggregate, Ohlc};
+use crate::portfolio::Portfolio;
+use crate::utils::error::BalanceHistoryError;
+use crate::utils::price_oracle::PriceOracle;
+use crate::utils::solana_rpc::shared_solana_rpc;
+
+fn ohlc(open: Option<f64>, high: Option<f64>, low: Option<f64>, close: Option<f64>) -> Option<Ohlc> {
+    Some(Ohlc { open: open?, high: high?, low: low?, close: close? })
+}
+
+/// Turns bucket aggregates into candles, carrying the last close into buckets without
+/// snapshots. `previous_sol` and `previous_usd` are the last values before the first bucket.
+pub fn fill_gaps(buckets: Vec<BucketAggregate>, previous_sol: Option<f64>, previous_usd: Option<f64>) -> Vec<BalanceCandle> {
+    let mut last_sol = previous_sol;
+    let mut last_usd = previous_usd;
+    buckets
+        .into_iter()
+        .map(|bucket| {
+            let sol = bucket.sol.or(last_sol.map(Ohlc::flat));
+            let usd = bucket.usd.or(last_usd.map(Ohlc::flat));
+            last_sol = sol.map(|sol| sol.close);
+            last_usd = usd.map(|usd| usd.close);
+            BalanceCandle { bucket: bucket.bucket, bucket_utc: bucket.bucket_utc, sol, usd, samples: bucket.samples }
+        })
+        .collect()
+}
+
+/// The latest `points` buckets of the agent balance up to now, oldest first.
+///
+/// Buckets start at local midnight, week or month boundaries of `timezone`, an IANA name
+/// such as `Europe/Berlin`; snapshots are stored in UTC.
+pub async fn balance_candles(
+    pool: &PgPool,
+    granularity: BalanceGranularity,
+    timezone: &str,
+    points: i64,
+) -> Result<Vec<BalanceCandle>, BalanceHistoryError> {
+    let known = sqlx::query_scalar!(r#"SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1) AS "known!""#, timezone)
+        .fetch_one(pool)
+        .await?;
+    if !known {
+        return Err(BalanceHistoryError::InvalidTimezone(timezone.to_string()));
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        WITH buckets AS (
+            SELECT generate_series(
+                date_trunc($1, NOW() AT TIME ZONE $2) - ($3::INT - 1) * $4::TEXT::INTERVAL,
+                date_trunc($1, NOW() AT TIME ZONE $2),
+                $4::TEXT::INTERVAL
+            ) AS bucket
+        ),
+        samples AS (
+            SELECT date_trunc($1, (created_at AT TIME ZONE 'UTC') AT TIME ZONE $2) AS bucket, id, created_at, sol_amount, amount
+            FROM agent_balance_changes
+            WHERE created_at >= ((SELECT MIN(bucket) FROM buckets) AT TIME ZONE $2) AT TIME ZONE 'UTC'
+        )
+        SELECT
+            b.bucket AS "bucket!",
+            (b.bucket AT TIME ZONE $2) AT TIME ZONE 'UTC' AS "bucket_utc!",
+            COUNT(s.id) AS "samples!",
+            (ARRAY_AGG(s.sol_amount ORDER BY s.created_at, s.id) FILTER (WHERE s.id IS NOT NULL))[1] AS open_sol,
+            MAX(s.sol_amount) AS high_sol,
+            MIN(s.sol_amount) AS low_sol,
+            (ARRAY_AGG(s.sol_amount ORDER BY s.created_at DESC, s.id DESC) FILTER (WHERE s.id IS NOT NULL))[1] AS close_sol,
+            (ARRAY_AGG(s.amount ORDER BY s.created_at, s.id) FILTER (WHERE s.amount IS NOT NULL))[1] AS open_usd,
+            MAX(s.amount) AS high_usd,
+            MIN(s.amount) AS low_usd,
+            (ARRAY_AGG(s.amount ORDER BY s.created_at DESC, s.id DESC) FILTER (WHERE s.amount IS NOT NULL))[1] AS close_usd
+        FROM buckets b
+        LEFT JOIN samples s ON s.bucket = b.bucket
+        GROUP BY b.bucket
+        ORDER BY b.bucket
+        "#,
+        granularity.as_date_trunc(),
+        timezone,
+        points.max(1) as i32,
+        granularity.as_interval()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let Some(first) = rows.first() else {
+        return Ok(Vec::new());
+    };
+    let previous = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT sol_amount FROM agent_balance_changes WHERE created_at < $1 ORDER BY created_at DESC, id DESC LIMIT 1) AS sol,
+            (SELECT amount FROM agent_balance_changes WHERE created_at < $1 AND amount IS NOT NULL ORDER BY created_at DESC, id DESC LIMIT 1) AS usd
+        "#,
+        first.bucket_utc
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let buckets = rows
+        .into_iter()
+        .map(|row| BucketAggregate {
+            bucket: row.bucket,
+            bucket_utc: row.bucket_utc,
+            sol: ohlc(row.open_sol, row.high_sol, row.low_sol, row.close_sol),
+            usd: ohlc(row.open_usd, row.high_usd, row.low_usd, row.close_usd),
+            samples: row.samples,
+        })
+        .collect();
+    Ok(fill_gaps(buckets, previous.sol, previous.usd))
+}
+
+static RECORDER: OnceCell<BalanceRecorder> = OnceCell::const_new();
+
+/// Records the agent's SOL balance and its USD value into `agent_balance_changes`.
+pub struct BalanceRecorder {
+    pool: PgPool,
+    portfolio: Portfolio,
+    oracle: PriceOracle,
+}
+
+impl BalanceRecorder {
+    pub fn new_balance_recorder(pool: PgPool, portfolio: Portfolio) -> Self {
+        let oracle = PriceOracle::new_price_oracle(shared_solana_rpc());
+        Self { pool, portfolio, oracle }
+    }
+
+    pub async fn from_settings(pool: PgPool) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let portfolio = Portfolio::for_agent(pool.clone()).await?;
+        Ok(Self::new_balance_recorder(pool, portfolio))
+    }
+
+    /// Recorder built once per process, so snapshots share the agent wallet lookup and the
+    /// oracle's price cache; `pool` is only used the first time.
+    pub async fn shared(pool: PgPool) -> Result<&'static Self, Box<dyn Error + Send + Sync>> {
+        RECORDER.get_or_try_init(|| Self::from_settings(pool)).await
+    }
+
+    /// Records the current balance; the USD value is left empty when SOL cannot be priced.
+    pub async fn record_snapshot(&self) -> Result<BalanceSnapshot, BalanceHistoryError> {
+        let lamports = self
+            .portfolio
+            .sol_balance_lamports()
+            .await
+            .map_err(|e| BalanceHistoryError::Wallet(e.to_string()))?;
+        let balance_sol = lamports_to_sol(lamports);
+        let balance_usd = match self.oracle.sol_usd().await {
+            Ok(price) => Some(balance_sol * price.price_usd),
+            Err(e) => {
+                warn!("Failed to price SOL for the balance snapshot: {}", e);
+                None
+            }
+        };
+
+        // Stored as UTC whatever the session time zone, as `balance_candles` expects.
+        let row = sqlx::query!(
+            "INSERT INTO agent_balance_changes (created_at, amount, sol_amount) VALUES (NOW() AT TIME ZONE 'UTC', $1, $2) RETURNING id, created_at",
+            balance_usd,
+            balance_sol
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        info!("Agent balance {} SOL ({:?} USD)", balance_sol, balance_usd);
+        Ok(BalanceSnapshot { id: row.id, created_at: row.created_at, balance_sol, balance_usd })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn bucket(day: u32, sol: Option<Ohlc>, usd: Option<Ohlc>, samples: i64) -> BucketAggregate {
+        let start = NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        BucketAggregate { bucket: start, bucket_utc: start, sol, usd, samples }
+    }
+
+    #[test]
+    fn carries_the_last_close_into_empty_buckets() {
+        let traded = Ohlc { open: 1.0, high: 3.0, low: 0.5, close: 2.0 };
+        let candles = fill_gaps(
+            vec![bucket(1, None, None, 0), bucket(2, Some(traded), Some(traded), 4), bucket(3, None, None, 0)],
+            Some(1.5),
+            Some(150.0),
+        );
+
+        assert_eq!(candles[0].sol, Some(Ohlc::flat(1.5)));
+        assert_eq!(candles[0].usd, Some(Ohlc::flat(150.0)));
+        assert_eq!(candles[1].sol, Some(traded));
+        assert_eq!(candles[2].sol, Some(Ohlc::flat(2.0)));
+        assert_eq!(candles[2].usd, Some(Ohlc::flat(2.0)));
+        assert_eq!(candles[2].samples, 0);
+    }
+
+    #[test]
+    fn leaves_buckets_before_the_first_snapshot_empty() {
+        let candles = fill_gaps(vec![bucket(1, None, None, 0), bucket(2, Some(Ohlc::flat(1.0)), None, 1)], None, None);
+
+        assert_eq!(candles[0].sol, None);
+        assert_eq!(candles[0].usd, None);
+        assert_eq!(candles[1].sol, Some(Ohlc::flat(1.0)));
+        assert_eq!(candles[1].usd, None);
+    }
+
+    #[test]
+    fn carries_usd_independently_of_sol() {
+        let candles = fill_gaps(
+            vec![bucket(1, Some(Ohlc::flat(1.0)), None, 1), bucket(2, Some(Ohlc::flat(2.0)), Some(Ohlc::flat(300.0)), 1), bucket(3, Some(Ohlc::flat(3.0)), None, 1)],
+            None,
+            Some(120.0),
+        );
+
+        assert_eq!(candles[0].usd, Some(Ohlc::flat(120.0)));
+        assert_eq!(candles[1].usd, Some(Ohlc::flat(300.0)));
+        assert_eq!(candles[2].usd, Some(Ohlc::flat(300.0)));
+        assert_eq!(candles[2].sol, Some(Ohlc::flat(3.0)));
+    }
+}
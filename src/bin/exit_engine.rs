@@ -0,0 +1,22 @@
+//! Background exit engine: sells open positions when their take-profit, stop-loss,
+//! trailing stop or maximum hold rule fires, then announces the sell. Passes run as the
+//! `exit_positions` job under its scheduler lease, so several instances never sell at once.
+
+use llm_server::core::config::LlmSettings;
+use llm_server::core::db::get_db_pool;
+use llm_server::llm::provider::build_provider;
+use llm_server::scheduler::Scheduler;
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    env_logger::init();
+    dotenv::dotenv().ok();
+
+    let pool = get_db_pool().await;
+    let provider = build_provider(&LlmSettings::new_llm());
+    let scheduler = Scheduler::for_exit_engine(pool, provider).await.map_err(|e| anyhow::anyhow!(e))?;
+    info!("Exit engine started");
+    scheduler.run().await;
+    Ok(())
+}
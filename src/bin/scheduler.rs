@@ -0,0 +1,19 @@
+//! Background scheduler: runs the agent's recurring jobs (retweet checks, balance
+//! snapshots and sells) on their cron or interval schedules.
+
+use llm_server::core::config::LlmSettings;
+use llm_server::core::db::get_db_pool;
+use llm_server::llm::provider::build_provider;
+use llm_server::scheduler::Scheduler;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    env_logger::init();
+    dotenv::dotenv().ok();
+
+    let pool = get_db_pool().await;
+    let provider = build_provider(&LlmSettings::new_llm());
+    let scheduler = Scheduler::from_settings(pool, provider).await.map_err(|e| anyhow::anyhow!(e))?;
+    scheduler.run().await;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Stand-in for an external signer: holds the configured agent keys and answers the
+//! signer protocol over HTTP or a Unix socket, applying the `SIGNER_*` policy limits.
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use llm_server::core::config::{SMCSettings, SignerSettings, SolanaSettings};
+use llm_server::utils::key_loader::SecretString;
+use llm_server::utils::signer::{
+    handle_signer_request, is_authorized, serve_unix, LimitPolicy, LocalSigner, SignerRequest, TransactionSigner,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use structopt::StructOpt;
+use tracing::info;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "stub_signer")]
+struct Opt {
+    /// Listen on this Unix socket
+    #[structopt(long, parse(from_os_str))]
+    socket: Option<PathBuf>,
+
+    /// Listen for HTTP on this address, e.g. 127.0.0.1:7070
+    #[structopt(long)]
+    http: Option<String>,
+}
+
+async fn sign(
+    signer: web::Data<Arc<dyn TransactionSigner>>,
+    token: web::Data<SecretString>,
+    http_request: HttpRequest,
+    request: web::Json<SignerRequest>,
+) -> HttpResponse {
+    let authorization = http_request.headers().get("Authorization").and_then(|value| value.to_str().ok());
+    if !is_authorized(&token, authorization) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    HttpResponse::Ok().json(handle_signer_request(signer.get_ref().as_ref(), request.into_inner()).await)
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), anyhow::Error> {
+    env_logger::init();
+    let opt = Opt::from_args();
+
+    let settings = SignerSettings::new_signer();
+    let policy = Arc::new(LimitPolicy::from_settings(&settings));
+    let signer: Arc<dyn TransactionSigner> = Arc::new(LocalSigner::from_settings(
+        &SolanaSettings::new_solana(),
+        &SMCSettings::new_smc(),
+        policy,
+    ));
+    info!("Signing for Solana {:?}, EVM {:?}", signer.solana_pubkey(), signer.evm_address());
+
+    match (opt.socket, opt.http) {
+        (Some(path), None) => serve_unix(&path, signer).await?,
+        (None, Some(address)) => {
+            if settings.signer_auth_token.is_empty() {
+                anyhow::bail!("Set SIGNER_AUTH_TOKEN to serve over HTTP");
+            }
+            let data = web::Data::new(signer);
+            let token = web::Data::new(settings.signer_auth_token);
+            HttpServer::new(move || {
+                App::new().app_data(data.clone()).app_data(token.clone()).route("/", web::post().to(sign))
+            })
+                .bind(address)?
+                .run()
+                .await?
+        }
+        _ => anyhow::bail!("Pass exactly one of --socket or --http"),
+    }
+    Ok(())
+}
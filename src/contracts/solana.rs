@@ -1,19 +1,20 @@
-use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::utils::solana_rpc::{shared_solana_rpc, SolanaRpc};
 
 pub struct AgaveClient {
-    pub client: RpcClient,
+    pub rpc: Arc<SolanaRpc>,
 }
 
 impl AgaveClient {
     pub fn new() -> Self {
-        let rpc_url = "https://rpc.agave.io"; 
-        Self { client: RpcClient::new(rpc_url.to_string()) }
+        Self { rpc: shared_solana_rpc() }
     }
 
-    pub fn get_balance(&self, address: &str) -> Result<u64, String> {
+    pub async fn get_balance(&self, address: &str) -> Result<u64, String> {
         let pubkey = Pubkey::from_str(address).map_err(|_| "Invalid address".to_string())?;
-        self.client.get_balance(&pubkey).map_err(|e| e.to_string())
+        self.rpc.get_balance(&pubkey).await.map_err(|e| e.to_string())
     }
 }
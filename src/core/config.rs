@@ -1,6 +1,9 @@
 use dotenv::dotenv;
+use std::collections::HashMap;
 use std::env;
 
+use crate::utils::key_loader::SecretString;
+
 #[derive(Debug)]
 pub struct Config {
     pub api_v1_prefix: String,
@@ -108,8 +111,10 @@ pub struct SMCSettings {
     pub web3_provider: String,
     pub prize_pool_contract_address: String,
     pub bonding_contract_address: String,
-    pub owner_private_key: String,
-    pub prize_pool_admin_private_key: String,
+    /// Hex key or keystore path of the contract owner wallet.
+    pub owner_private_key: SecretString,
+    pub owner_keystore_password: Option<SecretString>,
+    pub prize_pool_admin_private_key: SecretString,
 }
 
 impl SMCSettings {
@@ -119,8 +124,9 @@ impl SMCSettings {
             web3_provider: "https://1rpc.io/sepolia".to_string(),
             prize_pool_contract_address: env::var("PRIZE_POOL_CONTRACT_ADDRESS").unwrap_or_default(),
             bonding_contract_address: env::var("BONDING_CONTRACT_ADDRESS").unwrap_or_default(),
-            owner_private_key: env::var("OWNER_PRIVATE_KEY").unwrap_or_default(),
-            prize_pool_admin_private_key: env::var("PRIZE_POOL_ADMIN_PRIVATE_KEY").unwrap_or_default(),
+            owner_private_key: SecretString::new(env::var("OWNER_PRIVATE_KEY").unwrap_or_default()),
+            owner_keystore_password: env::var("OWNER_KEYSTORE_PASSWORD").ok().map(SecretString::new),
+            prize_pool_admin_private_key: SecretString::new(env::var("PRIZE_POOL_ADMIN_PRIVATE_KEY").unwrap_or_default()),
         }
     }
 }
@@ -128,17 +134,125 @@ impl SMCSettings {
 #[derive(Debug)]
 pub struct SolanaSettings {
     pub rpc_url: String,
+    /// Endpoints tried in order on failover; `RPC_URL` alone when `RPC_URLS` is not set.
+    pub rpc_urls: Vec<String>,
+    pub rpc_timeout_ms: u64,
+    /// Timeout overrides per JSON-RPC method, e.g. `sendTransaction=60000`.
+    pub rpc_method_timeouts_ms: HashMap<String, u64>,
+    pub rpc_max_retries: u32,
+    /// First retry delay, doubled on every further attempt.
+    pub rpc_backoff_ms: u64,
+    /// How long an endpoint that failed stays out of rotation before its health is checked again.
+    pub rpc_health_recheck_secs: u64,
+    /// Delay between status polls of an outgoing transaction.
+    pub tx_poll_interval_ms: u64,
+    /// How many times an expired transaction is re-signed with a fresh blockhash before giving up.
+    pub tx_max_resubmits: i32,
+    /// How long an unfinished transaction goes without a status poll before recovery takes it over.
+    pub tx_recover_after_secs: u64,
+    /// Percentile of recent prioritization fees on the written accounts used as the unit price.
+    pub priority_fee_percentile: u8,
+    /// Upper bound of the priority fee of a single transaction, in lamports.
+    pub priority_fee_cap_lamports: u64,
+    /// Compute unit limit used when the transaction cannot be simulated.
+    pub compute_unit_limit: u32,
+    /// Headroom added to the simulated compute units, in percent.
+    pub compute_unit_margin_pct: u64,
     pub solana_config_path: String,
-    pub agent_keypair: String,
+    /// Keypair file path, keystore path or base58 secret of the agent wallet.
+    pub agent_keypair: SecretString,
+    /// Password of `agent_keypair` when it is an encrypted keystore.
+    pub agent_keypair_password: Option<SecretString>,
+    /// Tolerated price move between quote and execution, in basis points.
+    pub swap_slippage_bps: u64,
+    /// SOL spent on every approved shilling.
+    pub buy_amount_sol: f64,
+    /// Highest round-trip loss beyond pool fees, in percent, before a token counts as taxed.
+    pub max_round_trip_tax_pct: f64,
 }
 
 impl SolanaSettings {
     pub fn new_solana() -> Self {
         dotenv().ok();
+        let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+        let rpc_urls = env::var("RPC_URLS")
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>())
+            .ok()
+            .filter(|urls| !urls.is_empty())
+            .unwrap_or_else(|| vec![rpc_url.clone()]);
+        let rpc_method_timeouts_ms = env::var("RPC_METHOD_TIMEOUTS")
+            .unwrap_or_else(|_| "sendAndConfirmTransaction=60000,getProgramAccounts=30000".to_string())
+            .split(',')
+            .filter_map(|pair| {
+                let (method, ms) = pair.split_once('=')?;
+                Some((method.trim().to_string(), ms.trim().parse().ok()?))
+            })
+            .collect();
         Self {
-            rpc_url: env::var("RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+            rpc_url,
+            rpc_urls,
+            rpc_timeout_ms: env::var("RPC_TIMEOUT_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(10_000),
+            rpc_method_timeouts_ms,
+            rpc_max_retries: env::var("RPC_MAX_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(3),
+            rpc_backoff_ms: env::var("RPC_BACKOFF_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(250),
+            rpc_health_recheck_secs: env::var("RPC_HEALTH_RECHECK_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+            tx_poll_interval_ms: env::var("TX_POLL_INTERVAL_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(1_000),
+            tx_max_resubmits: env::var("TX_MAX_RESUBMITS").ok().and_then(|v| v.parse().ok()).unwrap_or(3),
+            tx_recover_after_secs: env::var("TX_RECOVER_AFTER_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60),
+            priority_fee_percentile: env::var("PRIORITY_FEE_PERCENTILE").ok().and_then(|v| v.parse().ok()).unwrap_or(75),
+            priority_fee_cap_lamports: env::var("PRIORITY_FEE_CAP_LAMPORTS").ok().and_then(|v| v.parse().ok()).unwrap_or(500_000),
+            compute_unit_limit: env::var("COMPUTE_UNIT_LIMIT").ok().and_then(|v| v.parse().ok()).unwrap_or(300_000),
+            compute_unit_margin_pct: env::var("COMPUTE_UNIT_MARGIN_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(20),
             solana_config_path: env::var("SOLANA_CONFIG_PATH").unwrap_or_else(|_| "configs/mainnet_raydium.json".to_string()),
-            agent_keypair: env::var("AGENT_KEYPAIR").unwrap_or_default(),
+            agent_keypair: SecretString::new(env::var("AGENT_KEYPAIR").unwrap_or_default()),
+            agent_keypair_password: env::var("AGENT_KEYPAIR_PASSWORD").ok().map(SecretString::new),
+            swap_slippage_bps: env::var("SWAP_SLIPPAGE_BPS").ok().and_then(|v| v.parse().ok()).unwrap_or(100),
+            buy_amount_sol: env::var("BUY_AMOUNT_SOL").ok().and_then(|v| v.parse().ok()).unwrap_or(0.0001),
+            max_round_trip_tax_pct: env::var("MAX_ROUND_TRIP_TAX_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(5.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SignerSettings {
+    /// `local` signs in process with the configured keys, `remote` delegates to `signer_url`.
+    pub mode: String,
+    /// `http://host:port` or `unix:/path/to/socket` of the remote signer.
+    pub signer_url: String,
+    pub signer_timeout_ms: u64,
+    /// Bearer token shared with an HTTP remote signer.
+    pub signer_auth_token: SecretString,
+    /// Programs a Solana transaction may invoke; any program when empty.
+    pub allowed_programs: Vec<String>,
+    /// Programs a Solana transaction must never invoke.
+    pub blocked_programs: Vec<String>,
+    /// Most SOL a single transaction may transfer out of the signer.
+    pub max_sol_per_transaction: Option<f64>,
+    /// Most ETH a single EVM transaction may send.
+    pub max_eth_per_transaction: Option<f64>,
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl SignerSettings {
+    pub fn new_signer() -> Self {
+        dotenv().ok();
+        Self {
+            mode: env::var("SIGNER_MODE").unwrap_or_else(|_| "local".to_string()),
+            signer_url: env::var("SIGNER_URL").unwrap_or_else(|_| "unix:/tmp/agent-signer.sock".to_string()),
+            signer_timeout_ms: env::var("SIGNER_TIMEOUT_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(5_000),
+            signer_auth_token: SecretString::new(env::var("SIGNER_AUTH_TOKEN").unwrap_or_default()),
+            allowed_programs: env_list("SIGNER_ALLOWED_PROGRAMS"),
+            blocked_programs: env_list("SIGNER_BLOCKED_PROGRAMS"),
+            max_sol_per_transaction: env::var("SIGNER_MAX_SOL_PER_TX").ok().and_then(|v| v.parse().ok()),
+            max_eth_per_transaction: env::var("SIGNER_MAX_ETH_PER_TX").ok().and_then(|v| v.parse().ok()),
         }
     }
 }
@@ -150,6 +264,13 @@ pub struct TwitterSettings {
     pub access_token: String,
     pub access_secret: String,
     pub bearer_token: String,
+    /// Twitter user id of the agent account.
+    pub user_id: String,
+    pub api_url: String,
+    /// `api` for the Twitter API, `fixture` for the fixture-backed fake.
+    pub client: String,
+    /// JSON fixture read by the fake client.
+    pub fixture_path: String,
 }
 
 impl TwitterSettings {
@@ -161,6 +282,233 @@ impl TwitterSettings {
             access_token: env::var("ACCESS_TOKEN").unwrap_or_default(),
             access_secret: env::var("ACCESS_SECRET").unwrap_or_default(),
             bearer_token: env::var("BEARER_TOKEN").unwrap_or_default(),
+            user_id: env::var("TWITTER_USER_ID").unwrap_or_default(),
+            api_url: env::var("TWITTER_API_URL").unwrap_or_else(|_| "https://api.twitter.com".to_string()),
+            client: env::var("TWITTER_CLIENT").unwrap_or_else(|_| "api".to_string()),
+            fixture_path: env::var("TWITTER_FIXTURE_PATH").unwrap_or_else(|_| "fixtures/twitter.json".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LlmSettings {
+    pub provider: String,
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+    pub post_model: String,
+    pub max_agent_steps: usize,
+    pub parallel_tool_calls: bool,
+}
+
+impl LlmSettings {
+    pub fn new_llm() -> Self {
+        dotenv().ok();
+        Self {
+            provider: env::var("LLM_PROVIDER").unwrap_or_else(|_| "openai".to_string()),
+            api_key: env::var("LLM_API_KEY").or_else(|_| env::var("OPENAI_API_KEY")).unwrap_or_default(),
+            base_url: env::var("LLM_BASE_URL").unwrap_or_else(|_| "http://localhost:8000/v1".to_string()),
+            model: env::var("LLM_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string()),
+            post_model: env::var("LLM_POST_MODEL").unwrap_or_else(|_| "gpt-4o".to_string()),
+            max_agent_steps: env::var("LLM_MAX_AGENT_STEPS").ok().and_then(|v| v.parse().ok()).unwrap_or(6),
+            parallel_tool_calls: env::var("LLM_PARALLEL_TOOL_CALLS").map(|v| v == "true").unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TradeRulesSettings {
+    pub min_fdv_usd: f64,
+    pub min_age_hours: f64,
+    pub min_transactions_24h: u64,
+    pub min_holders: u64,
+    pub max_dev_allocation_pct: f64,
+    pub trade_cooldown_minutes: i64,
+    /// Rules that hard-block `approveShilling` when they fail (comma separated rule names).
+    /// `freeze_authority` is advisory by default since many legitimate mints keep one.
+    pub mandatory_rules: Vec<String>,
+}
+
+impl TradeRulesSettings {
+    pub fn new_trade_rules() -> Self {
+        dotenv().ok();
+        let parse_or = |key: &str, default: f64| env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            min_fdv_usd: parse_or("RULE_MIN_FDV_USD", 200_000.0),
+            min_age_hours: parse_or("RULE_MIN_AGE_HOURS", 48.0),
+            min_transactions_24h: parse_or("RULE_MIN_TRANSACTIONS_24H", 200.0) as u64,
+            min_holders: parse_or("RULE_MIN_HOLDERS", 200.0) as u64,
+            max_dev_allocation_pct: parse_or("RULE_MAX_DEV_ALLOCATION_PCT", 5.0),
+            trade_cooldown_minutes: parse_or("RULE_TRADE_COOLDOWN_MINUTES", 60.0) as i64,
+            mandatory_rules: env::var("RULE_MANDATORY")
+                .unwrap_or_else(|_| "fdv,age,transactions_24h,holders,dev_allocation,not_in_portfolio,cooldown,balance".to_string())
+                .split(',')
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PriceOracleSettings {
+    /// Quotes last updated longer ago than this are ignored.
+    pub max_quote_age_secs: i64,
+    /// How long a computed price is served from Redis.
+    pub cache_ttl_secs: u64,
+    /// Quotes further than this from the median of all sources are dropped, in percent.
+    pub max_deviation_pct: f64,
+    /// Fewest agreeing sources needed for a price; quotes of the same pool count once.
+    pub min_sources: usize,
+    pub alchemy_api_key: Option<String>,
+    pub alchemy_network: String,
+    /// Raydium SOL/USDC pool used to price SOL on chain.
+    pub sol_usdc_pool: String,
+}
+
+impl PriceOracleSettings {
+    pub fn new_price_oracle() -> Self {
+        dotenv().ok();
+        Self {
+            max_quote_age_secs: env::var("PRICE_MAX_QUOTE_AGE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300),
+            cache_ttl_secs: env::var("PRICE_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+            max_deviation_pct: env::var("PRICE_MAX_DEVIATION_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(5.0),
+            min_sources: env::var("PRICE_MIN_SOURCES").ok().and_then(|v| v.parse().ok()).unwrap_or(2),
+            alchemy_api_key: env::var("ALCHEMY_API_KEY").ok().filter(|key| !key.is_empty()),
+            alchemy_network: env::var("ALCHEMY_SOLANA_NETWORK").unwrap_or_else(|_| "solana-mainnet".to_string()),
+            sol_usdc_pool: env::var("SOL_USDC_POOL")
+                .unwrap_or_else(|_| "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DexScreenerSettings {
+    pub base_url: String,
+}
+
+impl DexScreenerSettings {
+    pub fn new_dexscreener() -> Self {
+        dotenv().ok();
+        Self {
+            base_url: env::var("DEXSCREENER_API_URL").unwrap_or_else(|_| "https://api.dexscreener.com".to_string()),
+        }
+    }
+}
+
+/// Global exit rules of the exit engine; a rule left unset never fires.
+#[derive(Debug)]
+pub struct ExitSettings {
+    /// Sell once the position is up this much, in percent.
+    pub take_profit_pct: Option<f64>,
+    /// Sell once the position is down this much, in percent.
+    pub stop_loss_pct: Option<f64>,
+    /// Sell once the position falls this far below its peak value, in percent.
+    pub trailing_stop_pct: Option<f64>,
+    /// Sell positions held longer than this.
+    pub max_hold_minutes: Option<i64>,
+    pub poll_interval_secs: u64,
+}
+
+impl ExitSettings {
+    pub fn new_exit() -> Self {
+        dotenv().ok();
+        Self {
+            // Thresholds that are not positive would sell every position at once, so they count as unset.
+            take_profit_pct: env::var("EXIT_TAKE_PROFIT_PCT").ok().and_then(|v| v.parse().ok()).filter(|v: &f64| *v > 0.0),
+            stop_loss_pct: env::var("EXIT_STOP_LOSS_PCT").ok().and_then(|v| v.parse().ok()).filter(|v: &f64| *v > 0.0),
+            trailing_stop_pct: env::var("EXIT_TRAILING_STOP_PCT").ok().and_then(|v| v.parse().ok()).filter(|v: &f64| *v > 0.0),
+            max_hold_minutes: env::var("EXIT_MAX_HOLD_MINUTES").ok().and_then(|v| v.parse().ok()).filter(|v: &i64| *v > 0),
+            poll_interval_secs: env::var("EXIT_POLL_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PayoutSettings {
+    /// Part of the realized profit sent to the shiller, in percent.
+    pub share_pct: f64,
+    /// Shares below this are not sent, in SOL.
+    pub min_payout_sol: f64,
+    /// Transfers attempted per payout before it is left failed.
+    pub max_attempts: i32,
+    /// Wait before retrying a failed payout.
+    pub retry_interval_secs: i64,
+}
+
+impl PayoutSettings {
+    pub fn new_payout() -> Self {
+        dotenv().ok();
+        Self {
+            share_pct: env::var("PAYOUT_SHARE_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(50.0),
+            min_payout_sol: env::var("PAYOUT_MIN_SOL").ok().and_then(|v| v.parse().ok()).unwrap_or(0.001),
+            max_attempts: env::var("PAYOUT_MAX_ATTEMPTS").ok().and_then(|v| v.parse().ok()).unwrap_or(5),
+            retry_interval_secs: env::var("PAYOUT_RETRY_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StatisticsSettings {
+    /// How long dashboard aggregations are served from Redis.
+    pub cache_ttl_secs: u64,
+    /// Buckets returned by the agent balance charts.
+    pub balance_history_points: i64,
+    /// IANA timezone balance buckets are aligned to when a request does not name one.
+    pub timezone: String,
+}
+
+impl StatisticsSettings {
+    pub fn new_statistics() -> Self {
+        dotenv().ok();
+        Self {
+            cache_ttl_secs: env::var("STATISTICS_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60),
+            balance_history_points: env::var("STATISTICS_BALANCE_HISTORY_POINTS").ok().and_then(|v| v.parse().ok()).unwrap_or(60),
+            timezone: env::var("STATISTICS_TIMEZONE").unwrap_or_else(|_| "UTC".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SchedulerSettings {
+    /// Key expected in the `X-Admin-Key` header of the admin endpoints; empty disables them.
+    pub admin_api_key: SecretString,
+    /// How long a lease outlives its last renewal; a running job renews it every third of that.
+    pub lease_ttl_secs: u64,
+    /// How often due jobs are looked for.
+    pub poll_interval_secs: u64,
+    pub check_retwitts_schedule: String,
+    pub sell_tokens_schedule: String,
+    pub log_agent_balance_schedule: String,
+    pub recover_transactions_schedule: String,
+}
+
+impl SchedulerSettings {
+    pub fn new_scheduler() -> Self {
+        dotenv().ok();
+        Self {
+            admin_api_key: SecretString::new(env::var("ADMIN_API_KEY").unwrap_or_default()),
+            lease_ttl_secs: env::var("SCHEDULER_LEASE_TTL_SECS").ok().and_then(|v| v.parse().ok()).filter(|ttl| *ttl >= 3).unwrap_or(60),
+            poll_interval_secs: env::var("SCHEDULER_POLL_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(5),
+            check_retwitts_schedule: env::var("SCHEDULE_CHECK_RETWITTS").unwrap_or_else(|_| "*/10 * * * *".to_string()),
+            sell_tokens_schedule: env::var("SCHEDULE_SELL_TOKENS").unwrap_or_else(|_| "0 0 * * *".to_string()),
+            log_agent_balance_schedule: env::var("SCHEDULE_LOG_AGENT_BALANCE").unwrap_or_else(|_| "@every 5m".to_string()),
+            recover_transactions_schedule: env::var("SCHEDULE_RECOVER_TRANSACTIONS").unwrap_or_else(|_| "@every 1m".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RetweetCreditSettings {
+    /// Latest agent posts whose retweets earn credits.
+    pub lookback_posts: usize,
+}
+
+impl RetweetCreditSettings {
+    pub fn new_retweet_credit() -> Self {
+        dotenv().ok();
+        Self {
+            lookback_posts: env::var("RETWEET_LOOKBACK_POSTS").ok().and_then(|v| v.parse().ok()).unwrap_or(20),
         }
     }
 }
@@ -15,6 +15,8 @@ impl std::fmt::Display for ChatErrors {
     }
 }
 
+impl std::error::Error for ChatErrors {}
+
 #[derive(Debug)]
 pub enum LLMErrors {
     CallFunctionError,
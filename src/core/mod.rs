@@ -2,3 +2,5 @@ pub mod db;
 pub mod exceptions;
 pub mod errors;
 pub mod config;
+#[cfg(test)]
+pub mod test_fixtures;
@@ -0,0 +1,42 @@
+use sqlx::PgPool;
+
+/// The `users`, `chats` and `credits` tables as they stood before `migrations/`, which
+/// expects them to exist already.
+const BASE_TABLES: &str = r#"
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    wallet TEXT NOT NULL UNIQUE,
+    twitter_id TEXT,
+    restricted_until TIMESTAMP
+);
+CREATE TABLE chats (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    uuid TEXT NOT NULL UNIQUE,
+    name TEXT,
+    user_id INT NOT NULL REFERENCES users (id),
+    state TEXT NOT NULL DEFAULT 'active'
+);
+CREATE TABLE credits (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users (id),
+    twitter_post_id TEXT,
+    is_used BOOLEAN NOT NULL DEFAULT false
+);
+"#;
+
+/// Creates `users`, `chats` and `credits` without the unique retweet key, as a database
+/// about to run the credits migration has them.
+pub async fn create_base_tables(pool: &PgPool) {
+    sqlx::raw_sql(BASE_TABLES).execute(pool).await.unwrap();
+}
+
+/// Creates `users`, `chats` and `credits` with the unique retweet key from the migration.
+pub async fn create_user_tables(pool: &PgPool) {
+    create_base_tables(pool).await;
+    sqlx::raw_sql(include_str!("../../migrations/20261018000000_credits_unique_retweet.sql"))
+        .execute(pool)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+
+/// A credit given to a user for retweeting one of the agent's posts.
+#[derive(Debug, Clone, Serialize)]
+pub struct AwardedCredit {
+    pub credit_id: i32,
+    pub user_id: i32,
+    pub wallet: String,
+    pub twitter_id: String,
+    pub twitter_post_id: String,
+}
+
+/// What was found for one post.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostRetweets {
+    pub twitter_post_id: String,
+    pub retweeters: usize,
+    /// Retweeters with a linked account.
+    pub matched_users: usize,
+    pub awarded: usize,
+    /// Set when the retweeters of the post could not be fetched.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetweetCreditReport {
+    pub posts: Vec<PostRetweets>,
+    pub awarded: Vec<AwardedCredit>,
+    /// Retweets that had already earned their credit.
+    pub already_credited: usize,
+    /// Set when Twitter rate limited the run; the posts after the last one reported are
+    /// checked on a later run.
+    pub retry_after_secs: Option<u64>,
+}
+
+impl RetweetCreditReport {
+    pub fn summary(&self) -> String {
+        let failed = self.posts.iter().filter(|post| post.error.is_some()).count();
+        let summary = format!(
+            "Checked {} posts ({} failed), awarded {} credits, {} already credited",
+            self.posts.len(),
+            failed,
+            self.awarded.len(),
+            self.already_credited
+        );
+        match self.retry_after_secs {
+            Some(secs) => format!("{}, rate limited for {} seconds", summary, secs),
+            None => summary,
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::core::config::RetweetCreditSettings;
+use crate::credits::models::{AwardedCredit, PostRetweets, RetweetCreditReport};
+use crate::utils::error::{CreditError, TwitterError};
+use crate::utils::twitter_client::{twitter_client_from_settings, TwitterClient};
+
+/// Gives users one credit per retweet of the agent's recent posts.
+///
+/// Retweeters are matched to users by `users.twitter_id`. A (user, post) pair earns a
+/// single credit however often the pipeline runs, so runs can be repeated freely.
+pub struct RetweetCreditService {
+    pool: PgPool,
+    twitter: Arc<dyn TwitterClient>,
+    settings: RetweetCreditSettings,
+}
+
+impl RetweetCreditService {
+    pub fn new_retweet_credit_service(pool: PgPool, twitter: Arc<dyn TwitterClient>, settings: RetweetCreditSettings) -> Self {
+        Self { pool, twitter, settings }
+    }
+
+    /// Service using the Twitter client selected by `TWITTER_CLIENT`.
+    pub fn from_settings(pool: PgPool) -> Result<Self, TwitterError> {
+        Ok(Self::new_retweet_credit_service(pool, twitter_client_from_settings()?, RetweetCreditSettings::new_retweet_credit()))
+    }
+
+    /// Checks the latest `RETWEET_LOOKBACK_POSTS` posts. A post whose retweeters cannot be
+    /// fetched is reported and skipped; it is checked again on the next run. A rate limit
+    /// ends the run early with `retry_after_secs` set; one before any post is an error.
+    pub async fn award_retweet_credits(&self) -> Result<RetweetCreditReport, CreditError> {
+        let posts = self.twitter.recent_posts(self.settings.lookback_posts).await?;
+        let mut report = RetweetCreditReport::default();
+
+        for post in posts {
+            let mut retweeters = match self.twitter.retweeters(&post.id).await {
+                Ok(retweeters) => retweeters,
+                // Every later request would be refused too; keep what was awarded so far.
+                Err(TwitterError::RateLimited(retry_after_secs)) => {
+                    warn!("Twitter rate limit reached at post {}, retrying in {} seconds", post.id, retry_after_secs);
+                    report.retry_after_secs = Some(retry_after_secs);
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to fetch retweeters of post {}: {}", post.id, e);
+                    report.posts.push(PostRetweets {
+                        twitter_post_id: post.id,
+                        retweeters: 0,
+                        matched_users: 0,
+                        awarded: 0,
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            retweeters.sort();
+            retweeters.dedup();
+
+            let users = sqlx::query!(
+                r#"SELECT id, wallet, twitter_id AS "twitter_id!" FROM users WHERE twitter_id = ANY($1)"#,
+                &retweeters
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let mut awarded = 0;
+            for user in &users {
+                match self.award_credit(user.id, &post.id).await? {
+                    Some(credit_id) => {
+                        awarded += 1;
+                        report.awarded.push(AwardedCredit {
+                            credit_id,
+                            user_id: user.id,
+                            wallet: user.wallet.clone(),
+                            twitter_id: user.twitter_id.clone(),
+                            twitter_post_id: post.id.clone(),
+                        });
+                    }
+                    None => report.already_credited += 1,
+                }
+            }
+            report.posts.push(PostRetweets {
+                twitter_post_id: post.id,
+                retweeters: retweeters.len(),
+                matched_users: users.len(),
+                awarded,
+                error: None,
+            });
+        }
+
+        info!("Retweet credits: {}", report.summary());
+        Ok(report)
+    }
+
+    /// Inserts the credit of a retweet unless it exists; `None` if it was already given.
+    /// Relies on `UNIQUE (user_id, twitter_post_id)` on `credits`, see `models::credit`.
+    pub async fn award_credit(&self, user_id: i32, twitter_post_id: &str) -> Result<Option<i32>, CreditError> {
+        let credit_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO credits (user_id, twitter_post_id, is_used)
+            VALUES ($1, $2, false)
+            ON CONFLICT (user_id, twitter_post_id) DO NOTHING
+            RETURNING id
+            "#,
+            user_id,
+            twitter_post_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(credit_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::core::test_fixtures::{create_base_tables, create_user_tables};
+    use crate::utils::twitter_client::{FixtureTwitterClient, TwitterPost};
+
+    const FIXTURE: &str = r#"{"posts": [
+        {"id": "2", "text": "newest", "retweeters": ["42"]},
+        {"id": "1", "text": "oldest", "retweeters": ["42", "43", "42", "99"]}
+    ]}"#;
+
+    /// `users` and `credits`, with the unique key the service relies on.
+    async fn setup(pool: &PgPool) {
+        create_user_tables(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO users (wallet, twitter_id) VALUES ('wallet-a', '42'), ('wallet-b', '43'), ('wallet-c', NULL);
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn service(pool: PgPool, twitter: Arc<dyn TwitterClient>) -> RetweetCreditService {
+        RetweetCreditService::new_retweet_credit_service(pool, twitter, RetweetCreditSettings { lookback_posts: 10 })
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn awards_one_credit_per_retweet(pool: PgPool) {
+        setup(&pool).await;
+        let service = service(pool.clone(), Arc::new(FixtureTwitterClient::from_json(FIXTURE).unwrap()));
+
+        let first = service.award_retweet_credits().await.unwrap();
+        assert_eq!(first.awarded.len(), 3);
+        assert_eq!(first.already_credited, 0);
+        assert_eq!(first.posts[1].retweeters, 3);
+        assert_eq!(first.posts[1].matched_users, 2);
+
+        let second = service.award_retweet_credits().await.unwrap();
+        assert!(second.awarded.is_empty());
+        assert_eq!(second.already_credited, 3);
+
+        let credits = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM credits").fetch_one(&pool).await.unwrap();
+        assert_eq!(credits, 3);
+    }
+
+    /// Serves the fixture's posts but is rate limited on the retweeters of `limited_post`.
+    struct RateLimitedClient {
+        fixture: FixtureTwitterClient,
+        limited_post: &'static str,
+    }
+
+    #[async_trait]
+    impl TwitterClient for RateLimitedClient {
+        async fn recent_posts(&self, limit: usize) -> Result<Vec<TwitterPost>, TwitterError> {
+            self.fixture.recent_posts(limit).await
+        }
+
+        async fn retweeters(&self, post_id: &str) -> Result<Vec<String>, TwitterError> {
+            if post_id == self.limited_post {
+                return Err(TwitterError::RateLimited(60));
+            }
+            self.fixture.retweeters(post_id).await
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn stops_at_a_rate_limit_and_keeps_earlier_credits(pool: PgPool) {
+        setup(&pool).await;
+        let twitter = RateLimitedClient { fixture: FixtureTwitterClient::from_json(FIXTURE).unwrap(), limited_post: "1" };
+        let report = service(pool, Arc::new(twitter)).award_retweet_credits().await.unwrap();
+
+        assert_eq!(report.retry_after_secs, Some(60));
+        assert_eq!(report.posts.len(), 1);
+        assert_eq!(report.awarded.len(), 1);
+        assert_eq!(report.awarded[0].twitter_post_id, "2");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn migration_keeps_one_credit_per_retweet(pool: PgPool) {
+        create_base_tables(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO users (wallet, twitter_id) VALUES ('wallet-a', '42'), ('wallet-b', '43');
+            INSERT INTO credits (user_id, twitter_post_id, is_used) VALUES
+                (1, '1', false), (1, '1', true), (1, '1', true),
+                (1, '2', false), (1, '2', false),
+                (2, '1', false),
+                (2, NULL, false), (2, NULL, false);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::raw_sql(include_str!("../../migrations/20261018000000_credits_unique_retweet.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let credits = sqlx::query_as::<_, (i32, i32, Option<String>, bool)>(
+            "SELECT id, user_id, twitter_post_id, is_used FROM credits ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            credits,
+            vec![
+                (2, 1, Some("1".to_string()), true),
+                (4, 1, Some("2".to_string()), false),
+                (6, 2, Some("1".to_string()), false),
+                (7, 2, None, false),
+                (8, 2, None, false),
+            ]
+        );
+
+        let service = service(pool, Arc::new(FixtureTwitterClient::from_json(FIXTURE).unwrap()));
+        assert_eq!(service.award_credit(1, "2").await.unwrap(), None);
+    }
+}
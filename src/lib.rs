@@ -1,9 +1,18 @@
 mod api;
 mod contracts;
 pub mod core;
+pub mod credits;
 pub mod llm;
 pub mod utils;
 pub mod models;
+pub mod balance_history;
+pub mod payouts;
+pub mod pnl;
+pub mod portfolio;
+pub mod positions;
+pub mod scheduler;
+pub mod statistics;
 pub mod alchemy;
 pub mod price_forecasting;
-pub mod strategy_analysis;
\ No newline at end of file
+pub mod strategy_analysis;
+pub mod trade_rules;
\ No newline at end of file
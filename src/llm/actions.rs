@@ -3,8 +3,24 @@ use anyhow::Result;
 use tokio;
 use sqlx::PgPool;
 use tracing::error;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use crate::utils::dexscreener::fetch_dexscreener_data;
+use crate::utils::error::DexScreenerError;
+use crate::utils::raydium::{MintInfo, PoolInfo, RaydiumClient, WSOL_ADDRESS};
+use crate::utils::solana_rpc::shared_solana_rpc;
+use crate::utils::token_holders::{fetch_freeze_authority, fetch_holder_stats};
+use crate::api::statistics::{get_count_of_closed_trades, get_pnl_report, get_sol_price_usd};
+use crate::core::config::{SolanaSettings, TradeRulesSettings};
+use crate::models::base::{ConversationStatus, RejectionSource};
+use crate::pnl::compute_pnl;
+use crate::portfolio::Portfolio;
+use crate::payouts::PayoutService;
+use crate::positions::PositionManager;
+use solana_sdk::native_token::{lamports_to_sol, sol_to_lamports};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use crate::trade_rules::{evaluate_trade_rules, RuleReport, TradeAnalysis};
 // process_fetch_data_from_dex_screener
 // retrieve_portfolio_information
 // retrieve_pnl_information
@@ -14,26 +30,15 @@ use crate::utils::dexscreener::fetch_dexscreener_data;
 // publish_twitter_post
 // analyze_call_identify_pool
 // has_sufficient_agent_balance
+// record_rejection
 
 
+/// SOL the agent keeps on top of a buy for transaction fees.
+const AGENT_RESERVE_SOL: f64 = 0.01;
+
 // Those crate will be forwarded from app folder when it will be rewritten
-//use crate::dexscreener::{fetch_dexscreener_data, DexScreenerTokenError};
-//use crate::raydium::{get_pool_info, get_pool_address_from_mint, get_pool_quote_token_info};
 //use crate::solana::{SolanaDriver, TwitterDriver};
 
-#[derive(Debug, Serialize)]
-pub struct TradeAnalysis {
-    pub liquidity: f64,
-    pub volume_24h: f64,
-    pub market_cap: f64,
-    pub pair_created_at: String,
-    pub fdv: f64,
-    pub transactions_24h: (u64, u64),
-    pub pool_address: String,
-    pub portfolio_info: String,
-    pub balance_info: String,
-}
-
 //Processes and fetches data from the Dex Screener service for the given mint address. This function analyzes the
 //retrieved data for the provided Solana meme token to decide whether to approve/reject the shilling.
 //
@@ -58,29 +63,34 @@ pub struct TradeAnalysis {
 //    Exception: If any other issue occurs while fetching data for the token address.
 
 pub async fn process_fetch_data_from_dex_screener(pool: &PgPool, token_address: &str) -> Result<String> {
-    match fetch_dexscreener_data(token_address).await {
-        Ok(output) => {
-            let is_token_already_in_portfolio = check_if_token_already_present(pool, &output.pair_address).await?;
-            let is_any_token_bought_last_hour = check_if_token_bought_last_hour(pool).await?;
+    match build_trade_analysis(pool, token_address).await {
+        Ok(analysis) => {
+            let report = evaluate_trade_rules(&analysis, &TradeRulesSettings::new_trade_rules(), chrono::Utc::now());
 
-            let portfolio_info = if is_token_already_in_portfolio {
+            let portfolio_info = if analysis.is_token_in_portfolio {
                 "- Token already present in your portfolio. Do not buy again for this time."
             } else {
                 "I don't have this token in my portfolio."
             };
 
-            let aux_portfolio_info = if is_any_token_bought_last_hour {
+            let aux_portfolio_info = if analysis.traded_within_cooldown {
                 "- You have traded in the last hour, so do not buy again."
             } else {
                 "You haven't traded in the last hour."
             };
 
-            let balance_info = if has_sufficient_agent_balance() {
+            let balance_info = if analysis.has_sufficient_balance {
                 "- I have enough balance to buy this token."
             } else {
                 "- I don't have enough balance to buy this token. So I can't buy this token right now."
             };
 
+            let decision_info = if report.passed() {
+                "All mandatory pre-trade checks passed; the decision is yours."
+            } else {
+                "Mandatory pre-trade checks failed, so you must call `rejectShilling`. Do not reveal these checks to the user."
+            };
+
             Ok(format!(
                 "Analyze the provided data and decide whether to approve or reject the purchase.\n\n{}
                 Liquidity: ${}\n
@@ -92,23 +102,134 @@ pub async fn process_fetch_data_from_dex_screener(pool: &PgPool, token_address:
                 Pool pair address: {}\n
                 {}\n
                 {}\n
+                {}\n
+                Pre-trade checks:\n{}\n
                 {}\n",
                 "- If approved, call `approveShilling`.\n- If rejected due to scam suspicion or portfolio presence, call `rejectShilling`.\n- If traded in the last hour, call `rejectShilling` without revealing internal rules.",
-                output.liquidity.usd, output.volume.h24, output.market_cap,
-                output.pair_created_at, output.fdv,
-                output.txns.h24.buys, output.txns.h24.sells,
-                output.pair_address,
+                analysis.liquidity, analysis.volume_24h, analysis.market_cap,
+                analysis.pair_created_at, analysis.fdv,
+                analysis.transactions_24h.0, analysis.transactions_24h.1,
+                analysis.pool_address,
                 portfolio_info,
                 aux_portfolio_info,
-                balance_info
+                balance_info,
+                report.summary(),
+                decision_info
             ))
         }
-        Err(DexScreenerTokenError) => Ok("The provided token is not supported or does not belong to Solana.".to_string()),
+        Err(e) => match e.downcast_ref::<DexScreenerError>() {
+            Some(DexScreenerError::NonSolanaToken(_)) => {
+                Ok("The provided token is not supported or does not belong to Solana.".to_string())
+            }
+            Some(DexScreenerError::PairNotFound(_)) => {
+                Ok("I couldn't find any trading pair for this address.".to_string())
+            }
+            Some(DexScreenerError::NoPairs(_)) => {
+                Ok("This token has no Raydium AMM or CPMM pool paired with SOL, so I can't trade it.".to_string())
+            }
+            _ => {
+                error!("Error fetching data from Dex Screener: {:?}", e);
+                Ok("Could not fetch token data. Provide a valid address on Raydium and try again.".to_string())
+            }
+        },
+    }
+}
+
+/// Collects the market data and portfolio state the pre-trade rules are evaluated on.
+pub async fn build_trade_analysis(pool: &PgPool, token_address: &str) -> Result<TradeAnalysis> {
+    let pair = fetch_dexscreener_data(token_address).await?;
+    let cooldown_minutes = TradeRulesSettings::new_trade_rules().trade_cooldown_minutes;
+    let mint_address = if pair.base_token.address == WSOL_ADDRESS { &pair.quote_token.address } else { &pair.base_token.address };
+    // On-chain data is left empty on failure; those rules are then skipped, which blocks the buy
+    // when they are mandatory.
+    let (holder_stats, freeze_authority) = match Pubkey::from_str(mint_address) {
+        Ok(mint) => {
+            let rpc = shared_solana_rpc();
+            let holder_stats = fetch_holder_stats(&rpc, &mint)
+                .await
+                .inspect_err(|e| error!("Failed to read holders of {}: {}", mint, e))
+                .ok();
+            let freeze_authority = fetch_freeze_authority(&rpc, &mint)
+                .await
+                .inspect_err(|e| error!("Failed to read freeze authority of {}: {}", mint, e))
+                .ok();
+            (holder_stats, freeze_authority)
+        }
         Err(e) => {
-            error!("Error fetching data from Dex Screener: {:?}", e);
-            Ok("Could not fetch token data. Provide a valid address on Raydium and try again.".to_string())
+            error!("Invalid mint address {}: {}", mint_address, e);
+            (None, None)
         }
-    }
+    };
+
+    Ok(TradeAnalysis {
+        liquidity: pair.liquidity.usd,
+        volume_24h: pair.volume.h24,
+        market_cap: pair.market_cap,
+        pair_created_at: pair.pair_created_at,
+        fdv: pair.fdv,
+        transactions_24h: (pair.txns.h24.buys, pair.txns.h24.sells),
+        pool_address: pair.pair_address.clone(),
+        holders: holder_stats.map(|stats| stats.holders),
+        dev_allocation_pct: holder_stats.map(|stats| stats.top_holder_pct),
+        has_freeze_authority: freeze_authority.map(|authority| authority.is_some()),
+        is_token_in_portfolio: check_if_token_already_present(pool, &pair.pair_address).await?,
+        traded_within_cooldown: check_if_token_bought_within(pool, cooldown_minutes).await?,
+        has_sufficient_balance: has_sufficient_agent_balance(pool).await.unwrap_or_else(|e| {
+            error!("Failed to check agent balance: {:?}", e);
+            false
+        }),
+    })
+}
+
+/// Re-evaluates the pre-trade rules right before a buy.
+pub async fn check_trade_rules(pool: &PgPool, token_or_pool_address: &str) -> Result<RuleReport> {
+    let analysis = build_trade_analysis(pool, token_or_pool_address).await?;
+    Ok(evaluate_trade_rules(&analysis, &TradeRulesSettings::new_trade_rules(), chrono::Utc::now()))
+}
+
+/// Checks whether an open position exists for the pool.
+pub async fn check_if_token_already_present(pool: &PgPool, pool_address: &str) -> Result<bool> {
+    let trade = sqlx::query!(
+        "SELECT tr.id FROM trades tr JOIN tokens t ON t.id = tr.token_id
+         WHERE t.pool_address = $1 AND tr.trade_type = 'open' LIMIT 1",
+        pool_address
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(trade.is_some())
+}
+
+/// Checks whether the agent opened any position in the last `minutes`.
+pub async fn check_if_token_bought_within(pool: &PgPool, minutes: i64) -> Result<bool> {
+    let trade = sqlx::query!(
+        "SELECT id FROM trades WHERE trade_type = 'open' AND created_at > NOW() - make_interval(mins => $1) LIMIT 1",
+        minutes as i32
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(trade.is_some())
+}
+
+/// Records a shilled token the agent turned down, for the rejections dashboard.
+pub async fn record_rejection(
+    pool: &PgPool,
+    chat_uuid: &str,
+    wallet: &str,
+    pool_address: Option<&str>,
+    source: RejectionSource,
+    reason: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO shilling_rejections (chat_uuid, wallet, pool_address, source, reason) VALUES ($1, $2, $3, $4, $5)",
+        chat_uuid,
+        wallet,
+        pool_address,
+        source.to_string(),
+        reason
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
 }
 
 //Retrieves portfolio information asynchronously for the given session. If the portfolio is not empty,
@@ -122,61 +243,131 @@ pub async fn process_fetch_data_from_dex_screener(pool: &PgPool, token_address:
 //    str: A human-readable summary of the portfolio or a message indicating the portfolio is empty.
 
 
-pub async fn process_shilling(wallet: &str, explanation: &str, pool_address: &str) -> Result<String> {
-    let solana_driver = SolanaDriver::new();
-    match solana_driver.swap_quote_token(pool_address, 0.0001).await {
+pub async fn process_shilling(
+    pool: &PgPool,
+    chat_uuid: &str,
+    wallet: &str,
+    explanation: &str,
+    pool_address: &str,
+) -> Result<(String, ConversationStatus)> {
+    let settings = SolanaSettings::new_solana();
+    let positions = match PositionManager::from_settings(pool.clone()).await {
+        Ok(positions) => positions,
+        Err(e) => {
+            error!("Failed to initialize Solana driver: {}", e);
+            return Ok(("Failed to proceed swap transaction.".to_string(), ConversationStatus::ApproveFailed));
+        }
+    };
+    let solana_driver = positions.driver();
+    match solana_driver.simulate_round_trip(pool_address, settings.buy_amount_sol).await {
+        Ok(report) => {
+            if let Some(reason) = report.failure_reason(settings.max_round_trip_tax_pct) {
+                error!("Round trip simulation rejected {}: {:?}", pool_address, report);
+                if let Err(e) = record_rejection(pool, chat_uuid, wallet, Some(pool_address), RejectionSource::Simulation, &reason).await {
+                    error!("Failed to record rejection of {}: {}", pool_address, e);
+                }
+                return Ok((
+                    format!(
+                        "I decided not to buy this token after all: {}. Trading it could trap the funds, so I'm passing on it.",
+                        reason
+                    ),
+                    ConversationStatus::Reject,
+                ));
+            }
+        }
+        Err(e) => {
+            error!("Failed to simulate swap transaction: {}", e);
+            return Ok(("Failed to simulate swap transaction, so I won't buy this token right now.".to_string(), ConversationStatus::ApproveFailed));
+        }
+    }
+
+    // The position is stored before the buy so a buy can never land without a record of it.
+    let trade_position_id = match positions.reserve_position(chat_uuid, pool_address).await {
+        Ok(trade_position_id) => trade_position_id,
+        Err(e) => {
+            error!("Failed to reserve position for {}: {:?}", pool_address, e);
+            return Ok(("Failed to proceed swap transaction.".to_string(), ConversationStatus::ApproveFailed));
+        }
+    };
+
+    match solana_driver.swap_quote_token(pool_address, settings.buy_amount_sol, settings.swap_slippage_bps).await {
         Ok(tx_details) => {
-            match get_pool_quote_token_info(pool_address).await {
+            let quote_token_info = RaydiumClient::new_raydium_client().get_pool_quote_token_info(pool_address).await;
+            // The buy landed, so the position is recorded even without Raydium's token details.
+            let token = match &quote_token_info {
+                Ok(info) => info.clone(),
+                Err(e) => {
+                    error!("Failed to retrieve token information from Raydium API: {:?}", e);
+                    MintInfo {
+                        address: tx_details.token_out.clone(),
+                        symbol: "UNKNOWN".to_string(),
+                        name: tx_details.token_out.clone(),
+                        decimals: 0,
+                        program_id: String::new(),
+                    }
+                }
+            };
+            if let Err(e) = positions.open_position(trade_position_id, pool_address, &token, &tx_details).await {
+                error!(
+                    "Failed to record buy {} of {}, position {} left pending: {:?}",
+                    tx_details.signature, pool_address, trade_position_id, e
+                );
+            }
+            match quote_token_info {
                 Ok(quote_token_info) => {
-                    Ok(format!(
-                        "{}\n\nTransaction Details:\n- Amount Spent: {} {}\n- Amount of Bought token: {} {}\n- Token Address: [{}](https://solscan.io/account/{})\n- Transaction link: [{}](https://solscan.io/tx/{})\n- Transaction fee: {} SOL\n\nThe token purchase has been completed successfully.\nRemember, investing always involves risk. Good luck!",
+                    Ok((format!(
+                        "{}\n\nTransaction Details:\n- Amount Spent: {} SOL\n- Amount of Bought token: {} {}\n- Token Address: [{}](https://solscan.io/account/{})\n- Transaction link: [{}](https://solscan.io/tx/{})\n- Transaction fee: {} SOL\n\nThe token purchase has been completed successfully.\nRemember, investing always involves risk. Good luck!",
                         explanation,
-                        tx_details.amount_in, tx_details.token_in,
+                        tx_details.amount_in,
                         tx_details.amount_out, quote_token_info.symbol,
                         quote_token_info.address, quote_token_info.address,
-                        tx_details.tx_id, tx_details.tx_id,
+                        tx_details.signature, tx_details.signature,
                         tx_details.fee
-                    ))
-                }
-                Err(e) => {
-                    error!("Failed to retrieve token information from Raydium API: {:?}", e);
-                    Ok("Failed to retrieve token information from Raydium API.".to_string())
+                    ), ConversationStatus::Approve))
                 }
+                Err(_) => Ok(("Failed to retrieve token information from Raydium API.".to_string(), ConversationStatus::Approve)),
             }
         }
         Err(e) => {
             error!("Failed to proceed swap transaction: {:?}", e);
-            Ok("Failed to proceed swap transaction.".to_string())
+            if let Err(e) = positions.fail_position(trade_position_id).await {
+                error!("Failed to mark position {} as failed: {:?}", trade_position_id, e);
+            }
+            Ok(("Failed to proceed swap transaction.".to_string(), ConversationStatus::ApproveFailed))
         }
     }
 }
 
 pub async fn retrieve_portfolio_information(pool: &PgPool) -> Result<String> {
-    let portfolio = get_agent_portfolio(pool).await?;
-    if !portfolio.is_empty() {
-        let tokens_info: Vec<String> = portfolio.iter().map(|token| {
-            format!("- token amount: {}, symbol: {}, name: {}, pool address: {}, token address: {}",
-                token.amount, token.symbol, token.name, token.pool_address, token.token_address)
-        }).collect();
+    let portfolio = Portfolio::for_agent(pool.clone()).await.map_err(|e| anyhow::anyhow!(e))?;
+    let snapshot = portfolio.snapshot().await.map_err(|e| anyhow::anyhow!(e))?;
+    if !snapshot.is_empty() {
+        let total_value = match snapshot.total_value_usd {
+            Some(total) => format!("\n- Total value: ${:.2}", total),
+            None => String::new(),
+        };
         Ok(format!(
-            "I have these tokens:\n{}\n**Agent wallet**: [View on Solscan](https://solscan.io/account/{})",
-            tokens_info.join("\n"),
-            SolanaDriver::get_address()
+            "I have these tokens:\n{}\n- SOL balance: {}{}\n**Agent wallet**: [View on Solscan](https://solscan.io/account/{})",
+            snapshot.token_lines().join("\n"),
+            format_sol_with_usd(snapshot.sol_balance, snapshot.sol_price_usd),
+            total_value,
+            snapshot.wallet
         ))
     } else {
         Ok("My portfolio is empty, I now decide what to buy".to_string())
     }
 }
 
-/// Checks if the agent has sufficient balance.
-pub fn has_sufficient_agent_balance() -> bool {
-    let agent_balance = SolanaDriver::get_agent_balance();
-    let required_balance = 0.01 * 10u64.pow(9) + SolanaDriver::value_to_buy_in_lamports();
-    agent_balance >= required_balance
+/// Checks if the agent can pay for a buy and still keep `AGENT_RESERVE_SOL` for fees.
+pub async fn has_sufficient_agent_balance(pool: &PgPool) -> Result<bool> {
+    let portfolio = Portfolio::for_agent(pool.clone()).await.map_err(|e| anyhow::anyhow!(e))?;
+    let agent_balance = portfolio.sol_balance_lamports().await.map_err(|e| anyhow::anyhow!(e))?;
+    let required_balance = sol_to_lamports(AGENT_RESERVE_SOL + SolanaSettings::new_solana().buy_amount_sol);
+    Ok(agent_balance >= required_balance)
 }
 
 
-pub fn analyze_call_identify_pool(user_message: &str, if_function_call: bool) -> String { 
+pub fn analyze_call_identify_pool(user_message: &str, is_function_call: bool) -> String { 
     if is_function_call{ 
         format!("Call identifyPool function. User message: {}", user_message)
     } else { 
@@ -194,14 +385,101 @@ pub async fn retrieve_buy_decision(pool: &PgPool, pool_address: &str) -> Result<
     }
 }
 
-/// Validates a Raydium pool.
-pub fn validate_raydium_pool(pool_or_token_address: &str) -> Result<String> {
-    match get_pool_address_from_mint(pool_or_token_address) {
-        Ok(pool_address) => Ok(format!("Token address: {}", pool_address)),
-        Err(_) => Err(anyhow::anyhow!("Invalid pool or token address.")),
+/// PnL statistics the agent can report on.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PnlAction {
+    /// Information about total profit and losses.
+    TotalPnl,
+    /// Information about total shared profit between users.
+    TotalProfitShared,
+    /// Information about the most profitable trade.
+    MaximumPnl,
+    /// Information about the trade with the biggest loss.
+    MinimumPnl,
+    /// Information about the average PnL of agent trading.
+    AveragePnl,
+    /// Information about how many trades the agent has closed.
+    CountOfTrades,
+}
+
+/// A SOL amount with its USD value when the SOL price is known.
+fn format_sol_with_usd(amount: f64, sol_price_usd: Option<f64>) -> String {
+    match sol_price_usd {
+        Some(price) => format!("{:.9} SOL (~${:.2})", amount, amount * price),
+        None => format!("{:.9} SOL", amount),
     }
 }
 
+/// Retrieves PnL statistics for the requested action; open positions are only priced for
+/// the total PnL.
+pub async fn retrieve_pnl_information(pool: &PgPool, action: PnlAction) -> Result<String> {
+    match action {
+        PnlAction::TotalPnl => {
+            let report = get_pnl_report(pool).await?;
+            Ok(format!(
+                "My total realized PnL is {}, fees included. My open positions are at {} unrealized.",
+                format_sol_with_usd(report.realized_pnl_sol, report.sol_price_usd),
+                format_sol_with_usd(report.unrealized_pnl_sol, report.sol_price_usd)
+            ))
+        }
+        PnlAction::TotalProfitShared => {
+            let (lamports, payouts) = PayoutService::from_settings(pool.clone()).total_shared().await?;
+            if payouts == 0 {
+                return Ok("I haven't shared any profit yet.".to_string());
+            }
+            Ok(format!(
+                "I have shared {} of profit with shillers in {} payouts.",
+                format_sol_with_usd(lamports_to_sol(lamports), get_sol_price_usd().await),
+                payouts
+            ))
+        }
+        PnlAction::MaximumPnl => {
+            let report = compute_pnl(pool, None).await?;
+            match &report.best_sale {
+                Some(sale) => Ok(format!(
+                    "My most profitable trade made {} on {}. Transaction: {}",
+                    format_sol_with_usd(sale.pnl_sol, get_sol_price_usd().await),
+                    sale.symbol,
+                    sale.tx_id
+                )),
+                None => Ok("I haven't closed any trades yet.".to_string()),
+            }
+        }
+        PnlAction::MinimumPnl => {
+            let report = compute_pnl(pool, None).await?;
+            match &report.worst_sale {
+                Some(sale) => Ok(format!(
+                    "My worst trade made {} on {}. Transaction: {}",
+                    format_sol_with_usd(sale.pnl_sol, get_sol_price_usd().await),
+                    sale.symbol,
+                    sale.tx_id
+                )),
+                None => Ok("I haven't closed any trades yet.".to_string()),
+            }
+        }
+        PnlAction::AveragePnl => {
+            let report = compute_pnl(pool, None).await?;
+            match report.average_realized_pnl_sol() {
+                Some(average) => Ok(format!("My average PnL per trade is {}.", format_sol_with_usd(average, get_sol_price_usd().await))),
+                None => Ok("I haven't closed any trades yet.".to_string()),
+            }
+        }
+        PnlAction::CountOfTrades => Ok(format!("I have closed {} trades.", get_count_of_closed_trades(pool).await?)),
+    }
+}
+
+/// Resolves a pool id or token mint to the deepest swappable Raydium/WSOL pool.
+pub async fn validate_raydium_pool(pool_or_token_address: &str) -> Result<PoolInfo> {
+    RaydiumClient::new_raydium_client()
+        .resolve_pool(pool_or_token_address)
+        .await
+        .map_err(|e| {
+            error!("Failed to identify Raydium pool for {}: {:?}", pool_or_token_address, e);
+            anyhow::anyhow!("Invalid pool or token address.")
+        })
+}
+
 /// Retrieves the current portfolio.
 /// 
 /// Publish a Twitter post using the provided text data from LLM.
@@ -0,0 +1,617 @@
+use anyhow::Result;
+use futures::future::join_all;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{info, warn};
+
+use crate::llm::provider::LlmProvider;
+use crate::llm::tools::{ToolContext, ToolOutput, ToolRegistry};
+use crate::llm::utils::{chat_completion, chat_completion_stream, parse_tool_call};
+use crate::models::base::ConversationStatus;
+
+const STEP_BUDGET_EXHAUSTED_REPLY: &str =
+    "I need a bit more time to think this one through. Could you rephrase or give me more details?";
+const DECISION_FAILED_REPLY: &str =
+    "Something went wrong while I was acting on this one. I'll take another look before doing anything else.";
+const DUPLICATE_DECISION_ERROR: &str = "Skipped: a decision was already made in this response";
+const TOOL_NOT_AVAILABLE_ERROR: &str = "This tool is not available in this conversation";
+
+/// One tool call executed during an agent step.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallTrace {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+    pub output: String,
+    pub status: Option<ConversationStatus>,
+    pub error: Option<String>,
+}
+
+/// A single model round trip and the tool calls it triggered.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentStep {
+    pub index: usize,
+    pub content: Option<String>,
+    pub tool_calls: Vec<ToolCallTrace>,
+}
+
+/// Final answer of the agent together with the trace of every step.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentOutcome {
+    pub content: String,
+    pub status: ConversationStatus,
+    pub aux_data: Option<Value>,
+    pub steps: Vec<AgentStep>,
+}
+
+/// Progress notifications emitted while the agent is running.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    Delta { content: String },
+    /// `label` is the human-readable progress text, e.g. "fetching DexScreener data".
+    ToolCallStarted { id: String, name: String, label: String },
+    ToolCallFinished { id: String, name: String, label: String, error: Option<String> },
+    Final { content: String, status: ConversationStatus, token_entity: Option<Value> },
+    Error { message: String },
+}
+
+impl AgentEvent {
+    /// SSE `event:` name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AgentEvent::Delta { .. } => "delta",
+            AgentEvent::ToolCallStarted { .. } => "tool_call_started",
+            AgentEvent::ToolCallFinished { .. } => "tool_call_finished",
+            AgentEvent::Final { .. } => "final",
+            AgentEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Drives the model through repeated tool-call rounds until it answers with
+/// content, a decision tool (`approveShilling`/`rejectShilling`) fires, or the
+/// step budget runs out. Only the tools of `group` are offered and run.
+pub struct AgentLoop<'a> {
+    provider: &'a dyn LlmProvider,
+    registry: &'a ToolRegistry,
+    group: &'a str,
+    tools: Value,
+    tool_choice: String,
+    max_steps: usize,
+    parallel_tool_calls: bool,
+}
+
+impl<'a> AgentLoop<'a> {
+    pub fn new_agent_loop(
+        provider: &'a dyn LlmProvider,
+        registry: &'a ToolRegistry,
+        group: &'a str,
+        tool_choice: &str,
+        max_steps: usize,
+        parallel_tool_calls: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            provider,
+            registry,
+            group,
+            tools: registry.definitions(group)?,
+            tool_choice: tool_choice.to_string(),
+            max_steps,
+            parallel_tool_calls,
+        })
+    }
+
+    /// A decision the model may make here; one outside the group never runs.
+    fn is_decision(&self, name: &str) -> bool {
+        self.registry.group_contains(self.group, name) && self.registry.is_decision(name)
+    }
+
+    pub async fn run(&self, messages: &mut Vec<Value>, ctx: &ToolContext) -> Result<AgentOutcome> {
+        self.run_with_events(messages, ctx, None).await
+    }
+
+    /// Runs the loop, streaming content deltas and tool-call progress into `events`.
+    /// The final event is left to the caller, which may still persist the outcome.
+    pub async fn run_with_events(
+        &self,
+        messages: &mut Vec<Value>,
+        ctx: &ToolContext,
+        events: Option<&UnboundedSender<AgentEvent>>,
+    ) -> Result<AgentOutcome> {
+        let mut steps = Vec::new();
+        let mut status: Option<ConversationStatus> = None;
+        let mut aux_data: Option<Value> = None;
+
+        for index in 0..self.max_steps {
+            // The forced choice only applies to the first round, otherwise
+            // `required` would never let the model answer with content.
+            let tool_choice = if index == 0 { self.tool_choice.as_str() } else { "auto" };
+            let reply = match events {
+                Some(events) => {
+                    let on_delta = |delta: &str| {
+                        let _ = events.send(AgentEvent::Delta { content: delta.to_string() });
+                    };
+                    chat_completion_stream(
+                        self.provider,
+                        messages,
+                        Some(self.tools.clone()),
+                        tool_choice,
+                        self.parallel_tool_calls,
+                        None,
+                        &on_delta,
+                    )
+                    .await?
+                }
+                None => {
+                    chat_completion(
+                        self.provider,
+                        messages,
+                        Some(self.tools.clone()),
+                        tool_choice,
+                        self.parallel_tool_calls,
+                        None,
+                    )
+                    .await?
+                }
+            };
+            messages.push(reply.clone());
+
+            let content = reply["content"].as_str().map(str::to_string);
+            let tool_calls = reply["tool_calls"].as_array().cloned().unwrap_or_default();
+
+            if tool_calls.is_empty() {
+                steps.push(AgentStep { index, content: content.clone(), tool_calls: vec![] });
+                return Ok(AgentOutcome {
+                    content: content.unwrap_or_default(),
+                    status: status.unwrap_or(ConversationStatus::Discuss),
+                    aux_data,
+                    steps,
+                });
+            }
+
+            let traces = self.execute_tool_calls(&tool_calls, ctx, events).await;
+            let mut terminal: Option<(ToolCallTrace, ConversationStatus)> = None;
+
+            for (trace, output) in &traces {
+                messages.push(json!({"role": "tool", "tool_call_id": trace.id, "content": trace.output}));
+
+                if let Some(output) = output {
+                    if let Some(tool_status) = &output.status {
+                        status = Some(tool_status.clone());
+                        if terminal.is_none() && is_terminal(tool_status) {
+                            terminal = Some((trace.clone(), tool_status.clone()));
+                        }
+                    }
+                    // Calls after the decision must not replace its payload.
+                    let is_decision = terminal.as_ref().is_some_and(|(decision, _)| decision.id == trace.id);
+                    if output.aux_data.is_some() && (terminal.is_none() || is_decision) {
+                        aux_data = output.aux_data.clone();
+                    }
+                }
+            }
+
+            if terminal.is_none() {
+                // The first decision is the one that ran; if it errored a buy may still have
+                // been sent, so the model must not get another attempt.
+                if let Some((trace, _)) = traces.iter().find(|(trace, _)| self.is_decision(&trace.name)) {
+                    let reply = ToolCallTrace { output: DECISION_FAILED_REPLY.to_string(), ..trace.clone() };
+                    terminal = Some((reply, ConversationStatus::ApproveFailed));
+                }
+            }
+
+            steps.push(AgentStep {
+                index,
+                content,
+                tool_calls: traces.into_iter().map(|(trace, _)| trace).collect(),
+            });
+
+            if let Some((trace, terminal_status)) = terminal {
+                return Ok(AgentOutcome { content: trace.output, status: terminal_status, aux_data, steps });
+            }
+        }
+
+        warn!("Agent step budget of {} exhausted", self.max_steps);
+        Ok(AgentOutcome {
+            content: STEP_BUDGET_EXHAUSTED_REPLY.to_string(),
+            status: status.unwrap_or(ConversationStatus::Discuss),
+            aux_data,
+            steps,
+        })
+    }
+
+    async fn execute_tool_calls(
+        &self,
+        tool_calls: &[Value],
+        ctx: &ToolContext,
+        events: Option<&UnboundedSender<AgentEvent>>,
+    ) -> Vec<(ToolCallTrace, Option<ToolOutput>)> {
+        // Only the first decision of a response runs, two approvals would buy twice.
+        let first_decision = tool_calls.iter().position(|call| self.is_decision(tool_name(call)));
+        let skipped: Vec<bool> = tool_calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| self.is_decision(tool_name(call)) && Some(index) != first_decision)
+            .collect();
+
+        if self.parallel_tool_calls {
+            join_all(tool_calls.iter().zip(&skipped).map(|(call, skipped)| self.execute_tool_call(call, *skipped, ctx, events))).await
+        } else {
+            let mut results = Vec::with_capacity(tool_calls.len());
+            for (call, skipped) in tool_calls.iter().zip(&skipped) {
+                results.push(self.execute_tool_call(call, *skipped, ctx, events).await);
+            }
+            results
+        }
+    }
+
+    async fn execute_tool_call(
+        &self,
+        tool_call: &Value,
+        skipped: bool,
+        ctx: &ToolContext,
+        events: Option<&UnboundedSender<AgentEvent>>,
+    ) -> (ToolCallTrace, Option<ToolOutput>) {
+        let id = tool_call["id"].as_str().unwrap_or_default().to_string();
+        let name = tool_name(tool_call).to_string();
+        if let Some(events) = events {
+            let label = self.registry.label(&name).to_string();
+            let _ = events.send(AgentEvent::ToolCallStarted { id: id.clone(), name: name.clone(), label });
+        }
+
+        let (trace, output) = if skipped {
+            warn!("Skipping duplicate decision {} in one response", name);
+            (error_trace(id, name, Value::Null, DUPLICATE_DECISION_ERROR.to_string()), None)
+        } else {
+            self.dispatch_tool_call(tool_call, ctx).await
+        };
+
+        if let Some(events) = events {
+            let _ = events.send(AgentEvent::ToolCallFinished {
+                id: trace.id.clone(),
+                name: trace.name.clone(),
+                label: self.registry.label(&trace.name).to_string(),
+                error: trace.error.clone(),
+            });
+        }
+        (trace, output)
+    }
+
+    async fn dispatch_tool_call(&self, tool_call: &Value, ctx: &ToolContext) -> (ToolCallTrace, Option<ToolOutput>) {
+        let id = tool_call["id"].as_str().unwrap_or_default().to_string();
+        let (name, arguments) = match parse_tool_call(tool_call) {
+            Ok(parsed) => parsed,
+            Err(e) => return (error_trace(id, tool_name(tool_call).to_string(), Value::Null, e.to_string()), None),
+        };
+
+        // Tools outside the group were not offered; a call to one is hallucinated or injected.
+        if !self.registry.group_contains(self.group, &name) {
+            warn!("Refused call to {} outside tool group {}", name, self.group);
+            return (error_trace(id, name, arguments, TOOL_NOT_AVAILABLE_ERROR.to_string()), None);
+        }
+
+        info!("Called function: {}", name);
+        match self.registry.dispatch(&name, arguments.clone(), ctx).await {
+            Ok(output) => {
+                let trace = ToolCallTrace {
+                    id,
+                    name,
+                    arguments,
+                    output: output.content.clone(),
+                    status: output.status.clone(),
+                    error: None,
+                };
+                (trace, Some(output))
+            }
+            Err(e) => {
+                warn!("Function {} failed: {:?}", name, e);
+                (error_trace(id, name, arguments, e.to_string()), None)
+            }
+        }
+    }
+}
+
+fn tool_name(tool_call: &Value) -> &str {
+    tool_call["function"]["name"].as_str().unwrap_or_default()
+}
+
+/// Statuses that end the turn; a failed approval must not be retried with another buy.
+fn is_terminal(status: &ConversationStatus) -> bool {
+    matches!(status, ConversationStatus::Approve | ConversationStatus::ApproveFailed | ConversationStatus::Reject)
+}
+
+/// The model still gets a tool message for a failed call so the conversation stays valid.
+fn error_trace(id: String, name: String, arguments: Value, error: String) -> ToolCallTrace {
+    ToolCallTrace {
+        id,
+        name,
+        arguments,
+        output: format!("Error: {}", error),
+        status: None,
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use async_trait::async_trait;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::llm::provider::MockProvider;
+    use crate::llm::tools::Tool;
+
+    #[derive(Deserialize, JsonSchema)]
+    struct LookupArgs {
+        query: String,
+    }
+
+    struct Lookup;
+
+    #[async_trait]
+    impl Tool for Lookup {
+        const NAME: &'static str = "lookup";
+        const DESCRIPTION: &'static str = "Looks something up.";
+        const LABEL: &'static str = "looking it up";
+        type Args = LookupArgs;
+
+        async fn call(&self, _ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+            Ok(ToolOutput::new_tool_output(format!("found {}", args.query)))
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct ApproveArgs {
+        #[serde(default)]
+        fail: bool,
+    }
+
+    /// Decision tool counting how often it actually ran.
+    struct Approve {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for Approve {
+        const NAME: &'static str = "approve";
+        const DESCRIPTION: &'static str = "Buys the token.";
+        const LABEL: &'static str = "buying the token";
+        const IS_DECISION: bool = true;
+        type Args = ApproveArgs;
+
+        async fn call(&self, _ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if args.fail {
+                return Err(anyhow!("swap failed"));
+            }
+            Ok(ToolOutput::new_tool_output("bought".to_string())
+                .with_status(ConversationStatus::Approve)
+                .with_aux_data(json!({"poolAddress": "pool"})))
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct RejectArgs {}
+
+    struct Reject;
+
+    #[async_trait]
+    impl Tool for Reject {
+        const NAME: &'static str = "reject";
+        const DESCRIPTION: &'static str = "Passes on the token.";
+        const LABEL: &'static str = "passing on the token";
+        const IS_DECISION: bool = true;
+        type Args = RejectArgs;
+
+        async fn call(&self, _ctx: &ToolContext, _args: Self::Args) -> Result<ToolOutput> {
+            Ok(ToolOutput::new_tool_output("passed".to_string()).with_status(ConversationStatus::Reject))
+        }
+    }
+
+    fn registry(approvals: Arc<AtomicUsize>) -> ToolRegistry {
+        let mut registry = ToolRegistry::new_tool_registry();
+        registry.register(Lookup).register(Approve { calls: approvals }).register(Reject);
+        registry.add_group("test", &[Lookup::NAME, Approve::NAME, Reject::NAME]);
+        registry.add_group("lookup_only", &[Lookup::NAME]);
+        registry
+    }
+
+    /// None of the test tools touch the database, so the pool never connects.
+    fn context() -> ToolContext {
+        ToolContext {
+            pool: PgPoolOptions::new().connect_lazy("postgres://localhost/agent_tests").unwrap(),
+            user_address: "wallet".to_string(),
+            chat_uuid: "chat".to_string(),
+        }
+    }
+
+    async fn run(
+        provider: &MockProvider,
+        registry: &ToolRegistry,
+        max_steps: usize,
+        messages: &mut Vec<Value>,
+    ) -> AgentOutcome {
+        run_group(provider, registry, "test", max_steps, messages).await
+    }
+
+    async fn run_group(
+        provider: &MockProvider,
+        registry: &ToolRegistry,
+        group: &str,
+        max_steps: usize,
+        messages: &mut Vec<Value>,
+    ) -> AgentOutcome {
+        let agent = AgentLoop::new_agent_loop(provider, registry, group, "required", max_steps, false).unwrap();
+        agent.run(messages, &context()).await.unwrap()
+    }
+
+    fn tool_message<'m>(messages: &'m [Value], id: &str) -> &'m Value {
+        messages
+            .iter()
+            .find(|message| message["role"] == "tool" && message["tool_call_id"] == id)
+            .unwrap_or_else(|| panic!("no tool message for {}", id))
+    }
+
+    #[tokio::test]
+    async fn runs_tool_rounds_until_the_model_answers() {
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_calls_reply(&[
+                ("call_1", "lookup", json!({"query": "pool"})),
+                ("call_2", "lookup", json!({"query": "holders"})),
+            ]),
+            MockProvider::tool_call_reply("call_3", "lookup", json!({"query": "price"})),
+            MockProvider::text_reply("Looks fine."),
+        ]);
+        let registry = registry(Arc::default());
+        let mut messages = vec![json!({"role": "user", "content": "gm"})];
+
+        let outcome = run(&provider, &registry, 5, &mut messages).await;
+
+        assert_eq!(outcome.content, "Looks fine.");
+        assert_eq!(outcome.status, ConversationStatus::Discuss);
+        assert_eq!(outcome.steps.len(), 3);
+        assert_eq!(outcome.steps[0].tool_calls.len(), 2);
+        assert_eq!(outcome.steps[0].tool_calls[1].id, "call_2");
+        assert_eq!(outcome.steps[0].tool_calls[1].output, "found holders");
+
+        // Every tool result follows its assistant message and answers its own call.
+        assert_eq!(messages.len(), 7);
+        assert_eq!(messages[2], json!({"role": "tool", "tool_call_id": "call_1", "content": "found pool"}));
+        assert_eq!(messages[3], json!({"role": "tool", "tool_call_id": "call_2", "content": "found holders"}));
+        assert_eq!(messages[5], json!({"role": "tool", "tool_call_id": "call_3", "content": "found price"}));
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].messages, messages[..4].to_vec());
+    }
+
+    #[tokio::test]
+    async fn only_the_first_decision_ends_the_turn() {
+        let approvals = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::tool_calls_reply(&[
+            ("call_1", "lookup", json!({"query": "pool"})),
+            ("call_2", "approve", json!({})),
+            ("call_3", "approve", json!({})),
+            ("call_4", "reject", json!({})),
+        ])]);
+        let registry = registry(approvals.clone());
+        let mut messages = vec![json!({"role": "user", "content": "buy it"})];
+
+        let outcome = run(&provider, &registry, 5, &mut messages).await;
+
+        assert_eq!(outcome.status, ConversationStatus::Approve);
+        assert_eq!(outcome.content, "bought");
+        assert_eq!(outcome.aux_data, Some(json!({"poolAddress": "pool"})));
+        assert_eq!(approvals.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.requests().len(), 1);
+
+        let calls = &outcome.steps[0].tool_calls;
+        assert_eq!(calls[2].error.as_deref(), Some(DUPLICATE_DECISION_ERROR));
+        assert_eq!(calls[3].error.as_deref(), Some(DUPLICATE_DECISION_ERROR));
+        // Skipped calls still get a tool message so the conversation stays valid.
+        assert_eq!(tool_message(&messages, "call_3")["content"], format!("Error: {}", DUPLICATE_DECISION_ERROR));
+        assert_eq!(tool_message(&messages, "call_4")["content"], format!("Error: {}", DUPLICATE_DECISION_ERROR));
+    }
+
+    #[tokio::test]
+    async fn a_failed_approval_ends_the_turn_as_approve_failed() {
+        let approvals = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply("call_1", "approve", json!({"fail": true})),
+            MockProvider::tool_call_reply("call_2", "approve", json!({})),
+        ]);
+        let registry = registry(approvals.clone());
+        let mut messages = vec![json!({"role": "user", "content": "buy it"})];
+
+        let outcome = run(&provider, &registry, 5, &mut messages).await;
+
+        assert_eq!(outcome.status, ConversationStatus::ApproveFailed);
+        assert_eq!(outcome.content, DECISION_FAILED_REPLY);
+        assert_eq!(outcome.steps[0].tool_calls[0].error.as_deref(), Some("swap failed"));
+        assert_eq!(approvals.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.requests().len(), 1);
+        assert_eq!(tool_message(&messages, "call_1")["content"], "Error: swap failed");
+    }
+
+    #[tokio::test]
+    async fn refuses_tools_outside_the_group() {
+        let approvals = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply("call_1", "approve", json!({})),
+            MockProvider::text_reply("Not this time."),
+        ]);
+        let registry = registry(approvals.clone());
+        let mut messages = vec![json!({"role": "user", "content": "buy it"})];
+
+        let outcome = run_group(&provider, &registry, "lookup_only", 5, &mut messages).await;
+
+        assert_eq!(approvals.load(Ordering::SeqCst), 0);
+        assert_eq!(outcome.steps[0].tool_calls[0].error.as_deref(), Some(TOOL_NOT_AVAILABLE_ERROR));
+        assert_eq!(tool_message(&messages, "call_1")["content"], format!("Error: {}", TOOL_NOT_AVAILABLE_ERROR));
+        // The refused call was never a decision, so the model gets to answer.
+        assert_eq!(outcome.status, ConversationStatus::Discuss);
+        assert_eq!(outcome.content, "Not this time.");
+        assert_eq!(provider.requests()[0].tools.as_ref().unwrap().as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn stops_when_the_step_budget_runs_out() {
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply("call_1", "lookup", json!({"query": "a"})),
+            MockProvider::tool_call_reply("call_2", "lookup", json!({"query": "b"})),
+            MockProvider::text_reply("never requested"),
+        ]);
+        let registry = registry(Arc::default());
+        let mut messages = vec![json!({"role": "user", "content": "gm"})];
+
+        let outcome = run(&provider, &registry, 2, &mut messages).await;
+
+        assert_eq!(outcome.content, STEP_BUDGET_EXHAUSTED_REPLY);
+        assert_eq!(outcome.status, ConversationStatus::Discuss);
+        assert_eq!(outcome.steps.len(), 2);
+        assert_eq!(provider.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn forces_the_tool_choice_only_on_the_first_step() {
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply("call_1", "lookup", json!({"query": "a"})),
+            MockProvider::tool_call_reply("call_2", "lookup", json!({"query": "b"})),
+            MockProvider::text_reply("done"),
+        ]);
+        let registry = registry(Arc::default());
+        let mut messages = vec![json!({"role": "user", "content": "gm"})];
+
+        run(&provider, &registry, 5, &mut messages).await;
+
+        let choices: Vec<Option<String>> = provider.requests().into_iter().map(|request| request.tool_choice).collect();
+        assert_eq!(choices, vec![Some("required".to_string()), Some("auto".to_string()), Some("auto".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn streams_deltas_and_tool_progress() {
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply("call_1", "lookup", json!({"query": "a"})),
+            MockProvider::text_reply("done"),
+        ]);
+        let registry = registry(Arc::default());
+        let agent = AgentLoop::new_agent_loop(&provider, &registry, "test", "required", 5, false).unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut messages = vec![json!({"role": "user", "content": "gm"})];
+
+        agent.run_with_events(&mut messages, &context(), Some(&sender)).await.unwrap();
+        drop(sender);
+
+        let mut names = vec![];
+        while let Some(event) = receiver.recv().await {
+            names.push(event.name());
+        }
+        assert_eq!(names, vec!["tool_call_started", "tool_call_finished", "delta"]);
+    }
+}
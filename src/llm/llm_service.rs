@@ -1,57 +1,70 @@
 use sqlx::PgPool;
-use rig::{completion::Prompt, providers::openai};
-use serde_json::json;
+use serde_json::{json, Value};
 use tracing::{error, info};
 use anyhow::Result;
-use models::{Trade, LlmResponse, ActionParameter, ConversationStatus};
+use tokio::sync::mpsc::UnboundedSender;
+use crate::models::trade::Model as Trade;
+use crate::core::config::LlmSettings;
+use crate::llm::agent::{AgentEvent, AgentLoop, AgentOutcome};
+use crate::llm::prompts::{MAIN_PROMPTS, PROMPT_ACTIONS};
+use crate::llm::provider::LlmProvider;
+use crate::llm::tools::{ToolContext, SHILLING_GROUP, SHILLING_NOT_ALLOWED_GROUP, TOOL_REGISTRY, TWITTER_GROUP};
+use crate::llm::utils::{call_function, chat_completion, parse_tool_call};
+use crate::models::base::PayoutStatus;
+use crate::payouts::Payout;
+use crate::pnl::trade_pnl;
+use crate::positions::service::get_token_by_trade_id;
+use crate::utils::redis::RedisChatMessage;
 
 pub async fn answer_users_msg(
+    provider: &dyn LlmProvider,
     pool: &PgPool,
+    chat_uuid: &str,
     message: &str,
     user_address: &str,
-    history_uuid: &str,
+    history_messages: &[RedisChatMessage],
     is_shilling_allowed: bool,
+    events: Option<&UnboundedSender<AgentEvent>>,
 
     //Get response from the LLM and process the user's message.
+    //param chat_uuid: Chat the message was sent in.
     //param msg: User's message.
     //param user_address: User's wallet address.
-    //param history_uiid: Identifier for the message history in Redis.
-    //return: A tuple containing the response message, conversation status, and (optionally) a token entity.
+    //param history_messages: Previous turns of the chat loaded from Redis.
+    //param events: Optional sink for streamed deltas and tool-call progress.
+    //return: The agent outcome: response message, conversation status, (optionally) a token entity and the step trace.
 
 
-) -> Result<LlmResponse> {
-    let openai_api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let client = openai::Client::new(&openai_api_key);
-
-    let history_messages = get_redis_history(history_uuid).await.unwrap_or_default();
+) -> Result<AgentOutcome> {
     let aux_prompt_action = if is_shilling_allowed { "shilling_allowed" } else { "shilling_not_allowed" };
 
-    let messages = vec![
-        json!({"role": "system", "content": main_prompts::get(ActionParameter::Shilling)}),
-        json!({"role": "system", "content": prompt_actions::get(aux_prompt_action)}),
+    let mut messages = vec![
+        json!({"role": "system", "content": MAIN_PROMPTS["shilling"]}),
+        json!({"role": "system", "content": PROMPT_ACTIONS[aux_prompt_action]}),
     ]
     .into_iter()
     .chain(history_messages.iter().map(|m| json!(m)))
     .chain(vec![json!({"role": "user", "content": message})])
     .collect::<Vec<_>>();
 
-    let tools = if is_shilling_allowed {
-        main_tools::get(ActionParameter::Shilling)
-    } else {
-        main_tools::get("shilling_not_allowed")
-    };
+    let group = if is_shilling_allowed { SHILLING_GROUP } else { SHILLING_NOT_ALLOWED_GROUP };
+    let ctx = ToolContext { pool: pool.clone(), user_address: user_address.to_string(), chat_uuid: chat_uuid.to_string() };
 
     let tool_choice = if is_shilling_allowed { "required" } else { "auto" };
+    let settings = LlmSettings::new_llm();
+    let agent = AgentLoop::new_agent_loop(
+        provider,
+        &TOOL_REGISTRY,
+        group,
+        tool_choice,
+        settings.max_agent_steps,
+        settings.parallel_tool_calls,
+    )?;
 
-    match get_reply(&client, messages, tools, tool_choice).await {
-        Ok(reply) => {
-            if let Some(tool_calls) = reply.tool_calls {
-                process_tool_calls(&client, &pool, reply, user_address).await
-            } else if let Some(content) = reply.content {
-                process_filtering_reply(reply, tools).await
-            } else {
-                Err(anyhow::anyhow!("No response from LLM"))
-            }
+    match agent.run_with_events(&mut messages, &ctx, events).await {
+        Ok(outcome) => {
+            info!("Agent finished in {} steps with status {}", outcome.steps.len(), outcome.status);
+            Ok(outcome)
         }
         Err(e) => {
             error!("LLM processing error: {:?}", e);
@@ -62,6 +75,7 @@ pub async fn answer_users_msg(
 
 // Function to generate Twitter post
 pub async fn generate_twitter_post(
+    provider: &dyn LlmProvider,
     pool: &PgPool,
     user_address: Option<&str>,
     trade: &Trade,
@@ -75,14 +89,15 @@ pub async fn generate_twitter_post(
     let mut aux_sell_message = String::new();
 
     if trade_type.eq_ignore_ascii_case("sell") {
-        let (pnl, percentage_pnl) = calculate_pnl(pool, trade).await?;
+        let sale = trade_pnl(pool, trade.id).await?;
+        let (pnl, percentage_pnl) = (sale.pnl_sol, sale.pnl_pct);
         aux_sell_message = format!(
             "- Got also PNL: {} SOL, how much I earned/lost in percentage: {:.2}%",
             pnl, percentage_pnl
         );
     }
 
-    let short_solscan_link = shorten_url(&format!("https://solscan.io/tx/{}", trade.tx_id));
+    let short_solscan_link = shorten_url(&format!("https://solscan.io/tx/{}", trade.tx_id)).await;
 
     let prompt_message = format!(
         "I need to publish a post with trade type: `{}`. For PNL, do not use scientific format. 
@@ -102,7 +117,7 @@ pub async fn generate_twitter_post(
     );
 
     let messages = vec![
-        json!({"role": "system", "content": main_prompts::get(ActionParameter::Shilling)}),
+        json!({"role": "system", "content": MAIN_PROMPTS["shilling"]}),
         json!({"role": "system", "content": "
                 Generate a Twitter post about the trade that is under 280 characters. Write naturally as if spoken by a real person. Use minimal hashtags, but include the mandatory hashtags #KajaAI and #Raydium.
                 Call function `generatePostInTwitter` to publish post in twitter.
@@ -116,14 +131,20 @@ pub async fn generate_twitter_post(
         json!({"role": "user", "content": prompt_message}),
     ];
 
-    let client = openai::Client::new(&std::env::var("OPENAI_API_KEY")?);
-    let response = client.chat().completions().create("gpt-4o", messages).await?;
+    let tools = TOOL_REGISTRY.definitions(TWITTER_GROUP)?;
+    let ctx = ToolContext {
+        pool: pool.clone(),
+        user_address: user_address.unwrap_or_default().to_string(),
+        chat_uuid: String::new(),
+    };
+    let model = LlmSettings::new_llm().post_model;
+    let reply = chat_completion(provider, &messages, Some(tools), "required", false, Some(&model)).await?;
 
-    if let Some(tool_calls) = response.tool_calls {
+    if let Some(tool_calls) = reply.get("tool_calls").and_then(|c| c.as_array()) {
         for tool_call in tool_calls {
-            if tool_call.function.name == "generatePostInTwitter" {
-                let args = serde_json::from_str(&tool_call.function.arguments)?;
-                call_function("generatePostInTwitter", args).await?;
+            let (name, args) = parse_tool_call(tool_call)?;
+            if name == "generatePostInTwitter" {
+                call_function(&name, &args, &ctx).await?;
             }
         }
     }
@@ -132,31 +153,38 @@ pub async fn generate_twitter_post(
 }
 
 // Shorten URL using TinyURL
-pub fn shorten_url(url: &str) -> String {
+pub async fn shorten_url(url: &str) -> String {
     let api_url = format!("http://tinyurl.com/api-create.php?url={}", url);
-    match reqwest::blocking::get(&api_url) {
-        Ok(resp) => resp.text().unwrap_or_else(|_| url.to_string()),
+    match reqwest::get(&api_url).await {
+        Ok(resp) => resp.text().await.unwrap_or_else(|_| url.to_string()),
         Err(_) => url.to_string(),
     }
 }
 
 // Generate selling text
 pub async fn generate_selling_text(
+    provider: &dyn LlmProvider,
     pool: &PgPool,
-    transfer_signature: Option<&str>,
+    payout: Option<&Payout>,
     closed_trade: &Trade,
 ) -> Result<String> {
     let token = get_token_by_trade_id(pool, closed_trade.id).await?;
-    let (pnl, percentage_pnl) = calculate_pnl(pool, closed_trade).await?;
-    let is_trade_profitable = closed_trade.profit_loss;
-    let aux_message = if is_trade_profitable {
-        format!(
-            "I've shared with you {:.9} SOL (50% of my profit)! Check the transfer at https://solscan.io/tx/{}",
-            pnl * 0.5,
-            transfer_signature.unwrap_or("")
-        )
-    } else {
-        "Unfortunately, I didn't profit from this trade, so I couldn't share any funds.".to_string()
+    let sale = trade_pnl(pool, closed_trade.id).await?;
+    let (pnl, percentage_pnl) = (sale.pnl_sol, sale.pnl_pct);
+    let is_trade_profitable = pnl > 0.0;
+    let sent_payout = payout.filter(|payout| payout.status == PayoutStatus::Sent);
+    let aux_message = match (is_trade_profitable, sent_payout) {
+        (true, Some(payout)) => format!(
+            "I've shared with you {:.9} SOL ({}% of my profit)! Check the transfer at https://solscan.io/tx/{}",
+            payout.amount_sol(),
+            payout.share_pct,
+            payout.signature.as_deref().unwrap_or("")
+        ),
+        (true, None) if payout.is_some_and(|payout| payout.status == PayoutStatus::Skipped) => {
+            "I made a small profit on this trade, too little to share this time.".to_string()
+        }
+        (true, None) => "I made a profit on this trade; your share of it is on its way.".to_string(),
+        (false, _) => "Unfortunately, I didn't profit from this trade, so I couldn't share any funds.".to_string(),
     };
 
     let prompt_message = format!(
@@ -173,24 +201,104 @@ pub async fn generate_selling_text(
     );
 
     let messages = vec![
-        json!({"role": "system", "content": main_prompts::get(ActionParameter::Shilling)}),
+        json!({"role": "system", "content": MAIN_PROMPTS["shilling"]}),
         json!({"role": "system", "content": "
         Generate a chat message for the user about a closed selling trade. Write naturally and conversationally, as if you are a real person. 
                Ensure that you refer only to yourself—the trading agent—and do not mention any other trader names or identities. 
                Do not use scientific notation for numbers; format them as standard decimals. 
-               If the trade was profitable, mention that you've shared 50% of your profit with the user by completing a transfer of the funds, and include the SolScan transaction link. 
+               If the trade was profitable and the profit share was transferred, mention the amount you've shared with the user and include the SolScan transaction link; if it was not transferred, say so as stated and do not claim a transfer. 
                 If the trade resulted in a loss, explain that no funds were shared. 
                 Make sure the message flows naturally, reflects your own trading performance, and sounds genuine
         "}),
         json!({"role": "user", "content": prompt_message}),
     ];
 
-    let client = openai::Client::new(&std::env::var("OPENAI_API_KEY")?);
-    let response = client.chat().completions().create("gpt-4o", messages).await?;
+    let model = LlmSettings::new_llm().post_model;
+    let reply = chat_completion(provider, &messages, None, "", false, Some(&model)).await?;
 
-    if let Some(content) = response.choices[0].message.content {
-        Ok(content)
+    if let Some(content) = reply["content"].as_str() {
+        Ok(content.to_string())
     } else {
         Err(anyhow::anyhow!("No response from LLM"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::MockProvider;
+    use sqlx::postgres::PgPoolOptions;
+
+    /// The tools exercised here never touch the database, so the pool never connects.
+    fn lazy_pool() -> PgPool {
+        PgPoolOptions::new().connect_lazy("postgres://localhost/llm_service_tests").unwrap()
+    }
+
+    fn tool_names(tools: &Option<Value>) -> Vec<String> {
+        tools
+            .as_ref()
+            .and_then(Value::as_array)
+            .map(|tools| tools.iter().filter_map(|tool| tool["function"]["name"].as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn answers_with_history_and_without_decision_tools_when_shilling_is_not_allowed() {
+        let provider = MockProvider::new_mock_provider(vec![MockProvider::text_reply("gm, how can I help?")]);
+        let history = vec![
+            RedisChatMessage { role: "user".to_string(), content: "hi".to_string() },
+            RedisChatMessage { role: "assistant".to_string(), content: "hello".to_string() },
+        ];
+
+        let outcome = answer_users_msg(&provider, &lazy_pool(), "chat", "gm", "wallet", &history, false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.content, "gm, how can I help?");
+        assert_eq!(outcome.status, crate::models::base::ConversationStatus::Discuss);
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 1);
+        let messages = &requests[0].messages;
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[2], json!({"role": "user", "content": "hi"}));
+        assert_eq!(messages[3], json!({"role": "assistant", "content": "hello"}));
+        assert_eq!(messages[4], json!({"role": "user", "content": "gm"}));
+        assert_eq!(requests[0].tool_choice.as_deref(), Some("auto"));
+
+        let names = tool_names(&requests[0].tools);
+        assert!(names.contains(&"identifyPool".to_string()));
+        assert!(!names.contains(&"approveShilling".to_string()));
+        assert!(!names.contains(&"rejectShilling".to_string()));
+    }
+
+    #[tokio::test]
+    async fn runs_the_shilling_tools_and_answers_after_the_tool_round() {
+        let provider = MockProvider::new_mock_provider(vec![
+            MockProvider::tool_call_reply(
+                "call_1",
+                "analyzeCallIdentifyPool",
+                json!({"is_function_call": false, "user_message": "gm"}),
+            ),
+            MockProvider::text_reply("Send me a token address and I'll take a look."),
+        ]);
+
+        let outcome = answer_users_msg(&provider, &lazy_pool(), "chat", "gm", "wallet", &[], true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.content, "Send me a token address and I'll take a look.");
+        assert_eq!(outcome.steps.len(), 2);
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].tool_choice.as_deref(), Some("required"));
+        assert_eq!(requests[1].tool_choice.as_deref(), Some("auto"));
+        assert!(tool_names(&requests[0].tools).contains(&"approveShilling".to_string()));
+
+        let tool_message = requests[1].messages.last().unwrap();
+        assert_eq!(tool_message["role"], "tool");
+        assert_eq!(tool_message["tool_call_id"], "call_1");
+        assert_eq!(tool_message["content"], "Don't call identifyPool function");
+    }
+}
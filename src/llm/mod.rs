@@ -1,5 +1,8 @@
 pub mod actions;
+pub mod agent;
 pub mod llm_service;
 pub mod prompts;
+pub mod provider;
 pub mod utils;
-pub mod schemas;
\ No newline at end of file
+pub mod schemas;
+pub mod tools;
\ No newline at end of file
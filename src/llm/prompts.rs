@@ -86,130 +86,3 @@ pub static PROMPT_ACTIONS: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
     );
     map
 });
-
-// For future purposes, in pythong backend is detailed JSON-like structured, while in rust struct is absctracted 
-// in hashmap, this approach will be more structured.
-
-
-pub struct FunctionCall {
-    pub name: &'static str, 
-    pub description: &'static str, 
-    pub parameters: HashMap<&'static str, &'static str>,
-    pub required: Vec<&'static str>,
-}
-
-pub fn get_main_tools() -> HashMap<&'static str, Vec<FunctionCall>> {
-    let mut tools = HashMap::new();
-
-    tools.insert(
-        "shilling",
-        vec![
-            FunctionCall {
-                name: "approveShilling",
-                description: "Approve buying meme token from Raydium explanation.",
-                parameters: HashMap::from([
-                    ("explanation", "Explanation for why you decide to buy the token."),
-                    ("poolAddress", "Extract the poolAddress from analytic data."),
-                ]),
-                required: vec!["explanation", "poolAddress"],
-            },
-            FunctionCall {
-                name: "rejectShilling",
-                description: "Reject buying meme token from Raydium and provide an explanation.",
-                parameters: HashMap::from([
-                    ("explanation", "Explanation for why you reject buying the token."),
-                ]),
-                required: vec!["explanation"],
-            },
-            FunctionCall {
-                name: "identifyPool",
-                description: "Fetch address of a given pool or token address on Raydium.",
-                parameters: HashMap::from([
-                    ("pool_or_token_address", "Fetch token or pool address from Raydium URL."),
-                ]),
-                required: vec!["pool_or_token_address"],
-            },
-            FunctionCall {
-                name: "fetch_pool_data",
-                description: "Fetch analytics data for a given token address.",
-                parameters: HashMap::from([
-                    ("token_address", "The address of the pool to fetch data for."),
-                ]),
-                required: vec!["token_address"],
-            },
-            FunctionCall {
-                name: "retrieveCurrentPortfolio",
-                description: "Retrieve information about the current agent's portfolio.",
-                parameters: HashMap::new(),
-                required: vec![],
-            },
-            FunctionCall {
-                name: "retrieveBuyExplanation",
-                description: "Retrieve explanation for why a specific meme token was bought.",
-                parameters: HashMap::from([
-                    ("pool_address", "Retrieve the pool address of the token pair."),
-                ]),
-                required: vec!["pool_address"],
-            },
-            FunctionCall {
-                name: "retrievePnlInformation",
-                description: "Retrieve profit and loss (PnL) statistics based on the user's request.",
-                parameters: HashMap::from([
-                    ("action", "Specify the action to retrieve PnL statistics."),
-                ]),
-                required: vec!["action"],
-            },
-        ],
-    );
-
-    tools.insert(
-        "shilling_not_allowed",
-        vec![
-            FunctionCall {
-                name: "identifyPool",
-                description: "Fetch address of a given pool or token address on Raydium.",
-                parameters: HashMap::from([
-                    ("pool_or_token_address", "Fetch token or pool address from Raydium URL."),
-                ]),
-                required: vec!["pool_or_token_address"],
-            },
-            FunctionCall {
-                name: "retrieveCurrentPortfolio",
-                description: "Retrieve information about the current agent's portfolio.",
-                parameters: HashMap::new(),
-                required: vec![],
-            },
-            FunctionCall {
-                name: "retrieveBuyExplanation",
-                description: "Retrieve explanation for why a specific meme token was bought.",
-                parameters: HashMap::from([
-                    ("pool_address", "Retrieve the pool address of the token pair."),
-                ]),
-                required: vec!["pool_address"],
-            },
-            FunctionCall {
-                name: "retrievePnlInformation",
-                description: "Retrieve profit and loss (PnL) statistics based on the user's request.",
-                parameters: HashMap::from([
-                    ("action", "Specify the action to retrieve PnL statistics."),
-                ]),
-                required: vec!["action"],
-            },
-        ],
-    );
-    tools.insert(
-        "twitter_generation",
-        vec![
-            FunctionCall {
-                name: "generatePostInTwitter",
-                description: "Generate and post text on Twitter.",
-                parameters: HashMap::from([
-                    ("data", "Data provided to generate a post on Twitter."),
-                ]),
-                required: vec!["data"],
-            },
-        ],
-    );
-
-    tools
-}
\ No newline at end of file
@@ -0,0 +1,323 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::core::config::LlmSettings;
+
+const OPENAI_API_URL: &str = "https://api.openai.com/v1";
+
+/// A single chat completion request sent to an `LlmProvider`.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub messages: Vec<Value>,
+    pub tools: Option<Value>,
+    pub tool_choice: Option<String>,
+    pub parallel_tool_calls: bool,
+    /// Overrides the provider's default model for this call only.
+    pub model: Option<String>,
+}
+
+/// Backend able to answer OpenAI-style chat completion requests.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Model used when the request does not pick one.
+    fn model(&self) -> &str;
+
+    /// Sends the request and returns the assistant message (`choices[0].message`).
+    async fn chat(&self, request: ChatRequest) -> Result<Value>;
+
+    /// Same as `chat`, but calls `on_delta` with every content chunk as it arrives.
+    /// Backends without streaming support deliver the whole content as one chunk.
+    async fn chat_stream(&self, request: ChatRequest, on_delta: &(dyn Fn(&str) + Send + Sync)) -> Result<Value> {
+        let message = self.chat(request).await?;
+        if let Some(content) = message["content"].as_str() {
+            on_delta(content);
+        }
+        Ok(message)
+    }
+}
+
+/// Builds the `/chat/completions` body shared by every OpenAI-compatible backend.
+fn build_request_body(request: &ChatRequest, default_model: &str) -> Value {
+    let mut body = json!({
+        "model": request.model.as_deref().unwrap_or(default_model),
+        "messages": request.messages,
+    });
+
+    // OpenAI rejects `tool_choice` and `parallel_tool_calls` when no tools are sent.
+    if let Some(tools) = &request.tools {
+        body["tools"] = tools.clone();
+        body["parallel_tool_calls"] = json!(request.parallel_tool_calls);
+        if let Some(tool_choice) = &request.tool_choice {
+            body["tool_choice"] = json!(tool_choice);
+        }
+    }
+    body
+}
+
+async fn send_chat_request(
+    client: &Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    body: &Value,
+) -> Result<Value> {
+    let mut builder = client.post(format!("{}/chat/completions", base_url)).json(body);
+    if let Some(key) = api_key {
+        builder = builder.bearer_auth(key);
+    }
+
+    let response = builder.send().await?;
+    let status = response.status();
+    let payload: Value = response.json().await?;
+    if !status.is_success() {
+        return Err(anyhow!("LLM request failed with {}: {}", status, payload));
+    }
+
+    payload
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("message"))
+        .cloned()
+        .ok_or_else(|| anyhow!("No response from LLM"))
+}
+
+/// Sends the request with `stream: true` and reassembles the assistant message
+/// (content and tool calls) from the server-sent chunks.
+async fn send_chat_stream_request(
+    client: &Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    body: &Value,
+    on_delta: &(dyn Fn(&str) + Send + Sync),
+) -> Result<Value> {
+    let mut body = body.clone();
+    body["stream"] = json!(true);
+
+    let mut builder = client.post(format!("{}/chat/completions", base_url)).json(&body);
+    if let Some(key) = api_key {
+        builder = builder.bearer_auth(key);
+    }
+
+    let response = builder.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let payload = response.text().await.unwrap_or_default();
+        return Err(anyhow!("LLM request failed with {}: {}", status, payload));
+    }
+
+    let mut stream = response.bytes_stream();
+    // Raw bytes: a multibyte character may be split across network chunks, so only
+    // complete lines are decoded.
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut content = String::new();
+    let mut tool_calls: Vec<Value> = Vec::new();
+
+    while let Some(bytes) = stream.next().await {
+        buffer.extend_from_slice(&bytes?);
+
+        while let Some(pos) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = std::str::from_utf8(&line)?;
+            let Some(data) = line.trim().strip_prefix("data:") else { continue };
+            let data = data.trim();
+            if data.is_empty() || data == "[DONE]" {
+                continue;
+            }
+
+            let chunk: Value = serde_json::from_str(data)?;
+            let delta = &chunk["choices"][0]["delta"];
+            if let Some(text) = delta["content"].as_str() {
+                content.push_str(text);
+                on_delta(text);
+            }
+            if let Some(calls) = delta["tool_calls"].as_array() {
+                for call in calls {
+                    merge_tool_call_delta(&mut tool_calls, call);
+                }
+            }
+        }
+    }
+
+    let mut message = json!({
+        "role": "assistant",
+        "content": if content.is_empty() { Value::Null } else { json!(content) },
+    });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = json!(tool_calls);
+    }
+    Ok(message)
+}
+
+/// Tool calls arrive in fragments keyed by `index`; names and arguments are concatenated.
+fn merge_tool_call_delta(tool_calls: &mut Vec<Value>, delta: &Value) {
+    let index = delta["index"].as_u64().unwrap_or(0) as usize;
+    while tool_calls.len() <= index {
+        tool_calls.push(json!({"id": "", "type": "function", "function": {"name": "", "arguments": ""}}));
+    }
+
+    let call = &mut tool_calls[index];
+    if let Some(id) = delta["id"].as_str() {
+        call["id"] = json!(id);
+    }
+    for field in ["name", "arguments"] {
+        if let Some(fragment) = delta["function"][field].as_str() {
+            let merged = format!("{}{}", call["function"][field].as_str().unwrap_or_default(), fragment);
+            call["function"][field] = json!(merged);
+        }
+    }
+}
+
+/// Provider backed by the hosted OpenAI API.
+pub struct OpenAiProvider {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new_openai_provider(api_key: &str, model: &str) -> Self {
+        Self {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<Value> {
+        let body = build_request_body(&request, &self.model);
+        send_chat_request(&self.client, OPENAI_API_URL, Some(&self.api_key), &body).await
+    }
+
+    async fn chat_stream(&self, request: ChatRequest, on_delta: &(dyn Fn(&str) + Send + Sync)) -> Result<Value> {
+        let body = build_request_body(&request, &self.model);
+        send_chat_stream_request(&self.client, OPENAI_API_URL, Some(&self.api_key), &body, on_delta).await
+    }
+}
+
+/// Provider for a self-hosted endpoint speaking the OpenAI chat completions protocol
+/// (vLLM, Ollama, llama.cpp server, ...).
+pub struct LocalProvider {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl LocalProvider {
+    pub fn new_local_provider(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for LocalProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<Value> {
+        let body = build_request_body(&request, &self.model);
+        send_chat_request(&self.client, &self.base_url, self.api_key.as_deref(), &body).await
+    }
+
+    async fn chat_stream(&self, request: ChatRequest, on_delta: &(dyn Fn(&str) + Send + Sync)) -> Result<Value> {
+        let body = build_request_body(&request, &self.model);
+        send_chat_stream_request(&self.client, &self.base_url, self.api_key.as_deref(), &body, on_delta).await
+    }
+}
+
+/// In-memory provider that replays scripted assistant messages in order and
+/// records every request it receives. Used to drive the agent offline.
+pub struct MockProvider {
+    model: String,
+    replies: Mutex<VecDeque<Value>>,
+    requests: Mutex<Vec<ChatRequest>>,
+}
+
+impl MockProvider {
+    pub fn new_mock_provider(replies: Vec<Value>) -> Self {
+        Self {
+            model: "mock".to_string(),
+            replies: Mutex::new(replies.into()),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    /// Scripted assistant message with plain text content.
+    pub fn text_reply(content: &str) -> Value {
+        json!({"role": "assistant", "content": content})
+    }
+
+    /// Scripted assistant message requesting a single tool call.
+    pub fn tool_call_reply(id: &str, name: &str, arguments: Value) -> Value {
+        Self::tool_calls_reply(&[(id, name, arguments)])
+    }
+
+    /// Scripted assistant message requesting several tool calls in one response.
+    pub fn tool_calls_reply(calls: &[(&str, &str, Value)]) -> Value {
+        let tool_calls: Vec<Value> = calls
+            .iter()
+            .map(|(id, name, arguments)| {
+                json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {"name": name, "arguments": arguments.to_string()}
+                })
+            })
+            .collect();
+        json!({"role": "assistant", "content": null, "tool_calls": tool_calls})
+    }
+
+    pub fn push_reply(&self, reply: Value) {
+        self.replies.lock().unwrap().push_back(reply);
+    }
+
+    /// Requests received so far, in call order.
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<Value> {
+        self.requests.lock().unwrap().push(request);
+        self.replies
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow!("MockProvider has no scripted replies left"))
+    }
+}
+
+/// Builds the provider selected by `LLM_PROVIDER`.
+pub fn build_provider(settings: &LlmSettings) -> Arc<dyn LlmProvider> {
+    match settings.provider.as_str() {
+        "local" => Arc::new(LocalProvider::new_local_provider(
+            &settings.base_url,
+            &settings.model,
+            (!settings.api_key.is_empty()).then(|| settings.api_key.clone()),
+        )),
+        _ => Arc::new(OpenAiProvider::new_openai_provider(&settings.api_key, &settings.model)),
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use crate::llm::actions::{
+    analyze_call_identify_pool, check_trade_rules, process_fetch_data_from_dex_screener, process_shilling, publish_twitter_post,
+    record_rejection, retrieve_buy_decision, retrieve_pnl_information, retrieve_portfolio_information, validate_raydium_pool,
+    PnlAction,
+};
+use crate::models::base::{ConversationStatus, RejectionSource};
+
+/// State shared with every tool handler during a conversation.
+pub struct ToolContext {
+    pub pool: PgPool,
+    pub user_address: String,
+    /// Chat the conversation belongs to; trades opened from it are linked to this chat.
+    pub chat_uuid: String,
+}
+
+/// Result of a tool invocation: the text returned to the model plus optional
+/// conversation outcome and payload for the caller.
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub content: String,
+    pub status: Option<ConversationStatus>,
+    pub aux_data: Option<Value>,
+}
+
+impl ToolOutput {
+    pub fn new_tool_output(content: String) -> Self {
+        Self { content, status: None, aux_data: None }
+    }
+
+    pub fn with_status(mut self, status: ConversationStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_aux_data(mut self, aux_data: Value) -> Self {
+        self.aux_data = Some(aux_data);
+        self
+    }
+}
+
+/// A function the model can call. The JSON schema sent to the model is generated
+/// from `Args`, and dispatch deserializes into the same type.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    const NAME: &'static str;
+    const DESCRIPTION: &'static str;
+    /// Progress shown to the user while the tool runs, e.g. "fetching DexScreener data".
+    const LABEL: &'static str;
+    /// Buy or reject decision that ends the turn; at most one runs per turn.
+    const IS_DECISION: bool = false;
+    type Args: JsonSchema + DeserializeOwned + Send;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput>;
+}
+
+/// Object-safe view of `Tool` stored in the registry.
+#[async_trait]
+trait ErasedTool: Send + Sync {
+    fn definition(&self) -> Value;
+    fn label(&self) -> &'static str;
+    fn is_decision(&self) -> bool;
+    async fn call_json(&self, ctx: &ToolContext, args: Value) -> Result<ToolOutput>;
+}
+
+#[async_trait]
+impl<T: Tool> ErasedTool for T {
+    fn definition(&self) -> Value {
+        let generator = SchemaSettings::draft07()
+            .with(|s| {
+                s.inline_subschemas = true;
+                s.meta_schema = None;
+            })
+            .into_generator();
+        let mut parameters = serde_json::to_value(generator.into_root_schema_for::<T::Args>())
+            .unwrap_or_else(|_| json!({"type": "object"}));
+
+        if let Some(object) = parameters.as_object_mut() {
+            object.remove("title");
+            object.entry("properties").or_insert_with(|| json!({}));
+        }
+
+        json!({
+            "type": "function",
+            "function": {
+                "name": T::NAME,
+                "description": T::DESCRIPTION,
+                "parameters": parameters,
+            }
+        })
+    }
+
+    fn label(&self) -> &'static str {
+        T::LABEL
+    }
+
+    fn is_decision(&self) -> bool {
+        T::IS_DECISION
+    }
+
+    async fn call_json(&self, ctx: &ToolContext, args: Value) -> Result<ToolOutput> {
+        let args: T::Args = serde_json::from_value(args)
+            .map_err(|e| anyhow!("Invalid arguments for `{}`: {}", T::NAME, e))?;
+        self.call(ctx, args).await
+    }
+}
+
+/// Named collection of tools and the groups exposed to the model per conversation mode.
+pub struct ToolRegistry {
+    tools: HashMap<&'static str, Arc<dyn ErasedTool>>,
+    groups: HashMap<&'static str, Vec<&'static str>>,
+}
+
+impl ToolRegistry {
+    pub fn new_tool_registry() -> Self {
+        Self {
+            tools: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn register<T: Tool + 'static>(&mut self, tool: T) -> &mut Self {
+        self.tools.insert(T::NAME, Arc::new(tool));
+        self
+    }
+
+    /// Declares a group. Every name must already be registered.
+    pub fn add_group(&mut self, group: &'static str, names: &[&'static str]) -> &mut Self {
+        for name in names {
+            assert!(self.tools.contains_key(name), "tool `{}` in group `{}` is not registered", name, group);
+        }
+        self.groups.insert(group, names.to_vec());
+        self
+    }
+
+    pub fn group_contains(&self, group: &str, name: &str) -> bool {
+        self.groups.get(group).is_some_and(|names| names.contains(&name))
+    }
+
+    /// Label of the tool, or the name itself for unknown tools.
+    pub fn label<'n>(&self, name: &'n str) -> &'n str {
+        self.tools.get(name).map_or(name, |tool| tool.label())
+    }
+
+    pub fn is_decision(&self, name: &str) -> bool {
+        self.tools.get(name).is_some_and(|tool| tool.is_decision())
+    }
+
+    /// OpenAI `tools` array for the group.
+    pub fn definitions(&self, group: &str) -> Result<Value> {
+        let names = self.groups.get(group).ok_or_else(|| anyhow!("Unknown tool group: {}", group))?;
+        Ok(Value::Array(names.iter().map(|name| self.tools[name].definition()).collect()))
+    }
+
+    pub async fn dispatch(&self, name: &str, args: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let tool = self.tools.get(name).ok_or_else(|| anyhow!("Unknown tool: {}", name))?;
+        tool.call_json(ctx, args).await
+    }
+}
+
+pub const SHILLING_GROUP: &str = "shilling";
+pub const SHILLING_NOT_ALLOWED_GROUP: &str = "shilling_not_allowed";
+pub const TWITTER_GROUP: &str = "twitter";
+
+pub static TOOL_REGISTRY: Lazy<ToolRegistry> = Lazy::new(|| {
+    let mut registry = ToolRegistry::new_tool_registry();
+    registry
+        .register(ApproveShilling)
+        .register(RejectShilling)
+        .register(IdentifyPool)
+        .register(FetchPoolData)
+        .register(RetrieveCurrentPortfolio)
+        .register(RetrieveBuyExplanation)
+        .register(RetrievePnlInformation)
+        .register(AnalyzeCallIdentifyPool)
+        .register(GeneratePostInTwitter);
+
+    registry
+        .add_group(SHILLING_GROUP, &[
+            AnalyzeCallIdentifyPool::NAME,
+            ApproveShilling::NAME,
+            RejectShilling::NAME,
+            IdentifyPool::NAME,
+            FetchPoolData::NAME,
+            RetrieveCurrentPortfolio::NAME,
+            RetrieveBuyExplanation::NAME,
+            RetrievePnlInformation::NAME,
+        ])
+        .add_group(SHILLING_NOT_ALLOWED_GROUP, &[
+            AnalyzeCallIdentifyPool::NAME,
+            IdentifyPool::NAME,
+            RetrieveCurrentPortfolio::NAME,
+            RetrieveBuyExplanation::NAME,
+            RetrievePnlInformation::NAME,
+        ])
+        .add_group(TWITTER_GROUP, &[GeneratePostInTwitter::NAME]);
+    registry
+});
+
+#[derive(Deserialize, JsonSchema)]
+pub struct NoArgs {}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ApproveShillingArgs {
+    /// Explanation for why you decide to buy the token.
+    pub explanation: String,
+    /// Extract the poolAddress from analytic data.
+    #[serde(rename = "poolAddress")]
+    pub pool_address: String,
+}
+
+pub struct ApproveShilling;
+
+#[async_trait]
+impl Tool for ApproveShilling {
+    const NAME: &'static str = "approveShilling";
+    const DESCRIPTION: &'static str = "Approve buying meme token from Raydium explanation.";
+    const LABEL: &'static str = "buying the token";
+    const IS_DECISION: bool = true;
+    type Args = ApproveShillingArgs;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        // The model's approval is not enough: mandatory rules are re-checked on fresh data.
+        let report = check_trade_rules(&ctx.pool, &args.pool_address).await?;
+        if !report.passed() {
+            warn!("approveShilling blocked for {}:\n{}", args.pool_address, report.summary());
+            let reason = report.summary();
+            if let Err(e) = record_rejection(&ctx.pool, &ctx.chat_uuid, &ctx.user_address, Some(&args.pool_address), RejectionSource::TradeRules, &reason).await {
+                error!("Failed to record rejection of {}: {}", args.pool_address, e);
+            }
+            return Ok(ToolOutput::new_tool_output(
+                "After a closer look at the market data and my risk management signals, I'm passing on this one for now. Thanks for bringing it to me — keep the gems coming!".to_string(),
+            )
+            .with_status(ConversationStatus::Reject)
+            .with_aux_data(json!({"poolAddress": args.pool_address, "ruleReport": report})));
+        }
+
+        let (details, status) = process_shilling(&ctx.pool, &ctx.chat_uuid, &ctx.user_address, &args.explanation, &args.pool_address).await?;
+        Ok(ToolOutput::new_tool_output(details)
+            .with_status(status)
+            .with_aux_data(json!({"poolAddress": args.pool_address})))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RejectShillingArgs {
+    /// Explanation for why you reject buying the token.
+    pub explanation: String,
+    /// Pool address of the rejected token, if known.
+    #[serde(rename = "poolAddress", default)]
+    pub pool_address: Option<String>,
+}
+
+pub struct RejectShilling;
+
+#[async_trait]
+impl Tool for RejectShilling {
+    const NAME: &'static str = "rejectShilling";
+    const DESCRIPTION: &'static str = "Reject buying meme token from Raydium and provide an explanation.";
+    const LABEL: &'static str = "passing on the token";
+    const IS_DECISION: bool = true;
+    type Args = RejectShillingArgs;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        if let Err(e) = record_rejection(
+            &ctx.pool,
+            &ctx.chat_uuid,
+            &ctx.user_address,
+            args.pool_address.as_deref(),
+            RejectionSource::Model,
+            &args.explanation,
+        )
+        .await
+        {
+            error!("Failed to record rejection: {}", e);
+        }
+        Ok(ToolOutput::new_tool_output(args.explanation).with_status(ConversationStatus::Reject))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct IdentifyPoolArgs {
+    /// Fetch token or pool address from Raydium URL.
+    pub pool_or_token_address: String,
+}
+
+pub struct IdentifyPool;
+
+#[async_trait]
+impl Tool for IdentifyPool {
+    const NAME: &'static str = "identifyPool";
+    const DESCRIPTION: &'static str = "Fetch address of a given pool or token address on Raydium.";
+    const LABEL: &'static str = "looking up the Raydium pool";
+    type Args = IdentifyPoolArgs;
+
+    async fn call(&self, _ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        match validate_raydium_pool(&args.pool_or_token_address).await {
+            Ok(pool) => {
+                let token = pool.token_mint();
+                let result = format!(
+                    "Pool address: {}\nToken: {} ({}), address: {}\nPool type: {:?}, TVL: ${:.0}",
+                    pool.id, token.symbol, token.name, token.address, pool.pool_type(), pool.tvl
+                );
+                Ok(ToolOutput::new_tool_output(result)
+                    .with_status(ConversationStatus::ReadyToShilling)
+                    .with_aux_data(json!({"poolAddress": pool.id, "tokenAddress": token.address})))
+            }
+            Err(e) => Ok(ToolOutput::new_tool_output(e.to_string()).with_status(ConversationStatus::Discuss)),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct FetchPoolDataArgs {
+    /// The address of the pool to fetch data for.
+    pub token_address: String,
+}
+
+pub struct FetchPoolData;
+
+#[async_trait]
+impl Tool for FetchPoolData {
+    const NAME: &'static str = "fetch_pool_data";
+    const DESCRIPTION: &'static str = "Fetch analytics data for a given token address.";
+    const LABEL: &'static str = "fetching DexScreener data";
+    type Args = FetchPoolDataArgs;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        let analysis = process_fetch_data_from_dex_screener(&ctx.pool, &args.token_address).await?;
+        Ok(ToolOutput::new_tool_output(analysis))
+    }
+}
+
+pub struct RetrieveCurrentPortfolio;
+
+#[async_trait]
+impl Tool for RetrieveCurrentPortfolio {
+    const NAME: &'static str = "retrieveCurrentPortfolio";
+    const DESCRIPTION: &'static str = "Retrieve information about the current agent's portfolio.";
+    const LABEL: &'static str = "checking the portfolio";
+    type Args = NoArgs;
+
+    async fn call(&self, ctx: &ToolContext, _args: Self::Args) -> Result<ToolOutput> {
+        Ok(ToolOutput::new_tool_output(retrieve_portfolio_information(&ctx.pool).await?))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RetrieveBuyExplanationArgs {
+    /// Retrieve the pool address of the token pair.
+    pub pool_address: String,
+}
+
+pub struct RetrieveBuyExplanation;
+
+#[async_trait]
+impl Tool for RetrieveBuyExplanation {
+    const NAME: &'static str = "retrieveBuyExplanation";
+    const DESCRIPTION: &'static str = "Retrieve explanation for why a specific meme token was bought.";
+    const LABEL: &'static str = "looking up why the token was bought";
+    type Args = RetrieveBuyExplanationArgs;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        Ok(ToolOutput::new_tool_output(retrieve_buy_decision(&ctx.pool, &args.pool_address).await?))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RetrievePnlInformationArgs {
+    /// Specify the action to retrieve PnL statistics.
+    pub action: PnlAction,
+}
+
+pub struct RetrievePnlInformation;
+
+#[async_trait]
+impl Tool for RetrievePnlInformation {
+    const NAME: &'static str = "retrievePnlInformation";
+    const DESCRIPTION: &'static str = "Retrieve profit and loss (PnL) statistics based on the user's request.";
+    const LABEL: &'static str = "calculating profit and loss";
+    type Args = RetrievePnlInformationArgs;
+
+    async fn call(&self, ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        Ok(ToolOutput::new_tool_output(retrieve_pnl_information(&ctx.pool, args.action).await?))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AnalyzeCallIdentifyPoolArgs {
+    /// Boolean value to check if user message contains address or pool address or link.
+    pub is_function_call: bool,
+    /// User message to analyze.
+    pub user_message: String,
+}
+
+pub struct AnalyzeCallIdentifyPool;
+
+#[async_trait]
+impl Tool for AnalyzeCallIdentifyPool {
+    const NAME: &'static str = "analyzeCallIdentifyPool";
+    const DESCRIPTION: &'static str = "Analyze user message if there address of token to call identifyPool function.";
+    const LABEL: &'static str = "reading the message for a token address";
+    type Args = AnalyzeCallIdentifyPoolArgs;
+
+    async fn call(&self, _ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        Ok(ToolOutput::new_tool_output(analyze_call_identify_pool(&args.user_message, args.is_function_call)))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GeneratePostInTwitterArgs {
+    /// Data provided to generate a post on Twitter.
+    pub data: String,
+}
+
+pub struct GeneratePostInTwitter;
+
+#[async_trait]
+impl Tool for GeneratePostInTwitter {
+    const NAME: &'static str = "generatePostInTwitter";
+    const DESCRIPTION: &'static str = "Generate and post text on Twitter.";
+    const LABEL: &'static str = "posting on Twitter";
+    type Args = GeneratePostInTwitterArgs;
+
+    async fn call(&self, _ctx: &ToolContext, args: Self::Args) -> Result<ToolOutput> {
+        Ok(ToolOutput::new_tool_output(publish_twitter_post(&args.data)?))
+    }
+}
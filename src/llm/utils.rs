@@ -1,137 +1,78 @@
 use anyhow::{Result, anyhow};
 use serde_json::{json, Value};
 use sqlx::PgPool;
-use tracing::{error, info};
 
-//"retrievePnlInformation" => retrieve_pnl_information(args, pool).await,
-
-use crate::llm::actions::{process_fetch_data_from_dex_screener, process_shilling, retrieve_portfolio_information, retrieve_buy_decision, validate_raydium_pool, publish_twitter_post, analyze_call_identify_pool};
-use crate::models::base::{ConversationStatus};
-use crate::core::errors::LLMErrors;
-use rig::providers::openai;
+use crate::llm::provider::{ChatRequest, LlmProvider};
+use crate::llm::tools::{ToolContext, ToolOutput, TOOL_REGISTRY};
 
 
 /// Call the appropriate function based on LLM request
-pub async fn call_function(name: &str, args: &Value, pool: &PgPool) -> Result<Value> {
-    match name {
-        "fetch_pool_data" => process_fetch_data_from_dex_screener(args).await,
-        "approveShilling" => process_shilling(args),
-        "retrieveCurrentPortfolio" => retrieve_portfolio_information(args, pool).await,
-        "retrieveBuyExplanation" => retrieve_buy_decision(args, pool).await,
-        //"retrievePnlInformation" => retrieve_pnl_information(args, pool).await,
-        "identifyPool" => validate_raydium_pool(args),
-        "generatePostInTwitter" => publish_twitter_post(args),
-        "analyzeCallIdentifyPool" => analyze_call_identify_pool(args),
-        _ => Err(anyhow!(LlmErrors::CALL_FUNCTION_ERROR)),
-    }
-}
-
-/// Process response filtering
-pub async fn process_filtering_reply(
-    reply: Value,
-    messages: &mut Vec<Value>,
-    tools: Value,
-    pool: &PgPool,
-) -> Result<(String, ConversationStatus, Option<Value>)> {
-    let filtering_reply = get_reply(messages, &tools, "auto").await?;
-
-    if let Some(tool_calls) = filtering_reply.get("tool_calls") {
-        let tool_call = &tool_calls[0];
-        let (name, args) = parse_tool_call(tool_call)?;
-
-        info!("Called filtering function: {}", name);
-        messages.push(filtering_reply.clone());
-
-        if name == "identifyPool" {
-            let (result, is_pool_exists, aux_data) = call_function(&name, &args, pool).await?;
-            messages.push(json!({"role": "tool", "tool_call_id": tool_call["id"], "content": result}));
-
-            let nested_reply = get_reply(messages, &tools, "auto").await?;
-            let status = if is_pool_exists { ConversationStatus::ReadyToShilling } else { ConversationStatus::Discuss };
-            return Ok((nested_reply["content"].to_string(), status, aux_data));
-        }
-    }
-
-    Ok((reply["content"].to_string(), ConversationStatus::Discuss, None))
-}
-
-/// Process LLM tool calls
-pub async fn process_tool_calls(
-    reply: Value,
-    messages: &mut Vec<Value>,
-    tools: Value,
-    pool: &PgPool,
-    user_address: &str,
-) -> Result<(String, ConversationStatus, Option<Value>)> {
-    let tool_call = &reply["tool_calls"][0];
-    let (name, args) = parse_tool_call(tool_call)?;
-    info!("Called function: {}", name);
-
-    let result = call_function(&name, &args, pool).await?;
-    messages.push(json!({"role": "tool", "tool_call_id": tool_call["id"], "content": result}));
-
-    let nested_reply = get_reply(messages, &tools, "auto").await?;
-    if let Some(nested_tool_calls) = nested_reply.get("tool_calls") {
-        let nested_tool_call = &nested_tool_calls[0];
-        let (nested_name, nested_args) = parse_tool_call(nested_tool_call)?;
-
-        info!("Called nested function: {}", nested_name);
-
-        if nested_name == "approveShilling" {
-            let token_entity = call_function(&nested_name, &nested_args, pool).await?;
-            return Ok((format!("{}. {}", nested_args["explanation"], result), ConversationStatus::Approve, Some(token_entity)));
-        }
-    }
-
-    Ok((nested_reply["content"].to_string(), ConversationStatus::Discuss, None))
+pub async fn call_function(name: &str, args: &Value, ctx: &ToolContext) -> Result<ToolOutput> {
+    TOOL_REGISTRY.dispatch(name, args.clone(), ctx).await
 }
 
 /// Wrapper to call chat completion
 pub async fn chat_completion(
+    provider: &dyn LlmProvider,
     messages: &Vec<Value>,
     tools: Option<Value>,
     tool_choice: &str,
     parallel_tool_calls: bool,
-    model: &str,
+    model: Option<&str>,
 ) -> Result<Value> {
-    let client = openai::Client::new(&std::env::var("OPENAI_API_KEY")?);
-    let mut request = json!({
-        "model": model,
-        "messages": messages,
-        "parallel_tool_calls": parallel_tool_calls
-    });
-
-    if let Some(t) = tools {
-        request["tools"] = t;
-    }
-    if !tool_choice.is_empty() {
-        request["tool_choice"] = json!(tool_choice);
-    }
+    let request = ChatRequest {
+        messages: messages.clone(),
+        tools,
+        tool_choice: (!tool_choice.is_empty()).then(|| tool_choice.to_string()),
+        parallel_tool_calls,
+        model: model.map(str::to_string),
+    };
+    provider.chat(request).await
+}
 
-    let response = client.chat().completions().create("gpt-4o", request).await?;
-    Ok(response)
+/// Wrapper to call chat completion streaming content chunks into `on_delta`
+pub async fn chat_completion_stream(
+    provider: &dyn LlmProvider,
+    messages: &Vec<Value>,
+    tools: Option<Value>,
+    tool_choice: &str,
+    parallel_tool_calls: bool,
+    model: Option<&str>,
+    on_delta: &(dyn Fn(&str) + Send + Sync),
+) -> Result<Value> {
+    let request = ChatRequest {
+        messages: messages.clone(),
+        tools,
+        tool_choice: (!tool_choice.is_empty()).then(|| tool_choice.to_string()),
+        parallel_tool_calls,
+        model: model.map(str::to_string),
+    };
+    provider.chat_stream(request, on_delta).await
 }
 
 /// Parse a tool call into its name and arguments
 pub fn parse_tool_call(tool_call: &Value) -> Result<(String, Value)> {
-    let args = serde_json::from_value(tool_call["function"]["arguments"].clone())?;
-    let name = tool_call["function"]["name"].as_str().unwrap().to_string();
+    // OpenAI sends the arguments as a JSON-encoded string.
+    let args = match &tool_call["function"]["arguments"] {
+        Value::String(raw) if raw.trim().is_empty() => json!({}),
+        Value::String(raw) => serde_json::from_str(raw)?,
+        other => other.clone(),
+    };
+    let name = tool_call["function"]["name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Tool call without function name"))?
+        .to_string();
     Ok((name, args))
 }
 
-/// Get response from OpenAI chat completion
+/// Get the assistant message for the conversation using the provider's default model
 pub async fn get_reply(
+    provider: &dyn LlmProvider,
     messages: &Vec<Value>,
     tools: &Value,
     tool_choice: &str,
 ) -> Result<Value> {
-    let response = chat_completion(messages, Some(tools.clone()), tool_choice, false, "gpt-4o-mini").await?;
-    if let Some(choice) = response.get("choices").and_then(|c| c.get(0)) {
-        if let Some(message) = choice.get("message") {
-            return Ok(message.clone());
-        }
-    }
-    Err(anyhow!("No response from LLM"))
+    chat_completion(provider, messages, Some(tools.clone()), tool_choice, false, None).await
 }
 
 /// Async context manager to get DB session
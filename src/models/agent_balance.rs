@@ -10,7 +10,9 @@ pub struct Model {
     #[sea_orm(default_expr = "Expr::current_timestamp()")] 
     pub created_at: NaiveDateTime, 
 
-    pub amount: f64, 
+    /// Balance in USD; `None` when SOL could not be priced.
+    pub amount: Option<f64>, 
+    /// Balance in SOL.
     pub sol_amount: f64,
 }
 
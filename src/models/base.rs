@@ -3,6 +3,7 @@ use sea_orm::entity::prelude::*;
 use sea_orm::DeriveActiveEnum;  
 use strum::{EnumString, Display};
 use strum_macros::{EnumIter};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq)]
 #[sea_orm(rs_type = "String", db_type = "String")]  // ✅ Fix: Proper db_type
@@ -29,7 +30,7 @@ pub enum ActionParameter {
     Shilling,
 }
 
-#[derive(Debug, Clone, EnumString, Display)]
+#[derive(Debug, Clone, EnumString, Display, PartialEq, Serialize, Deserialize)]
 pub enum ConversationStatus { 
     Approve, 
     ApproveFailed, 
@@ -38,3 +39,141 @@ pub enum ConversationStatus {
     Discuss, 
     ReadyToShilling,
 }
+
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SolanaTransactionStatus {
+    #[sea_orm(string_value = "sent")]
+    Sent,
+    #[sea_orm(string_value = "processed")]
+    Processed,
+    #[sea_orm(string_value = "confirmed")]
+    Confirmed,
+    #[sea_orm(string_value = "finalized")]
+    Finalized,
+    #[sea_orm(string_value = "expired")]
+    Expired,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+impl SolanaTransactionStatus {
+    /// The transaction is on chain and succeeded.
+    pub fn is_landed(&self) -> bool {
+        matches!(self, Self::Confirmed | Self::Finalized)
+    }
+
+    /// No further status change is expected except confirmed -> finalized.
+    pub fn is_settled(&self) -> bool {
+        !matches!(self, Self::Sent | Self::Processed)
+    }
+}
+
+/// Why a position was sold.
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    #[sea_orm(string_value = "take_profit")]
+    TakeProfit,
+    #[sea_orm(string_value = "stop_loss")]
+    StopLoss,
+    #[sea_orm(string_value = "trailing_stop")]
+    TrailingStop,
+    #[sea_orm(string_value = "max_hold")]
+    MaxHold,
+    /// Sold through `/sell_tokens`.
+    #[sea_orm(string_value = "manual")]
+    Manual,
+    /// Run once when the scheduler starts.
+    #[sea_orm(string_value = "startup")]
+    Startup,
+}
+
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutStatus {
+    /// Recorded, transfer not attempted yet.
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// A transfer is being submitted.
+    #[sea_orm(string_value = "sending")]
+    Sending,
+    #[sea_orm(string_value = "sent")]
+    Sent,
+    /// The last transfer did not land; retried until `PAYOUT_MAX_ATTEMPTS`.
+    #[sea_orm(string_value = "failed")]
+    Failed,
+    /// No profit, or a share below `PAYOUT_MIN_SOL`.
+    #[sea_orm(string_value = "skipped")]
+    Skipped,
+}
+
+/// Lifecycle of a position.
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PositionStatus {
+    /// Recorded before the buy is sent.
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "open")]
+    Open,
+    /// A sell is running; no other sell may start.
+    #[sea_orm(string_value = "closing")]
+    Closing,
+    #[sea_orm(string_value = "closed")]
+    Closed,
+    /// The buy did not go through.
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// What turned a shilled token down.
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionSource {
+    /// The model called `rejectShilling`.
+    #[sea_orm(string_value = "model")]
+    Model,
+    /// A mandatory trade rule failed when `approveShilling` was called.
+    #[sea_orm(string_value = "trade_rules")]
+    TradeRules,
+    /// The round-trip swap simulation found the token untradeable or taxed.
+    #[sea_orm(string_value = "simulation")]
+    Simulation,
+}
+
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobRunStatus {
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "succeeded")]
+    Succeeded,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// What started a job run.
+#[derive(Debug, Clone, Copy, EnumString, Display, EnumIter, DeriveActiveEnum, PartialEq, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String")]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobTrigger {
+    #[sea_orm(string_value = "schedule")]
+    Schedule,
+    /// Triggered through the admin API.
+    #[sea_orm(string_value = "manual")]
+    Manual,
+}
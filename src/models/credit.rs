@@ -1,6 +1,10 @@
 use sea_orm::prelude::*;
 use crate::models::user;
 
+/// One credit per retweet: `(user_id, twitter_post_id)` is unique, as
+/// `UNIQUE (user_id, twitter_post_id)`, so awarding a credit twice is a no-op.
+/// Existing databases get the constraint from
+/// `migrations/20261018000000_credits_unique_retweet.sql`.
 #[derive(Clone, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "credits")]
 pub struct Model {
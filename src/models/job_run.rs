@@ -0,0 +1,31 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::base::{JobRunStatus, JobTrigger};
+
+/// One execution of a scheduled job.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "job_runs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    pub job_name: String,
+    pub trigger: JobTrigger,
+    pub status: JobRunStatus,
+    /// Scheduler instance that held the lease.
+    pub instance_id: String,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub started_at: NaiveDateTime,
+
+    pub finished_at: Option<NaiveDateTime>,
+    pub duration_ms: Option<i64>,
+    /// Summary of what the job did.
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -3,6 +3,14 @@ pub mod base; // +
 pub mod chat; // + 
 pub mod credit; // + 
 pub mod db_helper; // + 
+pub mod job_run;
+pub mod payout;
+pub mod position;
+pub mod position_exit_rule;
+pub mod scheduled_job;
+pub mod shilling_rejection;
+pub mod solana_transaction;
+pub mod token;
 pub mod trade;
 pub mod user; // + 
 pub mod price_forecasting;
\ No newline at end of file
@@ -0,0 +1,44 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::base::PayoutStatus;
+
+/// Profit share owed to the shiller of a position. At most one row per position, so a
+/// position is never paid out twice.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "payouts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub updated_at: NaiveDateTime,
+
+    #[sea_orm(unique)]
+    pub trade_position_id: i32,
+    /// The `closed` trade the PnL was realized by.
+    pub trade_id: i32,
+    pub chat_uuid: String,
+    /// Wallet of the user who shilled the token.
+    pub wallet: String,
+
+    /// Realized PnL of the position after network fees, in SOL.
+    pub pnl_sol: f64,
+    pub share_pct: f64,
+    pub amount_lamports: i64,
+
+    #[sea_orm(default_value = "pending")]
+    pub status: PayoutStatus,
+    pub attempts: i32,
+    /// Latest transfer in `solana_transactions`.
+    pub solana_transaction_id: Option<i32>,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,38 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::base::{ExitReason, PositionStatus};
+
+/// A buy and its sells. The row is written before the buy is sent, so its id, the
+/// `trade_position_id` of the trades, comes from the table's sequence.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "positions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub updated_at: NaiveDateTime,
+
+    /// Chat the buy was approved in.
+    pub chat_uuid: String,
+    pub pool_address: String,
+    /// Set once the buy is recorded.
+    pub token_id: Option<i32>,
+
+    #[sea_orm(default_value = "pending")]
+    pub status: PositionStatus,
+    /// Tokens not sold yet, in UI units; set once the buy is recorded.
+    pub remaining_quantity: Option<f64>,
+    /// When the running sell claimed the position.
+    pub closing_started_at: Option<NaiveDateTime>,
+    /// Exit rule the running sell was started for, to record it with if its caller stops.
+    pub closing_reason: Option<ExitReason>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
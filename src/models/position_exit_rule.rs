@@ -0,0 +1,39 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+
+/// Exit rules of a single position, overriding the `EXIT_*` settings where set.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "position_exit_rules")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub updated_at: NaiveDateTime,
+
+    #[sea_orm(unique)]
+    pub trade_position_id: i32,
+    pub take_profit_pct: Option<f64>,
+    pub stop_loss_pct: Option<f64>,
+    pub trailing_stop_pct: Option<f64>,
+    pub max_hold_minutes: Option<i64>,
+    /// Rules turned off for this position even when set globally.
+    #[sea_orm(default_value = false)]
+    pub take_profit_disabled: bool,
+    #[sea_orm(default_value = false)]
+    pub stop_loss_disabled: bool,
+    #[sea_orm(default_value = false)]
+    pub trailing_stop_disabled: bool,
+    #[sea_orm(default_value = false)]
+    pub max_hold_disabled: bool,
+    /// Highest value of the position seen by the exit engine, in SOL; the trailing stop follows it.
+    pub peak_value_sol: Option<f64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,29 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+
+/// A recurring job known to the scheduler, shared by every instance.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "scheduled_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub updated_at: NaiveDateTime,
+
+    #[sea_orm(unique)]
+    pub name: String,
+    /// Cron or `@every` expression the job was last declared with.
+    pub schedule: String,
+    pub paused: bool,
+    /// When the job is due next, in UTC.
+    pub next_run_at: Option<NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,26 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::base::RejectionSource;
+
+/// A shilled token the agent decided not to buy.
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "shilling_rejections")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    pub chat_uuid: String,
+    /// Wallet of the user who shilled the token.
+    pub wallet: String,
+    pub pool_address: Option<String>,
+    pub source: RejectionSource,
+    pub reason: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
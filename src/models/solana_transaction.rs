@@ -0,0 +1,39 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::base::SolanaTransactionStatus;
+
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "solana_transactions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub updated_at: NaiveDateTime,
+
+    /// What the transaction does, e.g. `swap` or `payout`.
+    pub purpose: String,
+    /// Pool, wallet or record the transaction belongs to.
+    pub reference: Option<String>,
+
+    /// Signature of the latest submission; replaced when the transaction is re-signed.
+    #[sea_orm(unique)]
+    pub signature: String,
+    /// Hex encoded message of the latest submission, kept to resubmit after a restart.
+    pub message: String,
+    pub last_valid_block_height: i64,
+
+    #[sea_orm(default_value = "sent")]
+    pub status: SolanaTransactionStatus,
+    pub attempts: i32,
+    pub slot: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
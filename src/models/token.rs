@@ -0,0 +1,25 @@
+use sea_orm::prelude::*;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "tokens")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: NaiveDateTime,
+
+    /// Mint address.
+    #[sea_orm(unique)]
+    pub address: String,
+    pub symbol: String,
+    pub name: String,
+    /// Raydium pool the agent trades the token in.
+    pub pool_address: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
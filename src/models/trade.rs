@@ -1,6 +1,6 @@
 use sea_orm::prelude::*;
 use chrono::NaiveDateTime;
-use crate::models::base::TradeTypeEnum;
+use crate::models::base::{ExitReason, TradeTypeEnum};
 use sea_orm::sea_query::Expr;
 
 #[derive(Clone, Debug, DeriveEntityModel)]
@@ -22,9 +22,11 @@ pub struct Model {
     pub tx_id: String, 
     pub profit_loss: Option<bool>,
     pub token_id: i32,
-    pub payment_id: i32, 
+    pub payment_id: Option<i32>, 
     pub trade_position_id: i32, 
     pub fee_rate: Option<f64>,
+    /// Rule that triggered the sell, on `closed` trades.
+    pub exit_reason: Option<ExitReason>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,5 @@
+pub mod models;
+pub mod service;
+
+pub use models::{payout_amount, Payout};
+pub use service::PayoutService;
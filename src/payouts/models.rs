@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use solana_sdk::native_token::{lamports_to_sol, sol_to_lamports};
+
+use crate::models::base::PayoutStatus;
+
+/// Profit share of a closed position, as recorded in `payouts`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Payout {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub trade_position_id: i32,
+    pub trade_id: i32,
+    pub chat_uuid: String,
+    pub wallet: String,
+    /// Realized PnL of the position after network fees, in SOL.
+    pub pnl_sol: f64,
+    pub share_pct: f64,
+    pub amount_lamports: i64,
+    pub status: PayoutStatus,
+    pub attempts: i32,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Payout {
+    pub fn amount_sol(&self) -> f64 {
+        lamports_to_sol(self.amount_lamports.max(0) as u64)
+    }
+}
+
+/// Lamports owed for `pnl_sol` of realized profit, or `None` when there was no profit
+/// or the share is below `min_payout_sol`.
+pub fn payout_amount(pnl_sol: f64, share_pct: f64, min_payout_sol: f64) -> Option<u64> {
+    if pnl_sol <= 0.0 || share_pct <= 0.0 {
+        return None;
+    }
+    let share_sol = pnl_sol * share_pct / 100.0;
+    (share_sol >= min_payout_sol).then(|| sol_to_lamports(share_sol)).filter(|lamports| *lamports > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pays_the_share_of_a_profit() {
+        assert_eq!(payout_amount(0.1, 50.0, 0.001), Some(50_000_000));
+        assert_eq!(payout_amount(2.0, 10.0, 0.001), Some(200_000_000));
+    }
+
+    #[test]
+    fn pays_nothing_without_profit() {
+        assert_eq!(payout_amount(0.0, 50.0, 0.001), None);
+        assert_eq!(payout_amount(-0.5, 50.0, 0.001), None);
+        assert_eq!(payout_amount(0.1, 0.0, 0.001), None);
+    }
+
+    #[test]
+    fn skips_shares_below_the_minimum() {
+        assert_eq!(payout_amount(0.0019, 50.0, 0.001), None);
+        assert_eq!(payout_amount(0.004, 50.0, 0.001), Some(2_000_000));
+        assert_eq!(payout_amount(1e-12, 50.0, 0.0), None);
+    }
+}
@@ -0,0 +1,441 @@
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use std::str::FromStr;
+use tracing::{error, info, warn};
+
+use crate::core::config::PayoutSettings;
+use crate::models::base::PayoutStatus;
+use crate::models::trade::Model as Trade;
+use crate::payouts::models::{payout_amount, Payout};
+use crate::pnl::position_pnl;
+use crate::utils::error::PayoutError;
+use crate::utils::solana_driver::SolanaDriver;
+use crate::utils::transaction_manager::TrackedTransaction;
+
+/// Row of `payouts` with the status still as text.
+struct PayoutRow {
+    id: i32,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    trade_position_id: i32,
+    trade_id: i32,
+    chat_uuid: String,
+    wallet: String,
+    pnl_sol: f64,
+    share_pct: f64,
+    amount_lamports: i64,
+    status: String,
+    attempts: i32,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+impl From<PayoutRow> for Payout {
+    fn from(row: PayoutRow) -> Self {
+        Payout {
+            id: row.id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            trade_position_id: row.trade_position_id,
+            trade_id: row.trade_id,
+            chat_uuid: row.chat_uuid,
+            wallet: row.wallet,
+            pnl_sol: row.pnl_sol,
+            share_pct: row.share_pct,
+            amount_lamports: row.amount_lamports,
+            status: PayoutStatus::from_str(&row.status).unwrap_or(PayoutStatus::Failed),
+            attempts: row.attempts,
+            signature: row.signature,
+            error: row.error,
+        }
+    }
+}
+
+/// Pays shillers their share of the realized profit of the positions they brought in.
+///
+/// The `payouts` row is written before any transfer and is unique per position, and a
+/// transfer is only started after atomically moving the row to `sending`. A payout whose
+/// outcome is unknown is settled from the on-chain status of its last transfer before
+/// it may be retried, so a share is never sent twice.
+pub struct PayoutService {
+    pool: PgPool,
+    settings: PayoutSettings,
+}
+
+impl PayoutService {
+    pub fn new_payout_service(pool: PgPool, settings: PayoutSettings) -> Self {
+        Self { pool, settings }
+    }
+
+    pub fn from_settings(pool: PgPool) -> Self {
+        Self::new_payout_service(pool, PayoutSettings::new_payout())
+    }
+
+    /// Records the payout of a closed trade's position and sends it if one is owed.
+    pub async fn pay_out(&self, driver: &SolanaDriver, closed_trade: &Trade) -> Result<Payout, PayoutError> {
+        let payout = self.record_payout(closed_trade).await?;
+        match payout.status {
+            PayoutStatus::Pending => self.send(driver, payout.id).await,
+            _ => Ok(payout),
+        }
+    }
+
+    /// Computes the share from the realized PnL of the position after fees and records it, once per position.
+    pub async fn record_payout(&self, closed_trade: &Trade) -> Result<Payout, PayoutError> {
+        if let Some(existing) = self.payout_of_position(closed_trade.trade_position_id).await? {
+            return Ok(existing);
+        }
+
+        // Every sell of the position counts, partial ones included, each against the position's own
+        // buys: the same cost `PositionManager` charged when it recorded the sell.
+        let pnl_sol: f64 = position_pnl(&self.pool, closed_trade.trade_position_id).await?.iter().map(|sale| sale.pnl_sol).sum();
+        let wallet = sqlx::query_scalar!(
+            "SELECT u.wallet FROM chats c JOIN users u ON u.id = c.user_id WHERE c.uuid = $1",
+            closed_trade.chat_uuid
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(PayoutError::NoRecipient(closed_trade.trade_position_id))?;
+
+        let amount = payout_amount(pnl_sol, self.settings.share_pct, self.settings.min_payout_sol);
+        let status = if amount.is_some() { PayoutStatus::Pending } else { PayoutStatus::Skipped };
+        sqlx::query!(
+            r#"
+            INSERT INTO payouts (trade_position_id, trade_id, chat_uuid, wallet, pnl_sol, share_pct, amount_lamports, status, attempts)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 0)
+            ON CONFLICT (trade_position_id) DO NOTHING
+            "#,
+            closed_trade.trade_position_id,
+            closed_trade.id,
+            closed_trade.chat_uuid,
+            wallet,
+            pnl_sol,
+            self.settings.share_pct,
+            amount.unwrap_or(0) as i64,
+            status.to_string()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.payout_of_position(closed_trade.trade_position_id)
+            .await?
+            .ok_or(PayoutError::NotFound(closed_trade.trade_position_id))
+    }
+
+    /// Claims a pending or failed payout and transfers it.
+    pub async fn send(&self, driver: &SolanaDriver, payout_id: i32) -> Result<Payout, PayoutError> {
+        // A failed payout may have landed after all, or still be in flight.
+        if let Some(transfer) = self.last_transfer(driver, payout_id).await? {
+            if transfer.is_landed() {
+                return self.finish(payout_id, PayoutStatus::Sent, Some(transfer.id), Some(&transfer.signature.to_string()), None).await;
+            }
+            if !transfer.status.is_settled() {
+                warn!("Payout {} transfer {} is still in flight", payout_id, transfer.signature);
+                return self.payout(payout_id).await;
+            }
+        }
+
+        let claimed = sqlx::query!(
+            r#"
+            UPDATE payouts SET status = 'sending', attempts = attempts + 1, updated_at = NOW()
+            WHERE id = $1 AND status IN ('pending', 'failed') AND attempts < $2
+            RETURNING wallet, amount_lamports
+            "#,
+            payout_id,
+            self.settings.max_attempts
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(claimed) = claimed else {
+            // Sent, skipped, out of attempts or being sent by someone else.
+            return self.payout(payout_id).await;
+        };
+
+        match driver.transfer_share_to_user(&claimed.wallet, claimed.amount_lamports as u64, payout_id).await {
+            Ok(tracked) if tracked.is_landed() => {
+                info!("Payout {} of {} lamports sent to {}: {}", payout_id, claimed.amount_lamports, claimed.wallet, tracked.signature);
+                self.finish(payout_id, PayoutStatus::Sent, Some(tracked.id), Some(&tracked.signature.to_string()), None)
+                    .await
+            }
+            Ok(tracked) => {
+                warn!("Payout {} transaction {} {}", payout_id, tracked.signature, tracked.status);
+                let error = tracked.error.unwrap_or_else(|| format!("transaction {}", tracked.status));
+                self.finish(payout_id, PayoutStatus::Failed, Some(tracked.id), Some(&tracked.signature.to_string()), Some(&error))
+                    .await
+            }
+            Err(e) => {
+                // The transfer may have been broadcast before the error, so its on-chain status decides.
+                error!("Failed to transfer payout {}: {}", payout_id, e);
+                self.reconcile(driver, payout_id, Some(e.to_string())).await
+            }
+        }
+    }
+
+    /// Latest transfer recorded for a payout, with its status read from chain if it was
+    /// left in flight.
+    async fn last_transfer(&self, driver: &SolanaDriver, payout_id: i32) -> Result<Option<TrackedTransaction>, PayoutError> {
+        let reference = format!("payout:{}", payout_id);
+        let transaction_id = sqlx::query_scalar!(
+            "SELECT id FROM solana_transactions WHERE purpose = 'payout' AND reference = $1 ORDER BY id DESC LIMIT 1",
+            reference
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(transaction_id) = transaction_id else {
+            return Ok(None);
+        };
+        let transfer = driver
+            .transactions()
+            .settle_from_chain(transaction_id)
+            .await
+            .map_err(|e| PayoutError::Transfer(e.to_string()))?;
+        Ok(Some(transfer))
+    }
+
+    /// Settles a payout left in `sending` from the on-chain status of its last transfer.
+    pub async fn reconcile(&self, driver: &SolanaDriver, payout_id: i32, error: Option<String>) -> Result<Payout, PayoutError> {
+        let Some(transfer) = self.last_transfer(driver, payout_id).await? else {
+            // Transactions are recorded before their first broadcast: nothing was sent.
+            let error = error.unwrap_or_else(|| "no transfer recorded".to_string());
+            return self.finish(payout_id, PayoutStatus::Failed, None, None, Some(&error)).await;
+        };
+        let signature = transfer.signature.to_string();
+        if transfer.is_landed() {
+            self.finish(payout_id, PayoutStatus::Sent, Some(transfer.id), Some(&signature), None).await
+        } else if transfer.status.is_settled() {
+            let error = transfer.error.or(error).unwrap_or_else(|| format!("transaction {}", transfer.status));
+            self.finish(payout_id, PayoutStatus::Failed, Some(transfer.id), Some(&signature), Some(&error)).await
+        } else {
+            // Still in flight; settled again by `retry_failed` once it went stale.
+            self.payout(payout_id).await
+        }
+    }
+
+    /// Settles stale `sending` payouts, then retries failed ones whose last attempt is
+    /// older than `PAYOUT_RETRY_INTERVAL_SECS`.
+    pub async fn retry_failed(&self, driver: &SolanaDriver) -> Result<Vec<Payout>, PayoutError> {
+        let stale = sqlx::query_scalar!(
+            "SELECT id FROM payouts WHERE status = 'sending' AND updated_at < NOW() - make_interval(secs => $1)",
+            self.settings.retry_interval_secs as f64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for payout_id in stale {
+            if let Err(e) = self.reconcile(driver, payout_id, None).await {
+                error!("Failed to reconcile payout {}: {}", payout_id, e);
+            }
+        }
+
+        let failed = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM payouts
+            WHERE status = 'failed' AND attempts < $1 AND updated_at < NOW() - make_interval(secs => $2)
+            ORDER BY id
+            "#,
+            self.settings.max_attempts,
+            self.settings.retry_interval_secs as f64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut retried = Vec::with_capacity(failed.len());
+        for payout_id in failed {
+            info!("Retrying payout {}", payout_id);
+            match self.send(driver, payout_id).await {
+                Ok(payout) => retried.push(payout),
+                Err(e) => error!("Failed to retry payout {}: {}", payout_id, e),
+            }
+        }
+        Ok(retried)
+    }
+
+    pub async fn payout(&self, payout_id: i32) -> Result<Payout, PayoutError> {
+        let row = sqlx::query_as!(
+            PayoutRow,
+            r#"
+            SELECT id, created_at, updated_at, trade_position_id, trade_id, chat_uuid, wallet,
+                   pnl_sol, share_pct, amount_lamports, status, attempts, signature, error
+            FROM payouts WHERE id = $1
+            "#,
+            payout_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(Payout::from).ok_or(PayoutError::NotFound(payout_id))
+    }
+
+    pub async fn payout_of_position(&self, trade_position_id: i32) -> Result<Option<Payout>, PayoutError> {
+        let row = sqlx::query_as!(
+            PayoutRow,
+            r#"
+            SELECT id, created_at, updated_at, trade_position_id, trade_id, chat_uuid, wallet,
+                   pnl_sol, share_pct, amount_lamports, status, attempts, signature, error
+            FROM payouts WHERE trade_position_id = $1
+            "#,
+            trade_position_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Payout::from))
+    }
+
+    /// Lamports sent to shillers so far and the number of payouts they were sent in.
+    pub async fn total_shared(&self) -> Result<(u64, i64), PayoutError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(amount_lamports), 0)::BIGINT AS "lamports!", COUNT(*) AS "count!"
+            FROM payouts WHERE status = $1
+            "#,
+            PayoutStatus::Sent.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((row.lamports.max(0) as u64, row.count))
+    }
+
+    /// Payouts of a wallet, newest first, with the total count.
+    pub async fn payouts_for_wallet(&self, wallet: &str, page_number: usize, page_size: usize) -> Result<(Vec<Payout>, usize), PayoutError> {
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM payouts WHERE wallet = $1"#, wallet)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query_as!(
+            PayoutRow,
+            r#"
+            SELECT id, created_at, updated_at, trade_position_id, trade_id, chat_uuid, wallet,
+                   pnl_sol, share_pct, amount_lamports, status, attempts, signature, error
+            FROM payouts WHERE wallet = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            wallet,
+            page_size as i64,
+            (page_number.saturating_sub(1) * page_size) as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((rows.into_iter().map(Payout::from).collect(), total as usize))
+    }
+
+    async fn finish(
+        &self,
+        payout_id: i32,
+        status: PayoutStatus,
+        solana_transaction_id: Option<i32>,
+        signature: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<Payout, PayoutError> {
+        sqlx::query!(
+            r#"
+            UPDATE payouts
+            SET status = $2, solana_transaction_id = COALESCE($3, solana_transaction_id),
+                signature = COALESCE($4, signature), error = $5, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            payout_id,
+            status.to_string(),
+            solana_transaction_id,
+            signature,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+        self.payout(payout_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_fixtures::create_user_tables;
+    use crate::models::base::TradeTypeEnum;
+
+    /// Two chats in the same token, each with its own shiller.
+    async fn setup(pool: &PgPool) {
+        create_user_tables(pool).await;
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE tokens (id SERIAL PRIMARY KEY, address TEXT NOT NULL, symbol TEXT NOT NULL, pool_address TEXT NOT NULL);
+            CREATE TABLE trades (
+                id SERIAL PRIMARY KEY,
+                created_at TIMESTAMP NOT NULL,
+                chat_uuid TEXT NOT NULL,
+                trade_position_id INT NOT NULL,
+                token_id INT NOT NULL REFERENCES tokens (id),
+                trade_type TEXT NOT NULL,
+                base_token_quantity DOUBLE PRECISION NOT NULL,
+                quote_token_quantity DOUBLE PRECISION NOT NULL,
+                fee_rate DOUBLE PRECISION,
+                tx_id TEXT NOT NULL
+            );
+            CREATE TABLE payouts (
+                id SERIAL PRIMARY KEY,
+                created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+                trade_position_id INT NOT NULL UNIQUE,
+                trade_id INT NOT NULL,
+                chat_uuid TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                pnl_sol DOUBLE PRECISION NOT NULL,
+                share_pct DOUBLE PRECISION NOT NULL,
+                amount_lamports BIGINT NOT NULL,
+                status TEXT NOT NULL,
+                attempts INT NOT NULL,
+                solana_transaction_id INT,
+                signature TEXT,
+                error TEXT
+            );
+            INSERT INTO users (wallet) VALUES ('wallet-a'), ('wallet-b');
+            INSERT INTO chats (uuid, user_id) VALUES ('chat-a', 1), ('chat-b', 2);
+            INSERT INTO tokens (address, symbol, pool_address) VALUES ('mint', 'TKN', 'pool');
+            INSERT INTO trades (created_at, chat_uuid, trade_position_id, token_id, trade_type, base_token_quantity, quote_token_quantity, fee_rate, tx_id) VALUES
+                ('2024-05-01 00:01', 'chat-a', 10, 1, 'open', 1.0, 1000.0, 0.0, 'tx1'),
+                ('2024-05-01 00:02', 'chat-b', 20, 1, 'open', 4.0, 1000.0, 0.0, 'tx2'),
+                ('2024-05-01 00:03', 'chat-b', 20, 1, 'closed', 3.0, 1000.0, 0.0, 'tx3'),
+                ('2024-05-01 00:04', 'chat-a', 10, 1, 'closed', 2.0, 1000.0, 0.0, 'tx4');
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn closed_trade(id: i32, trade_position_id: i32, chat_uuid: &str, sol_received: f64) -> Trade {
+        Trade {
+            id,
+            created_at: NaiveDateTime::default(),
+            chat_uuid: chat_uuid.to_string(),
+            base_token_quantity: sol_received,
+            quote_token_quantity: 1000.0,
+            trade_type: TradeTypeEnum::Closed,
+            tx_id: format!("tx{}", id),
+            profit_loss: Some(sol_received > 1.0),
+            token_id: 1,
+            payment_id: None,
+            trade_position_id,
+            fee_rate: Some(0.0),
+            exit_reason: None,
+        }
+    }
+
+    fn service(pool: PgPool) -> PayoutService {
+        let settings = PayoutSettings { share_pct: 10.0, min_payout_sol: 0.001, max_attempts: 3, retry_interval_secs: 60 };
+        PayoutService::new_payout_service(pool, settings)
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn pays_nothing_when_the_position_lost_on_its_own_buy(pool: PgPool) {
+        setup(&pool).await;
+        let service = service(pool);
+
+        // Sold above the token's first buy, but below what this position paid.
+        let losing = service.record_payout(&closed_trade(3, 20, "chat-b", 3.0)).await.unwrap();
+        assert_eq!(losing.status, PayoutStatus::Skipped);
+        assert_eq!(losing.amount_lamports, 0);
+        assert!((losing.pnl_sol + 1.0).abs() < 1e-9);
+
+        let winning = service.record_payout(&closed_trade(4, 10, "chat-a", 2.0)).await.unwrap();
+        assert_eq!(winning.status, PayoutStatus::Pending);
+        assert_eq!(winning.wallet, "wallet-a");
+        assert_eq!(winning.amount_lamports, 100_000_000);
+    }
+}
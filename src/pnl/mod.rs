@@ -0,0 +1,5 @@
+pub mod models;
+pub mod service;
+
+pub use models::{PnlReport, RealizedSale, TokenPnl, TradeFill};
+pub use service::{compute_pnl, fifo_pnl, position_pnl, trade_pnl};
@@ -0,0 +1,95 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// A buy or sell from `trades`, in the units the engine works with.
+#[derive(Debug, Clone)]
+pub struct TradeFill {
+    pub trade_id: i32,
+    pub trade_position_id: i32,
+    pub token_id: i32,
+    pub token_address: String,
+    pub symbol: String,
+    pub pool_address: String,
+    pub is_buy: bool,
+    /// SOL spent on a buy, or received from a sell.
+    pub sol_amount: f64,
+    pub token_quantity: f64,
+    /// Network fee of the transaction, priority fee included, in SOL.
+    pub fee_sol: f64,
+    pub tx_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Tokens of one buy not sold yet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Lot {
+    pub trade_id: i32,
+    pub quantity: f64,
+    /// SOL paid per token, buy fee included.
+    pub cost_per_token: f64,
+    pub opened_at: NaiveDateTime,
+}
+
+/// A sell matched against the oldest lots of its token.
+#[derive(Debug, Clone, Serialize)]
+pub struct RealizedSale {
+    pub trade_id: i32,
+    pub trade_position_id: i32,
+    pub token_address: String,
+    pub symbol: String,
+    pub tx_id: String,
+    pub token_quantity: f64,
+    /// SOL received, sell fee deducted.
+    pub proceeds_sol: f64,
+    /// SOL paid for the lots sold, buy fees included.
+    pub cost_basis_sol: f64,
+    pub pnl_sol: f64,
+    pub pnl_pct: f64,
+    pub closed_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPnl {
+    pub token_address: String,
+    pub symbol: String,
+    pub pool_address: String,
+    pub realized_pnl_sol: f64,
+    /// Network fees of every buy and sell of the token, in SOL.
+    pub fees_sol: f64,
+    pub open_lots: Vec<Lot>,
+    pub open_quantity: f64,
+    pub open_cost_basis_sol: f64,
+    /// `None` when the token could not be priced.
+    pub market_value_sol: Option<f64>,
+    pub unrealized_pnl_sol: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PnlReport {
+    pub realized_pnl_sol: f64,
+    /// Over the tokens that could be priced.
+    pub unrealized_pnl_sol: f64,
+    pub fees_sol: f64,
+    pub sol_price_usd: Option<f64>,
+    pub realized_pnl_usd: Option<f64>,
+    pub unrealized_pnl_usd: Option<f64>,
+    pub tokens: Vec<TokenPnl>,
+    pub sales_count: i64,
+    pub best_sale: Option<RealizedSale>,
+    pub worst_sale: Option<RealizedSale>,
+}
+
+impl PnlReport {
+    pub fn total_pnl_sol(&self) -> f64 {
+        self.realized_pnl_sol + self.unrealized_pnl_sol
+    }
+
+    /// Realized PnL per sell, or `None` before the first sell.
+    pub fn average_realized_pnl_sol(&self) -> Option<f64> {
+        (self.sales_count > 0).then(|| self.realized_pnl_sol / self.sales_count as f64)
+    }
+
+    pub fn to_usd(&self, sol: f64) -> Option<f64> {
+        self.sol_price_usd.map(|price| sol * price)
+    }
+}
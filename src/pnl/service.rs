@@ -0,0 +1,389 @@
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use tracing::warn;
+
+use crate::pnl::models::{Lot, PnlReport, RealizedSale, TokenPnl, TradeFill};
+use crate::utils::error::PnlError;
+use crate::utils::price_oracle::PriceOracle;
+
+/// Quantities below this are rounding leftovers of UI amounts.
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// Trades of one position, or of every position, in execution order.
+async fn load_fills(pool: &PgPool, trade_position_id: Option<i32>) -> Result<Vec<TradeFill>, PnlError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT tr.id, tr.trade_position_id, tr.token_id, tr.trade_type, tr.base_token_quantity, tr.quote_token_quantity,
+               tr.fee_rate, tr.tx_id, tr.created_at, t.address, t.symbol, t.pool_address
+        FROM trades tr
+        JOIN tokens t ON t.id = tr.token_id
+        WHERE $1::INT IS NULL OR tr.trade_position_id = $1
+        ORDER BY tr.created_at, tr.id
+        "#,
+        trade_position_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TradeFill {
+            trade_id: row.id,
+            trade_position_id: row.trade_position_id,
+            token_id: row.token_id,
+            token_address: row.address,
+            symbol: row.symbol,
+            pool_address: row.pool_address,
+            is_buy: row.trade_type == "open",
+            sol_amount: row.base_token_quantity,
+            token_quantity: row.quote_token_quantity,
+            fee_sol: row.fee_rate.unwrap_or(0.0),
+            tx_id: row.tx_id,
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// Matches every sell against the oldest unsold buys of its token in `fills`, whichever
+/// position bought them; given the fills of one position, against that position's own buys.
+///
+/// Buy fees are part of a lot's cost and sell fees are deducted from the proceeds, so
+/// realized PnL is net of network fees; pool fees are already in the swapped amounts.
+/// Tokens sold beyond the recorded buys carry no cost.
+pub fn fifo_pnl(fills: &[TradeFill]) -> (Vec<TokenPnl>, Vec<RealizedSale>) {
+    let mut tokens: Vec<TokenPnl> = Vec::new();
+    let mut lots: Vec<VecDeque<Lot>> = Vec::new();
+    let mut index: HashMap<i32, usize> = HashMap::new();
+    let mut sales = Vec::new();
+
+    for fill in fills {
+        let position = *index.entry(fill.token_id).or_insert_with(|| {
+            tokens.push(TokenPnl {
+                token_address: fill.token_address.clone(),
+                symbol: fill.symbol.clone(),
+                pool_address: fill.pool_address.clone(),
+                realized_pnl_sol: 0.0,
+                fees_sol: 0.0,
+                open_lots: Vec::new(),
+                open_quantity: 0.0,
+                open_cost_basis_sol: 0.0,
+                market_value_sol: None,
+                unrealized_pnl_sol: None,
+            });
+            lots.push(VecDeque::new());
+            tokens.len() - 1
+        });
+        let token = &mut tokens[position];
+        let token_lots = &mut lots[position];
+        token.fees_sol += fill.fee_sol;
+
+        if fill.is_buy {
+            if fill.token_quantity > QUANTITY_EPSILON {
+                token_lots.push_back(Lot {
+                    trade_id: fill.trade_id,
+                    quantity: fill.token_quantity,
+                    cost_per_token: (fill.sol_amount + fill.fee_sol) / fill.token_quantity,
+                    opened_at: fill.created_at,
+                });
+            }
+            continue;
+        }
+
+        let mut to_match = fill.token_quantity;
+        let mut cost_basis_sol = 0.0;
+        while to_match > QUANTITY_EPSILON {
+            let Some(lot) = token_lots.front_mut() else {
+                warn!("Sell {} of {} exceeds recorded buys by {}", fill.trade_id, fill.token_address, to_match);
+                break;
+            };
+            let matched = lot.quantity.min(to_match);
+            cost_basis_sol += matched * lot.cost_per_token;
+            lot.quantity -= matched;
+            to_match -= matched;
+            if lot.quantity <= QUANTITY_EPSILON {
+                token_lots.pop_front();
+            }
+        }
+
+        let proceeds_sol = fill.sol_amount - fill.fee_sol;
+        let pnl_sol = proceeds_sol - cost_basis_sol;
+        token.realized_pnl_sol += pnl_sol;
+        sales.push(RealizedSale {
+            trade_id: fill.trade_id,
+            trade_position_id: fill.trade_position_id,
+            token_address: fill.token_address.clone(),
+            symbol: fill.symbol.clone(),
+            tx_id: fill.tx_id.clone(),
+            token_quantity: fill.token_quantity,
+            proceeds_sol,
+            cost_basis_sol,
+            pnl_sol,
+            pnl_pct: if cost_basis_sol > 0.0 { pnl_sol / cost_basis_sol * 100.0 } else { 0.0 },
+            closed_at: fill.created_at,
+        });
+    }
+
+    for (token, token_lots) in tokens.iter_mut().zip(lots) {
+        token.open_lots = token_lots.into();
+        token.open_quantity = token.open_lots.iter().map(|lot| lot.quantity).sum();
+        token.open_cost_basis_sol = token.open_lots.iter().map(|lot| lot.quantity * lot.cost_per_token).sum();
+    }
+    (tokens, sales)
+}
+
+/// Realized PnL of every sell per token, with the best and worst sell. With an oracle, the
+/// open lots are marked to market for unrealized PnL and SOL amounts are also given in USD.
+pub async fn compute_pnl(pool: &PgPool, oracle: Option<&PriceOracle>) -> Result<PnlReport, PnlError> {
+    let (mut tokens, sales) = fifo_pnl(&load_fills(pool, None).await?);
+
+    let sol_price_usd = match oracle {
+        Some(oracle) => match oracle.sol_usd().await {
+            Ok(price) => Some(price.price_usd),
+            Err(e) => {
+                warn!("Failed to price SOL: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    if let (Some(oracle), Some(sol_price)) = (oracle, sol_price_usd) {
+        for token in tokens.iter_mut().filter(|token| token.open_quantity > QUANTITY_EPSILON) {
+            match oracle.token_usd(&token.token_address, Some(&token.pool_address)).await {
+                Ok(price) => {
+                    let market_value_sol = token.open_quantity * price.price_usd / sol_price;
+                    token.market_value_sol = Some(market_value_sol);
+                    token.unrealized_pnl_sol = Some(market_value_sol - token.open_cost_basis_sol);
+                }
+                Err(e) => warn!("Failed to price {}: {}", token.token_address, e),
+            }
+        }
+    }
+
+    let realized_pnl_sol = tokens.iter().map(|token| token.realized_pnl_sol).sum::<f64>();
+    let unrealized_pnl_sol = tokens.iter().filter_map(|token| token.unrealized_pnl_sol).sum::<f64>();
+    Ok(PnlReport {
+        realized_pnl_sol,
+        unrealized_pnl_sol,
+        fees_sol: tokens.iter().map(|token| token.fees_sol).sum(),
+        sol_price_usd,
+        realized_pnl_usd: sol_price_usd.map(|price| realized_pnl_sol * price),
+        unrealized_pnl_usd: sol_price_usd.map(|price| unrealized_pnl_sol * price),
+        tokens,
+        sales_count: sales.len() as i64,
+        best_sale: extreme_sale(&sales, true),
+        worst_sale: extreme_sale(&sales, false),
+    })
+}
+
+/// Sell with the highest realized PnL, or the lowest when `best` is false; the earliest on a tie.
+fn extreme_sale(sales: &[RealizedSale], best: bool) -> Option<RealizedSale> {
+    sales
+        .iter()
+        .reduce(|extreme, sale| {
+            let better = if best { sale.pnl_sol > extreme.pnl_sol } else { sale.pnl_sol < extreme.pnl_sol };
+            if better { sale } else { extreme }
+        })
+        .cloned()
+}
+
+/// Realized PnL of every sell of a position, costed against the position's own buys only,
+/// as `PositionManager` does when it records the sell.
+pub async fn position_pnl(pool: &PgPool, trade_position_id: i32) -> Result<Vec<RealizedSale>, PnlError> {
+    let (_, sales) = fifo_pnl(&load_fills(pool, Some(trade_position_id)).await?);
+    Ok(sales)
+}
+
+/// Realized PnL of one sell, costed against the buys of its position.
+pub async fn trade_pnl(pool: &PgPool, trade_id: i32) -> Result<RealizedSale, PnlError> {
+    let trade_position_id =
+        sqlx::query_scalar!("SELECT trade_position_id FROM trades WHERE id = $1 AND trade_type = 'closed'", trade_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(PnlError::SaleNotFound(trade_id))?;
+    let (_, sales) = fifo_pnl(&load_fills(pool, Some(trade_position_id)).await?);
+    sales
+        .into_iter()
+        .find(|sale| sale.trade_id == trade_id)
+        .ok_or(PnlError::SaleNotFound(trade_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn fill(trade_id: i32, trade_position_id: i32, is_buy: bool, sol_amount: f64, token_quantity: f64, fee_sol: f64) -> TradeFill {
+        TradeFill {
+            trade_id,
+            trade_position_id,
+            token_id: 1,
+            token_address: "mint".to_string(),
+            symbol: "TKN".to_string(),
+            pool_address: "pool".to_string(),
+            is_buy,
+            sol_amount,
+            token_quantity,
+            fee_sol,
+            tx_id: format!("tx{}", trade_id),
+            created_at: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(0, 0, 0).unwrap() + Duration::minutes(trade_id as i64),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn nets_fees_out_of_realized_pnl() {
+        let fills = [fill(1, 10, true, 1.0, 1_000.0, 0.01), fill(2, 10, false, 1.5, 1_000.0, 0.01)];
+
+        let (tokens, sales) = fifo_pnl(&fills);
+
+        assert_eq!(sales.len(), 1);
+        assert_close(sales[0].cost_basis_sol, 1.01);
+        assert_close(sales[0].proceeds_sol, 1.49);
+        assert_close(sales[0].pnl_sol, 0.48);
+        assert_close(tokens[0].fees_sol, 0.02);
+        assert!(tokens[0].open_lots.is_empty());
+    }
+
+    #[test]
+    fn matches_sells_against_the_oldest_lots_of_the_token() {
+        // Token totals: selling the later of two positions uses up the earlier, cheaper buy.
+        let fills = [
+            fill(1, 10, true, 1.0, 1_000.0, 0.0),
+            fill(2, 20, true, 4.0, 1_000.0, 0.0),
+            fill(3, 20, false, 5.0, 1_000.0, 0.0),
+        ];
+
+        let (tokens, sales) = fifo_pnl(&fills);
+
+        assert_eq!(sales[0].trade_position_id, 20);
+        assert_close(sales[0].cost_basis_sol, 1.0);
+        assert_close(sales[0].pnl_sol, 4.0);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].open_lots.len(), 1);
+        assert_eq!(tokens[0].open_lots[0].trade_id, 2);
+        assert_close(tokens[0].open_quantity, 1_000.0);
+        assert_close(tokens[0].open_cost_basis_sol, 4.0);
+    }
+
+    /// `tokens` and `trades` with the columns the engine reads.
+    async fn setup(pool: &PgPool) {
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE tokens (id SERIAL PRIMARY KEY, address TEXT NOT NULL, symbol TEXT NOT NULL, pool_address TEXT NOT NULL);
+            CREATE TABLE trades (
+                id SERIAL PRIMARY KEY,
+                created_at TIMESTAMP NOT NULL,
+                trade_position_id INT NOT NULL,
+                token_id INT NOT NULL REFERENCES tokens (id),
+                trade_type TEXT NOT NULL,
+                base_token_quantity DOUBLE PRECISION NOT NULL,
+                quote_token_quantity DOUBLE PRECISION NOT NULL,
+                fee_rate DOUBLE PRECISION,
+                tx_id TEXT NOT NULL
+            );
+            INSERT INTO tokens (address, symbol, pool_address) VALUES ('mint', 'TKN', 'pool');
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn costs_each_position_against_its_own_buys(pool: PgPool) {
+        setup(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO trades (created_at, trade_position_id, token_id, trade_type, base_token_quantity, quote_token_quantity, fee_rate, tx_id) VALUES
+                ('2024-05-01 00:01', 10, 1, 'open', 1.0, 1000.0, 0.01, 'tx1'),
+                ('2024-05-01 00:02', 20, 1, 'open', 4.0, 1000.0, 0.01, 'tx2'),
+                ('2024-05-01 00:03', 20, 1, 'closed', 3.0, 1000.0, 0.01, 'tx3'),
+                ('2024-05-01 00:04', 10, 1, 'closed', 2.0, 1000.0, 0.01, 'tx4');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let later = position_pnl(&pool, 20).await.unwrap();
+        assert_eq!(later.len(), 1);
+        assert_close(later[0].cost_basis_sol, 4.01);
+        assert_close(later[0].pnl_sol, -1.02);
+
+        let earlier = trade_pnl(&pool, 4).await.unwrap();
+        assert_eq!(earlier.trade_position_id, 10);
+        assert_close(earlier.cost_basis_sol, 1.01);
+        assert_close(earlier.pnl_sol, 0.98);
+
+        // The token totals are the same however the sells are attributed.
+        let (tokens, _) = fifo_pnl(&load_fills(&pool, None).await.unwrap());
+        assert_close(tokens[0].realized_pnl_sol, -0.04);
+        assert!(matches!(trade_pnl(&pool, 1).await, Err(PnlError::SaleNotFound(1))));
+    }
+
+    #[test]
+    fn keeps_the_lots_of_each_token_apart() {
+        let other = |fill: TradeFill| TradeFill { token_id: 2, token_address: "other".to_string(), ..fill };
+        let fills = [
+            fill(1, 10, true, 1.0, 1_000.0, 0.0),
+            other(fill(2, 20, true, 3.0, 1_000.0, 0.0)),
+            other(fill(3, 20, false, 2.0, 500.0, 0.0)),
+        ];
+
+        let (tokens, sales) = fifo_pnl(&fills);
+
+        assert_close(sales[0].cost_basis_sol, 1.5);
+        assert_eq!(tokens.len(), 2);
+        assert_close(tokens[0].open_quantity, 1_000.0);
+        assert_close(tokens[1].open_quantity, 500.0);
+        assert_close(tokens[1].realized_pnl_sol, 0.5);
+    }
+
+    #[test]
+    fn picks_the_best_and_worst_sale_from_the_matched_sales() {
+        let fills = [
+            fill(1, 10, true, 3.0, 3_000.0, 0.0),
+            fill(2, 10, false, 0.5, 1_000.0, 0.0),
+            fill(3, 10, false, 2.0, 1_000.0, 0.0),
+            fill(4, 10, false, 2.0, 1_000.0, 0.0),
+        ];
+
+        let (_, sales) = fifo_pnl(&fills);
+
+        assert_eq!(extreme_sale(&sales, true).unwrap().trade_id, 3);
+        assert_eq!(extreme_sale(&sales, false).unwrap().trade_id, 2);
+        assert!(extreme_sale(&[], true).is_none());
+    }
+
+    #[test]
+    fn partial_sells_leave_the_rest_of_the_lot_open() {
+        let fills = [
+            fill(1, 10, true, 2.0, 1_000.0, 0.0),
+            fill(2, 10, false, 0.5, 250.0, 0.0),
+            fill(3, 10, false, 1.0, 250.0, 0.0),
+        ];
+
+        let (tokens, sales) = fifo_pnl(&fills);
+
+        assert_close(sales[0].pnl_sol, 0.0);
+        assert_close(sales[1].pnl_sol, 0.5);
+        assert_close(sales[1].pnl_pct, 100.0);
+        assert_close(tokens[0].realized_pnl_sol, 0.5);
+        assert_close(tokens[0].open_quantity, 500.0);
+        assert_close(tokens[0].open_cost_basis_sol, 1.0);
+    }
+
+    #[test]
+    fn sells_beyond_the_buys_carry_no_cost() {
+        let fills = [fill(1, 10, true, 1.0, 100.0, 0.0), fill(2, 10, false, 3.0, 300.0, 0.0)];
+
+        let (_, sales) = fifo_pnl(&fills);
+
+        assert_close(sales[0].cost_basis_sol, 1.0);
+        assert_close(sales[0].pnl_sol, 2.0);
+    }
+}
@@ -0,0 +1,132 @@
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const TOKEN_2022_PROGRAM: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const METADATA_PROGRAM: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Token-2022 extensions start after the base account length plus the account type byte.
+const TOKEN_2022_TLV_START: usize = 166;
+const TOKEN_2022_MINT_ACCOUNT_TYPE: u8 = 1;
+const TOKEN_METADATA_EXTENSION: u16 = 19;
+
+/// Display name and ticker of a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+}
+
+/// Metaplex metadata account of a mint.
+pub fn metaplex_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"metadata", METADATA_PROGRAM.as_ref(), mint.as_ref()], &METADATA_PROGRAM).0
+}
+
+/// Borsh string at `offset`; returns it with the offset right after it.
+fn read_borsh_string(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let len = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+    let start = offset + 4;
+    let bytes = data.get(start..start + len)?;
+    // Metaplex pads names and symbols with NUL bytes.
+    let value = String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string();
+    Some((value, start + len))
+}
+
+/// Name and symbol after the 32-byte update authority and 32-byte mint shared by both layouts.
+fn decode_name_and_symbol(data: &[u8], offset: usize) -> Option<TokenMetadata> {
+    let (name, next) = read_borsh_string(data, offset)?;
+    let (symbol, _) = read_borsh_string(data, next)?;
+    Some(TokenMetadata { name, symbol })
+}
+
+/// Metaplex metadata account: key (1), update authority (32), mint (32), name, symbol, uri.
+pub fn decode_metaplex_metadata(data: &[u8]) -> Option<TokenMetadata> {
+    decode_name_and_symbol(data, 1 + 32 + 32)
+}
+
+/// TokenMetadata extension embedded in a Token-2022 mint account, if it has one.
+pub fn decode_token_2022_metadata(mint_data: &[u8]) -> Option<TokenMetadata> {
+    if mint_data.get(TOKEN_2022_TLV_START - 1) != Some(&TOKEN_2022_MINT_ACCOUNT_TYPE) {
+        return None;
+    }
+    let mut offset = TOKEN_2022_TLV_START;
+    while offset + 4 <= mint_data.len() {
+        let extension = u16::from_le_bytes([mint_data[offset], mint_data[offset + 1]]);
+        let len = u16::from_le_bytes([mint_data[offset + 2], mint_data[offset + 3]]) as usize;
+        let value = mint_data.get(offset + 4..offset + 4 + len)?;
+        if extension == TOKEN_METADATA_EXTENSION {
+            // Update authority (32) and mint (32) precede the strings.
+            return decode_name_and_symbol(value, 64);
+        }
+        if extension == 0 {
+            break;
+        }
+        offset += 4 + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    fn tlv(extension: u16, value: &[u8]) -> Vec<u8> {
+        let mut bytes = extension.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    /// Token-2022 mint: base mint padded to the account length, account type, then extensions.
+    fn token_2022_mint(extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0u8; TOKEN_2022_TLV_START - 1];
+        data.push(TOKEN_2022_MINT_ACCOUNT_TYPE);
+        for extension in extensions {
+            data.extend_from_slice(extension);
+        }
+        data
+    }
+
+    fn metadata_extension(name: &str, symbol: &str) -> Vec<u8> {
+        let mut value = vec![7u8; 64];
+        value.extend(borsh_string(name));
+        value.extend(borsh_string(symbol));
+        value.extend(borsh_string("https://example.com/token.json"));
+        tlv(TOKEN_METADATA_EXTENSION, &value)
+    }
+
+    #[test]
+    fn decodes_metadata_after_other_extensions() {
+        let data = token_2022_mint(&[tlv(3, &[0; 2]), metadata_extension("Agent Token", "AGT")]);
+        let metadata = decode_token_2022_metadata(&data).unwrap();
+        assert_eq!(metadata, TokenMetadata { name: "Agent Token".to_string(), symbol: "AGT".to_string() });
+    }
+
+    #[test]
+    fn mint_without_metadata_extension_has_none() {
+        assert_eq!(decode_token_2022_metadata(&token_2022_mint(&[tlv(3, &[0; 2])])), None);
+        // A plain SPL mint is too short to carry extensions.
+        assert_eq!(decode_token_2022_metadata(&[0u8; 82]), None);
+    }
+
+    #[test]
+    fn truncated_extension_is_rejected() {
+        let mut data = token_2022_mint(&[metadata_extension("Agent Token", "AGT")]);
+        data.truncate(data.len() - 10);
+        assert_eq!(decode_token_2022_metadata(&data), None);
+    }
+
+    #[test]
+    fn decodes_padded_metaplex_metadata() {
+        let mut data = vec![4u8; 1 + 32 + 32];
+        data.extend(borsh_string("Agent Token\0\0\0\0"));
+        data.extend(borsh_string("AGT\0\0"));
+        let metadata = decode_metaplex_metadata(&data).unwrap();
+        assert_eq!(metadata, TokenMetadata { name: "Agent Token".to_string(), symbol: "AGT".to_string() });
+    }
+}
@@ -0,0 +1,6 @@
+pub mod metadata;
+pub mod models;
+pub mod service;
+
+pub use models::{OpenPosition, PortfolioSnapshot, PortfolioToken};
+pub use service::Portfolio;
@@ -0,0 +1,75 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Open trades of a token that have not been closed yet, summed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenPosition {
+    /// Tokens bought, in UI units.
+    pub token_quantity: f64,
+    /// SOL spent on the buys.
+    pub sol_invested: f64,
+    pub opened_at: Option<NaiveDateTime>,
+}
+
+/// A token held by the agent wallet, or one with an open position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioToken {
+    pub mint: String,
+    pub token_program: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    /// Balance across the wallet's token accounts of this mint, in UI units.
+    pub amount: f64,
+    pub raw_amount: u64,
+    pub pool_address: Option<String>,
+    pub position: Option<OpenPosition>,
+    /// `None` when no price source could value the token.
+    pub price_usd: Option<f64>,
+    pub value_usd: Option<f64>,
+    pub price_confidence: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub wallet: String,
+    pub sol_balance: f64,
+    pub sol_price_usd: Option<f64>,
+    pub sol_balance_usd: Option<f64>,
+    /// SOL plus every token that could be priced.
+    pub total_value_usd: Option<f64>,
+    pub tokens: Vec<PortfolioToken>,
+}
+
+impl PortfolioSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// One line per token, as given to the model.
+    pub fn token_lines(&self) -> Vec<String> {
+        self.tokens
+            .iter()
+            .map(|token| {
+                let position = match &token.position {
+                    Some(position) => format!(", bought for {:.9} SOL", position.sol_invested),
+                    None => String::new(),
+                };
+                let value = match token.value_usd {
+                    Some(value) => format!(", worth ${:.2}", value),
+                    None => String::new(),
+                };
+                format!(
+                    "- token amount: {}, symbol: {}, name: {}, pool address: {}, token address: {}{}{}",
+                    token.amount,
+                    token.symbol,
+                    token.name,
+                    token.pool_address.as_deref().unwrap_or("unknown"),
+                    token.mint,
+                    value,
+                    position
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,303 @@
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_response::RpcKeyedAccount;
+use solana_sdk::account::Account;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::portfolio::metadata::{
+    decode_metaplex_metadata, decode_token_2022_metadata, metaplex_metadata_address, TokenMetadata, TOKEN_2022_PROGRAM,
+};
+use crate::portfolio::models::{OpenPosition, PortfolioSnapshot, PortfolioToken};
+use crate::utils::error::SignerError;
+use crate::utils::raydium_swap::decode_mint_decimals;
+use crate::utils::price_oracle::PriceOracle;
+use crate::utils::signer::signer_from_settings;
+use crate::utils::solana_rpc::{shared_solana_rpc, SolanaRpc};
+
+/// `getMultipleAccounts` accepts at most this many accounts.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Balance of one mint across the wallet's token accounts.
+struct Holding {
+    token_program: Pubkey,
+    raw_amount: u64,
+    decimals: Option<u8>,
+}
+
+/// What the database knows about a token.
+struct KnownToken {
+    symbol: String,
+    name: String,
+    pool_address: String,
+    position: Option<OpenPosition>,
+}
+
+/// Mint, raw amount and decimals of a `jsonParsed` token account.
+fn parse_token_account(account: &RpcKeyedAccount) -> Option<(Pubkey, u64, u8)> {
+    let UiAccountData::Json(parsed) = &account.account.data else {
+        return None;
+    };
+    let info = parsed.parsed.get("info")?;
+    let mint = Pubkey::from_str(info.get("mint")?.as_str()?).ok()?;
+    let token_amount = info.get("tokenAmount")?;
+    let raw_amount = token_amount.get("amount")?.as_str()?.parse().ok()?;
+    let decimals = token_amount.get("decimals")?.as_u64()? as u8;
+    Some((mint, raw_amount, decimals))
+}
+
+fn to_ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// On-chain holdings of a wallet joined with the agent's open positions.
+pub struct Portfolio {
+    rpc: Arc<SolanaRpc>,
+    pool: PgPool,
+    wallet: Pubkey,
+    oracle: PriceOracle,
+}
+
+impl Portfolio {
+    pub fn new_portfolio(rpc: Arc<SolanaRpc>, pool: PgPool, wallet: Pubkey) -> Self {
+        let oracle = PriceOracle::new_price_oracle(rpc.clone());
+        Self { rpc, pool, wallet, oracle }
+    }
+
+    /// Portfolio of the agent wallet of the signer configured in `SIGNER_MODE`.
+    pub async fn for_agent(pool: PgPool) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = signer_from_settings().await?.solana_pubkey().ok_or(SignerError::NoSolanaKey)?;
+        Ok(Self::new_portfolio(shared_solana_rpc(), pool, wallet))
+    }
+
+    pub fn wallet(&self) -> Pubkey {
+        self.wallet
+    }
+
+    pub async fn sol_balance_lamports(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        Ok(self.rpc.get_balance(&self.wallet).await?)
+    }
+
+    pub async fn snapshot(&self) -> Result<PortfolioSnapshot, Box<dyn Error + Send + Sync>> {
+        let sol_balance = self.sol_balance_lamports().await?;
+        let mut holdings = self.token_holdings().await?;
+        let mut known = self.open_positions().await?;
+
+        let unknown: Vec<String> = holdings.keys().map(ToString::to_string).filter(|mint| !known.contains_key(mint)).collect();
+        known.extend(self.known_tokens(&unknown).await?);
+
+        // Positions whose tokens are no longer in the wallet are still reported, with a zero balance.
+        let mints: Vec<Pubkey> = known
+            .keys()
+            .filter_map(|mint| Pubkey::from_str(mint).ok())
+            .chain(holdings.keys().copied())
+            .fold(Vec::new(), |mut mints, mint| {
+                if !mints.contains(&mint) {
+                    mints.push(mint);
+                }
+                mints
+            });
+
+        let unresolved: Vec<Pubkey> = mints
+            .iter()
+            .filter(|mint| {
+                let missing_decimals = holdings.get(mint).is_none_or(|h| h.decimals.is_none());
+                missing_decimals || !known.contains_key(&mint.to_string())
+            })
+            .copied()
+            .collect();
+        let mint_accounts = self.multiple_accounts(&unresolved).await?;
+        let metadata = self.resolve_metadata(&unresolved, &mint_accounts, &known).await?;
+
+        let mut tokens = Vec::with_capacity(mints.len());
+        for mint in mints {
+            let mint_account = mint_accounts.get(&mint);
+            let holding = holdings.remove(&mint).unwrap_or_else(|| Holding {
+                token_program: mint_account.map(|a| a.owner).unwrap_or(spl_token::id()),
+                raw_amount: 0,
+                decimals: None,
+            });
+            let decimals = holding
+                .decimals
+                .or_else(|| mint_account.and_then(|a| decode_mint_decimals(&a.data).ok()))
+                .unwrap_or(0);
+
+            let known_token = known.remove(&mint.to_string());
+            let (symbol, name) = match (&known_token, metadata.get(&mint)) {
+                (Some(token), _) => (token.symbol.clone(), token.name.clone()),
+                (None, Some(meta)) => (meta.symbol.clone(), meta.name.clone()),
+                (None, None) => ("UNKNOWN".to_string(), mint.to_string()),
+            };
+            tokens.push(PortfolioToken {
+                mint: mint.to_string(),
+                token_program: holding.token_program.to_string(),
+                symbol,
+                name,
+                decimals,
+                amount: to_ui_amount(holding.raw_amount, decimals),
+                raw_amount: holding.raw_amount,
+                pool_address: known_token.as_ref().map(|t| t.pool_address.clone()),
+                position: known_token.and_then(|t| t.position),
+                price_usd: None,
+                value_usd: None,
+                price_confidence: None,
+            });
+        }
+        tokens.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        let mut snapshot = PortfolioSnapshot {
+            wallet: self.wallet.to_string(),
+            sol_balance: lamports_to_sol(sol_balance),
+            sol_price_usd: None,
+            sol_balance_usd: None,
+            total_value_usd: None,
+            tokens,
+        };
+        self.value_in_usd(&mut snapshot).await;
+        Ok(snapshot)
+    }
+
+    /// Fills in USD prices; anything the oracle cannot price is left out of the total.
+    async fn value_in_usd(&self, snapshot: &mut PortfolioSnapshot) {
+        match self.oracle.sol_usd().await {
+            Ok(sol) => {
+                snapshot.sol_price_usd = Some(sol.price_usd);
+                snapshot.sol_balance_usd = Some(snapshot.sol_balance * sol.price_usd);
+            }
+            Err(e) => warn!("Failed to price SOL: {}", e),
+        }
+
+        for token in &mut snapshot.tokens {
+            match self.oracle.token_usd(&token.mint, token.pool_address.as_deref()).await {
+                Ok(price) => {
+                    token.price_usd = Some(price.price_usd);
+                    token.value_usd = Some(token.amount * price.price_usd);
+                    token.price_confidence = Some(price.confidence);
+                }
+                Err(e) => warn!("Failed to price {}: {}", token.mint, e),
+            }
+        }
+
+        snapshot.total_value_usd = snapshot
+            .sol_balance_usd
+            .map(|sol| sol + snapshot.tokens.iter().filter_map(|t| t.value_usd).sum::<f64>());
+    }
+
+    /// Non-empty balances per mint across both the Token and Token-2022 programs.
+    async fn token_holdings(&self) -> Result<HashMap<Pubkey, Holding>, Box<dyn Error + Send + Sync>> {
+        let mut holdings: HashMap<Pubkey, Holding> = HashMap::new();
+        for program in [spl_token::id(), TOKEN_2022_PROGRAM] {
+            for account in self.rpc.get_token_accounts_by_owner(&self.wallet, &program).await? {
+                let Some((mint, raw_amount, decimals)) = parse_token_account(&account) else {
+                    continue;
+                };
+                if raw_amount == 0 {
+                    continue;
+                }
+                let holding = holdings.entry(mint).or_insert(Holding { token_program: program, raw_amount: 0, decimals: Some(decimals) });
+                holding.raw_amount = holding.raw_amount.saturating_add(raw_amount);
+            }
+        }
+        Ok(holdings)
+    }
+
+    /// Positions not closed yet with what is left of them, per token.
+    async fn open_positions(&self) -> Result<HashMap<String, KnownToken>, Box<dyn Error + Send + Sync>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT t.address, t.symbol, t.name, t.pool_address,
+                   SUM(COALESCE(p.remaining_quantity, tr.quote_token_quantity)) AS "token_quantity!",
+                   SUM(tr.base_token_quantity) AS "sol_invested!",
+                   MIN(tr.created_at) AS opened_at
+            FROM trades tr
+            JOIN tokens t ON t.id = tr.token_id
+            JOIN positions p ON p.id = tr.trade_position_id
+            WHERE tr.trade_type = 'open' AND p.status IN ('open', 'closing')
+            GROUP BY t.address, t.symbol, t.name, t.pool_address
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let position = OpenPosition {
+                    token_quantity: row.token_quantity,
+                    sol_invested: row.sol_invested,
+                    opened_at: row.opened_at,
+                };
+                let token = KnownToken {
+                    symbol: row.symbol,
+                    name: row.name,
+                    pool_address: row.pool_address,
+                    position: Some(position),
+                };
+                (row.address, token)
+            })
+            .collect())
+    }
+
+    async fn known_tokens(&self, mints: &[String]) -> Result<HashMap<String, KnownToken>, Box<dyn Error + Send + Sync>> {
+        if mints.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let rows = sqlx::query!("SELECT address, symbol, name, pool_address FROM tokens WHERE address = ANY($1)", mints)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let token = KnownToken { symbol: row.symbol, name: row.name, pool_address: row.pool_address, position: None };
+                (row.address, token)
+            })
+            .collect())
+    }
+
+    async fn multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<HashMap<Pubkey, Account>, Box<dyn Error + Send + Sync>> {
+        let mut accounts = HashMap::new();
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let fetched = self.rpc.get_multiple_accounts(chunk).await?;
+            accounts.extend(chunk.iter().copied().zip(fetched).filter_map(|(pubkey, account)| Some((pubkey, account?))));
+        }
+        Ok(accounts)
+    }
+
+    /// Name and symbol of tokens missing from the database: the Token-2022 metadata
+    /// extension if the mint has one, the Metaplex metadata account otherwise.
+    async fn resolve_metadata(
+        &self,
+        mints: &[Pubkey],
+        mint_accounts: &HashMap<Pubkey, Account>,
+        known: &HashMap<String, KnownToken>,
+    ) -> Result<HashMap<Pubkey, TokenMetadata>, Box<dyn Error + Send + Sync>> {
+        let mut metadata = HashMap::new();
+        let mut metaplex_mints = Vec::new();
+        for mint in mints.iter().filter(|mint| !known.contains_key(&mint.to_string())) {
+            let embedded = mint_accounts
+                .get(mint)
+                .filter(|account| account.owner == TOKEN_2022_PROGRAM)
+                .and_then(|account| decode_token_2022_metadata(&account.data));
+            match embedded {
+                Some(meta) => {
+                    metadata.insert(*mint, meta);
+                }
+                None => metaplex_mints.push(*mint),
+            }
+        }
+
+        let addresses: Vec<Pubkey> = metaplex_mints.iter().map(metaplex_metadata_address).collect();
+        let accounts = self.multiple_accounts(&addresses).await?;
+        for (mint, address) in metaplex_mints.into_iter().zip(addresses) {
+            if let Some(meta) = accounts.get(&address).and_then(|account| decode_metaplex_metadata(&account.data)) {
+                metadata.insert(mint, meta);
+            }
+        }
+        Ok(metadata)
+    }
+}
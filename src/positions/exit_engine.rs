@@ -0,0 +1,296 @@
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+use std::error::Error;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::core::config::{ExitSettings, RedisSettings, SolanaSettings};
+use crate::llm::llm_service::{generate_selling_text, generate_twitter_post};
+use crate::llm::provider::LlmProvider;
+use crate::models::base::ExitReason;
+use crate::payouts::PayoutService;
+use crate::positions::models::{ClosedPosition, ExitRules, Position, SellResult};
+use crate::positions::service::PositionManager;
+use crate::utils::error::PositionError;
+use crate::utils::redis::RedisChatHistory;
+
+/// First rule that fires for a position worth `value_sol` now, with a description of why.
+///
+/// `value_sol` is the worth of the tokens still held, so it is compared with their share of
+/// the buy's cost. Losses are checked before gains so that a position crossing several
+/// thresholds between two polls is recorded under the protective rule.
+pub fn evaluate_exit(
+    rules: &ExitRules,
+    position: &Position,
+    value_sol: f64,
+    peak_value_sol: f64,
+    now: NaiveDateTime,
+) -> Option<(ExitReason, String)> {
+    let (cost_sol, _) = position.cost_of(position.remaining_quantity);
+    let change_pct = if cost_sol > 0.0 { (value_sol - cost_sol) / cost_sol * 100.0 } else { 0.0 };
+
+    if let Some(stop_loss) = rules.stop_loss_pct {
+        if change_pct <= -stop_loss {
+            return Some((ExitReason::StopLoss, format!("down {:.2}%, stop loss at {:.2}%", -change_pct, stop_loss)));
+        }
+    }
+    if let Some(trailing_stop) = rules.trailing_stop_pct {
+        let drawdown_pct = if peak_value_sol > 0.0 { (peak_value_sol - value_sol) / peak_value_sol * 100.0 } else { 0.0 };
+        if drawdown_pct >= trailing_stop {
+            return Some((
+                ExitReason::TrailingStop,
+                format!("{:.2}% below peak of {:.9} SOL, trailing stop at {:.2}%", drawdown_pct, peak_value_sol, trailing_stop),
+            ));
+        }
+    }
+    if let Some(take_profit) = rules.take_profit_pct {
+        if change_pct >= take_profit {
+            return Some((ExitReason::TakeProfit, format!("up {:.2}%, take profit at {:.2}%", change_pct, take_profit)));
+        }
+    }
+    if let Some(max_hold) = rules.max_hold_minutes {
+        let held_minutes = (now - position.opened_at).num_minutes();
+        if held_minutes >= max_hold {
+            return Some((ExitReason::MaxHold, format!("held {} minutes, limit {} minutes", held_minutes, max_hold)));
+        }
+    }
+    None
+}
+
+/// Pays the shiller their profit share, then tells the chat that opened the position
+/// about the sell and tweets it.
+pub async fn announce_sell(provider: &dyn LlmProvider, pool: &PgPool, positions: &PositionManager, closed: &ClosedPosition) {
+    let trade = match positions.trade(closed.trade_id).await {
+        Ok(trade) => trade,
+        Err(e) => {
+            error!("Failed to load closed trade {}: {}", closed.trade_id, e);
+            return;
+        }
+    };
+
+    // A partial sell leaves the position open; it is paid out once, on its last sell.
+    let payout = if closed.position_closed {
+        match PayoutService::from_settings(pool.clone()).pay_out(positions.driver(), &trade).await {
+            Ok(payout) => Some(payout),
+            Err(e) => {
+                error!("Failed to pay out position {}: {}", closed.trade_position_id, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if !closed.chat_uuid.is_empty() {
+        match generate_selling_text(provider, pool, payout.as_ref(), &trade).await {
+            Ok(text) => {
+                let redis_url = RedisSettings::new_redis().redis_url;
+                // Errors are dropped before awaiting again: they are not `Send`, and sells run
+                // on the scheduler's tasks.
+                let history = RedisChatHistory::new_redis_chat(closed.chat_uuid.clone(), &redis_url).await.ok();
+                let saved = match history {
+                    Some(history) => history.add_message("assistant", &text).await.is_ok(),
+                    None => false,
+                };
+                if !saved {
+                    error!("Failed to add selling message to chat {}", closed.chat_uuid);
+                }
+            }
+            Err(e) => error!("Failed to generate selling text for trade {}: {:?}", trade.id, e),
+        }
+    }
+
+    let wallet = sqlx::query_scalar!(
+        "SELECT u.wallet FROM chats c JOIN users u ON u.id = c.user_id WHERE c.uuid = $1",
+        closed.chat_uuid
+    )
+    .fetch_optional(pool)
+    .await
+    .unwrap_or_else(|e| {
+        warn!("Failed to look up wallet of chat {}: {}", closed.chat_uuid, e);
+        None
+    });
+    if let Err(e) = generate_twitter_post(provider, pool, wallet.as_deref(), &trade, "sell").await {
+        error!("Failed to tweet about trade {}: {:?}", trade.id, e);
+    }
+}
+
+/// Sells every open position, announcing each sell. A position that fails to sell is
+/// reported and does not stop the others.
+pub async fn sell_all_positions(provider: &dyn LlmProvider, positions: &PositionManager, pool: &PgPool) -> Result<Vec<SellResult>, PositionError> {
+    let open_positions = positions.open_positions().await?;
+    let slippage_bps = SolanaSettings::new_solana().swap_slippage_bps;
+    let mut results = Vec::with_capacity(open_positions.len());
+    for position in open_positions {
+        let result = positions.close_position(position.trade_position_id, slippage_bps, ExitReason::Manual).await;
+        match &result {
+            Ok(closed) => announce_sell(provider, pool, positions, closed).await,
+            Err(e) => error!("Failed to close position {}: {}", position.trade_position_id, e),
+        }
+        results.push(SellResult {
+            trade_position_id: position.trade_position_id,
+            error: result.as_ref().err().map(ToString::to_string),
+            closed: result.ok(),
+        });
+    }
+    Ok(results)
+}
+
+/// Polls the value of every open position and sells those whose exit rules fire.
+pub struct ExitEngine {
+    pool: PgPool,
+    positions: PositionManager,
+    payouts: PayoutService,
+    provider: Arc<dyn LlmProvider>,
+    settings: ExitSettings,
+}
+
+impl ExitEngine {
+    pub fn new_exit_engine(pool: PgPool, positions: PositionManager, provider: Arc<dyn LlmProvider>, settings: ExitSettings) -> Self {
+        let payouts = PayoutService::from_settings(pool.clone());
+        Self { pool, positions, payouts, provider, settings }
+    }
+
+    pub async fn from_settings(pool: PgPool, provider: Arc<dyn LlmProvider>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let positions = PositionManager::from_settings(pool.clone()).await?;
+        Ok(Self::new_exit_engine(pool, positions, provider, ExitSettings::new_exit()))
+    }
+
+    /// Checks the open positions once, then retries failed payouts. Run by the scheduler's
+    /// `exit_positions` job, so one instance at a time does it.
+    pub async fn run_once(&self) -> Result<Vec<ClosedPosition>, PositionError> {
+        let closed = self.check_positions().await;
+        if let Err(e) = self.payouts.retry_failed(self.positions.driver()).await {
+            error!("Failed to retry payouts: {}", e);
+        }
+        closed
+    }
+
+    /// One pass over the open positions; a position that cannot be valued or sold is retried next pass.
+    pub async fn check_positions(&self) -> Result<Vec<ClosedPosition>, PositionError> {
+        let global = ExitRules::from_settings(&self.settings);
+        let mut closed = Vec::new();
+        for position in self.positions.open_positions().await? {
+            match self.check_position(&position, &global).await {
+                Ok(Some(position)) => closed.push(position),
+                Ok(None) => {}
+                Err(e) => error!("Failed to check exit of position {}: {}", position.trade_position_id, e),
+            }
+        }
+        Ok(closed)
+    }
+
+    async fn check_position(&self, position: &Position, global: &ExitRules) -> Result<Option<ClosedPosition>, PositionError> {
+        let state = self.positions.exit_state(position.trade_position_id).await?;
+        let rules = global.with_overrides(&state.overrides);
+        if rules == ExitRules::default() {
+            return Ok(None);
+        }
+
+        let amount = self.positions.sellable_amount(position).await?;
+        if amount == 0 {
+            warn!("No tokens of position {} left to value", position.trade_position_id);
+            return Ok(None);
+        }
+        let value_sol = self
+            .positions
+            .driver()
+            .quote_sell(&position.pool_address, amount)
+            .await
+            .map_err(|e| PositionError::Swap(e.to_string()))?;
+
+        let peak_value_sol = state.peak_value_sol.unwrap_or(value_sol).max(value_sol);
+        if state.peak_value_sol.is_none_or(|peak| value_sol > peak) {
+            self.positions.record_peak_value(position.trade_position_id, value_sol).await?;
+        }
+
+        let Some((reason, detail)) = evaluate_exit(&rules, position, value_sol, peak_value_sol, Utc::now().naive_utc()) else {
+            return Ok(None);
+        };
+        info!("Exiting position {} ({}): {}", position.trade_position_id, reason, detail);
+
+        let slippage_bps = SolanaSettings::new_solana().swap_slippage_bps;
+        let closed = self.positions.close_position(position.trade_position_id, slippage_bps, reason).await?;
+        announce_sell(self.provider.as_ref(), &self.pool, &self.positions, &closed).await;
+        Ok(Some(closed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn opened_at() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    fn position() -> Position {
+        Position {
+            trade_position_id: 1,
+            chat_uuid: "chat".to_string(),
+            token_id: 1,
+            token_address: "mint".to_string(),
+            symbol: "TKN".to_string(),
+            pool_address: "pool".to_string(),
+            token_quantity: 1_000.0,
+            remaining_quantity: 1_000.0,
+            sol_invested: 1.0,
+            open_fee: 0.0,
+            opened_at: opened_at(),
+            is_closed: false,
+        }
+    }
+
+    fn rules() -> ExitRules {
+        ExitRules { take_profit_pct: Some(50.0), stop_loss_pct: Some(20.0), trailing_stop_pct: Some(30.0), max_hold_minutes: Some(60) }
+    }
+
+    fn reason(rules: &ExitRules, value_sol: f64, peak_value_sol: f64, held_minutes: i64) -> Option<ExitReason> {
+        reason_of(&position(), rules, value_sol, peak_value_sol, held_minutes)
+    }
+
+    fn reason_of(position: &Position, rules: &ExitRules, value_sol: f64, peak_value_sol: f64, held_minutes: i64) -> Option<ExitReason> {
+        evaluate_exit(rules, position, value_sol, peak_value_sol, opened_at() + Duration::minutes(held_minutes))
+            .map(|(reason, _)| reason)
+    }
+
+    #[test]
+    fn holds_while_no_rule_fires() {
+        assert_eq!(reason(&rules(), 1.2, 1.3, 10), None);
+        assert_eq!(reason(&ExitRules::default(), 0.01, 5.0, 10_000), None);
+    }
+
+    #[test]
+    fn fires_each_rule_at_its_threshold() {
+        assert_eq!(reason(&rules(), 1.5, 1.5, 0), Some(ExitReason::TakeProfit));
+        assert_eq!(reason(&rules(), 0.8, 1.0, 0), Some(ExitReason::StopLoss));
+        assert_eq!(reason(&rules(), 1.4, 2.0, 0), Some(ExitReason::TrailingStop));
+        assert_eq!(reason(&rules(), 1.0, 1.0, 60), Some(ExitReason::MaxHold));
+    }
+
+    #[test]
+    fn prefers_protective_rules_when_several_fire() {
+        // Down 25% and 50% below the peak: the stop loss wins over the trailing stop and max hold.
+        assert_eq!(reason(&rules(), 0.75, 1.5, 120), Some(ExitReason::StopLoss));
+        // Up 60% but 40% below the peak: the trailing stop wins over take profit.
+        assert_eq!(reason(&rules(), 1.6, 2.7, 0), Some(ExitReason::TrailingStop));
+    }
+
+    #[test]
+    fn disabled_rules_never_fire() {
+        let rules = ExitRules { stop_loss_pct: None, ..rules() };
+        assert_eq!(reason(&rules, 0.85, 1.0, 0), None);
+    }
+
+    #[test]
+    fn measures_a_partly_sold_position_against_the_cost_of_what_is_left() {
+        // Half sold: the 500 tokens left cost 0.5 SOL, and the peak was reset by the sell.
+        let position = Position { remaining_quantity: 500.0, ..position() };
+
+        assert_eq!(reason_of(&position, &rules(), 0.5, 0.5, 0), None);
+        assert_eq!(reason_of(&position, &rules(), 0.39, 0.5, 0), Some(ExitReason::StopLoss));
+        assert_eq!(reason_of(&position, &rules(), 0.76, 0.76, 0), Some(ExitReason::TakeProfit));
+        assert_eq!(reason_of(&position, &rules(), 0.6, 0.9, 0), Some(ExitReason::TrailingStop));
+    }
+}
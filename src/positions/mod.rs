@@ -0,0 +1,7 @@
+pub mod exit_engine;
+pub mod models;
+pub mod service;
+
+pub use exit_engine::{announce_sell, evaluate_exit, sell_all_positions, ExitEngine};
+pub use models::{ClosedPosition, ExitRuleOverrides, ExitRules, Position, PositionExitState, RuleOverride, SellResult};
+pub use service::PositionManager;
@@ -0,0 +1,284 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::core::config::ExitSettings;
+use crate::models::base::ExitReason;
+use crate::utils::error::PositionError;
+
+/// A buy of a token, identified by the `trade_position_id` shared by its open and closed trades.
+#[derive(Debug, Clone, Serialize)]
+pub struct Position {
+    pub trade_position_id: i32,
+    /// Chat the buy was approved in.
+    pub chat_uuid: String,
+    pub token_id: i32,
+    pub token_address: String,
+    pub symbol: String,
+    pub pool_address: String,
+    /// Tokens bought, in UI units.
+    pub token_quantity: f64,
+    /// Tokens not sold yet, in UI units.
+    pub remaining_quantity: f64,
+    /// SOL spent on the buy.
+    pub sol_invested: f64,
+    /// Network fee of the buy, in SOL.
+    pub open_fee: f64,
+    pub opened_at: NaiveDateTime,
+    pub is_closed: bool,
+}
+
+impl Position {
+    /// Share of the buy's SOL cost and open fee carried by `quantity` tokens of it.
+    pub fn cost_of(&self, quantity: f64) -> (f64, f64) {
+        if self.token_quantity <= 0.0 {
+            return (self.sol_invested, self.open_fee);
+        }
+        let share = (quantity / self.token_quantity).clamp(0.0, 1.0);
+        (self.sol_invested * share, self.open_fee * share)
+    }
+}
+
+/// Outcome of selling one position of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct SellResult {
+    pub trade_position_id: i32,
+    pub closed: Option<ClosedPosition>,
+    pub error: Option<String>,
+}
+
+/// Result of selling a position.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosedPosition {
+    pub trade_position_id: i32,
+    /// The `closed` trade.
+    pub trade_id: i32,
+    pub exit_reason: ExitReason,
+    pub chat_uuid: String,
+    pub token_address: String,
+    pub symbol: String,
+    /// Tokens sold, in UI units.
+    pub token_quantity: f64,
+    /// Tokens of the position not sold, in UI units.
+    pub remaining_quantity: f64,
+    /// False for a partial sell that leaves the position open.
+    pub position_closed: bool,
+    /// Part of the buy's cost carried by the tokens sold.
+    pub sol_invested: f64,
+    pub sol_received: f64,
+    /// Network fees of the sell and the sold part of the buy, in SOL.
+    pub fees: f64,
+    pub profit_loss: bool,
+    pub tx_id: String,
+}
+
+impl ClosedPosition {
+    /// SOL gained (or lost, if negative) after fees.
+    pub fn net_sol(&self) -> f64 {
+        self.sol_received - self.sol_invested - self.fees
+    }
+}
+
+/// Thresholds that sell a position; `None` disables a rule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExitRules {
+    pub take_profit_pct: Option<f64>,
+    pub stop_loss_pct: Option<f64>,
+    pub trailing_stop_pct: Option<f64>,
+    pub max_hold_minutes: Option<i64>,
+}
+
+impl ExitRules {
+    pub fn from_settings(settings: &ExitSettings) -> Self {
+        Self {
+            take_profit_pct: settings.take_profit_pct,
+            stop_loss_pct: settings.stop_loss_pct,
+            trailing_stop_pct: settings.trailing_stop_pct,
+            max_hold_minutes: settings.max_hold_minutes,
+        }
+    }
+
+    /// Rules of `self` with the rules of `overrides` applied.
+    pub fn with_overrides(&self, overrides: &ExitRuleOverrides) -> Self {
+        Self {
+            take_profit_pct: overrides.take_profit_pct.apply(self.take_profit_pct),
+            stop_loss_pct: overrides.stop_loss_pct.apply(self.stop_loss_pct),
+            trailing_stop_pct: overrides.trailing_stop_pct.apply(self.trailing_stop_pct),
+            max_hold_minutes: overrides.max_hold_minutes.apply(self.max_hold_minutes),
+        }
+    }
+}
+
+/// Exit rule of a single position: left out in JSON it inherits the global rule, `null`
+/// turns the rule off and a value replaces it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RuleOverride<T> {
+    #[default]
+    Inherit,
+    Disabled,
+    Value(T),
+}
+
+impl<T: Copy> RuleOverride<T> {
+    /// Override as stored: a value column and a flag for a rule turned off.
+    pub fn from_columns(value: Option<T>, disabled: bool) -> Self {
+        match (value, disabled) {
+            (_, true) => RuleOverride::Disabled,
+            (Some(value), false) => RuleOverride::Value(value),
+            (None, false) => RuleOverride::Inherit,
+        }
+    }
+
+    pub fn apply(self, global: Option<T>) -> Option<T> {
+        match self {
+            RuleOverride::Inherit => global,
+            RuleOverride::Disabled => None,
+            RuleOverride::Value(value) => Some(value),
+        }
+    }
+
+    pub fn value(self) -> Option<T> {
+        match self {
+            RuleOverride::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn is_inherit(&self) -> bool {
+        matches!(self, RuleOverride::Inherit)
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        matches!(self, RuleOverride::Disabled)
+    }
+}
+
+impl<T: Serialize> Serialize for RuleOverride<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RuleOverride::Value(value) => value.serialize(serializer),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for RuleOverride<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // A missing field never gets here: `#[serde(default)]` makes it `Inherit`.
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => RuleOverride::Value(value),
+            None => RuleOverride::Disabled,
+        })
+    }
+}
+
+/// Exit rules set for a single position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExitRuleOverrides {
+    #[serde(default, skip_serializing_if = "RuleOverride::is_inherit")]
+    pub take_profit_pct: RuleOverride<f64>,
+    #[serde(default, skip_serializing_if = "RuleOverride::is_inherit")]
+    pub stop_loss_pct: RuleOverride<f64>,
+    #[serde(default, skip_serializing_if = "RuleOverride::is_inherit")]
+    pub trailing_stop_pct: RuleOverride<f64>,
+    #[serde(default, skip_serializing_if = "RuleOverride::is_inherit")]
+    pub max_hold_minutes: RuleOverride<i64>,
+}
+
+impl ExitRuleOverrides {
+    /// Percentages and the hold time must be positive; a zero threshold would sell at once.
+    pub fn validate(&self) -> Result<(), PositionError> {
+        let percentages = [
+            ("take_profit_pct", self.take_profit_pct),
+            ("stop_loss_pct", self.stop_loss_pct),
+            ("trailing_stop_pct", self.trailing_stop_pct),
+        ];
+        for (name, rule) in percentages {
+            if let Some(value) = rule.value() {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(PositionError::InvalidExitRule(format!("{} must be above 0, got {}", name, value)));
+                }
+            }
+        }
+        if let Some(max_hold) = self.max_hold_minutes.value() {
+            if max_hold <= 0 {
+                return Err(PositionError::InvalidExitRule(format!("max_hold_minutes must be above 0, got {}", max_hold)));
+            }
+        }
+        // A stop loss of 100% or more can never fire, nor can a trailing stop of more than 100%.
+        if self.stop_loss_pct.value().is_some_and(|value| value >= 100.0) {
+            return Err(PositionError::InvalidExitRule("stop_loss_pct must be below 100".to_string()));
+        }
+        if self.trailing_stop_pct.value().is_some_and(|value| value > 100.0) {
+            return Err(PositionError::InvalidExitRule("trailing_stop_pct must be at most 100".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Exit rules set for a single position and the peak value the trailing stop follows.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PositionExitState {
+    pub overrides: ExitRuleOverrides,
+    pub peak_value_sol: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(token_quantity: f64, remaining_quantity: f64) -> Position {
+        Position {
+            trade_position_id: 1,
+            chat_uuid: "chat".to_string(),
+            token_id: 1,
+            token_address: "mint".to_string(),
+            symbol: "TKN".to_string(),
+            pool_address: "pool".to_string(),
+            token_quantity,
+            remaining_quantity,
+            sol_invested: 2.0,
+            open_fee: 0.01,
+            opened_at: chrono::Utc::now().naive_utc(),
+            is_closed: false,
+        }
+    }
+
+    #[test]
+    fn cost_is_shared_out_by_the_quantity_sold() {
+        let position = position(1000.0, 1000.0);
+
+        let (sol_invested, open_fee) = position.cost_of(250.0);
+        assert!((sol_invested - 0.5).abs() < 1e-12);
+        assert!((open_fee - 0.0025).abs() < 1e-12);
+
+        assert_eq!(position.cost_of(1000.0), (2.0, 0.01));
+        assert_eq!(position.cost_of(1500.0), (2.0, 0.01));
+    }
+
+    #[test]
+    fn overrides_inherit_disable_or_replace_global_rules() {
+        let global = ExitRules { take_profit_pct: Some(50.0), stop_loss_pct: Some(20.0), trailing_stop_pct: None, max_hold_minutes: Some(60) };
+        let overrides: ExitRuleOverrides = serde_json::from_str(r#"{"stop_loss_pct": null, "trailing_stop_pct": 10.0}"#).unwrap();
+
+        let rules = global.with_overrides(&overrides);
+
+        assert_eq!(rules, ExitRules { take_profit_pct: Some(50.0), stop_loss_pct: None, trailing_stop_pct: Some(10.0), max_hold_minutes: Some(60) });
+        assert_eq!(serde_json::to_value(overrides).unwrap(), serde_json::json!({"stop_loss_pct": null, "trailing_stop_pct": 10.0}));
+    }
+
+    #[test]
+    fn rejects_thresholds_that_are_not_positive() {
+        let valid = ExitRuleOverrides { take_profit_pct: RuleOverride::Value(25.0), stop_loss_pct: RuleOverride::Disabled, ..Default::default() };
+        assert!(valid.validate().is_ok());
+
+        for invalid in [
+            ExitRuleOverrides { take_profit_pct: RuleOverride::Value(0.0), ..Default::default() },
+            ExitRuleOverrides { stop_loss_pct: RuleOverride::Value(-5.0), ..Default::default() },
+            ExitRuleOverrides { stop_loss_pct: RuleOverride::Value(100.0), ..Default::default() },
+            ExitRuleOverrides { trailing_stop_pct: RuleOverride::Value(f64::NAN), ..Default::default() },
+            ExitRuleOverrides { max_hold_minutes: RuleOverride::Value(0), ..Default::default() },
+        ] {
+            assert!(matches!(invalid.validate(), Err(PositionError::InvalidExitRule(_))), "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use std::error::Error;
+use std::str::FromStr;
+use tracing::{error, info};
+
+use crate::models::base::{ExitReason, PositionStatus, TradeTypeEnum};
+use crate::models::token::Model as Token;
+use crate::models::trade::Model as Trade;
+use crate::positions::models::{ClosedPosition, ExitRuleOverrides, Position, PositionExitState, RuleOverride};
+use crate::utils::error::PositionError;
+use crate::utils::raydium::MintInfo;
+use crate::utils::solana_driver::{SolanaDriver, SwapResult};
+
+/// Open trade of a position with its state.
+struct PositionRow {
+    trade_position_id: i32,
+    chat_uuid: String,
+    token_id: i32,
+    token_address: String,
+    symbol: String,
+    pool_address: String,
+    token_quantity: f64,
+    sol_invested: f64,
+    fee_rate: Option<f64>,
+    created_at: NaiveDateTime,
+    remaining_quantity: Option<f64>,
+    status: String,
+}
+
+impl From<PositionRow> for Position {
+    fn from(row: PositionRow) -> Self {
+        Position {
+            trade_position_id: row.trade_position_id,
+            chat_uuid: row.chat_uuid,
+            token_id: row.token_id,
+            token_address: row.token_address,
+            symbol: row.symbol,
+            pool_address: row.pool_address,
+            token_quantity: row.token_quantity,
+            remaining_quantity: row.remaining_quantity.unwrap_or(row.token_quantity).max(0.0),
+            sol_invested: row.sol_invested,
+            open_fee: row.fee_rate.unwrap_or(0.0),
+            opened_at: row.created_at,
+            is_closed: PositionStatus::from_str(&row.status).is_ok_and(|status| status == PositionStatus::Closed),
+        }
+    }
+}
+
+/// A sell that crashed before recording its outcome gives the position back after this long.
+const CLOSING_CLAIM_TIMEOUT_SECS: f64 = 900.0;
+
+/// Reference the sells of a position are recorded under in `solana_transactions`.
+fn sell_reference(trade_position_id: i32) -> String {
+    format!("position:{}", trade_position_id)
+}
+
+/// Outcome of claiming a position for a sell.
+enum Claim {
+    /// Another sell is running.
+    Refused,
+    Open,
+    /// Taken over from a sell that stopped without recording an outcome, started for this rule.
+    Abandoned(ExitReason),
+}
+
+/// Opens and closes positions. A `positions` row is reserved before the buy; the `open`
+/// trade written after the buy and the `closed` trades written after each sell share its
+/// id as `trade_position_id` and the chat of the buy.
+pub struct PositionManager {
+    pool: PgPool,
+    driver: SolanaDriver,
+}
+
+impl PositionManager {
+    pub fn new_position_manager(pool: PgPool, driver: SolanaDriver) -> Self {
+        Self { pool, driver }
+    }
+
+    pub async fn from_settings(pool: PgPool) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let driver = SolanaDriver::from_settings(pool.clone()).await?;
+        Ok(Self::new_position_manager(pool, driver))
+    }
+
+    pub fn driver(&self) -> &SolanaDriver {
+        &self.driver
+    }
+
+    /// Reserves a position for a buy about to be sent, so the buy can always be traced back
+    /// to its chat; returns its `trade_position_id`.
+    pub async fn reserve_position(&self, chat_uuid: &str, pool_address: &str) -> Result<i32, PositionError> {
+        let trade_position_id = sqlx::query_scalar!(
+            "INSERT INTO positions (chat_uuid, pool_address, status) VALUES ($1, $2, $3) RETURNING id",
+            chat_uuid,
+            pool_address,
+            PositionStatus::Pending.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(trade_position_id)
+    }
+
+    /// Marks a reserved position whose buy did not go through.
+    pub async fn fail_position(&self, trade_position_id: i32) -> Result<(), PositionError> {
+        sqlx::query!(
+            "UPDATE positions SET status = $2, updated_at = NOW() WHERE id = $1 AND status = $3",
+            trade_position_id,
+            PositionStatus::Failed.to_string(),
+            PositionStatus::Pending.to_string()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records the confirmed buy of `token` in a reserved position and opens it.
+    pub async fn open_position(
+        &self,
+        trade_position_id: i32,
+        pool_address: &str,
+        token: &MintInfo,
+        swap: &SwapResult,
+    ) -> Result<(), PositionError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let token_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO tokens (address, symbol, name, pool_address)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (address) DO UPDATE SET pool_address = EXCLUDED.pool_address
+            RETURNING id
+            "#,
+            token.address,
+            token.symbol,
+            token.name,
+            pool_address
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        let chat_uuid = sqlx::query_scalar!(
+            r#"
+            UPDATE positions SET status = $2, token_id = $3, remaining_quantity = $4, updated_at = NOW()
+            WHERE id = $1 AND status = $5
+            RETURNING chat_uuid
+            "#,
+            trade_position_id,
+            PositionStatus::Open.to_string(),
+            token_id,
+            swap.amount_out,
+            PositionStatus::Pending.to_string()
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(PositionError::NotFound(trade_position_id))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO trades (chat_uuid, base_token_quantity, quote_token_quantity, trade_type, tx_id, token_id, trade_position_id, fee_rate)
+            VALUES ($1, $2, $3, 'open', $4, $5, $6, $7)
+            "#,
+            chat_uuid,
+            swap.amount_in,
+            swap.amount_out,
+            swap.signature,
+            token_id,
+            trade_position_id,
+            // Rent of the token account the buy opened is part of what the position cost.
+            swap.fee + swap.rent
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+        info!("Opened position {} in {} for chat {}", trade_position_id, token.address, chat_uuid);
+        Ok(())
+    }
+
+    pub async fn position(&self, trade_position_id: i32) -> Result<Position, PositionError> {
+        get_position(&self.pool, trade_position_id).await
+    }
+
+    /// Tokens of the position not sold yet, in UI units.
+    pub async fn remaining_quantity(&self, trade_position_id: i32) -> Result<f64, PositionError> {
+        Ok(self.position(trade_position_id).await?.remaining_quantity)
+    }
+
+    /// Open positions, oldest first; those being sold are left out.
+    pub async fn open_positions(&self) -> Result<Vec<Position>, PositionError> {
+        get_open_positions(&self.pool).await
+    }
+
+    /// Raw amount of the position's token left to sell, capped by the agent's token balance.
+    pub async fn sellable_amount(&self, position: &Position) -> Result<u64, PositionError> {
+        Ok(self.sellable(position).await?.0)
+    }
+
+    /// Sellable raw amount and the token's decimals.
+    async fn sellable(&self, position: &Position) -> Result<(u64, u8), PositionError> {
+        let (balance, decimals) = self
+            .driver
+            .pool_token_balance(&position.pool_address)
+            .await
+            .map_err(|e| PositionError::Swap(e.to_string()))?;
+        let remaining = (position.remaining_quantity * 10f64.powi(decimals as i32)).floor() as u64;
+        Ok((remaining.min(balance), decimals))
+    }
+
+    /// Moves an open position, or one whose sell stopped without recording an outcome, to
+    /// `closing` for `exit_reason`; only a caller that is not refused may sell it.
+    async fn claim_for_closing(&self, trade_position_id: i32, exit_reason: ExitReason) -> Result<Claim, PositionError> {
+        let claimed = sqlx::query!(
+            r#"
+            WITH previous AS (
+                SELECT id, status, closing_reason FROM positions WHERE id = $1 FOR UPDATE
+            )
+            UPDATE positions p
+            SET status = $2, closing_started_at = NOW(), closing_reason = $5, updated_at = NOW()
+            FROM previous
+            WHERE p.id = previous.id
+              AND (previous.status = $3 OR (previous.status = $2 AND p.closing_started_at < NOW() - make_interval(secs => $4)))
+            RETURNING previous.status AS "previous_status!", previous.closing_reason
+            "#,
+            trade_position_id,
+            PositionStatus::Closing.to_string(),
+            PositionStatus::Open.to_string(),
+            CLOSING_CLAIM_TIMEOUT_SECS,
+            exit_reason.to_string()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(match claimed {
+            None => Claim::Refused,
+            Some(row) if row.previous_status == PositionStatus::Open.to_string() => Claim::Open,
+            Some(row) => Claim::Abandoned(
+                row.closing_reason
+                    .and_then(|reason| ExitReason::from_str(&reason).ok())
+                    .unwrap_or(ExitReason::Manual),
+            ),
+        })
+    }
+
+    /// Records the sells of a claimed position that landed after their caller stopped, read back
+    /// from its swap transactions not recorded as trades yet, and returns the last one. Errors
+    /// while one of them is still in flight or cannot be read; the position then stays claimed.
+    async fn record_abandoned_sells(&self, position: &Position, exit_reason: ExitReason) -> Result<Option<ClosedPosition>, PositionError> {
+        let transaction_ids = sqlx::query_scalar!(
+            r#"
+            SELECT s.id FROM solana_transactions s
+            WHERE s.purpose = 'swap' AND s.reference = $1
+              AND NOT EXISTS (SELECT 1 FROM trades t WHERE t.tx_id = s.signature)
+            ORDER BY s.id
+            "#,
+            sell_reference(position.trade_position_id)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut position = position.clone();
+        let mut recorded = None;
+        for transaction_id in transaction_ids {
+            let tracked = self
+                .driver
+                .transactions()
+                .settle_from_chain(transaction_id)
+                .await
+                .map_err(|e| PositionError::Swap(e.to_string()))?;
+            if !tracked.status.is_settled() {
+                return Err(PositionError::Closing(position.trade_position_id));
+            }
+            if !tracked.is_landed() {
+                continue;
+            }
+
+            let swap = self
+                .driver
+                .landed_sell(&position.pool_address, &tracked)
+                .await
+                .map_err(|e| PositionError::Swap(e.to_string()))?;
+            let (_, decimals) = self.sellable(&position).await?;
+            let closed = self.settle_sell(&position, swap, decimals, exit_reason, PositionStatus::Closing).await?;
+            info!("Recorded sell {} of position {} that landed after its caller stopped", closed.tx_id, position.trade_position_id);
+
+            position.remaining_quantity = closed.remaining_quantity;
+            let position_closed = closed.position_closed;
+            recorded = Some(closed);
+            if position_closed {
+                break;
+            }
+        }
+        Ok(recorded)
+    }
+
+    /// Puts back the rule of a sell still being waited for, so it is recorded with it later.
+    async fn restore_closing_reason(&self, trade_position_id: i32, exit_reason: ExitReason) {
+        let restored = sqlx::query!(
+            "UPDATE positions SET closing_reason = $2 WHERE id = $1 AND status = $3",
+            trade_position_id,
+            exit_reason.to_string(),
+            PositionStatus::Closing.to_string()
+        )
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = restored {
+            error!("Failed to restore the exit reason of position {}: {:?}", trade_position_id, e);
+        }
+    }
+
+    /// Hands a claimed position back when its sell did not happen.
+    async fn release_closing(&self, trade_position_id: i32) {
+        let released = sqlx::query!(
+            "UPDATE positions SET status = $2, closing_started_at = NULL, updated_at = NOW() WHERE id = $1 AND status = $3",
+            trade_position_id,
+            PositionStatus::Open.to_string(),
+            PositionStatus::Closing.to_string()
+        )
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = released {
+            error!("Failed to release position {}: {:?}", trade_position_id, e);
+        }
+    }
+
+    /// Sells what is left of the position and records the `closed` trade, with the
+    /// rule that triggered it, under the chat that opened it.
+    ///
+    /// The position is claimed first, so two sells never run at once. A claim taken over from a
+    /// sell that stopped first records whatever that sell landed, and returns it if it closed the
+    /// position. When the wallet holds fewer tokens than the position, the sell is recorded as
+    /// partial and the rest is kept in `remaining_quantity`.
+    pub async fn close_position(
+        &self,
+        trade_position_id: i32,
+        slippage_bps: u64,
+        exit_reason: ExitReason,
+    ) -> Result<ClosedPosition, PositionError> {
+        let mut position = self.position(trade_position_id).await?;
+        if position.is_closed {
+            return Err(PositionError::AlreadyClosed(trade_position_id));
+        }
+        match self.claim_for_closing(trade_position_id, exit_reason).await? {
+            Claim::Refused => return Err(PositionError::Closing(trade_position_id)),
+            Claim::Open => {}
+            Claim::Abandoned(abandoned_reason) => match self.record_abandoned_sells(&position, abandoned_reason).await {
+                Ok(Some(recorded)) if recorded.position_closed => return Ok(recorded),
+                Ok(Some(_)) => position = self.position(trade_position_id).await?,
+                Ok(None) => {}
+                Err(e) => {
+                    self.restore_closing_reason(trade_position_id, abandoned_reason).await;
+                    return Err(e);
+                }
+            },
+        }
+
+        let (amount, decimals) = match self.sellable(&position).await {
+            Ok(sellable) if sellable.0 > 0 => sellable,
+            Ok(_) => {
+                self.release_closing(trade_position_id).await;
+                return Err(PositionError::NothingToSell(trade_position_id));
+            }
+            Err(e) => {
+                self.release_closing(trade_position_id).await;
+                return Err(e);
+            }
+        };
+
+        let reference = sell_reference(trade_position_id);
+        let swap = match self.driver.sell_token(&position.pool_address, amount, slippage_bps, &reference).await {
+            Ok(swap) => swap,
+            Err(e) => {
+                self.release_closing(trade_position_id).await;
+                return Err(PositionError::Swap(e.to_string()));
+            }
+        };
+
+        self.settle_sell(&position, swap, decimals, exit_reason, PositionStatus::Open).await
+    }
+
+    /// Records a landed sell of the position. It is charged the share of the buy's cost and open
+    /// fee of the tokens sold; the position is closed once less than one raw unit of it is left,
+    /// and otherwise moves to `status_if_open`.
+    async fn settle_sell(
+        &self,
+        position: &Position,
+        swap: SwapResult,
+        decimals: u8,
+        exit_reason: ExitReason,
+        status_if_open: PositionStatus,
+    ) -> Result<ClosedPosition, PositionError> {
+        let trade_position_id = position.trade_position_id;
+        let remaining_quantity = (position.remaining_quantity - swap.amount_in).max(0.0);
+        let position_closed = remaining_quantity < 1.0 / 10f64.powi(decimals as i32);
+        let (sol_invested, open_fee) = position.cost_of(swap.amount_in);
+        let fees = open_fee + swap.fee;
+        let profit_loss = swap.amount_out - fees > sol_invested;
+
+        let status = if position_closed { PositionStatus::Closed } else { status_if_open };
+        let trade_id = match self.record_sell(position, &swap, profit_loss, exit_reason, remaining_quantity, status).await {
+            Ok(trade_id) => trade_id,
+            Err(e) => {
+                // The sell landed: the swap is still in `solana_transactions` under this signature,
+                // and the position stays `closing` until a later sell takes it over and records it.
+                error!("Failed to record sell {} of position {}: {:?}", swap.signature, trade_position_id, e);
+                return Err(e);
+            }
+        };
+
+        info!(
+            "{} position {} ({}) with {} SOL received",
+            if position_closed { "Closed" } else { "Partially closed" },
+            trade_position_id,
+            exit_reason,
+            swap.amount_out
+        );
+        Ok(ClosedPosition {
+            trade_position_id,
+            trade_id,
+            exit_reason,
+            chat_uuid: position.chat_uuid.clone(),
+            token_address: position.token_address.clone(),
+            symbol: position.symbol.clone(),
+            token_quantity: swap.amount_in,
+            remaining_quantity,
+            position_closed,
+            sol_invested,
+            sol_received: swap.amount_out,
+            fees,
+            profit_loss,
+            tx_id: swap.signature,
+        })
+    }
+
+    /// Writes the `closed` trade of a sell and the position's new state in one transaction, and
+    /// resets the trailing stop peak; a position left `closing` keeps its claim.
+    async fn record_sell(
+        &self,
+        position: &Position,
+        swap: &SwapResult,
+        profit_loss: bool,
+        exit_reason: ExitReason,
+        remaining_quantity: f64,
+        status: PositionStatus,
+    ) -> Result<i32, PositionError> {
+        let mut transaction = self.pool.begin().await?;
+        let trade_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO trades (chat_uuid, base_token_quantity, quote_token_quantity, trade_type, tx_id, profit_loss, token_id, trade_position_id, fee_rate, exit_reason)
+            VALUES ($1, $2, $3, 'closed', $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+            position.chat_uuid,
+            swap.amount_out,
+            swap.amount_in,
+            swap.signature,
+            profit_loss,
+            position.token_id,
+            position.trade_position_id,
+            swap.fee,
+            exit_reason.to_string()
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE positions
+            SET status = $2, remaining_quantity = $3, updated_at = NOW(),
+                closing_started_at = CASE WHEN $2 = 'closing' THEN closing_started_at END
+            WHERE id = $1
+            "#,
+            position.trade_position_id,
+            status.to_string(),
+            remaining_quantity
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        // The trailing stop peak was the worth of the tokens held before this sell.
+        sqlx::query!(
+            "UPDATE position_exit_rules SET peak_value_sol = NULL, updated_at = NOW() WHERE trade_position_id = $1",
+            position.trade_position_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(trade_id)
+    }
+
+    pub async fn trade(&self, trade_id: i32) -> Result<Trade, PositionError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, created_at, chat_uuid, base_token_quantity, quote_token_quantity, trade_type, tx_id,
+                   profit_loss, token_id, payment_id, trade_position_id, fee_rate, exit_reason
+            FROM trades WHERE id = $1
+            "#,
+            trade_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(PositionError::TradeNotFound(trade_id))?;
+        let trade_type = match row.trade_type.as_str() {
+            "open" => TradeTypeEnum::Open,
+            "closed" => TradeTypeEnum::Closed,
+            _ => return Err(PositionError::UnknownValue("trade type", row.trade_type)),
+        };
+        let exit_reason = row
+            .exit_reason
+            .map(|reason| ExitReason::from_str(&reason).map_err(|_| PositionError::UnknownValue("exit reason", reason)))
+            .transpose()?;
+        Ok(Trade {
+            id: row.id,
+            created_at: row.created_at,
+            chat_uuid: row.chat_uuid,
+            base_token_quantity: row.base_token_quantity,
+            quote_token_quantity: row.quote_token_quantity,
+            trade_type,
+            tx_id: row.tx_id,
+            profit_loss: row.profit_loss,
+            token_id: row.token_id,
+            payment_id: row.payment_id,
+            trade_position_id: row.trade_position_id,
+            fee_rate: row.fee_rate,
+            exit_reason,
+        })
+    }
+
+    pub async fn exit_state(&self, trade_position_id: i32) -> Result<PositionExitState, PositionError> {
+        get_exit_state(&self.pool, trade_position_id).await
+    }
+
+    pub async fn set_exit_rules(&self, trade_position_id: i32, rules: &ExitRuleOverrides) -> Result<(), PositionError> {
+        set_exit_rules(&self.pool, trade_position_id, rules).await
+    }
+
+    /// Raises the stored peak value of the position if `value_sol` is above it.
+    pub async fn record_peak_value(&self, trade_position_id: i32, value_sol: f64) -> Result<(), PositionError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO position_exit_rules (trade_position_id, peak_value_sol)
+            VALUES ($1, $2)
+            ON CONFLICT (trade_position_id) DO UPDATE
+            SET peak_value_sol = GREATEST(position_exit_rules.peak_value_sol, EXCLUDED.peak_value_sol),
+                updated_at = NOW()
+            "#,
+            trade_position_id,
+            value_sol
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+pub async fn get_position(pool: &PgPool, trade_position_id: i32) -> Result<Position, PositionError> {
+    let row = sqlx::query_as!(
+        PositionRow,
+        r#"
+        SELECT o.trade_position_id, o.chat_uuid, o.token_id,
+               t.address AS token_address, t.symbol, t.pool_address,
+               o.quote_token_quantity AS token_quantity, o.base_token_quantity AS sol_invested,
+               o.fee_rate, o.created_at, p.remaining_quantity, p.status
+        FROM trades o
+        JOIN tokens t ON t.id = o.token_id
+        JOIN positions p ON p.id = o.trade_position_id
+        WHERE o.trade_type = 'open' AND o.trade_position_id = $1
+        "#,
+        trade_position_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    row.map(Position::from).ok_or(PositionError::NotFound(trade_position_id))
+}
+
+/// Open positions, oldest first; those being sold are left out.
+pub async fn get_open_positions(pool: &PgPool) -> Result<Vec<Position>, PositionError> {
+    let rows = sqlx::query_as!(
+        PositionRow,
+        r#"
+        SELECT o.trade_position_id, o.chat_uuid, o.token_id,
+               t.address AS token_address, t.symbol, t.pool_address,
+               o.quote_token_quantity AS token_quantity, o.base_token_quantity AS sol_invested,
+               o.fee_rate, o.created_at, p.remaining_quantity, p.status
+        FROM trades o
+        JOIN tokens t ON t.id = o.token_id
+        JOIN positions p ON p.id = o.trade_position_id
+        WHERE o.trade_type = 'open' AND p.status = $1
+        ORDER BY o.created_at
+        "#,
+        PositionStatus::Open.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(Position::from).collect())
+}
+
+/// Per-position exit rules and trailing stop peak; defaults when none were set.
+pub async fn get_exit_state(pool: &PgPool, trade_position_id: i32) -> Result<PositionExitState, PositionError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT take_profit_pct, stop_loss_pct, trailing_stop_pct, max_hold_minutes,
+               take_profit_disabled, stop_loss_disabled, trailing_stop_disabled, max_hold_disabled,
+               peak_value_sol
+        FROM position_exit_rules WHERE trade_position_id = $1
+        "#,
+        trade_position_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row
+        .map(|row| PositionExitState {
+            overrides: ExitRuleOverrides {
+                take_profit_pct: RuleOverride::from_columns(row.take_profit_pct, row.take_profit_disabled),
+                stop_loss_pct: RuleOverride::from_columns(row.stop_loss_pct, row.stop_loss_disabled),
+                trailing_stop_pct: RuleOverride::from_columns(row.trailing_stop_pct, row.trailing_stop_disabled),
+                max_hold_minutes: RuleOverride::from_columns(row.max_hold_minutes, row.max_hold_disabled),
+            },
+            peak_value_sol: row.peak_value_sol,
+        })
+        .unwrap_or_default())
+}
+
+/// Replaces the exit rules of an open position; rules left to inherit follow the global ones.
+pub async fn set_exit_rules(pool: &PgPool, trade_position_id: i32, rules: &ExitRuleOverrides) -> Result<(), PositionError> {
+    rules.validate()?;
+    if get_position(pool, trade_position_id).await?.is_closed {
+        return Err(PositionError::AlreadyClosed(trade_position_id));
+    }
+    sqlx::query!(
+        r#"
+        INSERT INTO position_exit_rules (
+            trade_position_id, take_profit_pct, stop_loss_pct, trailing_stop_pct, max_hold_minutes,
+            take_profit_disabled, stop_loss_disabled, trailing_stop_disabled, max_hold_disabled
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (trade_position_id) DO UPDATE
+        SET take_profit_pct = EXCLUDED.take_profit_pct,
+            stop_loss_pct = EXCLUDED.stop_loss_pct,
+            trailing_stop_pct = EXCLUDED.trailing_stop_pct,
+            max_hold_minutes = EXCLUDED.max_hold_minutes,
+            take_profit_disabled = EXCLUDED.take_profit_disabled,
+            stop_loss_disabled = EXCLUDED.stop_loss_disabled,
+            trailing_stop_disabled = EXCLUDED.trailing_stop_disabled,
+            max_hold_disabled = EXCLUDED.max_hold_disabled,
+            updated_at = NOW()
+        "#,
+        trade_position_id,
+        rules.take_profit_pct.value(),
+        rules.stop_loss_pct.value(),
+        rules.trailing_stop_pct.value(),
+        rules.max_hold_minutes.value(),
+        rules.take_profit_pct.is_disabled(),
+        rules.stop_loss_pct.is_disabled(),
+        rules.trailing_stop_pct.is_disabled(),
+        rules.max_hold_minutes.is_disabled()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Token of a trade.
+pub async fn get_token_by_trade_id(pool: &PgPool, trade_id: i32) -> Result<Token, PositionError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT t.id, t.created_at, t.address, t.symbol, t.name, t.pool_address
+        FROM trades tr
+        JOIN tokens t ON t.id = tr.token_id
+        WHERE tr.id = $1
+        "#,
+        trade_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PositionError::TradeNotFound(trade_id))?;
+    Ok(Token {
+        id: row.id,
+        created_at: row.created_at,
+        address: row.address,
+        symbol: row.symbol,
+        name: row.name,
+        pool_address: row.pool_address,
+    })
+}
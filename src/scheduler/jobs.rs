@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::balance_history::BalanceRecorder;
+use crate::credits::RetweetCreditService;
+use crate::llm::provider::LlmProvider;
+use crate::positions::{sell_all_positions, ExitEngine, PositionManager};
+use crate::scheduler::schedule::Schedule;
+use crate::utils::error::{CreditError, TwitterError};
+use crate::utils::solana_driver::SolanaDriver;
+
+/// A recurring task run by the `Scheduler`.
+#[async_trait]
+pub trait Job: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Runs the task once; the returned summary is kept in the run history.
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>>;
+}
+
+/// A job together with when it runs.
+pub struct JobDefinition {
+    pub job: Box<dyn Job>,
+    pub schedule: Schedule,
+    /// State of a job the first time it is registered; sells are opt-in.
+    pub paused_by_default: bool,
+    /// Also run once when the scheduler starts, paused or not.
+    pub run_at_startup: bool,
+}
+
+impl JobDefinition {
+    pub fn new_job_definition(job: Box<dyn Job>, schedule: Schedule, paused_by_default: bool) -> Self {
+        Self { job, schedule, paused_by_default, run_at_startup: false }
+    }
+
+    pub fn with_run_at_startup(self) -> Self {
+        Self { run_at_startup: true, ..self }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.job.name()
+    }
+}
+
+/// Awards credits for retweets of the agent's posts, as `/check_retwitts` does.
+pub struct CheckRetwittsJob {
+    pool: PgPool,
+}
+
+impl CheckRetwittsJob {
+    /// Also the lease `/check_retwitts` takes, so a manual check never overlaps a scheduled one.
+    pub const NAME: &'static str = "check_retwitts";
+
+    pub fn new_check_retwitts_job(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Job for CheckRetwittsJob {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        // A rate limit is not a failure: the next scheduled run picks the posts up.
+        match RetweetCreditService::from_settings(self.pool.clone())?.award_retweet_credits().await {
+            Ok(report) => Ok(report.summary()),
+            Err(CreditError::Twitter(TwitterError::RateLimited(retry_after_secs))) => {
+                Ok(format!("Twitter rate limited for {} seconds, nothing checked", retry_after_secs))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Sells every open position, as `/sell_tokens` does.
+pub struct SellTokensJob {
+    pool: PgPool,
+    provider: Arc<dyn LlmProvider>,
+}
+
+impl SellTokensJob {
+    /// Also the lease `/sell_tokens` takes, so a manual sell never overlaps a scheduled one.
+    pub const NAME: &'static str = "sell_tokens";
+
+    pub fn new_sell_tokens_job(pool: PgPool, provider: Arc<dyn LlmProvider>) -> Self {
+        Self { pool, provider }
+    }
+}
+
+#[async_trait]
+impl Job for SellTokensJob {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let positions = PositionManager::from_settings(self.pool.clone()).await?;
+        let results = sell_all_positions(self.provider.as_ref(), &positions, &self.pool).await?;
+        let failed: Vec<String> = results
+            .iter()
+            .filter_map(|result| result.error.as_ref().map(|e| format!("{}: {}", result.trade_position_id, e)))
+            .collect();
+        if !failed.is_empty() {
+            return Err(format!("Failed to sell {} of {} positions: {}", failed.len(), results.len(), failed.join("; ")).into());
+        }
+        Ok(format!("Sold {} positions", results.len()))
+    }
+}
+
+/// Sells the open positions whose exit rules fire and retries failed payouts.
+pub struct ExitPositionsJob {
+    pool: PgPool,
+    provider: Arc<dyn LlmProvider>,
+}
+
+impl ExitPositionsJob {
+    pub fn new_exit_positions_job(pool: PgPool, provider: Arc<dyn LlmProvider>) -> Self {
+        Self { pool, provider }
+    }
+}
+
+#[async_trait]
+impl Job for ExitPositionsJob {
+    fn name(&self) -> &'static str {
+        "exit_positions"
+    }
+
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let engine = ExitEngine::from_settings(self.pool.clone(), self.provider.clone()).await?;
+        let closed = engine.run_once().await?;
+        Ok(format!("Sold {} positions", closed.len()))
+    }
+}
+
+/// Snapshots the agent balance, as `/log_agent_balance` does.
+pub struct LogAgentBalanceJob {
+    pool: PgPool,
+}
+
+impl LogAgentBalanceJob {
+    /// Also the lease `/log_agent_balance` takes, so a manual snapshot never overlaps a scheduled one.
+    pub const NAME: &'static str = "log_agent_balance";
+
+    pub fn new_log_agent_balance_job(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Job for LogAgentBalanceJob {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let snapshot = BalanceRecorder::shared(self.pool.clone()).await?.record_snapshot().await?;
+        Ok(format!("Agent balance {} SOL", snapshot.balance_sol))
+    }
+}
+
+/// Finishes transactions a crashed or restarted process left in flight.
+pub struct RecoverTransactionsJob {
+    pool: PgPool,
+}
+
+impl RecoverTransactionsJob {
+    pub fn new_recover_transactions_job(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Job for RecoverTransactionsJob {
+    fn name(&self) -> &'static str {
+        "recover_transactions"
+    }
+
+    async fn run(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let recovered = SolanaDriver::from_settings(self.pool.clone()).await?.recover_pending_transactions().await?;
+        let landed = recovered.iter().filter(|transaction| transaction.is_landed()).count();
+        Ok(format!("Recovered {} transactions, {} landed", recovered.len(), landed))
+    }
+}
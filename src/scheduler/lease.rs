@@ -0,0 +1,68 @@
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::utils::error::SchedulerError;
+use crate::utils::redis::RedisClient;
+
+fn lease_key(job_name: &str) -> String {
+    format!("scheduler:lease:{}", job_name)
+}
+
+/// The Redis lease of a job, held by one run.
+///
+/// Each run takes the lease under its own token, so a run that outlived an expired lease
+/// cannot renew or release the lease of the run after it. While held, a heartbeat renews it
+/// every third of `ttl_secs`; a crashed run stops renewing and the lease expires.
+pub struct JobLease {
+    redis: RedisClient,
+    key: String,
+    token: String,
+    heartbeat: JoinHandle<()>,
+}
+
+impl JobLease {
+    /// Takes the lease of `job_name`; `SchedulerError::LeaseHeld` if another run holds it.
+    pub async fn acquire(redis: &RedisClient, job_name: &str, ttl_secs: u64) -> Result<Self, SchedulerError> {
+        let key = lease_key(job_name);
+        let token = Uuid::new_v4().to_string();
+        if !redis.try_acquire_lease(&key, &token, ttl_secs).await? {
+            return Err(SchedulerError::LeaseHeld(job_name.to_string()));
+        }
+
+        let heartbeat = tokio::spawn(Self::heartbeat(redis.clone(), key.clone(), token.clone(), ttl_secs));
+        Ok(Self { redis: redis.clone(), key, token, heartbeat })
+    }
+
+    async fn heartbeat(redis: RedisClient, key: String, token: String, ttl_secs: u64) {
+        let mut interval = tokio::time::interval(Duration::from_secs((ttl_secs / 3).max(1)));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match redis.renew_lease(&key, &token, ttl_secs).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    error!("Lease {} expired before its run finished", key);
+                    return;
+                }
+                Err(e) => warn!("Failed to renew lease {}: {}", key, e),
+            }
+        }
+    }
+
+    /// Stops the heartbeat and gives the lease back.
+    pub async fn release(self) {
+        self.heartbeat.abort();
+        if let Err(e) = self.redis.release_lease(&self.key, &self.token).await {
+            warn!("Failed to release lease {}: {}", self.key, e);
+        }
+    }
+}
+
+// A run dropped without `release` stops renewing, so its lease expires after `ttl_secs`.
+impl Drop for JobLease {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+    }
+}
@@ -0,0 +1,11 @@
+pub mod jobs;
+pub mod lease;
+pub mod models;
+pub mod schedule;
+pub mod service;
+
+pub use jobs::{CheckRetwittsJob, ExitPositionsJob, Job, JobDefinition, LogAgentBalanceJob, RecoverTransactionsJob, SellTokensJob};
+pub use lease::JobLease;
+pub use models::{JobInfo, JobRun};
+pub use schedule::{CronSchedule, Schedule};
+pub use service::Scheduler;
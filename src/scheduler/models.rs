@@ -0,0 +1,29 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::models::base::{JobRunStatus, JobTrigger};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: i32,
+    pub job_name: String,
+    pub trigger: JobTrigger,
+    pub status: JobRunStatus,
+    pub instance_id: String,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub duration_ms: Option<i64>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A declared job with its shared state and latest run.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub name: String,
+    pub schedule: String,
+    pub paused: bool,
+    /// When the job is due next, in UTC.
+    pub next_run_at: Option<NaiveDateTime>,
+    pub last_run: Option<JobRun>,
+}
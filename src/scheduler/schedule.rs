@@ -0,0 +1,260 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::utils::error::SchedulerError;
+
+/// Minutes searched for the next match of a cron expression, about four years.
+const MAX_CRON_SEARCH_MINUTES: i64 = 4 * 366 * 24 * 60;
+
+/// Values allowed by one cron field, as a bit set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CronField {
+    bits: u64,
+    /// `*` or `*/n`; matters for the day-of-month and day-of-week rule.
+    any: bool,
+}
+
+impl CronField {
+    fn parse(field: &str, min: u32, max: u32) -> Option<Self> {
+        let mut bits = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+                None => (part, 1),
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+                    // `a/n` runs from `a` to the end of the field.
+                    None if part.contains('/') => (range.parse().ok()?, max),
+                    None => {
+                        let value = range.parse().ok()?;
+                        (value, value)
+                    }
+                },
+            };
+            if start < min || end > max || start > end {
+                return None;
+            }
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Some(Self { bits, any: field.starts_with('*') })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+/// A five field cron expression (`minute hour day-of-month month day-of-week`), in UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: CronField,
+    hours: CronField,
+    days_of_month: CronField,
+    months: CronField,
+    days_of_week: CronField,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, SchedulerError> {
+        let invalid = || SchedulerError::InvalidSchedule(expression.to_string());
+        let expanded = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => expression,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(invalid());
+        };
+
+        let mut days_of_week = CronField::parse(days_of_week, 0, 7).ok_or_else(invalid)?;
+        // Both 0 and 7 are Sunday.
+        if days_of_week.contains(7) {
+            days_of_week.bits = (days_of_week.bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: CronField::parse(minutes, 0, 59).ok_or_else(invalid)?,
+            hours: CronField::parse(hours, 0, 23).ok_or_else(invalid)?,
+            days_of_month: CronField::parse(days_of_month, 1, 31).ok_or_else(invalid)?,
+            months: CronField::parse(months, 1, 12).ok_or_else(invalid)?,
+            days_of_week,
+        })
+    }
+
+    /// Day rule of cron: when both day fields are restricted, either one may match.
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month.contains(date.day());
+        let day_of_week = self.days_of_week.contains(date.weekday().num_days_from_sunday());
+        match (self.days_of_month.any, self.days_of_week.any) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    /// First matching minute strictly after `after`; `None` if the expression never matches,
+    /// such as `0 0 30 2 *`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = start + Duration::minutes(MAX_CRON_SEARCH_MINUTES);
+        let mut time = start;
+        while time < end {
+            let date = time.date();
+            if !self.months.contains(date.month()) {
+                let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !self.hours.contains(time.hour()) {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+            } else if !self.minutes.contains(time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time.and_utc());
+            }
+        }
+        None
+    }
+}
+
+/// When a job runs: a cron expression, or `@every <n><s|m|h|d>` for a fixed interval.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Next run strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => Some(after + *interval),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = SchedulerError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expression = expression.trim();
+        let Some(interval) = expression.strip_prefix("@every ") else {
+            return CronSchedule::parse(expression).map(Schedule::Cron);
+        };
+
+        let invalid = || SchedulerError::InvalidSchedule(expression.to_string());
+        let interval = interval.trim();
+        let unit = interval.chars().last().ok_or_else(invalid)?;
+        let amount: i64 = interval[..interval.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+        let interval = match unit {
+            's' => Duration::try_seconds(amount),
+            'm' => Duration::try_minutes(amount),
+            'h' => Duration::try_hours(amount),
+            'd' => Duration::try_days(amount),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        if interval <= Duration::zero() {
+            return Err(invalid());
+        }
+        Ok(Schedule::Every(interval))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "@every {}s", interval.num_seconds()),
+            Schedule::Cron(cron) => write!(f, "{}", cron.expression),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap().and_utc()
+    }
+
+    fn next(expression: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        CronSchedule::parse(expression).unwrap().next_after(after)
+    }
+
+    #[test]
+    fn finds_the_next_matching_minute() {
+        assert_eq!(next("*/10 * * * *", at(2024, 1, 1, 10, 3) + Duration::seconds(30)), Some(at(2024, 1, 1, 10, 10)));
+        assert_eq!(next("0 0 * * *", at(2024, 1, 1, 0, 0)), Some(at(2024, 1, 2, 0, 0)));
+        assert_eq!(next("15 9-17/4 * * *", at(2024, 1, 1, 13, 15)), Some(at(2024, 1, 1, 17, 15)));
+        assert_eq!(next("30 6 1 3 *", at(2024, 1, 15, 12, 0)), Some(at(2024, 3, 1, 6, 30)));
+        assert_eq!(next("0 0 29 2 *", at(2025, 1, 1, 0, 0)), Some(at(2028, 2, 29, 0, 0)));
+    }
+
+    #[test]
+    fn treats_seven_as_sunday() {
+        // 2024-01-01 is a Monday.
+        assert_eq!(next("0 0 * * 0", at(2024, 1, 1, 12, 0)), Some(at(2024, 1, 7, 0, 0)));
+        assert_eq!(next("0 0 * * 7", at(2024, 1, 1, 12, 0)), Some(at(2024, 1, 7, 0, 0)));
+    }
+
+    #[test]
+    fn matches_either_restricted_day_field() {
+        assert_eq!(next("0 0 13 * 5", at(2024, 1, 1, 0, 0)), Some(at(2024, 1, 5, 0, 0)));
+        assert_eq!(next("0 0 13 * 5", at(2024, 1, 12, 0, 0)), Some(at(2024, 1, 13, 0, 0)));
+    }
+
+    #[test]
+    fn never_fires_on_impossible_dates() {
+        assert_eq!(next("0 0 30 2 *", at(2024, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!("@every 30s".parse::<Schedule>().unwrap(), Schedule::Every(Duration::seconds(30)));
+        assert_eq!("@every 5m".parse::<Schedule>().unwrap(), Schedule::Every(Duration::minutes(5)));
+        assert_eq!(" @every 2h ".parse::<Schedule>().unwrap(), Schedule::Every(Duration::hours(2)));
+        assert_eq!("@every 1d".parse::<Schedule>().unwrap(), Schedule::Every(Duration::days(1)));
+        assert_eq!("@every 5m".parse::<Schedule>().unwrap().to_string(), "@every 300s");
+    }
+
+    #[test]
+    fn parses_cron_expressions_and_shorthands() {
+        assert!(matches!("*/10 * * * *".parse::<Schedule>(), Ok(Schedule::Cron(_))));
+        let daily = "@daily".parse::<Schedule>().unwrap();
+        assert_eq!(daily.to_string(), "@daily");
+        assert_eq!(daily.next_after(at(2024, 1, 1, 8, 0)), Some(at(2024, 1, 2, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        for expression in [
+            "@every",
+            "@every 0s",
+            "@every -1m",
+            "@every 5x",
+            "@every m",
+            "61 * * * *",
+            "* * *",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "0 0 0 * *",
+            "0 0 * 13 *",
+        ] {
+            assert!(expression.parse::<Schedule>().is_err(), "{} should be rejected", expression);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use chrono::{NaiveDateTime, Utc};
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::FutureExt;
+use sqlx::PgPool;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::core::config::{ExitSettings, RedisSettings, SchedulerSettings};
+use crate::llm::provider::LlmProvider;
+use crate::models::base::{JobRunStatus, JobTrigger};
+use crate::scheduler::jobs::{CheckRetwittsJob, ExitPositionsJob, JobDefinition, LogAgentBalanceJob, RecoverTransactionsJob, SellTokensJob};
+use crate::scheduler::lease::JobLease;
+use crate::scheduler::models::{JobInfo, JobRun};
+use crate::scheduler::schedule::Schedule;
+use crate::utils::error::SchedulerError;
+use crate::utils::redis::RedisClient;
+
+/// Row of `job_runs` with the enums still as text.
+struct JobRunRow {
+    id: i32,
+    job_name: String,
+    trigger: String,
+    status: String,
+    instance_id: String,
+    started_at: NaiveDateTime,
+    finished_at: Option<NaiveDateTime>,
+    duration_ms: Option<i64>,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+impl From<JobRunRow> for JobRun {
+    fn from(row: JobRunRow) -> Self {
+        JobRun {
+            id: row.id,
+            job_name: row.job_name,
+            trigger: JobTrigger::from_str(&row.trigger).unwrap_or(JobTrigger::Schedule),
+            status: JobRunStatus::from_str(&row.status).unwrap_or(JobRunStatus::Failed),
+            instance_id: row.instance_id,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            duration_ms: row.duration_ms,
+            output: row.output,
+            error: row.error,
+        }
+    }
+}
+
+/// Runs the declared jobs on their schedules.
+///
+/// Every instance polls `scheduled_jobs` for due jobs; a run first takes the job's Redis
+/// lease, so only one instance runs a job at a time, then moves `next_run_at` forward so
+/// the others skip it. Each run is recorded in `job_runs`.
+pub struct Scheduler {
+    pool: PgPool,
+    redis: RedisClient,
+    instance_id: String,
+    jobs: Vec<JobDefinition>,
+    settings: SchedulerSettings,
+}
+
+impl Scheduler {
+    pub fn new_scheduler(pool: PgPool, redis: RedisClient, jobs: Vec<JobDefinition>, settings: SchedulerSettings) -> Self {
+        Self { pool, redis, instance_id: Uuid::new_v4().to_string(), jobs, settings }
+    }
+
+    /// Scheduler of the agent's recurring jobs, with schedules from `SCHEDULE_*`.
+    pub async fn from_settings(pool: PgPool, provider: Arc<dyn LlmProvider>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let settings = SchedulerSettings::new_scheduler();
+        let redis = RedisClient::new_redis_client(&RedisSettings::new_redis().redis_url)
+            .await
+            .map_err(|e| e.to_string())?;
+        let jobs = vec![
+            JobDefinition::new_job_definition(
+                Box::new(CheckRetwittsJob::new_check_retwitts_job(pool.clone())),
+                Schedule::from_str(&settings.check_retwitts_schedule)?,
+                false,
+            ),
+            JobDefinition::new_job_definition(
+                Box::new(SellTokensJob::new_sell_tokens_job(pool.clone(), provider)),
+                Schedule::from_str(&settings.sell_tokens_schedule)?,
+                true,
+            ),
+            JobDefinition::new_job_definition(
+                Box::new(LogAgentBalanceJob::new_log_agent_balance_job(pool.clone())),
+                Schedule::from_str(&settings.log_agent_balance_schedule)?,
+                false,
+            ),
+            JobDefinition::new_job_definition(
+                Box::new(RecoverTransactionsJob::new_recover_transactions_job(pool.clone())),
+                Schedule::from_str(&settings.recover_transactions_schedule)?,
+                false,
+            )
+            .with_run_at_startup(),
+        ];
+        Ok(Self::new_scheduler(pool, redis, jobs, settings))
+    }
+
+    /// Scheduler of the exit engine alone, checking positions every `EXIT_POLL_INTERVAL_SECS`.
+    pub async fn for_exit_engine(pool: PgPool, provider: Arc<dyn LlmProvider>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let redis = RedisClient::new_redis_client(&RedisSettings::new_redis().redis_url)
+            .await
+            .map_err(|e| e.to_string())?;
+        let interval = ExitSettings::new_exit().poll_interval_secs;
+        let jobs = vec![JobDefinition::new_job_definition(
+            Box::new(ExitPositionsJob::new_exit_positions_job(pool.clone(), provider)),
+            Schedule::from_str(&format!("@every {}s", interval))?,
+            false,
+        )];
+        Ok(Self::new_scheduler(pool, redis, jobs, SchedulerSettings::new_scheduler()))
+    }
+
+    pub fn settings(&self) -> &SchedulerSettings {
+        &self.settings
+    }
+
+    fn job(&self, name: &str) -> Result<&JobDefinition, SchedulerError> {
+        self.jobs
+            .iter()
+            .find(|job| job.name() == name)
+            .ok_or_else(|| SchedulerError::UnknownJob(name.to_string()))
+    }
+
+    /// Adds the declared jobs to `scheduled_jobs`. A job keeps its paused state across
+    /// restarts; a changed schedule takes effect from now.
+    pub async fn register_jobs(&self) -> Result<(), SchedulerError> {
+        let now = Utc::now();
+        for job in &self.jobs {
+            let next_run_at = job.schedule.next_after(now).map(|next| next.naive_utc());
+            if next_run_at.is_none() {
+                warn!("Schedule {} of job {} never fires", job.schedule, job.name());
+            }
+            sqlx::query!(
+                r#"
+                INSERT INTO scheduled_jobs (name, schedule, paused, next_run_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name) DO UPDATE
+                SET schedule = EXCLUDED.schedule,
+                    next_run_at = CASE
+                        WHEN scheduled_jobs.schedule = EXCLUDED.schedule AND scheduled_jobs.next_run_at IS NOT NULL
+                        THEN scheduled_jobs.next_run_at
+                        ELSE EXCLUDED.next_run_at
+                    END,
+                    updated_at = NOW()
+                "#,
+                job.name(),
+                job.schedule.to_string(),
+                job.paused_by_default,
+                next_run_at
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Registers the jobs, then runs those that are due every `SCHEDULER_POLL_INTERVAL_SECS`
+    /// until the task is dropped. Jobs run concurrently with each other and with polling.
+    pub async fn run(&self) {
+        if let Err(e) = self.register_jobs().await {
+            error!("Failed to register scheduled jobs: {}", e);
+        }
+        info!("Scheduler {} started with {} jobs", self.instance_id, self.jobs.len());
+
+        let mut interval = tokio::time::interval(Duration::from_secs(self.settings.poll_interval_secs));
+        let mut running = FuturesUnordered::new();
+        running.extend(self.jobs.iter().filter(|job| job.run_at_startup).map(|job| self.run_at_startup(job).boxed()));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => match self.due_jobs().await {
+                    Ok(due) => running.extend(due.into_iter().map(|job| self.run_scheduled(job).boxed())),
+                    Err(e) => error!("Failed to look up due jobs: {}", e),
+                },
+                Some(()) = running.next(), if !running.is_empty() => {}
+            }
+        }
+    }
+
+    async fn due_jobs(&self) -> Result<Vec<&JobDefinition>, SchedulerError> {
+        let names = sqlx::query_scalar!(
+            "SELECT name FROM scheduled_jobs WHERE NOT paused AND next_run_at <= $1",
+            Utc::now().naive_utc()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(self.jobs.iter().filter(|job| names.iter().any(|name| name == job.name())).collect())
+    }
+
+    async fn run_scheduled(&self, job: &JobDefinition) {
+        let name = job.name();
+        let lease = match JobLease::acquire(&self.redis, name, self.settings.lease_ttl_secs).await {
+            Ok(lease) => lease,
+            Err(SchedulerError::LeaseHeld(_)) => {
+                debug!("Job {} is leased by another run", name);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to take lease of job {}: {}", name, e);
+                return;
+            }
+        };
+
+        match self.claim_scheduled_run(job).await {
+            Ok(true) => {
+                if let Err(e) = self.execute(job, JobTrigger::Schedule).await {
+                    error!("Failed to record run of job {}: {}", name, e);
+                }
+            }
+            Ok(false) => debug!("Job {} was already run by another instance", name),
+            Err(e) => error!("Failed to claim run of job {}: {}", name, e),
+        }
+        lease.release().await;
+    }
+
+    async fn run_at_startup(&self, job: &JobDefinition) {
+        if let Err(e) = self.run_now(job, JobTrigger::Startup).await {
+            error!("Failed to run job {} at startup: {}", job.name(), e);
+        }
+    }
+
+    /// Moves `next_run_at` past now if the job is still due, under the lease.
+    async fn claim_scheduled_run(&self, job: &JobDefinition) -> Result<bool, SchedulerError> {
+        let now = Utc::now();
+        let next_run_at = job.schedule.next_after(now).map(|next| next.naive_utc());
+        let claimed = sqlx::query!(
+            "UPDATE scheduled_jobs SET next_run_at = $2, updated_at = NOW() WHERE name = $1 AND NOT paused AND next_run_at <= $3",
+            job.name(),
+            next_run_at,
+            now.naive_utc()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(claimed.rows_affected() > 0)
+    }
+
+    /// Runs a job now, paused or not, unless it is already running somewhere.
+    pub async fn trigger(&self, job_name: &str) -> Result<JobRun, SchedulerError> {
+        self.run_now(self.job(job_name)?, JobTrigger::Manual).await
+    }
+
+    async fn run_now(&self, job: &JobDefinition, trigger: JobTrigger) -> Result<JobRun, SchedulerError> {
+        let lease = JobLease::acquire(&self.redis, job.name(), self.settings.lease_ttl_secs).await?;
+        let run = self.execute(job, trigger).await;
+        lease.release().await;
+        run
+    }
+
+    /// Runs a job and records it in `job_runs`; a failing job is a failed run, not an error.
+    async fn execute(&self, job: &JobDefinition, trigger: JobTrigger) -> Result<JobRun, SchedulerError> {
+        let run_id = sqlx::query_scalar!(
+            "INSERT INTO job_runs (job_name, trigger, status, instance_id) VALUES ($1, $2, $3, $4) RETURNING id",
+            job.name(),
+            trigger.to_string(),
+            JobRunStatus::Running.to_string(),
+            self.instance_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!("Running job {} ({})", job.name(), trigger);
+        let started = Instant::now();
+        let result = job.job.run().await;
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        let (status, output, error) = match result {
+            Ok(output) => {
+                info!("Job {} finished in {} ms: {}", job.name(), duration_ms, output);
+                (JobRunStatus::Succeeded, Some(output), None)
+            }
+            Err(e) => {
+                error!("Job {} failed after {} ms: {}", job.name(), duration_ms, e);
+                (JobRunStatus::Failed, None, Some(e.to_string()))
+            }
+        };
+        let row = sqlx::query_as!(
+            JobRunRow,
+            r#"
+            UPDATE job_runs
+            SET status = $2, finished_at = NOW(), duration_ms = $3, output = $4, error = $5
+            WHERE id = $1
+            RETURNING id, job_name, trigger, status, instance_id, started_at, finished_at, duration_ms, output, error
+            "#,
+            run_id,
+            status.to_string(),
+            duration_ms,
+            output,
+            error
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.into())
+    }
+
+    pub async fn set_paused(&self, job_name: &str, paused: bool) -> Result<JobInfo, SchedulerError> {
+        self.job(job_name)?;
+        sqlx::query!(
+            "UPDATE scheduled_jobs SET paused = $2, updated_at = NOW() WHERE name = $1",
+            job_name,
+            paused
+        )
+        .execute(&self.pool)
+        .await?;
+        info!("Job {} {}", job_name, if paused { "paused" } else { "resumed" });
+        self.job_info(job_name).await
+    }
+
+    /// Declared jobs with their state and latest run.
+    pub async fn jobs(&self) -> Result<Vec<JobInfo>, SchedulerError> {
+        let mut jobs = Vec::with_capacity(self.jobs.len());
+        for job in &self.jobs {
+            jobs.push(self.job_info(job.name()).await?);
+        }
+        Ok(jobs)
+    }
+
+    async fn job_info(&self, job_name: &str) -> Result<JobInfo, SchedulerError> {
+        let job = self.job(job_name)?;
+        let state = sqlx::query!("SELECT paused, next_run_at FROM scheduled_jobs WHERE name = $1", job_name)
+            .fetch_optional(&self.pool)
+            .await?;
+        let last_run = sqlx::query_as!(
+            JobRunRow,
+            r#"
+            SELECT id, job_name, trigger, status, instance_id, started_at, finished_at, duration_ms, output, error
+            FROM job_runs WHERE job_name = $1
+            ORDER BY started_at DESC, id DESC LIMIT 1
+            "#,
+            job_name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(JobInfo {
+            name: job_name.to_string(),
+            schedule: job.schedule.to_string(),
+            paused: state.as_ref().map(|state| state.paused).unwrap_or(job.paused_by_default),
+            next_run_at: state.and_then(|state| state.next_run_at),
+            last_run: last_run.map(JobRun::from),
+        })
+    }
+
+    /// Runs of a job, newest first, with the total count.
+    pub async fn runs(&self, job_name: &str, page_number: usize, page_size: usize) -> Result<(Vec<JobRun>, usize), SchedulerError> {
+        self.job(job_name)?;
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM job_runs WHERE job_name = $1"#, job_name)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query_as!(
+            JobRunRow,
+            r#"
+            SELECT id, job_name, trigger, status, instance_id, started_at, finished_at, duration_ms, output, error
+            FROM job_runs WHERE job_name = $1
+            ORDER BY started_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            job_name,
+            page_size as i64,
+            (page_number.saturating_sub(1) * page_size) as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((rows.into_iter().map(JobRun::from).collect(), total as usize))
+    }
+}
@@ -0,0 +1,8 @@
+pub mod models;
+pub mod service;
+
+pub use models::{
+    ApprovedToken, CurrentBalance, DashboardInfo, MaxMinTradePnl, RejectionItem, TotalPaid,
+    TradeItem, TradePnl, TransactionItem,
+};
+pub use service::{ShillingStatistics, StatisticsService};
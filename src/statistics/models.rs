@@ -0,0 +1,111 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::models::base::{ExitReason, RejectionSource, SolanaTransactionStatus};
+
+/// Headline numbers of the dashboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardInfo {
+    pub total_users: i64,
+    pub positions_opened: i64,
+    pub positions_closed: i64,
+    pub open_positions: i64,
+    pub rejections: i64,
+    /// Realized PnL of all sells, fees included.
+    pub realized_pnl_sol: f64,
+    pub total_paid_sol: f64,
+    /// `None` when the agent wallet could not be read.
+    pub balance_sol: Option<f64>,
+    pub sol_price_usd: Option<f64>,
+}
+
+/// Profit shares sent to shillers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotalPaid {
+    pub payouts_sent: i64,
+    pub total_paid_sol: f64,
+    pub total_paid_usd: Option<f64>,
+}
+
+/// Realized PnL of one sell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradePnl {
+    pub trade_id: i32,
+    pub trade_position_id: i32,
+    pub token_address: String,
+    pub symbol: String,
+    pub tx_id: String,
+    pub pnl_sol: f64,
+    pub pnl_pct: f64,
+    pub pnl_usd: Option<f64>,
+    pub closed_at: NaiveDateTime,
+}
+
+/// Best and worst sell; `None` until something was sold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxMinTradePnl {
+    pub max: Option<TradePnl>,
+    pub min: Option<TradePnl>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionItem {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub purpose: String,
+    pub reference: Option<String>,
+    pub signature: String,
+    pub status: SolanaTransactionStatus,
+    pub attempts: i32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionItem {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub chat_uuid: String,
+    pub wallet: String,
+    pub pool_address: Option<String>,
+    pub source: RejectionSource,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrentBalance {
+    pub wallet: String,
+    pub balance_sol: f64,
+    pub sol_price_usd: Option<f64>,
+    pub balance_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeItem {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub trade_position_id: i32,
+    /// `open` for buys, `closed` for sells.
+    pub trade_type: String,
+    pub token_address: String,
+    pub symbol: String,
+    pub sol_amount: f64,
+    pub token_quantity: f64,
+    pub fee_sol: Option<f64>,
+    pub tx_id: String,
+    pub exit_reason: Option<ExitReason>,
+}
+
+/// A token the agent bought, with the state of its position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovedToken {
+    pub trade_position_id: i32,
+    pub chat_uuid: String,
+    pub token_address: String,
+    pub symbol: String,
+    pub pool_address: String,
+    pub sol_invested: f64,
+    pub token_quantity: f64,
+    pub tx_id: String,
+    pub approved_at: NaiveDateTime,
+    pub is_closed: bool,
+}
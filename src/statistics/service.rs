@@ -0,0 +1,484 @@
+use async_trait::async_trait;
+use chrono_tz::Tz;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use solana_sdk::native_token::lamports_to_sol;
+use sqlx::PgPool;
+use std::future::Future;
+use redis::AsyncCommands;
+use std::str::FromStr;
+use tracing::warn;
+
+use crate::balance_history::{balance_candles, BalanceCandle, BalanceGranularity};
+use crate::core::config::StatisticsSettings;
+use crate::models::base::{ExitReason, RejectionSource, SolanaTransactionStatus};
+use crate::pnl::{compute_pnl, RealizedSale};
+use crate::portfolio::{Portfolio, PortfolioSnapshot};
+use crate::statistics::models::{
+    ApprovedToken, CurrentBalance, DashboardInfo, MaxMinTradePnl, RejectionItem, TotalPaid,
+    TradeItem, TradePnl, TransactionItem,
+};
+use crate::utils::error::{BalanceHistoryError, StatisticsError};
+use crate::utils::price_oracle::PriceOracle;
+use crate::utils::redis::shared_redis_connection;
+use crate::utils::solana_rpc::shared_solana_rpc;
+
+/// Prefix of the Redis keys aggregations are cached under.
+const CACHE_PREFIX: &str = "statistics:shilling";
+
+fn offset(page_number: usize, page_size: usize) -> i64 {
+    (page_number.saturating_sub(1) * page_size) as i64
+}
+
+/// Parses a stored enum value; an unknown one is an error rather than a guess.
+fn parse_stored<T: FromStr>(field: &'static str, value: String) -> Result<T, StatisticsError> {
+    T::from_str(&value).map_err(|_| StatisticsError::UnknownValue(field, value))
+}
+
+fn trade_pnl(sale: &RealizedSale, sol_price_usd: Option<f64>) -> TradePnl {
+    TradePnl {
+        trade_id: sale.trade_id,
+        trade_position_id: sale.trade_position_id,
+        token_address: sale.token_address.clone(),
+        symbol: sale.symbol.clone(),
+        tx_id: sale.tx_id.clone(),
+        pnl_sol: sale.pnl_sol,
+        pnl_pct: sale.pnl_pct,
+        pnl_usd: sol_price_usd.map(|price| sale.pnl_sol * price),
+        closed_at: sale.closed_at,
+    }
+}
+
+/// Data behind the shilling dashboard, one method per `ShillingStatisticsAction`.
+///
+/// List methods return one page, newest first, with the total count.
+#[async_trait]
+pub trait ShillingStatistics: Send + Sync {
+    async fn dashboard_info(&self) -> Result<DashboardInfo, StatisticsError>;
+
+    async fn total_paid(&self) -> Result<TotalPaid, StatisticsError>;
+
+    async fn max_min_trade_pnl(&self) -> Result<MaxMinTradePnl, StatisticsError>;
+
+    async fn current_balance(&self) -> Result<CurrentBalance, StatisticsError>;
+
+    /// Balance candles bucketed in `timezone`, or the configured one when `None`.
+    async fn agent_balance(&self, granularity: BalanceGranularity, timezone: Option<&str>) -> Result<Vec<BalanceCandle>, StatisticsError>;
+
+    async fn assets(&self) -> Result<PortfolioSnapshot, StatisticsError>;
+
+    async fn transactions(&self, page_number: usize, page_size: usize) -> Result<(Vec<TransactionItem>, usize), StatisticsError>;
+
+    async fn rejections(&self, page_number: usize, page_size: usize) -> Result<(Vec<RejectionItem>, usize), StatisticsError>;
+
+    async fn trades(&self, page_number: usize, page_size: usize) -> Result<(Vec<TradeItem>, usize), StatisticsError>;
+
+    async fn tokens_approved(&self, page_number: usize, page_size: usize) -> Result<(Vec<ApprovedToken>, usize), StatisticsError>;
+}
+
+/// `ShillingStatistics` read from the database and the agent wallet.
+///
+/// Aggregations are cached in Redis for `STATISTICS_CACHE_TTL_SECS` over the shared
+/// connection; lists are paginated and always read from the database.
+pub struct StatisticsService {
+    pool: PgPool,
+    oracle: PriceOracle,
+    settings: StatisticsSettings,
+}
+
+impl StatisticsService {
+    pub fn new_statistics_service(pool: PgPool, settings: StatisticsSettings) -> Self {
+        let oracle = PriceOracle::new_price_oracle(shared_solana_rpc());
+        Self { pool, oracle, settings }
+    }
+
+    pub fn from_settings(pool: PgPool) -> Self {
+        Self::new_statistics_service(pool, StatisticsSettings::new_statistics())
+    }
+
+    /// Serves `key` from Redis, computing and storing it on a miss. Redis failures only
+    /// cost the cache.
+    async fn cached<T, F>(&self, key: &str, compute: F) -> Result<T, StatisticsError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T, StatisticsError>>,
+    {
+        let key = format!("{}:{}", CACHE_PREFIX, key);
+        let mut redis = match shared_redis_connection().await {
+            Ok(redis) => Some(redis),
+            Err(e) => {
+                warn!("Failed to connect to Redis, {} is not cached: {}", key, e);
+                None
+            }
+        };
+        if let Some(redis) = redis.as_mut() {
+            match redis.get::<_, Option<String>>(&key).await {
+                Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                    Ok(value) => return Ok(value),
+                    Err(e) => warn!("Ignoring unreadable cache of {}: {}", key, e),
+                },
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read cache of {}: {}", key, e),
+            }
+        }
+
+        let value = compute.await?;
+        if let Some(redis) = redis.as_mut() {
+            let stored = match serde_json::to_string(&value) {
+                Ok(json) => redis.set_ex::<_, _, ()>(&key, json, self.settings.cache_ttl_secs).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = stored {
+                warn!("Failed to cache {}: {}", key, e);
+            }
+        }
+        Ok(value)
+    }
+
+    async fn sol_price_usd(&self) -> Option<f64> {
+        match self.oracle.sol_usd().await {
+            Ok(price) => Some(price.price_usd),
+            Err(e) => {
+                warn!("Failed to price SOL: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn current_balance_uncached(&self) -> Result<CurrentBalance, StatisticsError> {
+        let portfolio = Portfolio::for_agent(self.pool.clone())
+            .await
+            .map_err(|e| StatisticsError::Wallet(e.to_string()))?;
+        let lamports = portfolio.sol_balance_lamports().await.map_err(|e| StatisticsError::Wallet(e.to_string()))?;
+        let balance_sol = lamports_to_sol(lamports);
+        let sol_price_usd = self.sol_price_usd().await;
+        Ok(CurrentBalance {
+            wallet: portfolio.wallet().to_string(),
+            balance_sol,
+            sol_price_usd,
+            balance_usd: sol_price_usd.map(|price| balance_sol * price),
+        })
+    }
+}
+
+#[async_trait]
+impl ShillingStatistics for StatisticsService {
+    async fn dashboard_info(&self) -> Result<DashboardInfo, StatisticsError> {
+        self.cached("dashboard_info", async {
+            let counts = sqlx::query!(
+                r#"
+                SELECT
+                    (SELECT COUNT(*) FROM users) AS "total_users!",
+                    (SELECT COUNT(*) FROM positions WHERE status IN ('open', 'closing', 'closed')) AS "positions_opened!",
+                    (SELECT COUNT(*) FROM positions WHERE status = 'closed') AS "positions_closed!",
+                    (SELECT COUNT(*) FROM shilling_rejections) AS "rejections!",
+                    (SELECT COALESCE(SUM(amount_lamports), 0)::BIGINT FROM payouts WHERE status = 'sent') AS "paid_lamports!"
+                "#
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            let realized_pnl_sol = compute_pnl(&self.pool, None).await?.realized_pnl_sol;
+            let balance = match self.current_balance_uncached().await {
+                Ok(balance) => Some(balance),
+                Err(e) => {
+                    warn!("Failed to read agent balance: {}", e);
+                    None
+                }
+            };
+
+            Ok(DashboardInfo {
+                total_users: counts.total_users,
+                positions_opened: counts.positions_opened,
+                positions_closed: counts.positions_closed,
+                open_positions: counts.positions_opened - counts.positions_closed,
+                rejections: counts.rejections,
+                realized_pnl_sol,
+                total_paid_sol: lamports_to_sol(counts.paid_lamports.max(0) as u64),
+                balance_sol: balance.as_ref().map(|balance| balance.balance_sol),
+                sol_price_usd: balance.and_then(|balance| balance.sol_price_usd),
+            })
+        })
+        .await
+    }
+
+    async fn total_paid(&self) -> Result<TotalPaid, StatisticsError> {
+        self.cached("total_paid", async {
+            let paid = sqlx::query!(
+                r#"
+                SELECT COUNT(*) AS "payouts_sent!", COALESCE(SUM(amount_lamports), 0)::BIGINT AS "paid_lamports!"
+                FROM payouts WHERE status = 'sent'
+                "#
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            let total_paid_sol = lamports_to_sol(paid.paid_lamports.max(0) as u64);
+            Ok(TotalPaid {
+                payouts_sent: paid.payouts_sent,
+                total_paid_sol,
+                total_paid_usd: self.sol_price_usd().await.map(|price| total_paid_sol * price),
+            })
+        })
+        .await
+    }
+
+    async fn max_min_trade_pnl(&self) -> Result<MaxMinTradePnl, StatisticsError> {
+        self.cached("max_min_trade_pnl", async {
+            let report = compute_pnl(&self.pool, None).await?;
+            let sol_price_usd = if report.sales_count == 0 { None } else { self.sol_price_usd().await };
+            Ok(MaxMinTradePnl {
+                max: report.best_sale.as_ref().map(|sale| trade_pnl(sale, sol_price_usd)),
+                min: report.worst_sale.as_ref().map(|sale| trade_pnl(sale, sol_price_usd)),
+            })
+        })
+        .await
+    }
+
+    async fn current_balance(&self) -> Result<CurrentBalance, StatisticsError> {
+        self.cached("current_balance", self.current_balance_uncached()).await
+    }
+
+    /// The latest `STATISTICS_BALANCE_HISTORY_POINTS` balance candles, oldest first, bucketed
+    /// in `timezone` or `STATISTICS_TIMEZONE`.
+    async fn agent_balance(&self, granularity: BalanceGranularity, timezone: Option<&str>) -> Result<Vec<BalanceCandle>, StatisticsError> {
+        let timezone = timezone.unwrap_or(&self.settings.timezone);
+        // Unknown names are rejected before they can each add a cache entry.
+        let timezone = timezone
+            .parse::<Tz>()
+            .map_err(|_| BalanceHistoryError::InvalidTimezone(timezone.to_string()))?;
+        let key = format!("agent_balance:{}:{}", granularity.as_date_trunc(), timezone.name());
+        self.cached(&key, async {
+            Ok(balance_candles(&self.pool, granularity, timezone.name(), self.settings.balance_history_points).await?)
+        })
+        .await
+    }
+
+    /// What the agent wallet holds, valued.
+    async fn assets(&self) -> Result<PortfolioSnapshot, StatisticsError> {
+        self.cached("assets", async {
+            let portfolio = Portfolio::for_agent(self.pool.clone())
+                .await
+                .map_err(|e| StatisticsError::Wallet(e.to_string()))?;
+            portfolio.snapshot().await.map_err(|e| StatisticsError::Wallet(e.to_string()))
+        })
+        .await
+    }
+
+    /// Recorded Solana transactions, newest first, with the total count.
+    async fn transactions(&self, page_number: usize, page_size: usize) -> Result<(Vec<TransactionItem>, usize), StatisticsError> {
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM solana_transactions"#)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, created_at, purpose, reference, signature, status, attempts, error
+            FROM solana_transactions
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page_size as i64,
+            offset(page_number, page_size)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let transactions = rows
+            .into_iter()
+            .map(|row| Ok(TransactionItem {
+                id: row.id,
+                created_at: row.created_at,
+                purpose: row.purpose,
+                reference: row.reference,
+                signature: row.signature,
+                status: parse_stored::<SolanaTransactionStatus>("transaction status", row.status)?,
+                attempts: row.attempts,
+                error: row.error,
+            }))
+            .collect::<Result<_, StatisticsError>>()?;
+        Ok((transactions, total as usize))
+    }
+
+    /// Shilled tokens the agent turned down, newest first, with the total count.
+    async fn rejections(&self, page_number: usize, page_size: usize) -> Result<(Vec<RejectionItem>, usize), StatisticsError> {
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM shilling_rejections"#)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, created_at, chat_uuid, wallet, pool_address, source, reason
+            FROM shilling_rejections
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page_size as i64,
+            offset(page_number, page_size)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let rejections = rows
+            .into_iter()
+            .map(|row| Ok(RejectionItem {
+                id: row.id,
+                created_at: row.created_at,
+                chat_uuid: row.chat_uuid,
+                wallet: row.wallet,
+                pool_address: row.pool_address,
+                source: parse_stored::<RejectionSource>("rejection source", row.source)?,
+                reason: row.reason,
+            }))
+            .collect::<Result<_, StatisticsError>>()?;
+        Ok((rejections, total as usize))
+    }
+
+    /// Buys and sells, newest first, with the total count.
+    async fn trades(&self, page_number: usize, page_size: usize) -> Result<(Vec<TradeItem>, usize), StatisticsError> {
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM trades"#)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT tr.id, tr.created_at, tr.trade_position_id, tr.trade_type, t.address, t.symbol,
+                   tr.base_token_quantity, tr.quote_token_quantity, tr.fee_rate, tr.tx_id, tr.exit_reason
+            FROM trades tr
+            JOIN tokens t ON t.id = tr.token_id
+            ORDER BY tr.created_at DESC, tr.id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page_size as i64,
+            offset(page_number, page_size)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let trades = rows
+            .into_iter()
+            .map(|row| Ok(TradeItem {
+                id: row.id,
+                created_at: row.created_at,
+                trade_position_id: row.trade_position_id,
+                trade_type: row.trade_type,
+                token_address: row.address,
+                symbol: row.symbol,
+                sol_amount: row.base_token_quantity,
+                token_quantity: row.quote_token_quantity,
+                fee_sol: row.fee_rate,
+                tx_id: row.tx_id,
+                exit_reason: row.exit_reason.map(|reason| parse_stored::<ExitReason>("exit reason", reason)).transpose()?,
+            }))
+            .collect::<Result<_, StatisticsError>>()?;
+        Ok((trades, total as usize))
+    }
+
+    /// Tokens bought from approved shills, newest first, with the total count.
+    async fn tokens_approved(&self, page_number: usize, page_size: usize) -> Result<(Vec<ApprovedToken>, usize), StatisticsError> {
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM trades WHERE trade_type = 'open'"#)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT tr.trade_position_id, tr.chat_uuid, t.address, t.symbol, t.pool_address,
+                   tr.base_token_quantity, tr.quote_token_quantity, tr.tx_id, tr.created_at,
+                   p.status = 'closed' AS "is_closed!"
+            FROM trades tr
+            JOIN tokens t ON t.id = tr.token_id
+            JOIN positions p ON p.id = tr.trade_position_id
+            WHERE tr.trade_type = 'open'
+            ORDER BY tr.created_at DESC, tr.id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            page_size as i64,
+            offset(page_number, page_size)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tokens = rows
+            .into_iter()
+            .map(|row| ApprovedToken {
+                trade_position_id: row.trade_position_id,
+                chat_uuid: row.chat_uuid,
+                token_address: row.address,
+                symbol: row.symbol,
+                pool_address: row.pool_address,
+                sol_invested: row.base_token_quantity,
+                token_quantity: row.quote_token_quantity,
+                tx_id: row.tx_id,
+                approved_at: row.created_at,
+                is_closed: row.is_closed,
+            })
+            .collect();
+        Ok((tokens, total as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn service() -> StatisticsService {
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/statistics_tests").unwrap();
+        let settings = StatisticsSettings { cache_ttl_secs: 60, balance_history_points: 10, timezone: "UTC".to_string() };
+        StatisticsService::new_statistics_service(pool, settings)
+    }
+
+    async fn counted(computed: &AtomicUsize, value: i64) -> Result<i64, StatisticsError> {
+        computed.fetch_add(1, Ordering::SeqCst);
+        Ok(value)
+    }
+
+    #[test]
+    fn rejects_an_unknown_stored_value() {
+        assert_eq!(parse_stored::<RejectionSource>("rejection source", "model".to_string()).unwrap(), RejectionSource::Model);
+        match parse_stored::<SolanaTransactionStatus>("transaction status", "lost".to_string()) {
+            Err(StatisticsError::UnknownValue(field, value)) => assert_eq!((field, value.as_str()), ("transaction status", "lost")),
+            other => panic!("expected an unknown value, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_an_unknown_timezone_before_the_cache() {
+        match service().agent_balance(BalanceGranularity::Day, Some("Mars/Olympus")).await {
+            Err(StatisticsError::BalanceHistory(BalanceHistoryError::InvalidTimezone(timezone))) => assert_eq!(timezone, "Mars/Olympus"),
+            other => panic!("expected an invalid timezone, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    /// One test: the shared Redis connection is bound to the runtime of the test that opens it.
+    #[tokio::test]
+    #[ignore = "requires a Redis server at REDIS_URL"]
+    async fn serves_hits_and_computes_misses() {
+        let service = service();
+        let mut redis = shared_redis_connection().await.unwrap();
+        let run = Uuid::new_v4();
+        let (miss, preset, unreadable) = (format!("miss-{}", run), format!("preset-{}", run), format!("unreadable-{}", run));
+        let computed = AtomicUsize::new(0);
+
+        // A miss is computed and stored; the next call is served from the cache.
+        assert_eq!(service.cached(&miss, counted(&computed, 7)).await.unwrap(), 7);
+        assert_eq!(service.cached(&miss, counted(&computed, 8)).await.unwrap(), 7);
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+
+        let preset_key = format!("{}:{}", CACHE_PREFIX, preset);
+        redis.set::<_, _, ()>(&preset_key, "42").await.unwrap();
+        assert_eq!(service.cached(&preset, counted(&computed, 9)).await.unwrap(), 42);
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+
+        // An unreadable value is recomputed and replaced.
+        let unreadable_key = format!("{}:{}", CACHE_PREFIX, unreadable);
+        redis.set::<_, _, ()>(&unreadable_key, "not json").await.unwrap();
+        assert_eq!(service.cached(&unreadable, counted(&computed, 5)).await.unwrap(), 5);
+        assert_eq!(computed.load(Ordering::SeqCst), 2);
+        assert_eq!(redis.get::<_, Option<String>>(&unreadable_key).await.unwrap().as_deref(), Some("5"));
+
+        let ttl: i64 = redis.ttl(format!("{}:{}", CACHE_PREFIX, miss)).await.unwrap();
+        assert!(ttl > 0 && ttl <= 60);
+
+        redis
+            .del::<_, ()>(vec![format!("{}:{}", CACHE_PREFIX, miss), preset_key, unreadable_key])
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod models;
+pub mod service;
+
+pub use models::{RuleKind, RuleOutcome, RuleReport, RuleResult, TradeAnalysis};
+pub use service::evaluate_trade_rules;
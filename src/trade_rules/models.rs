@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+/// Market data and portfolio state for a token the agent is asked to buy.
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeAnalysis {
+    pub liquidity: f64,
+    pub volume_24h: f64,
+    pub market_cap: f64,
+    /// Pair creation time in milliseconds since the Unix epoch.
+    pub pair_created_at: i64,
+    pub fdv: f64,
+    pub transactions_24h: (u64, u64),
+    pub pool_address: String,
+    /// `None` when the holders could not be read; the rule is then skipped.
+    pub holders: Option<u64>,
+    /// Share of supply held by the developers, in percent; `None` skips the rule.
+    pub dev_allocation_pct: Option<f64>,
+    /// Whether the mint can freeze holder accounts; `None` skips the rule.
+    pub has_freeze_authority: Option<bool>,
+    pub is_token_in_portfolio: bool,
+    pub traded_within_cooldown: bool,
+    pub has_sufficient_balance: bool,
+}
+
+/// A single pre-trade check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RuleKind {
+    Fdv,
+    Age,
+    Transactions24h,
+    Holders,
+    DevAllocation,
+    FreezeAuthority,
+    NotInPortfolio,
+    Cooldown,
+    Balance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RuleOutcome {
+    Pass,
+    Fail,
+    /// The input needed by the rule is not available; fails the report when the rule is mandatory.
+    Skipped,
+}
+
+/// Outcome of one rule together with the observed value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleResult {
+    pub rule: RuleKind,
+    pub outcome: RuleOutcome,
+    pub mandatory: bool,
+    pub message: String,
+}
+
+/// Per-rule pass/fail report for a trade.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleReport {
+    pub results: Vec<RuleResult>,
+    /// Names in `RULE_MANDATORY` that match no rule; any of them fails the report.
+    #[serde(default)]
+    pub unknown_mandatory: Vec<String>,
+}
+
+impl RuleReport {
+    /// True when every mandatory rule passed and every mandatory name is known.
+    /// A mandatory rule that could not be evaluated counts as failed.
+    pub fn passed(&self) -> bool {
+        self.unknown_mandatory.is_empty() && self.failed_mandatory().is_empty()
+    }
+
+    pub fn failed_mandatory(&self) -> Vec<&RuleResult> {
+        self.results
+            .iter()
+            .filter(|r| r.mandatory && r.outcome != RuleOutcome::Pass)
+            .collect()
+    }
+
+    /// One line per rule, used in the analysis sent to the model and in logs.
+    pub fn summary(&self) -> String {
+        self.results
+            .iter()
+            .map(|r| {
+                format!(
+                    "- {}: {}{} ({})",
+                    r.rule,
+                    r.outcome,
+                    if r.mandatory { ", mandatory" } else { "" },
+                    r.message
+                )
+            })
+            .chain(self.unknown_mandatory.iter().map(|name| format!("- {}: unknown mandatory rule", name)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
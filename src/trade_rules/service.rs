@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+use crate::core::config::TradeRulesSettings;
+use crate::trade_rules::models::{RuleKind, RuleOutcome, RuleReport, RuleResult, TradeAnalysis};
+
+/// Evaluates the confidential pre-trade criteria against the analysis.
+///
+/// This runs before the model is asked for a decision, and again when
+/// `approveShilling` is called, so a failed mandatory rule blocks the buy
+/// regardless of what the model decided.
+pub fn evaluate_trade_rules(analysis: &TradeAnalysis, settings: &TradeRulesSettings, now: DateTime<Utc>) -> RuleReport {
+    let (buys, sells) = analysis.transactions_24h;
+    let age_hours = (now.timestamp_millis() - analysis.pair_created_at) as f64 / 3_600_000.0;
+
+    let checks = vec![
+        (
+            RuleKind::Fdv,
+            Some(analysis.fdv > settings.min_fdv_usd),
+            format!("FDV ${:.0}, required > ${:.0}", analysis.fdv, settings.min_fdv_usd),
+        ),
+        (
+            RuleKind::Age,
+            // An unknown creation time must not make a brand new pair look old enough.
+            Some(analysis.pair_created_at > 0 && age_hours > settings.min_age_hours),
+            if analysis.pair_created_at > 0 {
+                format!("age {:.1}h, required > {:.1}h", age_hours, settings.min_age_hours)
+            } else {
+                "pair creation time unknown".to_string()
+            },
+        ),
+        (
+            RuleKind::Transactions24h,
+            Some(buys + sells > settings.min_transactions_24h),
+            format!("{} txns in 24h, required > {}", buys + sells, settings.min_transactions_24h),
+        ),
+        (
+            RuleKind::Holders,
+            analysis.holders.map(|holders| holders > settings.min_holders),
+            match analysis.holders {
+                Some(holders) => format!("{} holders, required > {}", holders, settings.min_holders),
+                None => "holder count unavailable".to_string(),
+            },
+        ),
+        (
+            RuleKind::DevAllocation,
+            analysis.dev_allocation_pct.map(|pct| pct < settings.max_dev_allocation_pct),
+            match analysis.dev_allocation_pct {
+                Some(pct) => format!("dev allocation {:.2}%, required < {:.2}%", pct, settings.max_dev_allocation_pct),
+                None => "dev allocation unavailable".to_string(),
+            },
+        ),
+        (
+            RuleKind::FreezeAuthority,
+            analysis.has_freeze_authority.map(|has_freeze_authority| !has_freeze_authority),
+            match analysis.has_freeze_authority {
+                Some(true) => "mint can freeze holder accounts",
+                Some(false) => "no freeze authority",
+                None => "freeze authority unavailable",
+            }
+            .to_string(),
+        ),
+        (
+            RuleKind::NotInPortfolio,
+            Some(!analysis.is_token_in_portfolio),
+            if analysis.is_token_in_portfolio { "token already in portfolio" } else { "token not in portfolio" }.to_string(),
+        ),
+        (
+            RuleKind::Cooldown,
+            Some(!analysis.traded_within_cooldown),
+            format!(
+                "{} in the last {} minutes",
+                if analysis.traded_within_cooldown { "traded" } else { "no trades" },
+                settings.trade_cooldown_minutes
+            ),
+        ),
+        (
+            RuleKind::Balance,
+            Some(analysis.has_sufficient_balance),
+            if analysis.has_sufficient_balance { "balance sufficient" } else { "insufficient balance" }.to_string(),
+        ),
+    ];
+
+    let unknown_mandatory = settings
+        .mandatory_rules
+        .iter()
+        .filter(|name| RuleKind::from_str(name).is_err())
+        .cloned()
+        .collect();
+
+    let results = checks
+        .into_iter()
+        .map(|(rule, passed, message)| RuleResult {
+            rule,
+            outcome: match passed {
+                Some(true) => RuleOutcome::Pass,
+                Some(false) => RuleOutcome::Fail,
+                None => RuleOutcome::Skipped,
+            },
+            mandatory: settings.mandatory_rules.iter().any(|m| m == &rule.to_string()),
+            message,
+        })
+        .collect();
+
+    RuleReport { results, unknown_mandatory }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn settings(mandatory: &[&str]) -> TradeRulesSettings {
+        TradeRulesSettings {
+            min_fdv_usd: 200_000.0,
+            min_age_hours: 48.0,
+            min_transactions_24h: 200,
+            min_holders: 200,
+            max_dev_allocation_pct: 5.0,
+            trade_cooldown_minutes: 60,
+            mandatory_rules: mandatory.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    fn healthy_analysis() -> TradeAnalysis {
+        TradeAnalysis {
+            liquidity: 150_000.0,
+            volume_24h: 500_000.0,
+            market_cap: 900_000.0,
+            pair_created_at: (now() - chrono::Duration::days(7)).timestamp_millis(),
+            fdv: 1_000_000.0,
+            transactions_24h: (400, 300),
+            pool_address: "pool".to_string(),
+            holders: Some(1_500),
+            dev_allocation_pct: Some(2.0),
+            has_freeze_authority: Some(false),
+            is_token_in_portfolio: false,
+            traded_within_cooldown: false,
+            has_sufficient_balance: true,
+        }
+    }
+
+    fn outcome(report: &RuleReport, rule: RuleKind) -> RuleOutcome {
+        report.results.iter().find(|r| r.rule == rule).unwrap().outcome
+    }
+
+    #[test]
+    fn healthy_token_passes_every_rule() {
+        let report = evaluate_trade_rules(&healthy_analysis(), &settings(&["fdv", "age", "holders", "dev_allocation"]), now());
+        assert!(report.passed());
+        assert!(report.results.iter().all(|r| r.outcome == RuleOutcome::Pass));
+    }
+
+    #[test]
+    fn failing_mandatory_rule_blocks_the_trade() {
+        let analysis = TradeAnalysis { fdv: 50_000.0, ..healthy_analysis() };
+        let report = evaluate_trade_rules(&analysis, &settings(&["fdv"]), now());
+        assert!(!report.passed());
+        assert_eq!(report.failed_mandatory()[0].rule, RuleKind::Fdv);
+    }
+
+    #[test]
+    fn failing_optional_rule_does_not_block() {
+        let analysis = TradeAnalysis { fdv: 50_000.0, ..healthy_analysis() };
+        let report = evaluate_trade_rules(&analysis, &settings(&["age"]), now());
+        assert_eq!(outcome(&report, RuleKind::Fdv), RuleOutcome::Fail);
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn missing_data_on_a_mandatory_rule_fails_closed() {
+        let analysis = TradeAnalysis { holders: None, dev_allocation_pct: None, ..healthy_analysis() };
+
+        let report = evaluate_trade_rules(&analysis, &settings(&["holders"]), now());
+        assert_eq!(outcome(&report, RuleKind::Holders), RuleOutcome::Skipped);
+        assert!(!report.passed());
+
+        let report = evaluate_trade_rules(&analysis, &settings(&["fdv"]), now());
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn unknown_creation_time_fails_the_age_rule() {
+        let analysis = TradeAnalysis { pair_created_at: 0, ..healthy_analysis() };
+        let report = evaluate_trade_rules(&analysis, &settings(&["age"]), now());
+        assert_eq!(outcome(&report, RuleKind::Age), RuleOutcome::Fail);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn young_pair_fails_the_age_rule() {
+        let analysis = TradeAnalysis { pair_created_at: (now() - chrono::Duration::hours(3)).timestamp_millis(), ..healthy_analysis() };
+        let report = evaluate_trade_rules(&analysis, &settings(&["age"]), now());
+        assert_eq!(outcome(&report, RuleKind::Age), RuleOutcome::Fail);
+    }
+
+    #[test]
+    fn unknown_mandatory_rule_name_fails_the_report() {
+        let report = evaluate_trade_rules(&healthy_analysis(), &settings(&["fdv", "liquidty"]), now());
+        assert_eq!(report.unknown_mandatory, vec!["liquidty".to_string()]);
+        assert!(!report.passed());
+        assert!(report.summary().contains("liquidty: unknown mandatory rule"));
+    }
+
+    #[test]
+    fn freeze_authority_only_blocks_when_mandatory() {
+        let analysis = TradeAnalysis { has_freeze_authority: Some(true), ..healthy_analysis() };
+
+        let report = evaluate_trade_rules(&analysis, &settings(&["fdv"]), now());
+        assert_eq!(outcome(&report, RuleKind::FreezeAuthority), RuleOutcome::Fail);
+        assert!(report.passed());
+
+        let report = evaluate_trade_rules(&analysis, &settings(&["freeze_authority"]), now());
+        assert!(!report.passed());
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use reqwest;
 use serde::Deserialize;
 use std::error::Error;
@@ -7,8 +8,24 @@ struct BinancePrice {
     price: String,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceTicker24h {
+    last_price: String,
+    /// Milliseconds since the Unix epoch.
+    close_time: i64,
+}
+
 pub async fn fetch_solana_price_binance() -> Result<f64, Box<dyn Error>> {
     let url = "https://api.binance.com/api/v3/ticker/price?symbol=SOLUSDT";
     let response: BinancePrice = reqwest::get(url).await?.json().await?;
     Ok(response.price.parse::<f64>()?)
 }
+
+/// Last traded price of a Binance symbol, e.g. `SOLUSDT`, and when the ticker was last updated.
+pub async fn fetch_last_price_binance(symbol: &str) -> Result<(f64, DateTime<Utc>), Box<dyn Error + Send + Sync>> {
+    let url = format!("https://api.binance.com/api/v3/ticker/24hr?symbol={}", symbol);
+    let response: BinanceTicker24h = reqwest::get(&url).await?.error_for_status()?.json().await?;
+    let updated_at = DateTime::from_timestamp_millis(response.close_time).ok_or("Invalid Binance ticker time")?;
+    Ok((response.last_price.parse::<f64>()?, updated_at))
+}
@@ -1,22 +1,282 @@
-use reqwest;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::{HeaderMap, AGE, DATE};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use std::error::Error;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 
-#[derive(Deserialize, Debug)]
-pub struct DexScreenerResponse {
-    success: bool,
-    data: Vec<DexScreenerToken>,
+use crate::core::config::DexScreenerSettings;
+use crate::utils::error::DexScreenerError;
+use crate::utils::raydium::WSOL_ADDRESS;
+
+const SOLANA_CHAIN_ID: &str = "solana";
+const RAYDIUM_DEX_ID: &str = "raydium";
+/// Pool labels the native swap builder can trade: AMM v4 (`v4` or no label) and CPMM.
+const SWAPPABLE_POOL_LABELS: [&str; 2] = ["v4", "CPMM"];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DexScreenerToken {
+    pub address: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TxnCount {
+    #[serde(default)]
+    pub buys: u64,
+    #[serde(default)]
+    pub sells: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Txns {
+    #[serde(default)]
+    pub m5: TxnCount,
+    #[serde(default)]
+    pub h1: TxnCount,
+    #[serde(default)]
+    pub h6: TxnCount,
+    #[serde(default)]
+    pub h24: TxnCount,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Volume {
+    #[serde(default)]
+    pub m5: f64,
+    #[serde(default)]
+    pub h1: f64,
+    #[serde(default)]
+    pub h6: f64,
+    #[serde(default)]
+    pub h24: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PriceChange {
+    pub m5: Option<f64>,
+    pub h1: Option<f64>,
+    pub h6: Option<f64>,
+    pub h24: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Liquidity {
+    #[serde(default)]
+    pub usd: f64,
+    #[serde(default)]
+    pub base: f64,
+    #[serde(default)]
+    pub quote: f64,
+}
+
+/// A trading pair as returned by both `/tokens/v1/{chain}/{addresses}` and `/latest/dex/pairs/{chain}/{pair}`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DexScreenerPair {
+    pub chain_id: String,
+    pub dex_id: String,
+    pub url: Option<String>,
+    pub pair_address: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub base_token: DexScreenerToken,
+    pub quote_token: DexScreenerToken,
+    pub price_native: Option<String>,
+    pub price_usd: Option<String>,
+    #[serde(default)]
+    pub txns: Txns,
+    #[serde(default)]
+    pub volume: Volume,
+    #[serde(default)]
+    pub price_change: PriceChange,
+    #[serde(default)]
+    pub liquidity: Liquidity,
+    #[serde(default)]
+    pub fdv: f64,
+    #[serde(default)]
+    pub market_cap: f64,
+    /// Milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub pair_created_at: i64,
+}
+
+impl DexScreenerPair {
+    /// Price of the base token in the quote token.
+    pub fn price_native(&self) -> Option<f64> {
+        self.price_native.as_deref().and_then(|p| p.parse().ok())
+    }
+
+    pub fn price_usd(&self) -> Option<f64> {
+        self.price_usd.as_deref().and_then(|p| p.parse().ok())
+    }
+
+    pub fn is_wsol_quoted(&self) -> bool {
+        self.quote_token.address == WSOL_ADDRESS || self.base_token.address == WSOL_ADDRESS
+    }
+
+    /// False for concentrated liquidity (`CLMM`) and other Raydium pools the swap builder cannot trade.
+    pub fn is_swappable_pool_type(&self) -> bool {
+        self.labels
+            .iter()
+            .all(|label| SWAPPABLE_POOL_LABELS.iter().any(|swappable| swappable.eq_ignore_ascii_case(label)))
+    }
 }
 
 #[derive(Deserialize, Debug)]
-pub struct DexScreenerToken {
-    symbol: String,
-    address: String,
-    price_native: String,
+#[serde(rename_all = "camelCase")]
+pub struct DexScreenerPairsResponse {
+    pub schema_version: Option<String>,
+    pub pairs: Option<Vec<DexScreenerPair>>,
+}
+
+/// Picks the swappable Raydium pair quoted in WSOL with the deepest USD liquidity.
+pub fn select_best_raydium_pair(pairs: &[DexScreenerPair]) -> Option<&DexScreenerPair> {
+    pairs
+        .iter()
+        .filter(|p| p.chain_id == SOLANA_CHAIN_ID && p.dex_id == RAYDIUM_DEX_ID && p.is_wsol_quoted() && p.is_swappable_pool_type())
+        .max_by(|a, b| a.liquidity.usd.total_cmp(&b.liquidity.usd))
 }
 
-pub async fn fetch_dexscreener_data(token_address: &str) -> Result<DexScreenerResponse, Box<dyn Error>> {
-    let url = format!("https://api.dexscreener.com/tokens/v1/solana/{}", token_address);
-    let response: DexScreenerResponse = reqwest::get(&url).await?.json().await?;
-    Ok(response)
+pub struct DexScreenerClient {
+    client: Client,
+    base_url: String,
+}
+
+impl DexScreenerClient {
+    pub fn new_dexscreener_client() -> Self {
+        Self::with_base_url(&DexScreenerSettings::new_dexscreener().base_url)
+    }
+
+    /// Points the client at another host, e.g. a local stub.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Body of the response together with when DexScreener produced it.
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<(T, DateTime<Utc>), DexScreenerError> {
+        let response = self.client.get(format!("{}{}", self.base_url, path)).send().await?.error_for_status()?;
+        let produced_at = response_time(response.headers()).ok_or(DexScreenerError::MissingDate)?;
+        let body = response.text().await?;
+        Ok((serde_json::from_str(&body)?, produced_at))
+    }
+
+    /// All pairs of a Solana token.
+    pub async fn get_token_pairs(&self, token_address: &str) -> Result<Vec<DexScreenerPair>, DexScreenerError> {
+        Ok(self.get_token_pairs_with_time(token_address).await?.0)
+    }
+
+    /// All pairs of a Solana token, with when the data was produced.
+    pub async fn get_token_pairs_with_time(
+        &self,
+        token_address: &str,
+    ) -> Result<(Vec<DexScreenerPair>, DateTime<Utc>), DexScreenerError> {
+        self.get(&format!("/tokens/v1/{}/{}", SOLANA_CHAIN_ID, token_address)).await
+    }
+
+    /// A single Solana pair by its pool address.
+    pub async fn get_pair(&self, pair_address: &str) -> Result<Option<DexScreenerPair>, DexScreenerError> {
+        Ok(self.get_pair_with_time(pair_address).await?.0)
+    }
+
+    /// A single Solana pair by its pool address, with when the data was produced.
+    pub async fn get_pair_with_time(
+        &self,
+        pair_address: &str,
+    ) -> Result<(Option<DexScreenerPair>, DateTime<Utc>), DexScreenerError> {
+        let (response, produced_at): (DexScreenerPairsResponse, _) =
+            self.get(&format!("/latest/dex/pairs/{}/{}", SOLANA_CHAIN_ID, pair_address)).await?;
+        Ok((response.pairs.unwrap_or_default().into_iter().next(), produced_at))
+    }
+
+    /// Resolves a token mint or pool address to its deepest Raydium/WSOL pair.
+    pub async fn fetch_best_pair(&self, token_or_pair_address: &str) -> Result<DexScreenerPair, DexScreenerError> {
+        if Pubkey::from_str(token_or_pair_address).is_err() {
+            return Err(DexScreenerError::NonSolanaToken(token_or_pair_address.to_string()));
+        }
+
+        let mut pairs = self.get_token_pairs(token_or_pair_address).await?;
+        if pairs.is_empty() {
+            // Not a mint known to DexScreener, maybe a pool address.
+            let pair = self.get_pair(token_or_pair_address).await?;
+            match pair {
+                Some(pair) => pairs.push(pair),
+                None => return Err(DexScreenerError::PairNotFound(token_or_pair_address.to_string())),
+            }
+        }
+
+        select_best_raydium_pair(&pairs)
+            .cloned()
+            .ok_or_else(|| DexScreenerError::NoPairs(token_or_pair_address.to_string()))
+    }
+}
+
+/// When a response was produced: its `Date`, less the `Age` it spent in a cache.
+fn response_time(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let date = DateTime::parse_from_rfc2822(headers.get(DATE)?.to_str().ok()?).ok()?.with_timezone(&Utc);
+    let age = headers.get(AGE).and_then(|age| age.to_str().ok()?.parse().ok()).unwrap_or(0);
+    Some(date - Duration::seconds(age))
+}
+
+pub async fn fetch_dexscreener_data(token_address: &str) -> Result<DexScreenerPair, DexScreenerError> {
+    DexScreenerClient::new_dexscreener_client().fetch_best_pair(token_address).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(pair_address: &str, dex_id: &str, labels: &[&str], quote: &str, liquidity_usd: f64) -> DexScreenerPair {
+        serde_json::from_value(serde_json::json!({
+            "chainId": "solana",
+            "dexId": dex_id,
+            "pairAddress": pair_address,
+            "labels": labels,
+            "baseToken": {"address": "Mint111", "name": "Meme", "symbol": "MEME"},
+            "quoteToken": {"address": quote, "name": "Wrapped SOL", "symbol": "SOL"},
+            "liquidity": {"usd": liquidity_usd},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn picks_the_deepest_swappable_wsol_pair() {
+        let pairs = vec![
+            pair("amm", "raydium", &[], WSOL_ADDRESS, 50_000.0),
+            pair("cpmm", "raydium", &["CPMM"], WSOL_ADDRESS, 80_000.0),
+            pair("clmm", "raydium", &["CLMM"], WSOL_ADDRESS, 500_000.0),
+            pair("usdc", "raydium", &[], "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", 900_000.0),
+            pair("orca", "orca", &[], WSOL_ADDRESS, 700_000.0),
+        ];
+        assert_eq!(select_best_raydium_pair(&pairs).unwrap().pair_address, "cpmm");
+    }
+
+    #[test]
+    fn concentrated_liquidity_only_has_no_pair() {
+        let pairs = vec![pair("clmm", "raydium", &["CLMM"], WSOL_ADDRESS, 500_000.0)];
+        assert!(select_best_raydium_pair(&pairs).is_none());
+    }
+
+    #[test]
+    fn cached_responses_are_dated_back_by_their_age() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DATE, "Sun, 18 Oct 2026 12:00:00 GMT".parse().unwrap());
+        let date = DateTime::parse_from_rfc3339("2026-10-18T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(response_time(&headers), Some(date));
+
+        headers.insert(AGE, "30".parse().unwrap());
+        assert_eq!(response_time(&headers), Some(date - Duration::seconds(30)));
+
+        assert_eq!(response_time(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn v4_label_is_swappable() {
+        assert!(pair("amm", "raydium", &["v4"], WSOL_ADDRESS, 1.0).is_swappable_pool_type());
+    }
 }
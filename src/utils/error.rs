@@ -2,9 +2,294 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum DexScreenerError {
-    #[error("Error fetching or processing data from DexScreener API")]
-    DataError,
-    
-    #[error("Incorrect baseToken provided")]
-    TokenError,
+    #[error("Token {0} is not a Solana token or is not supported by DexScreener")]
+    NonSolanaToken(String),
+
+    #[error("No DexScreener pair found for {0}")]
+    PairNotFound(String),
+
+    #[error("No Raydium AMM v4 or CPMM pair quoted in SOL found for {0}")]
+    NoPairs(String),
+
+    #[error("Error fetching data from DexScreener API: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Error decoding DexScreener API response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("DexScreener response has no Date header")]
+    MissingDate,
+}
+
+#[derive(Error, Debug)]
+pub enum RaydiumError {
+    #[error("No Raydium pool found for {0}")]
+    PoolNotFound(String),
+
+    #[error("No swappable Raydium pool quoted in WSOL found for {0}")]
+    NoWsolPool(String),
+
+    #[error("Raydium API returned an unsuccessful response for {0}")]
+    Api(String),
+
+    #[error("Error fetching data from Raydium API: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Error decoding Raydium API response: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum SwapError {
+    #[error("Account {0} is not a supported Raydium AMM v4 or CPMM pool")]
+    UnsupportedPool(String),
+
+    #[error("Invalid {0} account data")]
+    InvalidAccountData(&'static str),
+
+    #[error("Pool has no liquidity for this swap")]
+    InsufficientLiquidity,
+
+    #[error("Swap amount is too small to produce any output")]
+    ZeroOutput,
+}
+
+#[derive(Error, Debug)]
+pub enum SolanaRpcError {
+    #[error("No Solana RPC endpoints configured")]
+    NoEndpoints,
+
+    #[error("{method} timed out on {endpoint}")]
+    Timeout { method: &'static str, endpoint: String },
+
+    #[error("Solana RPC error: {0}")]
+    Client(#[from] solana_client::client_error::ClientError),
+}
+
+#[derive(Error, Debug)]
+pub enum TokenHolderError {
+    #[error("Error reading token accounts: {0}")]
+    Rpc(#[from] SolanaRpcError),
+
+    #[error("Error decoding mint: {0}")]
+    InvalidMint(#[from] SwapError),
+}
+
+#[derive(Error, Debug)]
+pub enum KeyLoaderError {
+    #[error("No key configured")]
+    Missing,
+
+    #[error("Key file {0} not found")]
+    FileNotFound(String),
+
+    #[error("Error reading key file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Key file is not a valid Solana CLI keypair")]
+    InvalidKeypairFile,
+
+    #[error("Keystore file needs a password")]
+    MissingPassword,
+
+    #[error("Error decrypting keystore: {0}")]
+    Keystore(String),
+
+    #[error("Key is neither valid hex nor base58")]
+    InvalidEncoding,
+
+    #[error("Unexpected key length of {0} bytes")]
+    InvalidKeyLength(usize),
+
+    #[error("Key bytes do not form a valid private key")]
+    InvalidKey,
+}
+
+#[derive(Error, Debug)]
+pub enum SignerError {
+    #[error("Signer has no Solana key")]
+    NoSolanaKey,
+
+    #[error("Signer has no EVM key")]
+    NoEvmKey,
+
+    #[error("Signing policy rejected the transaction: {0}")]
+    PolicyViolation(String),
+
+    #[error("Transaction needs {0} signatures, the signer provides one")]
+    UnsupportedSigners(usize),
+
+    #[error("Remote signer returned an invalid signature")]
+    InvalidSignature,
+
+    #[error("Remote signer error: {0}")]
+    Remote(String),
+
+    #[error("Remote signer timed out")]
+    Timeout,
+
+    #[error("Unknown signer mode {0}")]
+    UnknownMode(String),
+
+    #[error("Error signing EVM transaction: {0}")]
+    Evm(String),
+
+    #[error("Error loading signer key: {0}")]
+    KeyLoader(String),
+
+    #[error("HTTP remote signer needs SIGNER_AUTH_TOKEN")]
+    MissingAuthToken,
+
+    #[error("Error talking to remote signer: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Error talking to remote signer: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Error decoding remote signer message: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum PriceOracleError {
+    #[error("No fresh price quotes for {0}")]
+    NoQuotes(String),
+
+    #[error("Only {found} of {required} required price sources agree on {asset}")]
+    TooFewSources { asset: String, found: usize, required: usize },
+}
+
+#[derive(Error, Debug)]
+pub enum PositionError {
+    #[error("Position {0} not found")]
+    NotFound(i32),
+
+    #[error("Trade {0} not found")]
+    TradeNotFound(i32),
+
+    #[error("Position {0} is already closed")]
+    AlreadyClosed(i32),
+
+    #[error("Position {0} is already being sold")]
+    Closing(i32),
+
+    #[error("Invalid exit rule: {0}")]
+    InvalidExitRule(String),
+
+    #[error("No tokens of position {0} left in the agent wallet")]
+    NothingToSell(i32),
+
+    #[error("Error swapping tokens: {0}")]
+    Swap(String),
+
+    #[error("Unknown {0} {1:?} in the database")]
+    UnknownValue(&'static str, String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum PayoutError {
+    #[error("Payout {0} not found")]
+    NotFound(i32),
+
+    #[error("No wallet to pay out position {0} to")]
+    NoRecipient(i32),
+
+    #[error("Error calculating PnL: {0}")]
+    Pnl(#[from] PnlError),
+
+    #[error("Error transferring payout: {0}")]
+    Transfer(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum PnlError {
+    #[error("No sell with trade id {0}")]
+    SaleNotFound(i32),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum BalanceHistoryError {
+    #[error("Unknown timezone {0}")]
+    InvalidTimezone(String),
+
+    #[error("Error reading the agent wallet: {0}")]
+    Wallet(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum StatisticsError {
+    #[error("Error calculating PnL: {0}")]
+    Pnl(#[from] PnlError),
+
+    #[error("Error reading balance history: {0}")]
+    BalanceHistory(#[from] BalanceHistoryError),
+
+    #[error("Error reading the agent wallet: {0}")]
+    Wallet(String),
+
+    #[error("Unknown {0} {1:?} in the database")]
+    UnknownValue(&'static str, String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+    #[error("Invalid schedule {0}")]
+    InvalidSchedule(String),
+
+    #[error("Unknown job {0}")]
+    UnknownJob(String),
+
+    #[error("Job {0} is already running")]
+    LeaseHeld(String),
+
+    #[error("Error taking job lease: {0}")]
+    Lease(#[from] redis::RedisError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum TwitterError {
+    #[error("Unknown Twitter client {0}")]
+    UnknownClient(String),
+
+    #[error("Twitter API error: {0}")]
+    Api(String),
+
+    #[error("Twitter rate limit reached, retry in {0} seconds")]
+    RateLimited(u64),
+
+    #[error("Error calling the Twitter API: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Error decoding Twitter data: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("Error reading Twitter fixture: {0}")]
+    Fixture(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum CreditError {
+    #[error("Twitter error: {0}")]
+    Twitter(#[from] TwitterError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
 }
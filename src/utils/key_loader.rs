@@ -0,0 +1,143 @@
+use base58::FromBase58;
+use ethers::signers::LocalWallet;
+use solana_sdk::signature::{keypair_from_seed, Keypair};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::utils::error::KeyLoaderError;
+
+/// A configuration value holding key material; wiped on drop and redacted in `Debug`.
+#[derive(Clone, Default)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+/// Raw private key bytes, wiped on drop and redacted in `Debug`.
+pub struct SecretKey(Zeroizing<Vec<u8>>);
+
+impl SecretKey {
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey({} bytes, ***)", self.0.len())
+    }
+}
+
+fn is_hex_key(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Separators and dots never appear in hex or base58 secrets, so such a value was meant as a path.
+fn looks_like_path(value: &str) -> bool {
+    value.contains(['/', '\\', '.', '~'])
+}
+
+/// Reads key material from any of the supported sources:
+/// - a path to a Solana CLI JSON keypair file (`[12, 34, ...]`)
+/// - a path to a password-encrypted Web3 Secret Storage keystore
+/// - a hex secret, with or without `0x` (EVM keys)
+/// - a base58 secret (Solana keys)
+pub fn load_secret_key(source: &SecretString, password: Option<&SecretString>) -> Result<SecretKey, KeyLoaderError> {
+    let value = source.expose().trim();
+    if value.is_empty() {
+        return Err(KeyLoaderError::Missing);
+    }
+
+    let path = Path::new(value);
+    if path.is_file() {
+        let contents = Zeroizing::new(fs::read_to_string(path)?);
+        if contents.trim_start().starts_with('[') {
+            let bytes: Vec<u8> = serde_json::from_str(&contents).map_err(|_| KeyLoaderError::InvalidKeypairFile)?;
+            return Ok(SecretKey(Zeroizing::new(bytes)));
+        }
+        let password = password.ok_or(KeyLoaderError::MissingPassword)?;
+        let bytes = eth_keystore::decrypt_key(path, password.expose().as_bytes())
+            .map_err(|e| KeyLoaderError::Keystore(e.to_string()))?;
+        return Ok(SecretKey(Zeroizing::new(bytes)));
+    }
+    if looks_like_path(value) {
+        return Err(KeyLoaderError::FileNotFound(value.to_string()));
+    }
+
+    let hex_value = value.strip_prefix("0x").unwrap_or(value);
+    if value.starts_with("0x") || is_hex_key(hex_value) {
+        let bytes = hex::decode(hex_value).map_err(|_| KeyLoaderError::InvalidEncoding)?;
+        return Ok(SecretKey(Zeroizing::new(bytes)));
+    }
+
+    let bytes = value.from_base58().map_err(|_| KeyLoaderError::InvalidEncoding)?;
+    Ok(SecretKey(Zeroizing::new(bytes)))
+}
+
+/// Solana keypair from a 64 byte secret key or a 32 byte ed25519 seed.
+pub fn load_solana_keypair(source: &SecretString, password: Option<&SecretString>) -> Result<Keypair, KeyLoaderError> {
+    let secret = load_secret_key(source, password)?;
+    match secret.bytes().len() {
+        64 => Keypair::try_from(secret.bytes()).map_err(|_| KeyLoaderError::InvalidKey),
+        32 => keypair_from_seed(secret.bytes()).map_err(|_| KeyLoaderError::InvalidKey),
+        length => Err(KeyLoaderError::InvalidKeyLength(length)),
+    }
+}
+
+/// EVM wallet from a 32 byte secp256k1 secret key.
+pub fn load_evm_wallet(source: &SecretString, password: Option<&SecretString>) -> Result<LocalWallet, KeyLoaderError> {
+    let secret = load_secret_key(source, password)?;
+    if secret.bytes().len() != 32 {
+        return Err(KeyLoaderError::InvalidKeyLength(secret.bytes().len()));
+    }
+    LocalWallet::from_bytes(secret.bytes()).map_err(|_| KeyLoaderError::InvalidKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_key_file_is_reported_as_such() {
+        let source = SecretString::new("/etc/agent/id.json".to_string());
+        assert!(matches!(load_secret_key(&source, None), Err(KeyLoaderError::FileNotFound(_))));
+
+        let source = SecretString::new("./keys/agent-keystore".to_string());
+        assert!(matches!(load_secret_key(&source, None), Err(KeyLoaderError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn inline_secrets_are_decoded() {
+        let keypair = Keypair::new();
+        let source = SecretString::new(keypair.to_base58_string());
+        assert_eq!(load_solana_keypair(&source, None).unwrap().to_bytes(), keypair.to_bytes());
+
+        let source = SecretString::new(format!("0x{}", "11".repeat(32)));
+        assert_eq!(load_secret_key(&source, None).unwrap().bytes(), &[0x11; 32]);
+    }
+
+    #[test]
+    fn garbage_is_an_encoding_error() {
+        let source = SecretString::new("not-a-key".to_string());
+        assert!(matches!(load_secret_key(&source, None), Err(KeyLoaderError::InvalidEncoding)));
+    }
+}
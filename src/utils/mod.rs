@@ -3,9 +3,18 @@ pub mod binance;
 pub mod dexscreener;
 pub mod error;
 pub mod general;
+pub mod key_loader;
 pub mod paginated_response;
+pub mod price_oracle;
+pub mod priority_fee;
 pub mod raydium;
+pub mod raydium_swap;
+pub mod signer;
 pub mod smc_driver;
 pub mod solana_driver;
+pub mod solana_rpc;
 pub mod telegram_bot;
+pub mod token_holders;
+pub mod transaction_manager;
+pub mod twitter_client;
 pub mod twitter_driver;
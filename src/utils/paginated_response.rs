@@ -21,3 +21,19 @@ impl<T> PaginatedResponse<T> {
         }
     }
 }
+
+/// Largest page a list endpoint returns.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+pub fn default_page_number() -> usize {
+    1
+}
+
+pub fn default_page_size() -> usize {
+    20
+}
+
+/// Page number and size from a query, made valid.
+pub fn page_bounds(page_number: usize, page_size: usize) -> (usize, usize) {
+    (page_number.max(1), page_size.clamp(1, MAX_PAGE_SIZE))
+}
@@ -0,0 +1,454 @@
+use chrono::{DateTime, Duration, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use strum::Display;
+use tracing::warn;
+
+use crate::alchemy::{AlchemyClient, TokenPrices};
+use crate::core::config::{PriceOracleSettings, RedisSettings};
+use crate::utils::binance::fetch_last_price_binance;
+use crate::utils::dexscreener::{select_best_raydium_pair, DexScreenerClient};
+use crate::utils::error::{PriceOracleError, SwapError};
+use crate::utils::raydium::RaydiumClient;
+use crate::utils::raydium_swap::{
+    decode_mint_decimals, decode_token_account_amount, AmmV4Pool, CpmmPool, AMM_V4_PROGRAM, CPMM_PROGRAM, WSOL_MINT,
+};
+use crate::utils::solana_rpc::SolanaRpc;
+
+/// Asset key of native SOL.
+pub const SOL_ASSET: &str = "SOL";
+const CACHE_KEY_PREFIX: &str = "price_oracle";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PriceSource {
+    Binance,
+    DexScreener,
+    Raydium,
+    Alchemy,
+}
+
+/// One source's USD price of an asset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub source: PriceSource,
+    pub price_usd: f64,
+    /// When the source produced the price, not when it was fetched.
+    pub observed_at: DateTime<Utc>,
+    /// Pool the price was read from; two quotes of one pool are a single source.
+    #[serde(default)]
+    pub pool: Option<String>,
+}
+
+/// USD price agreed on by the sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceEstimate {
+    /// `SOL` or a token mint.
+    pub asset: String,
+    /// Median of the quotes used.
+    pub price_usd: f64,
+    /// From 0 to 1: the share of sources that contributed, reduced by how far apart they are.
+    pub confidence: f64,
+    pub quotes: Vec<PriceQuote>,
+    /// Sources that failed, were stale or disagreed with the others.
+    pub rejected: Vec<PriceSource>,
+    pub computed_at: DateTime<Utc>,
+}
+
+type QuoteResult = Result<PriceQuote, Box<dyn Error + Send + Sync>>;
+
+/// Median of the values; the mean of the middle two for an even count.
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[middle - 1] + values[middle]) / 2.0)
+    } else {
+        Some(values[middle])
+    }
+}
+
+/// Combines the source results: drops failed and stale quotes, then quotes too far from the
+/// median, and prices the asset at the median of what is left.
+pub fn aggregate_quotes(
+    asset: &str,
+    results: Vec<(PriceSource, QuoteResult)>,
+    settings: &PriceOracleSettings,
+    now: DateTime<Utc>,
+) -> Result<PriceEstimate, PriceOracleError> {
+    let attempted = results.len();
+    let max_age = Duration::seconds(settings.max_quote_age_secs);
+    let mut rejected = Vec::new();
+    let mut fresh = Vec::new();
+    for (source, result) in results {
+        match result {
+            Ok(quote) if quote.price_usd.is_finite() && quote.price_usd > 0.0 && now - quote.observed_at <= max_age => {
+                fresh.push(quote)
+            }
+            Ok(quote) => {
+                warn!("Rejected {} price of {} from {} observed at {}", quote.price_usd, asset, source, quote.observed_at);
+                rejected.push(source);
+            }
+            Err(e) => {
+                warn!("{} price of {} unavailable: {}", source, asset, e);
+                rejected.push(source);
+            }
+        }
+    }
+
+    let mut pools: Vec<String> = Vec::new();
+    let (fresh, duplicates): (Vec<PriceQuote>, Vec<PriceQuote>) = fresh.into_iter().partition(|quote| match &quote.pool {
+        Some(pool) if pools.contains(pool) => false,
+        Some(pool) => {
+            pools.push(pool.clone());
+            true
+        }
+        None => true,
+    });
+    for duplicate in &duplicates {
+        warn!("Ignored {} price of {}: pool {:?} is already quoted", duplicate.source, asset, duplicate.pool);
+    }
+    rejected.extend(duplicates.iter().map(|q| q.source));
+
+    let prices: Vec<f64> = fresh.iter().map(|q| q.price_usd).collect();
+    let reference = median(&prices).ok_or_else(|| PriceOracleError::NoQuotes(asset.to_string()))?;
+    let max_deviation = settings.max_deviation_pct / 100.0;
+    let (quotes, outliers): (Vec<PriceQuote>, Vec<PriceQuote>) =
+        fresh.into_iter().partition(|q| (q.price_usd - reference).abs() / reference <= max_deviation);
+    for outlier in &outliers {
+        warn!("Rejected {} price of {}: {} is too far from the median {}", outlier.source, asset, outlier.price_usd, reference);
+    }
+    rejected.extend(outliers.iter().map(|q| q.source));
+
+    if quotes.len() < settings.min_sources.max(1) {
+        return Err(PriceOracleError::TooFewSources {
+            asset: asset.to_string(),
+            found: quotes.len(),
+            required: settings.min_sources.max(1),
+        });
+    }
+
+    let price_usd = median(&quotes.iter().map(|q| q.price_usd).collect::<Vec<_>>()).unwrap_or(reference);
+    let spread = quotes.iter().map(|q| (q.price_usd - price_usd).abs() / price_usd).fold(0.0, f64::max);
+    let agreement = if max_deviation > 0.0 { (1.0 - spread / max_deviation).clamp(0.0, 1.0) } else { 1.0 };
+    let confidence = quotes.len() as f64 / attempted as f64 * agreement;
+
+    Ok(PriceEstimate { asset: asset.to_string(), price_usd, confidence, quotes, rejected, computed_at: now })
+}
+
+/// USD prices of SOL and Solana tokens from Binance, DexScreener, Raydium pool reserves and
+/// Alchemy, cached in Redis.
+pub struct PriceOracle {
+    rpc: Arc<SolanaRpc>,
+    dexscreener: DexScreenerClient,
+    alchemy: Option<AlchemyClient>,
+    redis: Option<redis::Client>,
+    settings: PriceOracleSettings,
+}
+
+impl PriceOracle {
+    pub fn new_price_oracle(rpc: Arc<SolanaRpc>) -> Self {
+        let settings = PriceOracleSettings::new_price_oracle();
+        let redis = redis::Client::open(RedisSettings::new_redis().redis_url)
+            .map_err(|e| warn!("Price cache disabled: {}", e))
+            .ok();
+        Self {
+            rpc,
+            dexscreener: DexScreenerClient::new_dexscreener_client(),
+            alchemy: settings.alchemy_api_key.clone().map(AlchemyClient::new),
+            redis,
+            settings,
+        }
+    }
+
+    pub async fn sol_usd(&self) -> Result<PriceEstimate, PriceOracleError> {
+        if let Some(cached) = self.cached(SOL_ASSET).await {
+            return Ok(cached);
+        }
+
+        let (binance, dexscreener, raydium, alchemy) = futures::join!(
+            self.binance_sol_quote(),
+            self.dexscreener_sol_quote(),
+            self.raydium_sol_quote(),
+            self.alchemy_sol_quote(),
+        );
+        // Raydium first: when both read the same pool, the on-chain quote is the one kept.
+        let mut results =
+            vec![(PriceSource::Binance, binance), (PriceSource::Raydium, raydium), (PriceSource::DexScreener, dexscreener)];
+        if let Some(alchemy) = alchemy {
+            results.push((PriceSource::Alchemy, alchemy));
+        }
+
+        let estimate = aggregate_quotes(SOL_ASSET, results, &self.settings, Utc::now())?;
+        self.cache(&estimate).await;
+        Ok(estimate)
+    }
+
+    /// USD price of a token. DexScreener and Raydium price it in SOL, so their quotes are
+    /// converted with `sol_usd` and the result is never more confident than the SOL price.
+    pub async fn token_usd(&self, mint: &str, pool_address: Option<&str>) -> Result<PriceEstimate, PriceOracleError> {
+        if let Some(cached) = self.cached(mint).await {
+            return Ok(cached);
+        }
+
+        let sol = self.sol_usd().await?;
+        let (dexscreener, raydium, alchemy) = futures::join!(
+            self.dexscreener_token_quote(mint),
+            self.raydium_token_quote(mint, pool_address),
+            self.alchemy_token_quote(mint),
+        );
+        let in_usd = |result: QuoteResult| {
+            result.map(|quote| PriceQuote { price_usd: quote.price_usd * sol.price_usd, ..quote })
+        };
+        let mut results =
+            vec![(PriceSource::Raydium, in_usd(raydium)), (PriceSource::DexScreener, in_usd(dexscreener))];
+        if let Some(alchemy) = alchemy {
+            results.push((PriceSource::Alchemy, alchemy));
+        }
+
+        let mut estimate = aggregate_quotes(mint, results, &self.settings, Utc::now())?;
+        estimate.confidence = estimate.confidence.min(sol.confidence);
+        self.cache(&estimate).await;
+        Ok(estimate)
+    }
+
+    async fn cached(&self, asset: &str) -> Option<PriceEstimate> {
+        let mut conn = self.redis.as_ref()?.get_multiplexed_async_connection().await.ok()?;
+        let value: Option<String> = conn.get(format!("{}:{}", CACHE_KEY_PREFIX, asset)).await.ok()?;
+        value.and_then(|v| serde_json::from_str(&v).ok())
+    }
+
+    async fn cache(&self, estimate: &PriceEstimate) {
+        let Some(client) = &self.redis else {
+            return;
+        };
+        let key = format!("{}:{}", CACHE_KEY_PREFIX, estimate.asset);
+        let result = async {
+            let mut conn = client.get_multiplexed_async_connection().await?;
+            let value = serde_json::to_string(estimate).unwrap_or_default();
+            conn.set_ex::<_, _, ()>(key, value, self.settings.cache_ttl_secs).await
+        };
+        if let Err(e) = result.await {
+            warn!("Failed to cache price of {}: {}", estimate.asset, e);
+        }
+    }
+
+    async fn binance_sol_quote(&self) -> QuoteResult {
+        let (price_usd, observed_at) = fetch_last_price_binance("SOLUSDT").await?;
+        Ok(PriceQuote { source: PriceSource::Binance, price_usd, observed_at, pool: None })
+    }
+
+    async fn dexscreener_sol_quote(&self) -> QuoteResult {
+        let (pair, observed_at) = self.dexscreener.get_pair_with_time(&self.settings.sol_usdc_pool).await?;
+        let pair = pair.ok_or("SOL/USDC pair not found")?;
+        let price_usd = pair.price_native().ok_or("SOL/USDC pair has no price")?;
+        Ok(PriceQuote { source: PriceSource::DexScreener, price_usd, observed_at, pool: Some(pair.pair_address) })
+    }
+
+    async fn raydium_sol_quote(&self) -> QuoteResult {
+        let pool = Pubkey::from_str(&self.settings.sol_usdc_pool)?;
+        let (price_usd, observed_at) = self.pool_price(&pool, &WSOL_MINT).await?;
+        Ok(PriceQuote { source: PriceSource::Raydium, price_usd, observed_at, pool: Some(pool.to_string()) })
+    }
+
+    async fn alchemy_sol_quote(&self) -> Option<QuoteResult> {
+        let alchemy = self.alchemy.as_ref()?;
+        Some(match alchemy.get_token_prices_by_symbol(&[SOL_ASSET]).await {
+            Ok(response) => alchemy_usd_quote(response.data.first()),
+            Err(e) => Err(e.into()),
+        })
+    }
+
+    /// Token price in SOL from the deepest Raydium/WSOL pair on DexScreener.
+    async fn dexscreener_token_quote(&self, mint: &str) -> QuoteResult {
+        let (pairs, observed_at) = self.dexscreener.get_token_pairs_with_time(mint).await?;
+        let pair = select_best_raydium_pair(&pairs).ok_or("No Raydium/WSOL pair on DexScreener")?;
+        let price_native = pair.price_native().filter(|p| *p > 0.0).ok_or("Pair has no native price")?;
+        // `price_native` is the base token in the quote token; WSOL may be either side.
+        let price_sol = if pair.base_token.address == mint { price_native } else { 1.0 / price_native };
+        Ok(PriceQuote {
+            source: PriceSource::DexScreener,
+            price_usd: price_sol,
+            observed_at,
+            pool: Some(pair.pair_address.clone()),
+        })
+    }
+
+    /// Token price in SOL from the reserves of its Raydium pool.
+    async fn raydium_token_quote(&self, mint: &str, pool_address: Option<&str>) -> QuoteResult {
+        let pool_address = match pool_address {
+            Some(address) => address.to_string(),
+            None => RaydiumClient::new_raydium_client().resolve_pool(mint).await?.id,
+        };
+        let (price_sol, observed_at) = self.pool_price(&Pubkey::from_str(&pool_address)?, &Pubkey::from_str(mint)?).await?;
+        Ok(PriceQuote { source: PriceSource::Raydium, price_usd: price_sol, observed_at, pool: Some(pool_address) })
+    }
+
+    async fn alchemy_token_quote(&self, mint: &str) -> Option<QuoteResult> {
+        let alchemy = self.alchemy.as_ref()?;
+        Some(match alchemy.get_token_prices_by_address(&[mint], &self.settings.alchemy_network).await {
+            Ok(response) => alchemy_usd_quote(response.data.first()),
+            Err(e) => Err(e.into()),
+        })
+    }
+
+    /// Price of `base_mint` in the pool's other mint, from the vault reserves, with the time
+    /// of the slot the reserves were read at.
+    async fn pool_price(&self, pool_id: &Pubkey, base_mint: &Pubkey) -> Result<(f64, DateTime<Utc>), Box<dyn Error + Send + Sync>> {
+        let account = self.rpc.get_account(pool_id).await?;
+        let (mint_a, mint_b, reserve_a, reserve_b, slot) = if account.owner == AMM_V4_PROGRAM {
+            let pool = AmmV4Pool::decode(*pool_id, &account.data)?;
+            let (coin_amount, pc_amount, slot) = self.vault_amounts(&pool.coin_vault, &pool.pc_vault).await?;
+            let (coin, pc) = pool.reserves(coin_amount, pc_amount);
+            (pool.coin_mint, pool.pc_mint, coin, pc, slot)
+        } else if account.owner == CPMM_PROGRAM {
+            let pool = CpmmPool::decode(*pool_id, &account.data)?;
+            let (amount_0, amount_1, slot) = self.vault_amounts(&pool.token_0_vault, &pool.token_1_vault).await?;
+            let (token_0, token_1) = pool.reserves(amount_0, amount_1);
+            (pool.token_0_mint, pool.token_1_mint, token_0, token_1, slot)
+        } else {
+            return Err(SwapError::UnsupportedPool(pool_id.to_string()).into());
+        };
+
+        let mints = self.rpc.get_multiple_accounts(&[mint_a, mint_b]).await?;
+        let decimals = |index: usize| -> Result<i32, SwapError> {
+            let account = mints[index].as_ref().ok_or(SwapError::InvalidAccountData("mint"))?;
+            Ok(decode_mint_decimals(&account.data)? as i32)
+        };
+        let ui_a = reserve_a as f64 / 10f64.powi(decimals(0)?);
+        let ui_b = reserve_b as f64 / 10f64.powi(decimals(1)?);
+        if ui_a == 0.0 || ui_b == 0.0 {
+            return Err(SwapError::InsufficientLiquidity.into());
+        }
+
+        let price = if *base_mint == mint_a {
+            ui_b / ui_a
+        } else if *base_mint == mint_b {
+            ui_a / ui_b
+        } else {
+            return Err(format!("{} is not traded in pool {}", base_mint, pool_id).into());
+        };
+        let block_time = self.rpc.get_block_time(slot).await?;
+        let observed_at = DateTime::from_timestamp(block_time, 0).ok_or("Invalid block time")?;
+        Ok((price, observed_at))
+    }
+
+    /// Vault balances and the slot they were read at.
+    async fn vault_amounts(&self, vault_a: &Pubkey, vault_b: &Pubkey) -> Result<(u64, u64, u64), Box<dyn Error + Send + Sync>> {
+        let (slot, accounts) = self.rpc.get_multiple_accounts_with_slot(&[*vault_a, *vault_b]).await?;
+        let amount = |index: usize| -> Result<u64, SwapError> {
+            let account = accounts[index].as_ref().ok_or(SwapError::InvalidAccountData("vault"))?;
+            decode_token_account_amount(&account.data)
+        };
+        Ok((amount(0)?, amount(1)?, slot))
+    }
+}
+
+fn alchemy_usd_quote(prices: Option<&TokenPrices>) -> QuoteResult {
+    let prices = prices.ok_or("Alchemy returned no prices")?;
+    if let Some(error) = &prices.error {
+        return Err(error.clone().into());
+    }
+    let price = prices
+        .prices
+        .iter()
+        .find(|p| p.currency.eq_ignore_ascii_case("usd"))
+        .ok_or("Alchemy returned no USD price")?;
+    Ok(PriceQuote {
+        source: PriceSource::Alchemy,
+        price_usd: price.value.parse()?,
+        observed_at: price.last_updated_at,
+        pool: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(min_sources: usize) -> PriceOracleSettings {
+        PriceOracleSettings {
+            max_quote_age_secs: 300,
+            cache_ttl_secs: 30,
+            max_deviation_pct: 5.0,
+            min_sources,
+            alchemy_api_key: None,
+            alchemy_network: "solana-mainnet".to_string(),
+            sol_usdc_pool: "pool".to_string(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_790_000_000, 0).unwrap()
+    }
+
+    fn quote(source: PriceSource, price_usd: f64, age_secs: i64, pool: Option<&str>) -> (PriceSource, QuoteResult) {
+        let observed_at = now() - Duration::seconds(age_secs);
+        (source, Ok(PriceQuote { source, price_usd, observed_at, pool: pool.map(str::to_string) }))
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), Some(2.5));
+    }
+
+    #[test]
+    fn prices_at_the_median_of_agreeing_sources() {
+        let results = vec![
+            quote(PriceSource::Binance, 100.0, 5, None),
+            quote(PriceSource::Raydium, 101.0, 5, Some("pool")),
+            quote(PriceSource::Alchemy, 130.0, 5, None),
+        ];
+        let estimate = aggregate_quotes(SOL_ASSET, results, &settings(2), now()).unwrap();
+
+        assert_eq!(estimate.price_usd, 100.5);
+        assert_eq!(estimate.rejected, vec![PriceSource::Alchemy]);
+        assert!(estimate.confidence > 0.0 && estimate.confidence < 2.0 / 3.0);
+    }
+
+    #[test]
+    fn stale_and_failed_quotes_are_rejected() {
+        let results = vec![
+            quote(PriceSource::Binance, 100.0, 5, None),
+            quote(PriceSource::Alchemy, 100.0, 301, None),
+            (PriceSource::DexScreener, Err("timeout".into())),
+        ];
+        let error = aggregate_quotes(SOL_ASSET, results, &settings(2), now()).unwrap_err();
+        assert!(matches!(error, PriceOracleError::TooFewSources { found: 1, required: 2, .. }));
+    }
+
+    #[test]
+    fn one_pool_counts_as_one_source() {
+        let results = vec![
+            quote(PriceSource::Raydium, 100.0, 5, Some("pool")),
+            quote(PriceSource::DexScreener, 100.2, 5, Some("pool")),
+        ];
+        let error = aggregate_quotes(SOL_ASSET, results, &settings(2), now()).unwrap_err();
+        assert!(matches!(error, PriceOracleError::TooFewSources { found: 1, .. }));
+
+        let results = vec![
+            quote(PriceSource::Raydium, 100.0, 5, Some("pool")),
+            quote(PriceSource::DexScreener, 100.2, 5, Some("pool")),
+        ];
+        let estimate = aggregate_quotes(SOL_ASSET, results, &settings(1), now()).unwrap();
+        assert_eq!(estimate.price_usd, 100.0);
+        assert_eq!(estimate.rejected, vec![PriceSource::DexScreener]);
+    }
+
+    #[test]
+    fn no_usable_quote_is_an_error() {
+        let results = vec![quote(PriceSource::Binance, f64::NAN, 5, None)];
+        assert!(matches!(aggregate_quotes(SOL_ASSET, results, &settings(1), now()), Err(PriceOracleError::NoQuotes(_))));
+    }
+}
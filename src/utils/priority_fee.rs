@@ -0,0 +1,122 @@
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+/// Hard limit of the runtime per transaction.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// `getRecentPrioritizationFees` accepts at most this many accounts.
+const MAX_FEE_ACCOUNTS: usize = 128;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Compute budget attached to a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: u32,
+    /// Price per compute unit, in micro-lamports.
+    pub unit_price: u64,
+}
+
+impl ComputeBudget {
+    /// Priority fee paid on top of the base signature fee, in lamports.
+    pub fn priority_fee_lamports(&self) -> u64 {
+        (self.unit_limit as u128 * self.unit_price as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT) as u64
+    }
+
+    pub fn instructions(&self) -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(self.unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(self.unit_price),
+        ]
+    }
+}
+
+/// Accounts the instructions write to, which are the ones whose fee markets matter.
+pub fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !meta.is_signer && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_FEE_ACCOUNTS);
+    accounts
+}
+
+/// Nearest-rank percentile of recent per-slot fees; zero when there are none.
+pub fn percentile_fee(fees: &[u64], percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    let mut fees = fees.to_vec();
+    fees.sort_unstable();
+    let rank = (percentile.min(100) as usize * fees.len()).div_ceil(100).max(1);
+    fees[rank - 1]
+}
+
+/// Compute unit limit for a measured consumption, with headroom for state changing before landing.
+pub fn unit_limit_with_margin(units_consumed: u64, margin_pct: u64) -> u32 {
+    let limit = units_consumed + units_consumed * margin_pct / 100;
+    limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+/// Builds the budget, lowering the unit price if needed so the priority fee stays under `cap_lamports`.
+pub fn capped_compute_budget(unit_limit: u32, unit_price: u64, cap_lamports: u64) -> ComputeBudget {
+    let max_price = if unit_limit == 0 {
+        0
+    } else {
+        (cap_lamports as u128 * MICRO_LAMPORTS_PER_LAMPORT / unit_limit as u128) as u64
+    };
+    ComputeBudget { unit_limit, unit_price: unit_price.min(max_price) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    #[test]
+    fn percentile_fee_uses_nearest_rank() {
+        let fees = [500, 100, 400, 200, 300];
+        assert_eq!(percentile_fee(&fees, 0), 100);
+        assert_eq!(percentile_fee(&fees, 50), 300);
+        assert_eq!(percentile_fee(&fees, 75), 400);
+        assert_eq!(percentile_fee(&fees, 100), 500);
+        assert_eq!(percentile_fee(&fees, 255), 500);
+    }
+
+    #[test]
+    fn percentile_fee_of_no_fees_is_zero() {
+        assert_eq!(percentile_fee(&[], 75), 0);
+        assert_eq!(percentile_fee(&[42], 75), 42);
+    }
+
+    #[test]
+    fn unit_limit_adds_margin_up_to_runtime_limit() {
+        assert_eq!(unit_limit_with_margin(100_000, 20), 120_000);
+        assert_eq!(unit_limit_with_margin(1_300_000, 20), MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn capped_budget_lowers_price_to_stay_under_cap() {
+        let budget = capped_compute_budget(200_000, 10_000_000, 500_000);
+        assert_eq!(budget.unit_price, 2_500_000);
+        assert_eq!(budget.priority_fee_lamports(), 500_000);
+
+        let budget = capped_compute_budget(200_000, 1_000, 500_000);
+        assert_eq!(budget.unit_price, 1_000);
+        assert_eq!(budget.priority_fee_lamports(), 200);
+    }
+
+    #[test]
+    fn writable_accounts_skip_signers_and_duplicates() {
+        let payer = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            program,
+            &[],
+            vec![AccountMeta::new(payer, true), AccountMeta::new(pool, false), AccountMeta::new_readonly(program, false)],
+        );
+        assert_eq!(writable_accounts(&[instruction.clone(), instruction]), vec![pool]);
+    }
+}
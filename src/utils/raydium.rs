@@ -1,54 +1,187 @@
 use reqwest::Client;
-use serde_json::Value;
-use std::error::Error;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::error::RaydiumError;
 
 const RAYDIUM_API_URL: &str = "https://api-v3.raydium.io";
-const WSOL_ADDRESS: &str = "So11111111111111111111111111111111111111112";
+pub const WSOL_ADDRESS: &str = "So11111111111111111111111111111111111111112";
+
+pub const AMM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+pub const CPMM_PROGRAM_ID: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+pub const CLMM_PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PoolType {
+    AmmV4,
+    Cpmm,
+    Clmm,
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MintInfo {
+    pub address: String,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub name: String,
+    pub decimals: u8,
+    /// Token program owning the mint (SPL Token or Token-2022).
+    pub program_id: String,
+}
+
+/// Pool entry of the `/pools/info/*` endpoints.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolInfo {
+    pub id: String,
+    pub program_id: String,
+    pub mint_a: MintInfo,
+    pub mint_b: MintInfo,
+    /// Price of mint A in mint B.
+    #[serde(default)]
+    pub price: f64,
+    /// Reserve of mint A in UI units.
+    #[serde(rename = "mintAmountA", default)]
+    pub reserve_a: f64,
+    /// Reserve of mint B in UI units.
+    #[serde(rename = "mintAmountB", default)]
+    pub reserve_b: f64,
+    /// Trade fee as a fraction, e.g. `0.0025`.
+    #[serde(default)]
+    pub fee_rate: f64,
+    #[serde(default)]
+    pub tvl: f64,
+}
+
+impl PoolInfo {
+    pub fn pool_type(&self) -> PoolType {
+        match self.program_id.as_str() {
+            AMM_V4_PROGRAM_ID => PoolType::AmmV4,
+            CPMM_PROGRAM_ID => PoolType::Cpmm,
+            CLMM_PROGRAM_ID => PoolType::Clmm,
+            _ => PoolType::Unknown,
+        }
+    }
+
+    pub fn is_wsol_quoted(&self) -> bool {
+        self.mint_a.address == WSOL_ADDRESS || self.mint_b.address == WSOL_ADDRESS
+    }
+
+    /// The traded (non-WSOL) side of the pool.
+    pub fn token_mint(&self) -> &MintInfo {
+        if self.mint_b.address != WSOL_ADDRESS { &self.mint_b } else { &self.mint_a }
+    }
+
+    /// Pools the agent knows how to swap in.
+    pub fn is_swappable(&self) -> bool {
+        self.is_wsol_quoted() && matches!(self.pool_type(), PoolType::AmmV4 | PoolType::Cpmm)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct RaydiumResponse<T> {
+    success: bool,
+    data: Option<T>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PoolPage {
+    #[serde(default)]
+    data: Vec<PoolInfo>,
+}
+
+/// Orders candidate pools by TVL, deepest first, keeping only swappable WSOL pools.
+pub fn rank_wsol_pools(pools: Vec<PoolInfo>) -> Vec<PoolInfo> {
+    let mut pools: Vec<PoolInfo> = pools.into_iter().filter(PoolInfo::is_swappable).collect();
+    pools.sort_by(|a, b| b.tvl.total_cmp(&a.tvl));
+    pools
+}
 
 pub struct RaydiumClient {
     client: Client,
+    base_url: String,
 }
 
 impl RaydiumClient {
     pub fn new_raydium_client() -> Self {
+        Self::with_base_url(RAYDIUM_API_URL)
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
         Self {
             client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
         }
     }
 
-    pub async fn get_pool_quote_token_info(&self, pool_id: &str) -> Result<Value, Box<dyn Error>> {
-        let url = format!("{}/pools/info/ids?ids={}", RAYDIUM_API_URL, pool_id);
-        let resp = self.client.get(&url).send().await?.json::<Value>().await?;
+    async fn get<T: DeserializeOwned>(&self, path: &str, subject: &str) -> Result<T, RaydiumError> {
+        let body = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: RaydiumResponse<T> = serde_json::from_str(&body)?;
+        match (response.success, response.data) {
+            (true, Some(data)) => Ok(data),
+            _ => Err(RaydiumError::Api(subject.to_string())),
+        }
+    }
 
-        if resp["success"].as_bool().unwrap_or(false) {
-            let data = &resp["data"][0];
-            let mint_address = data["mintB"]["address"].as_str().unwrap_or("");
+    /// Pools by id; unknown ids come back as `null` and are dropped.
+    pub async fn get_pools_by_ids(&self, pool_ids: &[&str]) -> Result<Vec<PoolInfo>, RaydiumError> {
+        let ids = pool_ids.join(",");
+        let pools: Vec<Option<PoolInfo>> = self.get(&format!("/pools/info/ids?ids={}", ids), &ids).await?;
+        Ok(pools.into_iter().flatten().collect())
+    }
 
-            if mint_address != WSOL_ADDRESS {
-                Ok(data["mintB"].clone())
-            } else {
-                Ok(data["mintA"].clone())
-            }
-        } else {
-            Err(format!("Invalid pool_id or no data found for pool_id: {}", pool_id).into())
-        }
+    /// Pools pairing the mint with WSOL, as listed by `pools/info/mint`.
+    pub async fn get_pools_by_mint(&self, mint: &str) -> Result<Vec<PoolInfo>, RaydiumError> {
+        let path = format!(
+            "/pools/info/mint?mint1={}&mint2={}&poolType=all&poolSortField=liquidity&sortType=desc&pageSize=100&page=1",
+            mint, WSOL_ADDRESS
+        );
+        let page: PoolPage = self.get(&path, mint).await?;
+        Ok(page.data)
     }
 
-    pub async fn get_pool_info(&self, pool_id: &str) -> Result<(Value, bool), Box<dyn Error>> {
-        let url = format!("{}/pools/info/ids?ids={}", RAYDIUM_API_URL, pool_id);
-        let resp = self.client.get(&url).send().await?.json::<Value>().await?;
+    pub async fn get_pool_info(&self, pool_id: &str) -> Result<PoolInfo, RaydiumError> {
+        self.get_pools_by_ids(&[pool_id])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| RaydiumError::PoolNotFound(pool_id.to_string()))
+    }
 
-        if resp["success"].as_bool().unwrap_or(false) {
-            let data = &resp["data"][0];
-            let mint_address = data["mintB"]["address"].as_str().unwrap_or("");
+    pub async fn get_pool_quote_token_info(&self, pool_id: &str) -> Result<MintInfo, RaydiumError> {
+        Ok(self.get_pool_info(pool_id).await?.token_mint().clone())
+    }
 
-            if mint_address != WSOL_ADDRESS {
-                Ok((data.clone(), true))
-            } else {
-                Ok((data.clone(), false))
+    /// Accepts either a pool id or a token mint and returns the deepest swappable WSOL pool.
+    pub async fn resolve_pool(&self, pool_or_mint: &str) -> Result<PoolInfo, RaydiumError> {
+        // Unknown pool ids yield an empty list rather than an error.
+        if let Ok(pools) = self.get_pools_by_ids(&[pool_or_mint]).await {
+            if let Some(pool) = pools.into_iter().next() {
+                return if pool.is_swappable() {
+                    Ok(pool)
+                } else {
+                    Err(RaydiumError::NoWsolPool(pool_or_mint.to_string()))
+                };
             }
-        } else {
-            Err(format!("Invalid pool_id or no data found for pool_id: {}", pool_id).into())
         }
+
+        let pools = self.get_pools_by_mint(pool_or_mint).await?;
+        if pools.is_empty() {
+            return Err(RaydiumError::PoolNotFound(pool_or_mint.to_string()));
+        }
+        rank_wsol_pools(pools)
+            .into_iter()
+            .next()
+            .ok_or_else(|| RaydiumError::NoWsolPool(pool_or_mint.to_string()))
     }
 }
@@ -0,0 +1,478 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::utils::error::SwapError;
+
+pub const AMM_V4_PROGRAM: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+pub const CPMM_PROGRAM: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+pub const WSOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+const AMM_V4_STATE_LEN: usize = 752;
+const SERUM_MARKET_LEN: usize = 388;
+const CPMM_STATE_MIN_LEN: usize = 381;
+const CPMM_CONFIG_MIN_LEN: usize = 20;
+
+const AMM_V4_SWAP_BASE_IN_TAG: u8 = 9;
+const CPMM_SWAP_BASE_INPUT_DISCRIMINATOR: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+const CPMM_FEE_DENOMINATOR: u64 = 1_000_000;
+const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+const CPMM_AUTHORITY_SEED: &[u8] = b"vault_and_lp_mint_auth_seed";
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Buy spends SOL for the pool token, sell spends the token for SOL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    Buy,
+    Sell,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+/// The subset of the AMM v4 `AmmInfo` account needed to quote and swap.
+#[derive(Debug, Clone)]
+pub struct AmmV4Pool {
+    pub id: Pubkey,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub open_orders: Pubkey,
+    pub market: Pubkey,
+    pub market_program: Pubkey,
+    pub target_orders: Pubkey,
+}
+
+impl AmmV4Pool {
+    pub fn decode(id: Pubkey, data: &[u8]) -> Result<Self, SwapError> {
+        if data.len() != AMM_V4_STATE_LEN {
+            return Err(SwapError::InvalidAccountData("AMM v4 pool"));
+        }
+        Ok(Self {
+            id,
+            swap_fee_numerator: read_u64(data, 176),
+            swap_fee_denominator: read_u64(data, 184),
+            need_take_pnl_coin: read_u64(data, 192),
+            need_take_pnl_pc: read_u64(data, 200),
+            coin_vault: read_pubkey(data, 336),
+            pc_vault: read_pubkey(data, 368),
+            coin_mint: read_pubkey(data, 400),
+            pc_mint: read_pubkey(data, 432),
+            open_orders: read_pubkey(data, 496),
+            market: read_pubkey(data, 528),
+            market_program: read_pubkey(data, 560),
+            target_orders: read_pubkey(data, 592),
+        })
+    }
+
+    pub fn authority() -> Pubkey {
+        Pubkey::find_program_address(&[AMM_AUTHORITY_SEED], &AMM_V4_PROGRAM).0
+    }
+
+    /// Tradable reserves: vault balances minus the PnL the pool still owes to its owner.
+    pub fn reserves(&self, coin_vault_amount: u64, pc_vault_amount: u64) -> (u64, u64) {
+        (
+            coin_vault_amount.saturating_sub(self.need_take_pnl_coin),
+            pc_vault_amount.saturating_sub(self.need_take_pnl_pc),
+        )
+    }
+}
+
+/// OpenBook/Serum market accounts the AMM v4 swap instruction still expects.
+#[derive(Debug, Clone)]
+pub struct SerumMarket {
+    pub id: Pubkey,
+    pub program_id: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_queue: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub vault_signer: Pubkey,
+}
+
+impl SerumMarket {
+    pub fn decode(id: Pubkey, program_id: Pubkey, data: &[u8]) -> Result<Self, SwapError> {
+        // Market accounts are wrapped in 5 bytes of "serum" head and 7 bytes of tail padding.
+        if data.len() < SERUM_MARKET_LEN {
+            return Err(SwapError::InvalidAccountData("market"));
+        }
+        let nonce = read_u64(data, 45);
+        let vault_signer = Pubkey::create_program_address(&[id.as_ref(), &nonce.to_le_bytes()], &program_id)
+            .map_err(|_| SwapError::InvalidAccountData("market"))?;
+        Ok(Self {
+            id,
+            program_id,
+            coin_vault: read_pubkey(data, 117),
+            pc_vault: read_pubkey(data, 165),
+            event_queue: read_pubkey(data, 253),
+            bids: read_pubkey(data, 285),
+            asks: read_pubkey(data, 317),
+            vault_signer,
+        })
+    }
+}
+
+/// The subset of the CPMM `PoolState` account needed to quote and swap.
+#[derive(Debug, Clone)]
+pub struct CpmmPool {
+    pub id: Pubkey,
+    pub amm_config: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub token_0_program: Pubkey,
+    pub token_1_program: Pubkey,
+    pub observation_key: Pubkey,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+}
+
+impl CpmmPool {
+    pub fn decode(id: Pubkey, data: &[u8]) -> Result<Self, SwapError> {
+        // Anchor accounts start with an 8 byte discriminator.
+        if data.len() < CPMM_STATE_MIN_LEN {
+            return Err(SwapError::InvalidAccountData("CPMM pool"));
+        }
+        Ok(Self {
+            id,
+            amm_config: read_pubkey(data, 8),
+            token_0_vault: read_pubkey(data, 72),
+            token_1_vault: read_pubkey(data, 104),
+            token_0_mint: read_pubkey(data, 168),
+            token_1_mint: read_pubkey(data, 200),
+            token_0_program: read_pubkey(data, 232),
+            token_1_program: read_pubkey(data, 264),
+            observation_key: read_pubkey(data, 296),
+            protocol_fees_token_0: read_u64(data, 341),
+            protocol_fees_token_1: read_u64(data, 349),
+            fund_fees_token_0: read_u64(data, 357),
+            fund_fees_token_1: read_u64(data, 365),
+        })
+    }
+
+    pub fn authority() -> Pubkey {
+        Pubkey::find_program_address(&[CPMM_AUTHORITY_SEED], &CPMM_PROGRAM).0
+    }
+
+    /// Tradable reserves: vault balances minus the protocol and fund fees not yet collected.
+    pub fn reserves(&self, token_0_vault_amount: u64, token_1_vault_amount: u64) -> (u64, u64) {
+        (
+            token_0_vault_amount
+                .saturating_sub(self.protocol_fees_token_0)
+                .saturating_sub(self.fund_fees_token_0),
+            token_1_vault_amount
+                .saturating_sub(self.protocol_fees_token_1)
+                .saturating_sub(self.fund_fees_token_1),
+        )
+    }
+}
+
+/// Trade fee rate of a CPMM `AmmConfig` account, in millionths.
+pub fn decode_cpmm_trade_fee_rate(data: &[u8]) -> Result<u64, SwapError> {
+    if data.len() < CPMM_CONFIG_MIN_LEN {
+        return Err(SwapError::InvalidAccountData("CPMM config"));
+    }
+    Ok(read_u64(data, 12))
+}
+
+/// Reads `decimals` from a mint account; the offset is shared by SPL Token and Token-2022.
+pub fn decode_mint_decimals(data: &[u8]) -> Result<u8, SwapError> {
+    data.get(44).copied().ok_or(SwapError::InvalidAccountData("mint"))
+}
+
+/// Reads `supply` from a mint account; the offset is shared by SPL Token and Token-2022.
+pub fn decode_mint_supply(data: &[u8]) -> Result<u64, SwapError> {
+    if data.len() < 44 {
+        return Err(SwapError::InvalidAccountData("mint"));
+    }
+    Ok(read_u64(data, 36))
+}
+
+/// Reads the optional `freeze_authority` of a mint account.
+pub fn decode_mint_freeze_authority(data: &[u8]) -> Result<Option<Pubkey>, SwapError> {
+    if data.len() < 82 {
+        return Err(SwapError::InvalidAccountData("mint"));
+    }
+    let tag = u32::from_le_bytes(data[46..50].try_into().unwrap());
+    Ok((tag == 1).then(|| read_pubkey(data, 50)))
+}
+
+/// Reads `amount` from a token account; the offset is shared by SPL Token and Token-2022.
+pub fn decode_token_account_amount(data: &[u8]) -> Result<u64, SwapError> {
+    if data.len() < 72 {
+        return Err(SwapError::InvalidAccountData("token account"));
+    }
+    Ok(read_u64(data, 64))
+}
+
+/// Reserves of the swap's input and output side plus the pool's trade fee.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolReserves {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    /// Trade fee charged by the pool, in the input token.
+    pub fee: u64,
+    pub amount_out: u64,
+    pub min_amount_out: u64,
+}
+
+/// Constant product quote for an exact input amount; the fee is rounded up like on chain.
+pub fn quote_exact_in(reserves: &PoolReserves, amount_in: u64, slippage_bps: u64) -> Result<SwapQuote, SwapError> {
+    if reserves.reserve_in == 0 || reserves.reserve_out == 0 || reserves.fee_denominator == 0 {
+        return Err(SwapError::InsufficientLiquidity);
+    }
+
+    let amount_in_wide = amount_in as u128;
+    let fee = (amount_in_wide * reserves.fee_numerator as u128).div_ceil(reserves.fee_denominator as u128);
+    let amount_in_after_fee = amount_in_wide.saturating_sub(fee);
+    let amount_out = reserves.reserve_out as u128 * amount_in_after_fee
+        / (reserves.reserve_in as u128 + amount_in_after_fee);
+    if amount_out == 0 {
+        return Err(SwapError::ZeroOutput);
+    }
+
+    Ok(SwapQuote {
+        amount_in,
+        fee: fee as u64,
+        amount_out: amount_out as u64,
+        min_amount_out: min_amount_out(amount_out as u64, slippage_bps),
+    })
+}
+
+/// Reserves once `quote` has been executed against them, oriented for the opposite swap.
+/// The pool keeps the trade fee, so the full input is added to its side.
+pub fn reverse_after_swap(reserves: &PoolReserves, quote: &SwapQuote) -> PoolReserves {
+    PoolReserves {
+        reserve_in: reserves.reserve_out.saturating_sub(quote.amount_out),
+        reserve_out: reserves.reserve_in.saturating_add(quote.amount_in),
+        fee_numerator: reserves.fee_numerator,
+        fee_denominator: reserves.fee_denominator,
+    }
+}
+
+pub fn min_amount_out(amount_out: u64, slippage_bps: u64) -> u64 {
+    let slippage_bps = slippage_bps.min(BPS_DENOMINATOR);
+    (amount_out as u128 * (BPS_DENOMINATOR - slippage_bps) as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+pub fn amm_v4_reserves(pool: &AmmV4Pool, coin_vault_amount: u64, pc_vault_amount: u64, input_is_coin: bool) -> PoolReserves {
+    let (coin, pc) = pool.reserves(coin_vault_amount, pc_vault_amount);
+    let (reserve_in, reserve_out) = if input_is_coin { (coin, pc) } else { (pc, coin) };
+    PoolReserves {
+        reserve_in,
+        reserve_out,
+        fee_numerator: pool.swap_fee_numerator,
+        fee_denominator: pool.swap_fee_denominator,
+    }
+}
+
+pub fn cpmm_reserves(
+    pool: &CpmmPool,
+    token_0_vault_amount: u64,
+    token_1_vault_amount: u64,
+    trade_fee_rate: u64,
+    input_is_token_0: bool,
+) -> PoolReserves {
+    let (token_0, token_1) = pool.reserves(token_0_vault_amount, token_1_vault_amount);
+    let (reserve_in, reserve_out) = if input_is_token_0 { (token_0, token_1) } else { (token_1, token_0) };
+    PoolReserves {
+        reserve_in,
+        reserve_out,
+        fee_numerator: trade_fee_rate,
+        fee_denominator: CPMM_FEE_DENOMINATOR,
+    }
+}
+
+/// AMM v4 `swap_base_in`; the program infers the direction from the source account's mint.
+pub fn amm_v4_swap_base_in(
+    pool: &AmmV4Pool,
+    market: &SerumMarket,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(17);
+    data.push(AMM_V4_SWAP_BASE_IN_TAG);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+    Instruction {
+        program_id: AMM_V4_PROGRAM,
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(pool.id, false),
+            AccountMeta::new_readonly(AmmV4Pool::authority(), false),
+            AccountMeta::new(pool.open_orders, false),
+            AccountMeta::new(pool.target_orders, false),
+            AccountMeta::new(pool.coin_vault, false),
+            AccountMeta::new(pool.pc_vault, false),
+            AccountMeta::new_readonly(market.program_id, false),
+            AccountMeta::new(market.id, false),
+            AccountMeta::new(market.bids, false),
+            AccountMeta::new(market.asks, false),
+            AccountMeta::new(market.event_queue, false),
+            AccountMeta::new(market.coin_vault, false),
+            AccountMeta::new(market.pc_vault, false),
+            AccountMeta::new_readonly(market.vault_signer, false),
+            AccountMeta::new(*user_source, false),
+            AccountMeta::new(*user_destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// CPMM `swap_base_input`.
+pub fn cpmm_swap_base_input(
+    pool: &CpmmPool,
+    payer: &Pubkey,
+    input_token_account: &Pubkey,
+    output_token_account: &Pubkey,
+    input_is_token_0: bool,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(24);
+    data.extend_from_slice(&CPMM_SWAP_BASE_INPUT_DISCRIMINATOR);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+    let (input_vault, output_vault, input_program, output_program, input_mint, output_mint) = if input_is_token_0 {
+        (pool.token_0_vault, pool.token_1_vault, pool.token_0_program, pool.token_1_program, pool.token_0_mint, pool.token_1_mint)
+    } else {
+        (pool.token_1_vault, pool.token_0_vault, pool.token_1_program, pool.token_0_program, pool.token_1_mint, pool.token_0_mint)
+    };
+
+    Instruction {
+        program_id: CPMM_PROGRAM,
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new_readonly(CpmmPool::authority(), false),
+            AccountMeta::new_readonly(pool.amm_config, false),
+            AccountMeta::new(pool.id, false),
+            AccountMeta::new(*input_token_account, false),
+            AccountMeta::new(*output_token_account, false),
+            AccountMeta::new(input_vault, false),
+            AccountMeta::new(output_vault, false),
+            AccountMeta::new_readonly(input_program, false),
+            AccountMeta::new_readonly(output_program, false),
+            AccountMeta::new_readonly(input_mint, false),
+            AccountMeta::new_readonly(output_mint, false),
+            AccountMeta::new(pool.observation_key, false),
+        ],
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_account_decoder::UiAccount;
+    use solana_sdk::account::Account;
+
+    /// Pool accounts in `getAccountInfo` form, laid out like the on-chain programs write them.
+    fn fixture(json: &str) -> Account {
+        serde_json::from_str::<UiAccount>(json).unwrap().decode::<Account>().unwrap()
+    }
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    fn reserves() -> PoolReserves {
+        PoolReserves {
+            reserve_in: 1_000_000_000,
+            reserve_out: 1_000_000_000_000,
+            fee_numerator: 25,
+            fee_denominator: 10_000,
+        }
+    }
+
+    #[test]
+    fn decodes_amm_v4_pool() {
+        let account = fixture(include_str!("../../fixtures/raydium/amm_v4_pool.json"));
+        assert_eq!(account.owner, AMM_V4_PROGRAM);
+
+        let pool = AmmV4Pool::decode(key(99), &account.data).unwrap();
+        assert_eq!((pool.swap_fee_numerator, pool.swap_fee_denominator), (25, 10_000));
+        assert_eq!((pool.coin_vault, pool.pc_vault), (key(1), key(2)));
+        assert_eq!((pool.coin_mint, pool.pc_mint), (key(3), WSOL_MINT));
+        assert_eq!((pool.open_orders, pool.market, pool.market_program, pool.target_orders), (key(5), key(6), key(7), key(8)));
+        assert_eq!(pool.reserves(1_000_000, 5_000_000), (999_000, 4_998_000));
+    }
+
+    #[test]
+    fn rejects_truncated_amm_v4_pool() {
+        let account = fixture(include_str!("../../fixtures/raydium/amm_v4_pool.json"));
+        assert!(matches!(
+            AmmV4Pool::decode(key(99), &account.data[..700]),
+            Err(SwapError::InvalidAccountData("AMM v4 pool"))
+        ));
+    }
+
+    #[test]
+    fn decodes_cpmm_pool_and_config() {
+        let account = fixture(include_str!("../../fixtures/raydium/cpmm_pool.json"));
+        assert_eq!(account.owner, CPMM_PROGRAM);
+
+        let pool = CpmmPool::decode(key(98), &account.data).unwrap();
+        assert_eq!(pool.amm_config, key(11));
+        assert_eq!((pool.token_0_vault, pool.token_1_vault), (key(12), key(13)));
+        assert_eq!((pool.token_0_mint, pool.token_1_mint), (WSOL_MINT, key(15)));
+        assert_eq!((pool.token_0_program, pool.token_1_program, pool.observation_key), (key(16), key(17), key(18)));
+        assert_eq!(pool.reserves(10_000, 20_000), (9_650, 19_540));
+
+        let config = fixture(include_str!("../../fixtures/raydium/cpmm_config.json"));
+        assert_eq!(decode_cpmm_trade_fee_rate(&config.data).unwrap(), 2_500);
+        assert!(decode_cpmm_trade_fee_rate(&config.data[..10]).is_err());
+    }
+
+    #[test]
+    fn quotes_exact_in_with_fee_rounded_up() {
+        let quote = quote_exact_in(&reserves(), 10_000_000, 100).unwrap();
+        assert_eq!(quote.fee, 25_000);
+        assert_eq!(quote.amount_out, 9_876_482_091);
+        assert_eq!(quote.min_amount_out, 9_777_717_270);
+    }
+
+    #[test]
+    fn quote_rejects_empty_pool_and_dust() {
+        let empty = PoolReserves { reserve_in: 0, ..reserves() };
+        assert!(matches!(quote_exact_in(&empty, 10, 0), Err(SwapError::InsufficientLiquidity)));
+
+        let deep_in = PoolReserves { reserve_in: 1_000_000_000_000, reserve_out: 1_000, ..reserves() };
+        assert!(matches!(quote_exact_in(&deep_in, 10, 0), Err(SwapError::ZeroOutput)));
+    }
+
+    #[test]
+    fn reverse_after_swap_keeps_the_fee_in_the_pool() {
+        let quote = quote_exact_in(&reserves(), 10_000_000, 0).unwrap();
+        let reversed = reverse_after_swap(&reserves(), &quote);
+        assert_eq!(reversed.reserve_in, 1_000_000_000_000 - 9_876_482_091);
+        assert_eq!(reversed.reserve_out, 1_010_000_000);
+    }
+}
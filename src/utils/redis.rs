@@ -1,13 +1,29 @@
 use redis::AsyncCommands;
-use redis::Client;
+use redis::aio::{ConnectionManager, MultiplexedConnection};
+use redis::{Client, RedisError};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-#[derive(Debug, Serialize, Deserialize)]
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::core::config::RedisSettings;
+
+static SHARED_CONNECTION: OnceCell<ConnectionManager> = OnceCell::const_new();
+
+/// Process-wide connection to `REDIS_URL`. It is multiplexed and reconnects on its own, so
+/// callers clone it instead of locking a shared `RedisClient`.
+pub async fn shared_redis_connection() -> Result<ConnectionManager, RedisError> {
+    SHARED_CONNECTION
+        .get_or_try_init(|| async { ConnectionManager::new(Client::open(RedisSettings::new_redis().redis_url)?).await })
+        .await
+        .cloned()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedisChatMessage {
-    role: String,
-    content: String,
+    pub role: String,
+    pub content: String,
 }
 
 pub struct RedisChatHistory {
@@ -21,31 +37,54 @@ impl RedisChatHistory {
         Ok(Self { client, session_id })
     }
 
+    /// Messages oldest first. Histories written before they became Redis lists are a JSON
+    /// array in a string key and are still read.
     pub async fn base_messages(&self) -> Result<Vec<RedisChatMessage>, Box<dyn Error>> {
         let mut conn = self.client.get_multiplexed_async_connection().await?;
-        let messages: Option<String> = conn.get(&self.session_id).await?;
-
-        if let Some(data) = messages {
-            let decoded_messages: Vec<RedisChatMessage> = serde_json::from_str(&data)?;
-            Ok(decoded_messages)
-        } else {
-            Ok(vec![])
+        if self.is_legacy(&mut conn).await? {
+            let data: String = conn.get(&self.session_id).await?;
+            return Ok(serde_json::from_str(&data)?);
         }
+
+        let messages: Vec<String> = conn.lrange(&self.session_id, 0, -1).await?;
+        messages.iter().map(|message| Ok(serde_json::from_str(message)?)).collect()
     }
 
+    /// Appends with `RPUSH`, so concurrent turns of one chat cannot drop each other's messages.
     pub async fn add_message(&self, role: &str, content: &str) -> Result<(), Box<dyn Error>> {
         let mut conn = self.client.get_multiplexed_async_connection().await?;
-        let mut messages = self.base_messages().await?;
-        messages.push(RedisChatMessage {
+        if self.is_legacy(&mut conn).await? {
+            self.migrate_legacy(&mut conn).await?;
+        }
+
+        let message = RedisChatMessage {
             role: role.to_string(),
             content: content.to_string(),
-        });
-        let serialized = serde_json::to_string(&messages)?;
-        conn.set::<_, _, ()>(&self.session_id, serialized).await?;
+        };
+        conn.rpush::<_, _, ()>(&self.session_id, serde_json::to_string(&message)?).await?;
+        Ok(())
+    }
+
+    async fn is_legacy(&self, conn: &mut MultiplexedConnection) -> Result<bool, Box<dyn Error>> {
+        let key_type: String = redis::cmd("TYPE").arg(&self.session_id).query_async(conn).await?;
+        Ok(key_type == "string")
+    }
+
+    /// Rewrites a JSON array history as a list in one transaction.
+    async fn migrate_legacy(&self, conn: &mut MultiplexedConnection) -> Result<(), Box<dyn Error>> {
+        let data: String = conn.get(&self.session_id).await?;
+        let messages: Vec<RedisChatMessage> = serde_json::from_str(&data)?;
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&self.session_id).ignore();
+        for message in &messages {
+            pipe.rpush(&self.session_id, serde_json::to_string(message)?).ignore();
+        }
+        pipe.query_async::<()>(conn).await?;
         Ok(())
     }
 }
 
+#[derive(Clone)]
 pub struct RedisClient {
     client: Arc<Mutex<Client>>,
 }
@@ -58,9 +97,68 @@ impl RedisClient {
         })
     }
 
-    pub async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, Box<dyn Error>> {
+    pub async fn get_connection(&self) -> Result<MultiplexedConnection, Box<dyn Error>> {
+        Ok(self.connection().await?)
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, RedisError> {
         let client = self.client.lock().await;
-        let conn = client.get_multiplexed_async_connection().await?;
-        Ok(conn)
+        client.get_multiplexed_async_connection().await
+    }
+
+    /// JSON value stored under `key`; an error if the key is missing.
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<T, Box<dyn Error>> {
+        let mut conn = self.get_connection().await?;
+        let value: Option<String> = conn.get(key).await?;
+        let value = value.ok_or_else(|| format!("Key {} not found", key))?;
+        Ok(serde_json::from_str(&value)?)
     }
-}
\ No newline at end of file
+
+    /// Stores `value` as JSON under `key`, without expiry.
+    pub async fn set_json<T: Serialize>(&self, key: &str, value: &T) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_connection().await?;
+        conn.set::<_, _, ()>(key, serde_json::to_string(value)?).await?;
+        Ok(())
+    }
+
+    /// Takes the lease `key` for `owner` unless someone else holds it; it expires after `ttl_secs`.
+    pub async fn try_acquire_lease(&self, key: &str, owner: &str, ttl_secs: u64) -> Result<bool, RedisError> {
+        let mut conn = self.connection().await?;
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(owner)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await?;
+        Ok(acquired.is_some())
+    }
+
+    /// Pushes the expiry of the lease `key` to `ttl_secs` from now if `owner` still holds it.
+    pub async fn renew_lease(&self, key: &str, owner: &str, ttl_secs: u64) -> Result<bool, RedisError> {
+        let mut conn = self.connection().await?;
+        let renewed = redis::Script::new(
+            r#"if redis.call("GET", KEYS[1]) == ARGV[1] then return redis.call("EXPIRE", KEYS[1], ARGV[2]) else return 0 end"#,
+        )
+        .key(key)
+        .arg(owner)
+        .arg(ttl_secs)
+        .invoke_async::<i32>(&mut conn)
+        .await?;
+        Ok(renewed == 1)
+    }
+
+    /// Gives the lease `key` back if `owner` still holds it.
+    pub async fn release_lease(&self, key: &str, owner: &str) -> Result<(), RedisError> {
+        let mut conn = self.connection().await?;
+        redis::Script::new(
+            r#"if redis.call("GET", KEYS[1]) == ARGV[1] then return redis.call("DEL", KEYS[1]) else return 0 end"#,
+        )
+        .key(key)
+        .arg(owner)
+        .invoke_async::<i32>(&mut conn)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,678 @@
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer as EvmSigner};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Signature as EvmSignature, U256};
+use ethers::utils::parse_ether;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    compute_budget,
+    message::Message,
+    native_token::sol_to_lamports,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::Transaction,
+};
+use std::fs::DirBuilder;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::OnceCell;
+use tracing::{error, warn};
+
+use crate::core::config::{SMCSettings, SignerSettings, SolanaSettings};
+use crate::utils::error::{KeyLoaderError, SignerError};
+use crate::utils::key_loader::{load_evm_wallet, load_solana_keypair, SecretString};
+
+static SIGNER: OnceCell<Arc<dyn TransactionSigner>> = OnceCell::const_new();
+
+/// Signs agent transactions, either in process or by handing them to a separate signer.
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn solana_pubkey(&self) -> Option<Pubkey>;
+
+    fn evm_address(&self) -> Option<Address>;
+
+    /// Signature of the serialized message by the Solana key.
+    async fn sign_solana_message(&self, message: &Message) -> Result<Signature, SignerError>;
+
+    /// Signature of the transaction's sighash by the EVM key.
+    async fn sign_evm_transaction(&self, transaction: &TypedTransaction) -> Result<EvmSignature, SignerError>;
+}
+
+/// What a transaction does, as far as a signing policy is concerned.
+#[derive(Debug, Clone)]
+pub enum TransactionSummary {
+    Solana {
+        programs: Vec<Pubkey>,
+        /// Lamports the signer transfers or funds new accounts with.
+        lamports_out: u64,
+    },
+    Evm {
+        to: Option<Address>,
+        value: U256,
+    },
+}
+
+impl TransactionSummary {
+    pub fn from_solana_message(message: &Message, signer: &Pubkey) -> Self {
+        let mut programs: Vec<Pubkey> = Vec::new();
+        let mut lamports_out = 0u64;
+        for instruction in &message.instructions {
+            let program = message.account_keys[instruction.program_id_index as usize];
+            if !programs.contains(&program) {
+                programs.push(program);
+            }
+            if program != system_program::id() {
+                continue;
+            }
+            let account = |position: usize| instruction.accounts.get(position).map(|index| message.account_keys[*index as usize]);
+            // The account whose signature authorizes the lamports leaving.
+            let (authority, lamports) = match bincode::deserialize::<SystemInstruction>(&instruction.data) {
+                Ok(SystemInstruction::Transfer { lamports })
+                | Ok(SystemInstruction::CreateAccount { lamports, .. })
+                | Ok(SystemInstruction::CreateAccountWithSeed { lamports, .. }) => (account(0), lamports),
+                // Funded from an address derived from the base account, which signs.
+                Ok(SystemInstruction::TransferWithSeed { lamports, .. }) => (account(1), lamports),
+                Ok(SystemInstruction::WithdrawNonceAccount(lamports)) => (account(4), lamports),
+                _ => continue,
+            };
+            if authority.as_ref() == Some(signer) {
+                lamports_out = lamports_out.saturating_add(lamports);
+            }
+        }
+        Self::Solana { programs, lamports_out }
+    }
+
+    pub fn from_evm_transaction(transaction: &TypedTransaction) -> Self {
+        Self::Evm {
+            to: transaction.to_addr().copied(),
+            value: transaction.value().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Hook that may veto a transaction before it is signed.
+pub trait SigningPolicy: Send + Sync {
+    /// `Err` carries the reason the transaction is refused.
+    fn check(&self, summary: &TransactionSummary) -> Result<(), String>;
+}
+
+/// Vetoes transactions by invoked program and by amount.
+#[derive(Debug, Clone, Default)]
+pub struct LimitPolicy {
+    /// Programs a transaction may invoke besides the system and compute budget programs; any when empty.
+    pub allowed_programs: Vec<Pubkey>,
+    pub blocked_programs: Vec<Pubkey>,
+    pub max_lamports: Option<u64>,
+    pub max_wei: Option<U256>,
+}
+
+impl LimitPolicy {
+    pub fn from_settings(settings: &SignerSettings) -> Self {
+        let parse_programs = |programs: &[String]| -> Vec<Pubkey> {
+            programs
+                .iter()
+                .filter_map(|program| match Pubkey::from_str(program) {
+                    Ok(pubkey) => Some(pubkey),
+                    Err(_) => {
+                        warn!("Ignoring invalid program id {} in signer policy", program);
+                        None
+                    }
+                })
+                .collect()
+        };
+        Self {
+            allowed_programs: parse_programs(&settings.allowed_programs),
+            blocked_programs: parse_programs(&settings.blocked_programs),
+            max_lamports: settings.max_sol_per_transaction.map(sol_to_lamports),
+            max_wei: settings.max_eth_per_transaction.and_then(|eth| parse_ether(eth).ok()),
+        }
+    }
+}
+
+impl SigningPolicy for LimitPolicy {
+    fn check(&self, summary: &TransactionSummary) -> Result<(), String> {
+        match summary {
+            TransactionSummary::Solana { programs, lamports_out } => {
+                for program in programs {
+                    if self.blocked_programs.contains(program) {
+                        return Err(format!("program {} is blocked", program));
+                    }
+                    let always_allowed = *program == system_program::id() || *program == compute_budget::id();
+                    if !self.allowed_programs.is_empty() && !always_allowed && !self.allowed_programs.contains(program) {
+                        return Err(format!("program {} is not allowed", program));
+                    }
+                }
+                match self.max_lamports {
+                    Some(max) if *lamports_out > max => {
+                        Err(format!("transfers {} lamports, more than the limit of {}", lamports_out, max))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            TransactionSummary::Evm { value, .. } => match self.max_wei {
+                Some(max) if *value > max => Err(format!("sends {} wei, more than the limit of {}", value, max)),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// A key read on first use, so a broken key of one chain does not stop signing on the other.
+struct LazyKey<K> {
+    key: OnceLock<Result<Option<K>, String>>,
+    load: Box<dyn Fn() -> Result<Option<K>, KeyLoaderError> + Send + Sync>,
+}
+
+impl<K> LazyKey<K> {
+    fn loaded(key: Option<K>) -> Self {
+        Self { key: OnceLock::from(Ok(key)), load: Box::new(|| Ok(None)) }
+    }
+
+    /// No key when `source` is empty.
+    fn from_source(
+        source: &SecretString,
+        password: Option<&SecretString>,
+        load: fn(&SecretString, Option<&SecretString>) -> Result<K, KeyLoaderError>,
+    ) -> Self {
+        let source = source.clone();
+        let password = password.cloned();
+        Self {
+            key: OnceLock::new(),
+            load: Box::new(move || if source.is_empty() { Ok(None) } else { load(&source, password.as_ref()).map(Some) }),
+        }
+    }
+
+    fn get(&self) -> Result<Option<&K>, SignerError> {
+        self.key
+            .get_or_init(|| (self.load)().map_err(|e| e.to_string()))
+            .as_ref()
+            .map(Option::as_ref)
+            .map_err(|e| SignerError::KeyLoader(e.clone()))
+    }
+}
+
+/// Signs with keys held in this process.
+pub struct LocalSigner {
+    solana: LazyKey<Keypair>,
+    evm: LazyKey<LocalWallet>,
+    policy: Arc<dyn SigningPolicy>,
+}
+
+impl LocalSigner {
+    pub fn new_local_signer(solana: Option<Keypair>, evm: Option<LocalWallet>, policy: Arc<dyn SigningPolicy>) -> Self {
+        Self { solana: LazyKey::loaded(solana), evm: LazyKey::loaded(evm), policy }
+    }
+
+    /// Signer for the keys configured in `AGENT_KEYPAIR` and `OWNER_PRIVATE_KEY`; either may be unset.
+    /// Keys are read when first needed.
+    pub fn from_settings(solana: &SolanaSettings, smc: &SMCSettings, policy: Arc<dyn SigningPolicy>) -> Self {
+        Self {
+            solana: LazyKey::from_source(&solana.agent_keypair, solana.agent_keypair_password.as_ref(), load_solana_keypair),
+            evm: LazyKey::from_source(&smc.owner_private_key, smc.owner_keystore_password.as_ref(), load_evm_wallet),
+            policy,
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn solana_pubkey(&self) -> Option<Pubkey> {
+        let keypair = self.solana.get().inspect_err(|e| error!("Solana key unavailable: {}", e)).ok().flatten();
+        keypair.map(|keypair| keypair.pubkey())
+    }
+
+    fn evm_address(&self) -> Option<Address> {
+        let wallet = self.evm.get().inspect_err(|e| error!("EVM key unavailable: {}", e)).ok().flatten();
+        wallet.map(|wallet| wallet.address())
+    }
+
+    async fn sign_solana_message(&self, message: &Message) -> Result<Signature, SignerError> {
+        let keypair = self.solana.get()?.ok_or(SignerError::NoSolanaKey)?;
+        self.policy
+            .check(&TransactionSummary::from_solana_message(message, &keypair.pubkey()))
+            .map_err(SignerError::PolicyViolation)?;
+        Ok(keypair.sign_message(&message.serialize()))
+    }
+
+    async fn sign_evm_transaction(&self, transaction: &TypedTransaction) -> Result<EvmSignature, SignerError> {
+        let wallet = self.evm.get()?.ok_or(SignerError::NoEvmKey)?;
+        self.policy
+            .check(&TransactionSummary::from_evm_transaction(transaction))
+            .map_err(SignerError::PolicyViolation)?;
+        wallet.sign_transaction(transaction).await.map_err(|e| SignerError::Evm(e.to_string()))
+    }
+}
+
+/// Where a remote signer listens.
+#[derive(Debug, Clone)]
+pub enum SignerEndpoint {
+    Http(String),
+    Unix(PathBuf),
+}
+
+impl SignerEndpoint {
+    /// `unix:/path` for a Unix socket, anything else is an HTTP URL.
+    pub fn parse(url: &str) -> Self {
+        match url.strip_prefix("unix:") {
+            Some(path) => Self::Unix(PathBuf::from(path)),
+            None => Self::Http(url.to_string()),
+        }
+    }
+}
+
+/// Request of the signer protocol: one JSON object per HTTP POST body or per line on the Unix socket.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    Identity,
+    /// `message` is the hex of the serialized Solana message.
+    SignSolana { message: String },
+    SignEvm { transaction: TypedTransaction },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SignerResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solana_pubkey: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm_address: Option<Address>,
+    /// Base58 for Solana, hex for EVM signatures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether an HTTP signer request carries `Bearer <token>`; the token must be configured.
+pub fn is_authorized(token: &SecretString, authorization: Option<&str>) -> bool {
+    let Some(presented) = authorization.and_then(|header| header.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let expected = token.expose().trim().as_bytes();
+    // Compared in full so the time taken does not reveal how much of the token matched.
+    !expected.is_empty()
+        && presented.len() == expected.len()
+        && presented.bytes().zip(expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Signs by delegating to a signer process; signatures coming back are verified before use.
+pub struct RemoteSigner {
+    endpoint: SignerEndpoint,
+    timeout: Duration,
+    http: reqwest::Client,
+    /// Bearer token of HTTP endpoints; Unix sockets rely on file permissions.
+    auth_token: SecretString,
+    solana_pubkey: Option<Pubkey>,
+    evm_address: Option<Address>,
+    policy: Arc<dyn SigningPolicy>,
+}
+
+impl RemoteSigner {
+    /// Connects and asks the signer which keys it holds. HTTP endpoints need `auth_token`.
+    pub async fn connect(
+        endpoint: SignerEndpoint,
+        timeout: Duration,
+        auth_token: SecretString,
+        policy: Arc<dyn SigningPolicy>,
+    ) -> Result<Self, SignerError> {
+        if matches!(endpoint, SignerEndpoint::Http(_)) && auth_token.is_empty() {
+            return Err(SignerError::MissingAuthToken);
+        }
+        let mut signer = Self {
+            endpoint,
+            timeout,
+            http: reqwest::Client::builder().timeout(timeout).build()?,
+            auth_token,
+            solana_pubkey: None,
+            evm_address: None,
+            policy,
+        };
+        let identity = signer.request(&SignerRequest::Identity).await?;
+        signer.solana_pubkey = identity
+            .solana_pubkey
+            .map(|pubkey| Pubkey::from_str(&pubkey))
+            .transpose()
+            .map_err(|e| SignerError::Remote(e.to_string()))?;
+        signer.evm_address = identity.evm_address;
+        Ok(signer)
+    }
+
+    async fn request(&self, request: &SignerRequest) -> Result<SignerResponse, SignerError> {
+        let exchange = async {
+            match &self.endpoint {
+                SignerEndpoint::Http(url) => {
+                    let response = self.http.post(url).bearer_auth(self.auth_token.expose().trim()).json(request).send().await?;
+                    Ok::<_, SignerError>(response.error_for_status()?.json::<SignerResponse>().await?)
+                }
+                SignerEndpoint::Unix(path) => unix_request(path, request).await,
+            }
+        };
+        let response: SignerResponse = tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| SignerError::Timeout)??;
+        match response.error {
+            Some(error) => Err(SignerError::Remote(error)),
+            None => Ok(response),
+        }
+    }
+}
+
+async fn unix_request(path: &Path, request: &SignerRequest) -> Result<SignerResponse, SignerError> {
+    let mut stream = UnixStream::connect(path).await?;
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    stream.write_all(&line).await?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).await?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn solana_pubkey(&self) -> Option<Pubkey> {
+        self.solana_pubkey
+    }
+
+    fn evm_address(&self) -> Option<Address> {
+        self.evm_address
+    }
+
+    async fn sign_solana_message(&self, message: &Message) -> Result<Signature, SignerError> {
+        let pubkey = self.solana_pubkey.ok_or(SignerError::NoSolanaKey)?;
+        self.policy
+            .check(&TransactionSummary::from_solana_message(message, &pubkey))
+            .map_err(SignerError::PolicyViolation)?;
+
+        let bytes = message.serialize();
+        let response = self.request(&SignerRequest::SignSolana { message: hex::encode(&bytes) }).await?;
+        let signature = response
+            .signature
+            .and_then(|s| Signature::from_str(&s).ok())
+            .ok_or(SignerError::InvalidSignature)?;
+        if !signature.verify(pubkey.as_ref(), &bytes) {
+            return Err(SignerError::InvalidSignature);
+        }
+        Ok(signature)
+    }
+
+    async fn sign_evm_transaction(&self, transaction: &TypedTransaction) -> Result<EvmSignature, SignerError> {
+        let address = self.evm_address.ok_or(SignerError::NoEvmKey)?;
+        self.policy
+            .check(&TransactionSummary::from_evm_transaction(transaction))
+            .map_err(SignerError::PolicyViolation)?;
+
+        let response = self.request(&SignerRequest::SignEvm { transaction: transaction.clone() }).await?;
+        let signature = response
+            .signature
+            .and_then(|s| EvmSignature::from_str(&s).ok())
+            .ok_or(SignerError::InvalidSignature)?;
+        signature.verify(transaction.sighash(), address).map_err(|_| SignerError::InvalidSignature)?;
+        Ok(signature)
+    }
+}
+
+/// Signer configured by `SIGNER_MODE`, with the `SIGNER_*` limits as its policy.
+/// Built once per process; a failed remote connection is retried on the next call.
+pub async fn signer_from_settings() -> Result<Arc<dyn TransactionSigner>, SignerError> {
+    SIGNER.get_or_try_init(build_signer).await.cloned()
+}
+
+async fn build_signer() -> Result<Arc<dyn TransactionSigner>, SignerError> {
+    let settings = SignerSettings::new_signer();
+    let policy: Arc<dyn SigningPolicy> = Arc::new(LimitPolicy::from_settings(&settings));
+    match settings.mode.as_str() {
+        "local" => Ok(Arc::new(LocalSigner::from_settings(
+            &SolanaSettings::new_solana(),
+            &SMCSettings::new_smc(),
+            policy,
+        ))),
+        "remote" => {
+            let endpoint = SignerEndpoint::parse(&settings.signer_url);
+            let timeout = Duration::from_millis(settings.signer_timeout_ms);
+            Ok(Arc::new(RemoteSigner::connect(endpoint, timeout, settings.signer_auth_token, policy).await?))
+        }
+        mode => Err(SignerError::UnknownMode(mode.to_string())),
+    }
+}
+
+/// Signs a single-signer transaction whose fee payer is the signer's Solana key.
+pub async fn sign_solana_transaction(signer: &dyn TransactionSigner, message: Message) -> Result<Transaction, SignerError> {
+    let required = message.header.num_required_signatures as usize;
+    if required != 1 {
+        return Err(SignerError::UnsupportedSigners(required));
+    }
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.signatures[0] = signer.sign_solana_message(&transaction.message).await?;
+    Ok(transaction)
+}
+
+/// Answers one protocol request with the given signer; used by signer processes.
+pub async fn handle_signer_request(signer: &dyn TransactionSigner, request: SignerRequest) -> SignerResponse {
+    let result = match request {
+        SignerRequest::Identity => Ok(SignerResponse {
+            solana_pubkey: signer.solana_pubkey().map(|pubkey| pubkey.to_string()),
+            evm_address: signer.evm_address(),
+            ..Default::default()
+        }),
+        SignerRequest::SignSolana { message } => {
+            let decoded = hex::decode(&message)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| bincode::deserialize::<Message>(&bytes).map_err(|e| e.to_string()));
+            match decoded {
+                Ok(message) => signer
+                    .sign_solana_message(&message)
+                    .await
+                    .map(|signature| SignerResponse { signature: Some(signature.to_string()), ..Default::default() })
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(format!("Invalid message: {}", e)),
+            }
+        }
+        SignerRequest::SignEvm { transaction } => signer
+            .sign_evm_transaction(&transaction)
+            .await
+            .map(|signature| SignerResponse { signature: Some(signature.to_string()), ..Default::default() })
+            .map_err(|e| e.to_string()),
+    };
+    result.unwrap_or_else(|error| SignerResponse { error: Some(error), ..Default::default() })
+}
+
+/// Binds a Unix socket at `path` that only the owner can connect to.
+///
+/// Anyone who can connect can sign, so the socket is bound in a fresh 0700 directory next to
+/// `path`, made 0600 there and only then renamed into place: it is never reachable with looser
+/// permissions, whatever the umask.
+fn bind_private_socket(path: &Path) -> std::io::Result<UnixListener> {
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "socket path has no file name"))?;
+    let staging = parent.join(format!(".{}.{}", file_name.to_string_lossy(), std::process::id()));
+    DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("socket");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&staging)?;
+    bound
+}
+
+/// Serves the signer protocol on a Unix socket, one request per line.
+pub async fn serve_unix(path: &Path, signer: Arc<dyn TransactionSigner>) -> std::io::Result<()> {
+    let listener = bind_private_socket(path)?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let signer = signer.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let response = match serde_json::from_str::<SignerRequest>(&line) {
+                    Ok(request) => handle_signer_request(signer.as_ref(), request).await,
+                    Err(e) => SignerResponse { error: Some(format!("Invalid request: {}", e)), ..Default::default() },
+                };
+                let mut out = serde_json::to_vec(&response).unwrap_or_default();
+                out.push(b'\n');
+                if let Err(e) = writer.write_all(&out).await {
+                    error!("Failed to answer signer request: {}", e);
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::TransactionRequest;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::system_instruction;
+
+    fn policy(max_sol: Option<f64>, allowed: Vec<Pubkey>, blocked: Vec<Pubkey>) -> LimitPolicy {
+        LimitPolicy {
+            allowed_programs: allowed,
+            blocked_programs: blocked,
+            max_lamports: max_sol.map(sol_to_lamports),
+            max_wei: None,
+        }
+    }
+
+    fn summary(instructions: &[Instruction], payer: &Pubkey) -> TransactionSummary {
+        TransactionSummary::from_solana_message(&Message::new(instructions, Some(payer)), payer)
+    }
+
+    fn lamports_out(summary: &TransactionSummary) -> u64 {
+        match summary {
+            TransactionSummary::Solana { lamports_out, .. } => *lamports_out,
+            TransactionSummary::Evm { .. } => panic!("not a Solana summary"),
+        }
+    }
+
+    #[test]
+    fn summary_counts_every_way_lamports_leave_the_signer() {
+        let payer = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let derived = Pubkey::create_with_seed(&payer, "seed", &owner).unwrap();
+        let nonce = Pubkey::new_unique();
+        let instructions = [
+            system_instruction::transfer(&payer, &to, 1),
+            system_instruction::create_account(&payer, &to, 10, 0, &owner),
+            system_instruction::create_account_with_seed(&payer, &derived, &payer, "seed", 100, 0, &owner),
+            system_instruction::transfer_with_seed(&derived, &payer, "seed".to_string(), &owner, &to, 1_000),
+            system_instruction::withdraw_nonce_account(&nonce, &payer, &to, 10_000),
+            // Someone else's transfer does not count against the signer.
+            system_instruction::transfer(&to, &payer, 100_000),
+        ];
+        assert_eq!(lamports_out(&summary(&instructions, &payer)), 11_111);
+    }
+
+    #[test]
+    fn limit_policy_caps_lamports() {
+        let payer = Pubkey::new_unique();
+        let transfer = summary(&[system_instruction::transfer(&payer, &Pubkey::new_unique(), sol_to_lamports(2.0))], &payer);
+
+        assert!(policy(Some(2.0), vec![], vec![]).check(&transfer).is_ok());
+        assert!(policy(Some(1.0), vec![], vec![]).check(&transfer).is_err());
+        assert!(policy(None, vec![], vec![]).check(&transfer).is_ok());
+    }
+
+    #[test]
+    fn limit_policy_filters_programs() {
+        let payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let call = Instruction::new_with_bytes(program, &[], vec![AccountMeta::new(payer, true)]);
+        let transaction = summary(&[system_instruction::transfer(&payer, &Pubkey::new_unique(), 1), call], &payer);
+
+        assert!(policy(None, vec![program], vec![]).check(&transaction).is_ok());
+        assert!(policy(None, vec![Pubkey::new_unique()], vec![]).check(&transaction).is_err());
+        assert!(policy(None, vec![], vec![program]).check(&transaction).is_err());
+        assert!(policy(None, vec![], vec![system_program::id()]).check(&transaction).is_err());
+    }
+
+    #[test]
+    fn limit_policy_caps_wei() {
+        let policy = LimitPolicy { max_wei: Some(parse_ether(1).unwrap()), ..LimitPolicy::default() };
+        let send = |eth: u64| TransactionSummary::Evm { to: None, value: parse_ether(eth).unwrap() };
+        assert!(policy.check(&send(1)).is_ok());
+        assert!(policy.check(&send(2)).is_err());
+    }
+
+    #[tokio::test]
+    async fn stub_signer_answers_identity_and_signs() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let signer = LocalSigner::new_local_signer(Some(keypair), None, Arc::new(LimitPolicy::default()));
+
+        let identity = handle_signer_request(&signer, SignerRequest::Identity).await;
+        assert_eq!(identity.solana_pubkey, Some(pubkey.to_string()));
+        assert_eq!(identity.evm_address, None);
+
+        let message = Message::new(&[system_instruction::transfer(&pubkey, &Pubkey::new_unique(), 1)], Some(&pubkey));
+        let request = SignerRequest::SignSolana { message: hex::encode(message.serialize()) };
+        let response = handle_signer_request(&signer, request).await;
+        let signature = Signature::from_str(&response.signature.unwrap()).unwrap();
+        assert!(signature.verify(pubkey.as_ref(), &message.serialize()));
+    }
+
+    #[tokio::test]
+    async fn stub_signer_reports_policy_violations() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let signer = LocalSigner::new_local_signer(Some(keypair), None, Arc::new(policy(Some(0.5), vec![], vec![])));
+
+        let transfer = system_instruction::transfer(&pubkey, &Pubkey::new_unique(), sol_to_lamports(1.0));
+        let message = Message::new(&[transfer], Some(&pubkey));
+        let response = handle_signer_request(&signer, SignerRequest::SignSolana { message: hex::encode(message.serialize()) }).await;
+        assert!(response.signature.is_none());
+        assert!(response.error.unwrap().contains("limit"));
+    }
+
+    #[tokio::test]
+    async fn broken_evm_key_does_not_stop_solana_signing() {
+        let keypair = Keypair::new();
+        let solana = SolanaSettings { agent_keypair: SecretString::new(keypair.to_base58_string()), ..SolanaSettings::new_solana() };
+        let smc = SMCSettings { owner_private_key: SecretString::new("0xnot-hex".to_string()), ..SMCSettings::new_smc() };
+        let signer = LocalSigner::from_settings(&solana, &smc, Arc::new(LimitPolicy::default()));
+
+        assert_eq!(signer.solana_pubkey(), Some(keypair.pubkey()));
+        assert_eq!(signer.evm_address(), None);
+        let transaction = TypedTransaction::Legacy(TransactionRequest::new());
+        assert!(matches!(signer.sign_evm_transaction(&transaction).await, Err(SignerError::KeyLoader(_))));
+    }
+
+    #[test]
+    fn http_requests_need_the_bearer_token() {
+        let token = SecretString::new("s3cret".to_string());
+        assert!(is_authorized(&token, Some("Bearer s3cret")));
+        assert!(!is_authorized(&token, Some("Bearer s3cres")));
+        assert!(!is_authorized(&token, Some("s3cret")));
+        assert!(!is_authorized(&token, None));
+        assert!(!is_authorized(&SecretString::default(), Some("Bearer ")));
+    }
+
+    #[tokio::test]
+    async fn binds_an_owner_only_socket_over_a_stale_one() {
+        let dir = std::env::temp_dir().join(format!("signer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("signer.sock");
+        drop(bind_private_socket(&path).unwrap());
+
+        let _listener = bind_private_socket(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        UnixStream::connect(&path).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
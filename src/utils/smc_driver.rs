@@ -1,17 +1,23 @@
 use ethers::prelude::*;
 use ethers::abi::Abi;
-use ethers::signers::{LocalWallet, Signer};
 use ethers::contract::Contract;
+use ethers::types::transaction::eip2718::TypedTransaction;
 use std::sync::Arc;
 use std::error::Error;
 use std::fs;
 
+use crate::core::config::SMCSettings;
+use crate::utils::error::SignerError;
+use crate::utils::signer::{signer_from_settings, TransactionSigner};
+
 const CONTRACT_ABI_PATH: &str = "prize_contract.abi";
 const BONDING_ABI_PATH: &str = "bonding_curve_contract.abi";
 
 pub struct SMCDriver {
     provider: Arc<Provider<Http>>,
-    wallet: LocalWallet,
+    signer: Arc<dyn TransactionSigner>,
+    /// EVM address of `signer`, the prize pool wallet.
+    address: Address,
     prize_contract: Contract<Provider<Http>>,
     bonding_contract: Contract<Provider<Http>>,
 }
@@ -21,13 +27,12 @@ impl SMCDriver {
         rpc_url: &str,
         contract_address: &str,
         bonding_contract_address: &str,
-        private_key: &str,
+        signer: Arc<dyn TransactionSigner>,
     ) -> Result<Self, Box<dyn Error>> {
         // Initialize provider
         let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
 
-        // Parse wallet
-        let wallet: LocalWallet = private_key.parse::<LocalWallet>()?.with_chain_id(1u64);
+        let address = signer.evm_address().ok_or(SignerError::NoEvmKey)?;
 
         // Read and parse contract ABIs
         let prize_abi = serde_json::from_str::<Abi>(&fs::read_to_string(CONTRACT_ABI_PATH)?)?;
@@ -48,14 +53,27 @@ impl SMCDriver {
 
         Ok(Self {
             provider,
-            wallet,
+            signer,
+            address,
             prize_contract,
             bonding_contract,
         })
     }
 
+    /// Driver for the EVM wallet of the signer configured in `SIGNER_MODE`.
+    pub async fn from_settings(settings: &SMCSettings) -> Result<Self, Box<dyn Error>> {
+        let signer = signer_from_settings().await?;
+        Self::new_smc(
+            &settings.web3_provider,
+            &settings.prize_pool_contract_address,
+            &settings.bonding_contract_address,
+            signer,
+        )
+        .await
+    }
+
     pub async fn get_prize_pool_balance(&self) -> Result<U256, Box<dyn Error>> {
-        let balance = self.provider.get_balance(self.wallet.address(), None).await?;
+        let balance = self.provider.get_balance(self.address, None).await?;
         Ok(balance)
     }
 
@@ -68,12 +86,21 @@ impl SMCDriver {
 
         let value_to_send = balance.checked_sub(transaction_fee).ok_or("Insufficient balance")?;
 
-        let tx = TransactionRequest::new()
+        let nonce = self.provider.get_transaction_count(self.address, None).await?;
+        // Signed for the chain the provider is on, so the signature cannot be replayed elsewhere.
+        let chain_id = self.provider.get_chainid().await?;
+        let tx: TypedTransaction = TransactionRequest::new()
             .to(to)
             .value(value_to_send)
-            .from(self.wallet.address());
+            .from(self.address)
+            .gas(gas_limit)
+            .gas_price(gas_price)
+            .nonce(nonce)
+            .chain_id(chain_id.as_u64())
+            .into();
 
-        let pending_tx = self.provider.send_transaction(tx, None).await?;
+        let signature = self.signer.sign_evm_transaction(&tx).await?;
+        let pending_tx = self.provider.send_raw_transaction(tx.rlp_signed(&signature)).await?;
         let tx_hash = pending_tx.tx_hash();
         Ok(tx_hash)
     }
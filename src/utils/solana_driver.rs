@@ -1,43 +1,677 @@
-use solana_client::rpc_client::RpcClient;
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig, RpcTransactionConfig};
 use solana_sdk::{
+    account::Account,
     commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    message::Message,
+    native_token::{lamports_to_sol, sol_to_lamports},
     pubkey::Pubkey,
-    system_transaction,
-    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionTokenBalance};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use sqlx::PgPool;
+use std::{error::Error, str::FromStr, sync::Arc};
+
+use crate::core::config::SolanaSettings;
+use crate::models::base::SolanaTransactionStatus;
+use crate::utils::error::{SignerError, SwapError};
+use crate::utils::signer::{signer_from_settings, TransactionSigner};
+use crate::utils::priority_fee::{capped_compute_budget, percentile_fee, unit_limit_with_margin, writable_accounts, ComputeBudget};
+use crate::utils::solana_rpc::{shared_solana_rpc, SolanaRpc};
+use crate::utils::transaction_manager::{TrackedTransaction, TransactionManager};
+use crate::utils::raydium_swap::{
+    amm_v4_reserves, amm_v4_swap_base_in, cpmm_reserves, cpmm_swap_base_input, decode_cpmm_trade_fee_rate,
+    decode_mint_decimals, decode_mint_freeze_authority, decode_token_account_amount, quote_exact_in,
+    reverse_after_swap, AmmV4Pool, CpmmPool, PoolReserves, SerumMarket, SwapDirection, SwapQuote, AMM_V4_PROGRAM,
+    CPMM_PROGRAM, WSOL_MINT,
 };
-use std::{error::Error, str::FromStr};
 
 const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+const SOL_DECIMALS: u8 = 9;
+
+/// Outcome of a confirmed swap, in UI units.
+#[derive(Debug, Clone)]
+pub struct SwapResult {
+    pub amount_in: f64,
+    pub token_in: String,
+    pub amount_out: f64,
+    pub token_out: String,
+    /// Network fee paid for the transaction, priority fee included, in SOL.
+    pub fee: f64,
+    /// Priority part of `fee`, in SOL.
+    pub priority_fee: f64,
+    /// Trade fee kept by the pool, in `token_in`; already taken out of `amount_out`.
+    pub pool_fee: f64,
+    /// Rent locked in the token account a buy had to create, in SOL; zero on sells.
+    pub rent: f64,
+    pub signature: String,
+    /// Row of the swap in `solana_transactions`.
+    pub transaction_id: i32,
+    pub status: SolanaTransactionStatus,
+}
+
+/// On-chain state of a pool, decoded from its accounts.
+pub enum LoadedPool {
+    AmmV4 { pool: AmmV4Pool, market: SerumMarket },
+    Cpmm { pool: CpmmPool, trade_fee_rate: u64 },
+}
+
+/// A pool resolved for the agent wallet: decoded state plus the token side and the agent's accounts.
+pub struct PoolRoute {
+    pub pool: LoadedPool,
+    pub token_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub wsol_account: Pubkey,
+    pub token_account: Pubkey,
+}
+
+impl PoolRoute {
+    fn input_mint(&self, direction: SwapDirection) -> Pubkey {
+        match direction {
+            SwapDirection::Buy => WSOL_MINT,
+            SwapDirection::Sell => self.token_mint,
+        }
+    }
+
+    /// Source and destination token accounts of the agent.
+    fn accounts(&self, direction: SwapDirection) -> (Pubkey, Pubkey) {
+        match direction {
+            SwapDirection::Buy => (self.wsol_account, self.token_account),
+            SwapDirection::Sell => (self.token_account, self.wsol_account),
+        }
+    }
+
+    /// Owner of the pool vaults.
+    fn pool_authority(&self) -> Pubkey {
+        match &self.pool {
+            LoadedPool::AmmV4 { .. } => AmmV4Pool::authority(),
+            LoadedPool::Cpmm { .. } => CpmmPool::authority(),
+        }
+    }
+}
+
+/// Everything needed to send a swap: the instructions and the quote they were built from.
+pub struct SwapPlan {
+    pub instructions: Vec<Instruction>,
+    pub quote: SwapQuote,
+    pub reserves: PoolReserves,
+    pub direction: SwapDirection,
+    pub token_mint: Pubkey,
+    pub token_decimals: u8,
+    /// Owner of the pool vaults.
+    pub pool_authority: Pubkey,
+}
+
+/// What a landed swap actually moved, in raw units.
+struct SettledSwap {
+    amount_out: u64,
+    /// Lamports paid into accounts the swap created and left open.
+    rent: u64,
+}
+
+impl SwapPlan {
+    fn mints(&self) -> (Pubkey, Pubkey) {
+        match self.direction {
+            SwapDirection::Buy => (WSOL_MINT, self.token_mint),
+            SwapDirection::Sell => (self.token_mint, WSOL_MINT),
+        }
+    }
+
+    fn decimals(&self) -> (u8, u8) {
+        match self.direction {
+            SwapDirection::Buy => (SOL_DECIMALS, self.token_decimals),
+            SwapDirection::Sell => (self.token_decimals, SOL_DECIMALS),
+        }
+    }
+}
+
+/// Result of simulating a buy followed by an immediate sell of everything bought.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoundTripReport {
+    /// SOL spent on the simulated buy.
+    pub amount_in: f64,
+    /// Tokens the pool math promises for `amount_in`.
+    pub expected_tokens_out: f64,
+    pub tokens_received: f64,
+    /// SOL the pool math promises for selling the expected tokens right after the buy.
+    pub expected_sol_out: f64,
+    pub sol_received: f64,
+    /// Loss beyond pool fees across both legs, in percent.
+    pub effective_tax_pct: f64,
+    /// Informational; whether it blocks a buy is the `freeze_authority` trade rule's call.
+    pub freeze_authority: Option<String>,
+    /// Program errors reported by the simulations.
+    pub errors: Vec<String>,
+}
+
+impl RoundTripReport {
+    /// Why the token must not be bought, or `None` if the round trip looks healthy.
+    pub fn failure_reason(&self, max_tax_pct: f64) -> Option<String> {
+        if !self.errors.is_empty() {
+            Some(format!("the simulated trade failed: {}", self.errors.join("; ")))
+        } else if self.tokens_received == 0.0 {
+            Some("the buy would not return any tokens".to_string())
+        } else if self.sol_received == 0.0 {
+            Some("the token cannot be sold back".to_string())
+        } else if self.effective_tax_pct > max_tax_pct {
+            Some(format!("the token takes an effective {:.2}% tax on a round trip", self.effective_tax_pct))
+        } else {
+            None
+        }
+    }
+}
+
+struct SimulationOutcome {
+    errors: Vec<String>,
+    units_consumed: Option<u64>,
+    /// Post-simulation amounts of the watched token accounts; `None` if an account does not exist.
+    amounts: Vec<Option<u64>>,
+}
+
+fn to_ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
 
 pub struct SolanaDriver {
-    client: RpcClient,
-    agent_keypair: Keypair,
+    rpc: Arc<SolanaRpc>,
+    transactions: TransactionManager,
+    signer: Arc<dyn TransactionSigner>,
+    /// Solana key of `signer`, the agent wallet.
+    agent: Pubkey,
 }
 
 impl SolanaDriver {
-    pub fn new_solana_driver(rpc_url: &str, agent_keypair: Keypair) -> Self {
-        Self {
-            client: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
-            agent_keypair,
-        }
+    pub fn new_solana_driver(rpc: Arc<SolanaRpc>, pool: PgPool, signer: Arc<dyn TransactionSigner>) -> Result<Self, SignerError> {
+        let agent = signer.solana_pubkey().ok_or(SignerError::NoSolanaKey)?;
+        Ok(Self {
+            transactions: TransactionManager::new_transaction_manager(rpc.clone(), pool),
+            rpc,
+            signer,
+            agent,
+        })
     }
 
-    pub fn get_agent_balance(&self) -> Result<u64, Box<dyn Error>> {
-        let balance = self.client.get_balance(&self.agent_keypair.pubkey())?;
+    /// Driver for the agent wallet of the signer configured in `SIGNER_MODE`.
+    pub async fn from_settings(pool: PgPool) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let signer = signer_from_settings().await?;
+        Ok(Self::new_solana_driver(shared_solana_rpc(), pool, signer)?)
+    }
+
+    pub async fn get_agent_balance(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let balance = self.rpc.get_balance(&self.agent).await?;
         Ok(balance)
     }
 
-    pub fn transfer_share_to_user(&self, user_address: &str, amount: u64) -> Result<Signature, Box<dyn Error>> {
+    /// Sends `amount` lamports to the user; the transaction is recorded under `payout:{payout_id}`.
+    pub async fn transfer_share_to_user(
+        &self,
+        user_address: &str,
+        amount: u64,
+        payout_id: i32,
+    ) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
         let to_pubkey = Pubkey::from_str(user_address)?;
-        let recent_blockhash = self.client.get_latest_blockhash()?;
+        let instruction = system_instruction::transfer(&self.agent, &to_pubkey, amount);
+        let (instructions, _) = self.with_compute_budget(vec![instruction]).await?;
+        self.transactions
+            .submit("payout", Some(&format!("payout:{}", payout_id)), &instructions, self.signer.as_ref())
+            .await
+    }
 
-        let tx = system_transaction::transfer(&self.agent_keypair, &to_pubkey, amount, recent_blockhash);
+    /// Transaction records shared with trade and payout bookkeeping.
+    pub fn transactions(&self) -> &TransactionManager {
+        &self.transactions
+    }
 
-        let signature = self.client.send_and_confirm_transaction(&tx)?;
-        Ok(signature)
+    /// Drives transactions abandoned by a crashed or restarted process to a final status.
+    pub async fn recover_pending_transactions(&self) -> Result<Vec<TrackedTransaction>, Box<dyn Error + Send + Sync>> {
+        self.transactions.recover_pending(self.signer.as_ref()).await
     }
 
     pub fn get_address(&self) -> Pubkey {
-        self.agent_keypair.pubkey()
+        self.agent
+    }
+
+    /// Spends `amount_sol` SOL on the pool's token; the transaction is recorded under the pool.
+    pub async fn swap_quote_token(&self, pool_address: &str, amount_sol: f64, slippage_bps: u64) -> Result<SwapResult, Box<dyn Error + Send + Sync>> {
+        self.swap(pool_address, SwapDirection::Buy, sol_to_lamports(amount_sol), slippage_bps, pool_address).await
+    }
+
+    /// Sells `amount` raw units of the pool's token for SOL; the transaction is recorded under `reference`.
+    pub async fn sell_token(
+        &self,
+        pool_address: &str,
+        amount: u64,
+        slippage_bps: u64,
+        reference: &str,
+    ) -> Result<SwapResult, Box<dyn Error + Send + Sync>> {
+        self.swap(pool_address, SwapDirection::Sell, amount, slippage_bps, reference).await
+    }
+
+    pub async fn swap(
+        &self,
+        pool_address: &str,
+        direction: SwapDirection,
+        amount_in: u64,
+        slippage_bps: u64,
+        reference: &str,
+    ) -> Result<SwapResult, Box<dyn Error + Send + Sync>> {
+        let plan = self.build_swap_plan(pool_address, direction, amount_in, slippage_bps).await?;
+        let (instructions, budget) = self.with_compute_budget(plan.instructions.clone()).await?;
+
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let message = Message::new_with_blockhash(&instructions, Some(&self.agent), &recent_blockhash);
+        let fee = self.rpc.get_fee_for_message(&message).await?;
+
+        let tracked = self
+            .transactions
+            .submit("swap", Some(reference), &instructions, self.signer.as_ref())
+            .await?;
+        if !tracked.is_landed() {
+            return Err(format!(
+                "Swap transaction {} {}: {}",
+                tracked.signature,
+                tracked.status,
+                tracked.error.unwrap_or_default()
+            )
+            .into());
+        }
+        let signature = tracked.signature;
+
+        // Fall back to the quote if the RPC node has not indexed the transaction yet.
+        let settled = self
+            .settled_swap(&signature, &plan)
+            .await
+            .unwrap_or(SettledSwap { amount_out: plan.quote.amount_out, rent: 0 });
+
+        let (token_in, token_out) = plan.mints();
+        let (decimals_in, decimals_out) = plan.decimals();
+        Ok(SwapResult {
+            amount_in: to_ui_amount(plan.quote.amount_in, decimals_in),
+            token_in: token_in.to_string(),
+            amount_out: to_ui_amount(settled.amount_out, decimals_out),
+            token_out: token_out.to_string(),
+            fee: lamports_to_sol(fee),
+            priority_fee: lamports_to_sol(budget.priority_fee_lamports()),
+            pool_fee: to_ui_amount(plan.quote.fee, decimals_in),
+            rent: lamports_to_sol(settled.rent),
+            signature: signature.to_string(),
+            transaction_id: tracked.id,
+            status: tracked.status,
+        })
+    }
+
+    /// Prepends ComputeBudget instructions: the unit limit comes from simulating the
+    /// instructions, the unit price from recent prioritization fees on the accounts they write.
+    pub async fn with_compute_budget(
+        &self,
+        instructions: Vec<Instruction>,
+    ) -> Result<(Vec<Instruction>, ComputeBudget), Box<dyn Error + Send + Sync>> {
+        let settings = SolanaSettings::new_solana();
+
+        let simulation = self.simulate(&instructions, &[]).await?;
+        let unit_limit = match simulation.units_consumed {
+            Some(units) if simulation.errors.is_empty() => unit_limit_with_margin(units, settings.compute_unit_margin_pct),
+            _ => settings.compute_unit_limit,
+        };
+
+        let fees: Vec<u64> = self
+            .rpc
+            .get_recent_prioritization_fees(&writable_accounts(&instructions))
+            .await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        let unit_price = percentile_fee(&fees, settings.priority_fee_percentile);
+
+        let budget = capped_compute_budget(unit_limit, unit_price, settings.priority_fee_cap_lamports);
+        let mut budgeted = budget.instructions();
+        budgeted.extend(instructions);
+        Ok((budgeted, budget))
+    }
+
+    /// Simulates buying with `amount_sol` and selling everything received straight back,
+    /// to catch tokens that cannot be sold, tax transfers or are frozen.
+    pub async fn simulate_round_trip(&self, pool_address: &str, amount_sol: f64) -> Result<RoundTripReport, Box<dyn Error + Send + Sync>> {
+        let amount_in = sol_to_lamports(amount_sol);
+        let route = self.route_pool(pool_address).await?;
+        let buy_reserves = self.route_reserves(&route, SwapDirection::Buy).await?;
+        let buy_quote = quote_exact_in(&buy_reserves, amount_in, 0)?;
+        let expected_sol_out = quote_exact_in(&reverse_after_swap(&buy_reserves, &buy_quote), buy_quote.amount_out, 0)
+            .map(|q| q.amount_out)
+            .unwrap_or(0);
+
+        let mint_account = self.rpc.get_account(&route.token_mint).await?;
+        let token_decimals = decode_mint_decimals(&mint_account.data)?;
+        let freeze_authority = decode_mint_freeze_authority(&mint_account.data)?;
+
+        let pre_tokens = self.token_account_amount(&route.token_account).await?;
+        let pre_wsol = self.token_account_amount(&route.wsol_account).await?;
+
+        // Buy leg alone, to see how many tokens really arrive after any transfer fee.
+        // `min_amount_out` is zero so that a tax shows up in the numbers rather than as a slippage error.
+        let mut instructions = self.buy_setup_instructions(&route, amount_in)?;
+        instructions.push(self.swap_instruction(&route, SwapDirection::Buy, amount_in, 0));
+        let buy = self.simulate(&instructions, &[route.token_account]).await?;
+        let mut errors = buy.errors;
+        let tokens_received = buy.amounts.first().copied().flatten().map(|a| a.saturating_sub(pre_tokens)).unwrap_or(0);
+
+        let mut sol_received = 0;
+        if errors.is_empty() && tokens_received > 0 {
+            // The bought tokens only exist inside the simulation, so the sell rides in the same transaction.
+            instructions.push(self.swap_instruction(&route, SwapDirection::Sell, tokens_received, 0));
+            let sell = self.simulate(&instructions, &[route.wsol_account]).await?;
+            errors.extend(sell.errors);
+            sol_received = sell.amounts.first().copied().flatten().map(|a| a.saturating_sub(pre_wsol)).unwrap_or(0);
+        }
+
+        let effective_tax_pct = if expected_sol_out > 0 && errors.is_empty() {
+            ((1.0 - sol_received as f64 / expected_sol_out as f64) * 100.0).max(0.0)
+        } else {
+            0.0
+        };
+
+        Ok(RoundTripReport {
+            amount_in: lamports_to_sol(amount_in),
+            expected_tokens_out: to_ui_amount(buy_quote.amount_out, token_decimals),
+            tokens_received: to_ui_amount(tokens_received, token_decimals),
+            expected_sol_out: lamports_to_sol(expected_sol_out),
+            sol_received: lamports_to_sol(sol_received),
+            effective_tax_pct,
+            freeze_authority: freeze_authority.map(|a| a.to_string()),
+            errors,
+        })
+    }
+
+    async fn simulate(&self, instructions: &[Instruction], watched_accounts: &[Pubkey]) -> Result<SimulationOutcome, Box<dyn Error + Send + Sync>> {
+        // The node swaps in a fresh blockhash and skips signature checks, so the transaction stays unsigned.
+        let tx = Transaction::new_with_payer(instructions, Some(&self.agent));
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::confirmed()),
+            encoding: Some(UiTransactionEncoding::Base64),
+            accounts: (!watched_accounts.is_empty()).then(|| RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: watched_accounts.iter().map(ToString::to_string).collect(),
+            }),
+            min_context_slot: None,
+            inner_instructions: false,
+        };
+        let result = self.rpc.simulate_transaction(&tx, config).await?;
+
+        let mut errors = Vec::new();
+        if let Some(err) = result.err {
+            errors.push(err.to_string());
+            // The failing program's own message is usually the last log line mentioning it.
+            let logs = result.logs.unwrap_or_default();
+            if let Some(log) = logs.iter().rev().find(|l| l.contains("Error") || l.contains("failed")) {
+                errors.push(log.clone());
+            }
+        }
+
+        let amounts = result
+            .accounts
+            .unwrap_or_else(|| vec![None; watched_accounts.len()])
+            .into_iter()
+            .map(|account| {
+                account
+                    .and_then(|a| a.decode::<Account>())
+                    .and_then(|a| decode_token_account_amount(&a.data).ok())
+            })
+            .collect();
+        Ok(SimulationOutcome { errors, units_consumed: result.units_consumed, amounts })
+    }
+
+    /// Balance of a token account, or zero if it does not exist yet.
+    async fn token_account_amount(&self, account: &Pubkey) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let account = self.rpc.get_account_optional(account).await?;
+        match account {
+            Some(account) => Ok(decode_token_account_amount(&account.data)?),
+            None => Ok(0),
+        }
+    }
+
+    pub async fn load_pool(&self, pool_id: &Pubkey) -> Result<LoadedPool, Box<dyn Error + Send + Sync>> {
+        let account = self.rpc.get_account(pool_id).await?;
+        if account.owner == AMM_V4_PROGRAM {
+            let pool = AmmV4Pool::decode(*pool_id, &account.data)?;
+            let market_account = self.rpc.get_account(&pool.market).await?;
+            let market = SerumMarket::decode(pool.market, pool.market_program, &market_account.data)?;
+            Ok(LoadedPool::AmmV4 { pool, market })
+        } else if account.owner == CPMM_PROGRAM {
+            let pool = CpmmPool::decode(*pool_id, &account.data)?;
+            let config_account = self.rpc.get_account(&pool.amm_config).await?;
+            let trade_fee_rate = decode_cpmm_trade_fee_rate(&config_account.data)?;
+            Ok(LoadedPool::Cpmm { pool, trade_fee_rate })
+        } else {
+            Err(SwapError::UnsupportedPool(pool_id.to_string()).into())
+        }
+    }
+
+    async fn vault_amounts(&self, vault_a: &Pubkey, vault_b: &Pubkey) -> Result<(u64, u64), Box<dyn Error + Send + Sync>> {
+        let accounts = self.rpc.get_multiple_accounts(&[*vault_a, *vault_b]).await?;
+        let amount = |index: usize| -> Result<u64, SwapError> {
+            let account = accounts[index].as_ref().ok_or(SwapError::InvalidAccountData("vault"))?;
+            decode_token_account_amount(&account.data)
+        };
+        Ok((amount(0)?, amount(1)?))
+    }
+
+    /// Raw balance and decimals of the pool's token in the agent's token account.
+    pub async fn pool_token_balance(&self, pool_address: &str) -> Result<(u64, u8), Box<dyn Error + Send + Sync>> {
+        let route = self.route_pool(pool_address).await?;
+        let amount = self.token_account_amount(&route.token_account).await?;
+        let decimals = self.mint_decimals(&route.token_mint).await?;
+        Ok((amount, decimals))
+    }
+
+    /// SOL that selling `amount` raw units of the pool's token would return now, before slippage.
+    pub async fn quote_sell(&self, pool_address: &str, amount: u64) -> Result<f64, Box<dyn Error + Send + Sync>> {
+        let route = self.route_pool(pool_address).await?;
+        let reserves = self.route_reserves(&route, SwapDirection::Sell).await?;
+        Ok(lamports_to_sol(quote_exact_in(&reserves, amount, 0)?.amount_out))
+    }
+
+    async fn mint_decimals(&self, mint: &Pubkey) -> Result<u8, Box<dyn Error + Send + Sync>> {
+        let account = self.rpc.get_account(mint).await?;
+        Ok(decode_mint_decimals(&account.data)?)
+    }
+
+    /// Loads the pool and works out which side is the traded token and which accounts the agent swaps from.
+    pub async fn route_pool(&self, pool_address: &str) -> Result<PoolRoute, Box<dyn Error + Send + Sync>> {
+        let pool_id = Pubkey::from_str(pool_address)?;
+        let owner = self.agent;
+        let pool = self.load_pool(&pool_id).await?;
+
+        let (token_mint, token_program) = match &pool {
+            // AMM v4 only supports the classic token program.
+            LoadedPool::AmmV4 { pool, .. } if pool.pc_mint == WSOL_MINT => (pool.coin_mint, spl_token::id()),
+            LoadedPool::AmmV4 { pool, .. } if pool.coin_mint == WSOL_MINT => (pool.pc_mint, spl_token::id()),
+            LoadedPool::Cpmm { pool, .. } if pool.token_1_mint == WSOL_MINT => (pool.token_0_mint, pool.token_0_program),
+            LoadedPool::Cpmm { pool, .. } if pool.token_0_mint == WSOL_MINT => (pool.token_1_mint, pool.token_1_program),
+            _ => return Err(SwapError::UnsupportedPool(pool_address.to_string()).into()),
+        };
+
+        Ok(PoolRoute {
+            pool,
+            token_mint,
+            token_program,
+            wsol_account: get_associated_token_address(&owner, &WSOL_MINT),
+            token_account: get_associated_token_address_with_program_id(&owner, &token_mint, &token_program),
+        })
+    }
+
+    /// Current reserves of the route, oriented for the given direction.
+    pub async fn route_reserves(&self, route: &PoolRoute, direction: SwapDirection) -> Result<PoolReserves, Box<dyn Error + Send + Sync>> {
+        let input_mint = route.input_mint(direction);
+        Ok(match &route.pool {
+            LoadedPool::AmmV4 { pool, .. } => {
+                let (coin_amount, pc_amount) = self.vault_amounts(&pool.coin_vault, &pool.pc_vault).await?;
+                amm_v4_reserves(pool, coin_amount, pc_amount, pool.coin_mint == input_mint)
+            }
+            LoadedPool::Cpmm { pool, trade_fee_rate } => {
+                let (amount_0, amount_1) = self.vault_amounts(&pool.token_0_vault, &pool.token_1_vault).await?;
+                cpmm_reserves(pool, amount_0, amount_1, *trade_fee_rate, pool.token_0_mint == input_mint)
+            }
+        })
+    }
+
+    /// The pool's own swap instruction, without any account setup around it.
+    pub fn swap_instruction(&self, route: &PoolRoute, direction: SwapDirection, amount_in: u64, min_amount_out: u64) -> Instruction {
+        let owner = self.agent;
+        let (source, destination) = route.accounts(direction);
+        match &route.pool {
+            LoadedPool::AmmV4 { pool, market } => {
+                amm_v4_swap_base_in(pool, market, &source, &destination, &owner, amount_in, min_amount_out)
+            }
+            LoadedPool::Cpmm { pool, .. } => {
+                let input_is_token_0 = pool.token_0_mint == route.input_mint(direction);
+                cpmm_swap_base_input(pool, &owner, &source, &destination, input_is_token_0, amount_in, min_amount_out)
+            }
+        }
+    }
+
+    /// Account setup a buy needs: both ATAs and `amount_in` lamports wrapped into WSOL.
+    fn buy_setup_instructions(&self, route: &PoolRoute, amount_in: u64) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let owner = self.agent;
+        Ok(vec![
+            create_associated_token_account_idempotent(&owner, &owner, &WSOL_MINT, &spl_token::id()),
+            create_associated_token_account_idempotent(&owner, &owner, &route.token_mint, &route.token_program),
+            system_instruction::transfer(&owner, &route.wsol_account, amount_in),
+            spl_token::instruction::sync_native(&spl_token::id(), &route.wsol_account)?,
+        ])
+    }
+
+    /// Closing the WSOL account unwraps whatever it holds back into native SOL.
+    fn unwrap_instruction(&self, route: &PoolRoute) -> Result<Instruction, Box<dyn Error + Send + Sync>> {
+        let owner = self.agent;
+        Ok(spl_token::instruction::close_account(&spl_token::id(), &route.wsol_account, &owner, &owner, &[])?)
+    }
+
+    /// Builds the full swap: ATA creation, WSOL wrap, the pool swap and the WSOL unwrap.
+    pub async fn build_swap_plan(
+        &self,
+        pool_address: &str,
+        direction: SwapDirection,
+        amount_in: u64,
+        slippage_bps: u64,
+    ) -> Result<SwapPlan, Box<dyn Error + Send + Sync>> {
+        let owner = self.agent;
+        let route = self.route_pool(pool_address).await?;
+        let reserves = self.route_reserves(&route, direction).await?;
+        let quote = quote_exact_in(&reserves, amount_in, slippage_bps)?;
+
+        let mut instructions = match direction {
+            SwapDirection::Buy => self.buy_setup_instructions(&route, amount_in)?,
+            SwapDirection::Sell => {
+                vec![create_associated_token_account_idempotent(&owner, &owner, &WSOL_MINT, &spl_token::id())]
+            }
+        };
+        instructions.push(self.swap_instruction(&route, direction, amount_in, quote.min_amount_out));
+        instructions.push(self.unwrap_instruction(&route)?);
+
+        Ok(SwapPlan {
+            instructions,
+            quote,
+            reserves,
+            direction,
+            token_mint: route.token_mint,
+            token_decimals: self.mint_decimals(&route.token_mint).await?,
+            pool_authority: route.pool_authority(),
+        })
+    }
+
+    /// Output actually received and rent paid, read from the confirmed transaction's balance changes.
+    async fn settled_swap(&self, signature: &Signature, plan: &SwapPlan) -> Option<SettledSwap> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        let tx = self.rpc.get_transaction(signature, config).await.ok()?;
+        let meta = tx.transaction.meta?;
+        let pre_tokens: Vec<UiTransactionTokenBalance> = Option::from(meta.pre_token_balances).unwrap_or_default();
+        let post_tokens: Vec<UiTransactionTokenBalance> = Option::from(meta.post_token_balances).unwrap_or_default();
+
+        match plan.direction {
+            SwapDirection::Buy => {
+                let agent = self.agent.to_string();
+                let mint = plan.token_mint.to_string();
+                let post = find_token_balance(&post_tokens, &mint, &agent)?;
+                let pre_amount = find_token_balance(&pre_tokens, &mint, &agent).map_or(0, token_amount);
+                // A token account that already existed keeps its lamports; a new one holds exactly its rent.
+                let index = post.account_index as usize;
+                let rent = meta.post_balances.get(index)?.saturating_sub(*meta.pre_balances.get(index)?);
+                Some(SettledSwap { amount_out: token_amount(post).checked_sub(pre_amount)?, rent })
+            }
+            SwapDirection::Sell => Some(SettledSwap {
+                amount_out: wsol_vault_outflow(&pre_tokens, &post_tokens, &plan.pool_authority)?,
+                rent: 0,
+            }),
+        }
     }
+
+    /// Reads a landed sell back from the chain, for a sell whose caller stopped before recording
+    /// it: what left the agent's token account and the pool's WSOL vault, and the fee paid. The
+    /// priority and pool fees are not broken out.
+    pub async fn landed_sell(&self, pool_address: &str, tracked: &TrackedTransaction) -> Result<SwapResult, Box<dyn Error + Send + Sync>> {
+        let route = self.route_pool(pool_address).await?;
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        let tx = self.rpc.get_transaction(&tracked.signature, config).await?;
+        let meta = tx.transaction.meta.ok_or_else(|| format!("Sell {} has no status metadata", tracked.signature))?;
+        let pre_tokens: Vec<UiTransactionTokenBalance> = Option::from(meta.pre_token_balances).unwrap_or_default();
+        let post_tokens: Vec<UiTransactionTokenBalance> = Option::from(meta.post_token_balances).unwrap_or_default();
+
+        let agent = self.agent.to_string();
+        let mint = route.token_mint.to_string();
+        let pre = find_token_balance(&pre_tokens, &mint, &agent)
+            .ok_or_else(|| format!("Sell {} did not spend tokens of {}", tracked.signature, mint))?;
+        let post_amount = find_token_balance(&post_tokens, &mint, &agent).map_or(0, token_amount);
+        let amount_out = wsol_vault_outflow(&pre_tokens, &post_tokens, &route.pool_authority())
+            .ok_or_else(|| format!("Sell {} did not take SOL from the pool", tracked.signature))?;
+
+        Ok(SwapResult {
+            amount_in: to_ui_amount(token_amount(pre).saturating_sub(post_amount), pre.ui_token_amount.decimals),
+            token_in: mint,
+            amount_out: to_ui_amount(amount_out, SOL_DECIMALS),
+            token_out: WSOL_MINT.to_string(),
+            fee: lamports_to_sol(meta.fee),
+            priority_fee: 0.0,
+            pool_fee: 0.0,
+            rent: 0.0,
+            signature: tracked.signature.to_string(),
+            transaction_id: tracked.id,
+            status: tracked.status,
+        })
+    }
+}
+
+/// Closing the agent's WSOL account also unwraps any SOL it held before a sell, so the
+/// proceeds are what left the pool's WSOL vault.
+fn wsol_vault_outflow(pre_tokens: &[UiTransactionTokenBalance], post_tokens: &[UiTransactionTokenBalance], pool_authority: &Pubkey) -> Option<u64> {
+    let pre = find_token_balance(pre_tokens, &WSOL_MINT.to_string(), &pool_authority.to_string())?;
+    let post = post_tokens.iter().find(|b| b.account_index == pre.account_index)?;
+    token_amount(pre).checked_sub(token_amount(post))
+}
+
+fn find_token_balance<'b>(balances: &'b [UiTransactionTokenBalance], mint: &str, owner: &str) -> Option<&'b UiTransactionTokenBalance> {
+    balances
+        .iter()
+        .find(|b| b.mint == mint && Option::<String>::from(b.owner.clone()).as_deref() == Some(owner))
+}
+
+fn token_amount(balance: &UiTransactionTokenBalance) -> u64 {
+    balance.ui_token_amount.amount.parse().unwrap_or(0)
 }
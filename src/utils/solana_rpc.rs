@@ -0,0 +1,356 @@
+use once_cell::sync::Lazy;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcProgramAccountsConfig, RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_request::{RpcError, TokenAccountsFilter};
+use solana_client::rpc_response::{RpcKeyedAccount, RpcPrioritizationFee, RpcSimulateTransactionResult};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, TransactionStatus};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::core::config::SolanaSettings;
+use crate::utils::error::SolanaRpcError;
+
+/// JSON-RPC codes meaning the node, not the request, is at fault.
+const NODE_UNHEALTHY: i64 = -32005;
+const BLOCK_NOT_AVAILABLE: i64 = -32004;
+
+static SOLANA_RPC: Lazy<Arc<SolanaRpc>> = Lazy::new(|| Arc::new(SolanaRpc::new_solana_rpc(&SolanaSettings::new_solana())));
+
+/// Process-wide RPC layer, so endpoint health is shared by every caller.
+pub fn shared_solana_rpc() -> Arc<SolanaRpc> {
+    SOLANA_RPC.clone()
+}
+
+struct Endpoint {
+    url: String,
+    client: Arc<RpcClient>,
+    /// Set when a call failed for reasons attributable to the node.
+    unhealthy_since: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn unhealthy_since(&self) -> Option<Instant> {
+        *self.unhealthy_since.lock().unwrap()
+    }
+
+    fn mark_healthy(&self) {
+        *self.unhealthy_since.lock().unwrap() = None;
+    }
+
+    fn mark_unhealthy(&self) {
+        self.unhealthy_since.lock().unwrap().get_or_insert_with(Instant::now);
+    }
+}
+
+/// Errors worth another attempt, possibly on another endpoint.
+fn is_retryable(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == NODE_UNHEALTHY || *code == BLOCK_NOT_AVAILABLE
+        }
+        _ => false,
+    }
+}
+
+/// Async Solana RPC client over several endpoints with health-checked failover,
+/// retries with exponential backoff and per-method timeouts.
+pub struct SolanaRpc {
+    endpoints: Vec<Endpoint>,
+    /// Endpoint that served the last successful call; tried first.
+    preferred: AtomicUsize,
+    default_timeout: Duration,
+    method_timeouts: HashMap<String, Duration>,
+    max_retries: u32,
+    backoff: Duration,
+    health_recheck: Duration,
+}
+
+impl SolanaRpc {
+    pub fn new_solana_rpc(settings: &SolanaSettings) -> Self {
+        let default_timeout = Duration::from_millis(settings.rpc_timeout_ms);
+        // Per-method deadlines are enforced here, so the transport must never cut a call shorter.
+        let transport_timeout = settings
+            .rpc_method_timeouts_ms
+            .values()
+            .map(|ms| Duration::from_millis(*ms))
+            .fold(default_timeout, Duration::max);
+
+        let clients = settings
+            .rpc_urls
+            .iter()
+            .map(|url| {
+                let client = RpcClient::new_with_timeout_and_commitment(url.clone(), transport_timeout, CommitmentConfig::confirmed());
+                (url.clone(), client)
+            })
+            .collect();
+        Self::from_clients(clients, settings)
+    }
+
+    /// RPC layer over already built clients, keyed by their URL; timeouts and retries come from `settings`.
+    pub fn from_clients(clients: Vec<(String, RpcClient)>, settings: &SolanaSettings) -> Self {
+        let method_timeouts: HashMap<String, Duration> = settings
+            .rpc_method_timeouts_ms
+            .iter()
+            .map(|(method, ms)| (method.clone(), Duration::from_millis(*ms)))
+            .collect();
+
+        let endpoints = clients
+            .into_iter()
+            .map(|(url, client)| Endpoint { url, client: Arc::new(client), unhealthy_since: Mutex::new(None) })
+            .collect();
+
+        Self {
+            endpoints,
+            preferred: AtomicUsize::new(0),
+            default_timeout: Duration::from_millis(settings.rpc_timeout_ms),
+            method_timeouts,
+            max_retries: settings.rpc_max_retries,
+            backoff: Duration::from_millis(settings.rpc_backoff_ms),
+            health_recheck: Duration::from_secs(settings.rpc_health_recheck_secs),
+        }
+    }
+
+    pub fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::confirmed()
+    }
+
+    fn timeout(&self, method: &str) -> Duration {
+        self.method_timeouts.get(method).copied().unwrap_or(self.default_timeout)
+    }
+
+    /// Endpoint indices in the order to try them: healthy ones starting from the preferred one,
+    /// then failed ones whose `getHealth` check passes again, then the rest as a last resort.
+    async fn candidates(&self) -> Vec<usize> {
+        let count = self.endpoints.len();
+        let start = self.preferred.load(Ordering::Relaxed) % count;
+        let mut healthy = Vec::with_capacity(count);
+        let mut unhealthy = Vec::new();
+
+        for index in (0..count).map(|i| (start + i) % count) {
+            let endpoint = &self.endpoints[index];
+            match endpoint.unhealthy_since() {
+                None => healthy.push(index),
+                Some(since) if since.elapsed() >= self.health_recheck => {
+                    let check = tokio::time::timeout(self.timeout("getHealth"), endpoint.client.get_health()).await;
+                    if matches!(check, Ok(Ok(()))) {
+                        endpoint.mark_healthy();
+                        healthy.push(index);
+                    } else {
+                        // Restart the cooldown so the next check waits another full period.
+                        *endpoint.unhealthy_since.lock().unwrap() = Some(Instant::now());
+                        unhealthy.push(index);
+                    }
+                }
+                Some(_) => unhealthy.push(index),
+            }
+        }
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// Runs `request` against the endpoints until one succeeds, a non-retryable error
+    /// comes back, or the retry budget is spent.
+    pub async fn call<T, F, Fut>(&self, method: &'static str, request: F) -> Result<T, SolanaRpcError>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        if self.endpoints.is_empty() {
+            return Err(SolanaRpcError::NoEndpoints);
+        }
+
+        let timeout = self.timeout(method);
+        let mut delay = self.backoff;
+        let mut last_error = SolanaRpcError::NoEndpoints;
+
+        for attempt in 0..=self.max_retries {
+            for index in self.candidates().await {
+                let endpoint = &self.endpoints[index];
+                match tokio::time::timeout(timeout, request(endpoint.client.clone())).await {
+                    Ok(Ok(value)) => {
+                        endpoint.mark_healthy();
+                        self.preferred.store(index, Ordering::Relaxed);
+                        return Ok(value);
+                    }
+                    Ok(Err(e)) if !is_retryable(&e) => return Err(e.into()),
+                    Ok(Err(e)) => {
+                        warn!("{} failed on {}: {}", method, endpoint.url, e);
+                        endpoint.mark_unhealthy();
+                        last_error = e.into();
+                    }
+                    Err(_) => {
+                        warn!("{} timed out on {} after {:?}", method, endpoint.url, timeout);
+                        endpoint.mark_unhealthy();
+                        last_error = SolanaRpcError::Timeout { method, endpoint: endpoint.url.clone() };
+                    }
+                }
+            }
+
+            if attempt < self.max_retries {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+        Err(last_error)
+    }
+
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64, SolanaRpcError> {
+        self.call("getBalance", move |client| async move { client.get_balance(pubkey).await }).await
+    }
+
+    pub async fn get_latest_blockhash(&self) -> Result<Hash, SolanaRpcError> {
+        self.call("getLatestBlockhash", |client| async move { client.get_latest_blockhash().await }).await
+    }
+
+    /// Blockhash together with the last block height at which it is still accepted.
+    pub async fn get_latest_blockhash_with_height(&self) -> Result<(Hash, u64), SolanaRpcError> {
+        let commitment = self.commitment();
+        self.call("getLatestBlockhash", move |client| async move {
+            client.get_latest_blockhash_with_commitment(commitment).await
+        })
+        .await
+    }
+
+    pub async fn get_block_height(&self) -> Result<u64, SolanaRpcError> {
+        self.call("getBlockHeight", |client| async move { client.get_block_height().await }).await
+    }
+
+    pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Account, SolanaRpcError> {
+        self.call("getAccountInfo", move |client| async move { client.get_account(pubkey).await }).await
+    }
+
+    /// Like `get_account`, but a missing account is `None` rather than an error.
+    pub async fn get_account_optional(&self, pubkey: &Pubkey) -> Result<Option<Account>, SolanaRpcError> {
+        let commitment = self.commitment();
+        self.call("getAccountInfo", move |client| async move {
+            Ok(client.get_account_with_commitment(pubkey, commitment).await?.value)
+        })
+        .await
+    }
+
+    pub async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>, SolanaRpcError> {
+        self.call("getMultipleAccounts", move |client| async move { client.get_multiple_accounts(pubkeys).await })
+            .await
+    }
+
+    /// Like `get_multiple_accounts`, together with the slot the accounts were read at.
+    pub async fn get_multiple_accounts_with_slot(&self, pubkeys: &[Pubkey]) -> Result<(u64, Vec<Option<Account>>), SolanaRpcError> {
+        let commitment = self.commitment();
+        self.call("getMultipleAccounts", move |client| async move {
+            let response = client.get_multiple_accounts_with_commitment(pubkeys, commitment).await?;
+            Ok((response.context.slot, response.value))
+        })
+        .await
+    }
+
+    /// Estimated production time of a block, as a Unix timestamp.
+    pub async fn get_block_time(&self, slot: u64) -> Result<i64, SolanaRpcError> {
+        self.call("getBlockTime", move |client| async move { client.get_block_time(slot).await }).await
+    }
+
+    /// Token accounts of `owner` under one token program, as `jsonParsed` data.
+    pub async fn get_token_accounts_by_owner(&self, owner: &Pubkey, program_id: &Pubkey) -> Result<Vec<RpcKeyedAccount>, SolanaRpcError> {
+        self.call("getTokenAccountsByOwner", move |client| async move {
+            client.get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(*program_id)).await
+        })
+        .await
+    }
+
+    pub async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, SolanaRpcError> {
+        self.call("getProgramAccounts", move |client| {
+            let config = config.clone();
+            async move { client.get_program_accounts_with_config(program_id, config).await }
+        })
+        .await
+    }
+
+    pub async fn get_fee_for_message(&self, message: &Message) -> Result<u64, SolanaRpcError> {
+        self.call("getFeeForMessage", move |client| async move { client.get_fee_for_message(message).await })
+            .await
+    }
+
+    pub async fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature, SolanaRpcError> {
+        self.call("sendAndConfirmTransaction", move |client| async move {
+            client.send_and_confirm_transaction(transaction).await
+        })
+        .await
+    }
+
+    /// Broadcasts without waiting for confirmation; preflight runs at the RPC node.
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, SolanaRpcError> {
+        self.call("sendTransaction", move |client| async move { client.send_transaction(transaction).await }).await
+    }
+
+    /// Status of a signature, searching the full ledger history so old submissions are found too.
+    pub async fn get_signature_status(&self, signature: &Signature) -> Result<Option<TransactionStatus>, SolanaRpcError> {
+        self.call("getSignatureStatuses", move |client| async move {
+            let statuses = client.get_signature_statuses_with_history(std::slice::from_ref(signature)).await?.value;
+            Ok(statuses.into_iter().next().flatten())
+        })
+        .await
+    }
+
+    /// Block height and signature status read from the same node, height first: a status that
+    /// is still missing afterwards means the transaction had not landed by that height.
+    pub async fn get_block_height_and_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<(u64, Option<TransactionStatus>), SolanaRpcError> {
+        self.call("getSignatureStatuses", move |client| async move {
+            let block_height = client.get_block_height().await?;
+            let statuses = client.get_signature_statuses_with_history(std::slice::from_ref(signature)).await?.value;
+            Ok((block_height, statuses.into_iter().next().flatten()))
+        })
+        .await
+    }
+
+    pub async fn get_recent_prioritization_fees(&self, accounts: &[Pubkey]) -> Result<Vec<RpcPrioritizationFee>, SolanaRpcError> {
+        self.call("getRecentPrioritizationFees", move |client| async move {
+            client.get_recent_prioritization_fees(accounts).await
+        })
+        .await
+    }
+
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<RpcSimulateTransactionResult, SolanaRpcError> {
+        self.call("simulateTransaction", move |client| {
+            let config = config.clone();
+            async move { Ok(client.simulate_transaction_with_config(transaction, config).await?.value) }
+        })
+        .await
+    }
+
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, SolanaRpcError> {
+        self.call("getTransaction", move |client| {
+            let config = config.clone();
+            async move { client.get_transaction_with_config(signature, config).await }
+        })
+        .await
+    }
+}
@@ -0,0 +1,113 @@
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::utils::error::TokenHolderError;
+use crate::utils::raydium_swap::{decode_mint_freeze_authority, decode_mint_supply, AmmV4Pool, CpmmPool};
+use crate::utils::solana_rpc::SolanaRpc;
+
+/// Token account layout shared by SPL Token and Token-2022: mint, owner, amount.
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const TOKEN_ACCOUNT_OWNER_AND_AMOUNT_LEN: usize = 40;
+
+/// How the supply of a token is spread over wallets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HolderStats {
+    /// Wallets with a non-zero balance, not counting the Raydium pool authorities.
+    pub holders: u64,
+    /// Largest share of supply held by one of those wallets, in percent.
+    pub top_holder_pct: f64,
+}
+
+/// Owners that hold tokens on behalf of liquidity pools rather than for themselves.
+pub fn pool_authorities() -> Vec<Pubkey> {
+    vec![AmmV4Pool::authority(), CpmmPool::authority()]
+}
+
+/// Aggregates `(owner, amount)` balances per owner, leaving out `excluded_owners`.
+pub fn holder_stats(balances: &[(Pubkey, u64)], supply: u64, excluded_owners: &[Pubkey]) -> HolderStats {
+    let mut per_owner: HashMap<Pubkey, u64> = HashMap::new();
+    for (owner, amount) in balances {
+        if *amount > 0 && !excluded_owners.contains(owner) {
+            *per_owner.entry(*owner).or_default() += amount;
+        }
+    }
+
+    let largest = per_owner.values().copied().max().unwrap_or(0);
+    HolderStats {
+        holders: per_owner.len() as u64,
+        top_holder_pct: if supply == 0 { 0.0 } else { largest as f64 / supply as f64 * 100.0 },
+    }
+}
+
+/// Reads every token account of `mint` and summarizes its holders.
+///
+/// Developer wallets are not labelled on chain, so the largest holder other than the pool
+/// authorities stands in for the developer allocation.
+pub async fn fetch_holder_stats(rpc: &SolanaRpc, mint: &Pubkey) -> Result<HolderStats, TokenHolderError> {
+    let mint_account = rpc.get_account(mint).await?;
+    let supply = decode_mint_supply(&mint_account.data)?;
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            TOKEN_ACCOUNT_MINT_OFFSET,
+            mint.as_ref(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig {
+                offset: TOKEN_ACCOUNT_OWNER_OFFSET,
+                length: TOKEN_ACCOUNT_OWNER_AND_AMOUNT_LEN,
+            }),
+            commitment: Some(rpc.commitment()),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    // The mint's owner is the token program its accounts live under.
+    let accounts = rpc.get_program_accounts(&mint_account.owner, config).await?;
+
+    let balances: Vec<(Pubkey, u64)> = accounts
+        .iter()
+        .filter(|(_, account)| account.data.len() == TOKEN_ACCOUNT_OWNER_AND_AMOUNT_LEN)
+        .map(|(_, account)| {
+            let owner = Pubkey::new_from_array(account.data[..32].try_into().unwrap());
+            let amount = u64::from_le_bytes(account.data[32..40].try_into().unwrap());
+            (owner, amount)
+        })
+        .collect();
+    Ok(holder_stats(&balances, supply, &pool_authorities()))
+}
+
+/// Freeze authority of `mint`, if it has one.
+pub async fn fetch_freeze_authority(rpc: &SolanaRpc, mint: &Pubkey) -> Result<Option<Pubkey>, TokenHolderError> {
+    let mint_account = rpc.get_account(mint).await?;
+    Ok(decode_mint_freeze_authority(&mint_account.data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_accounts_per_owner_and_skips_pool_authorities() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let pool = AmmV4Pool::authority();
+        let balances = [(alice, 30), (alice, 20), (bob, 10), (pool, 900), (Pubkey::new_unique(), 0)];
+
+        let stats = holder_stats(&balances, 1_000, &pool_authorities());
+
+        assert_eq!(stats.holders, 2);
+        assert!((stats.top_holder_pct - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_supply_has_no_allocation() {
+        let stats = holder_stats(&[], 0, &[]);
+        assert_eq!(stats, HolderStats { holders: 0, top_holder_pct: 0.0 });
+    }
+}
@@ -0,0 +1,667 @@
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+use futures::future::join_all;
+use solana_transaction_status::TransactionConfirmationStatus;
+use sqlx::PgPool;
+use std::{error::Error, str::FromStr, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+use crate::core::config::SolanaSettings;
+use crate::models::base::SolanaTransactionStatus;
+use crate::utils::error::{SignerError, SolanaRpcError};
+use crate::utils::signer::{sign_solana_transaction, TransactionSigner};
+use crate::utils::solana_rpc::SolanaRpc;
+
+/// Current state of a transaction recorded in `solana_transactions`.
+#[derive(Debug, Clone)]
+pub struct TrackedTransaction {
+    pub id: i32,
+    pub signature: Signature,
+    pub status: SolanaTransactionStatus,
+    pub error: Option<String>,
+}
+
+impl TrackedTransaction {
+    pub fn is_landed(&self) -> bool {
+        self.status.is_landed()
+    }
+}
+
+/// A submission in flight: the signed transaction plus what is needed to replace it.
+struct Submission {
+    id: i32,
+    transaction: Transaction,
+    last_valid_block_height: u64,
+    attempts: i32,
+}
+
+/// Records every outgoing transaction before it is broadcast and follows it through
+/// sent / processed / confirmed / finalized, or to expired / failed. A transaction whose
+/// blockhash expired without landing is re-signed with a fresh one and resubmitted.
+pub struct TransactionManager {
+    rpc: Arc<SolanaRpc>,
+    pool: PgPool,
+    poll_interval: Duration,
+    max_resubmits: i32,
+    recover_after: Duration,
+}
+
+fn to_status(confirmation: Option<&TransactionConfirmationStatus>) -> SolanaTransactionStatus {
+    match confirmation {
+        Some(TransactionConfirmationStatus::Finalized) => SolanaTransactionStatus::Finalized,
+        Some(TransactionConfirmationStatus::Confirmed) => SolanaTransactionStatus::Confirmed,
+        _ => SolanaTransactionStatus::Processed,
+    }
+}
+
+/// Preflight rejections that no resubmission can fix.
+fn permanent_send_error(error: &SolanaRpcError) -> Option<TransactionError> {
+    match error {
+        SolanaRpcError::Client(e) => e
+            .get_transaction_error()
+            .filter(|e| !matches!(e, TransactionError::BlockhashNotFound)),
+        _ => None,
+    }
+}
+
+impl TransactionManager {
+    pub fn new_transaction_manager(rpc: Arc<SolanaRpc>, pool: PgPool) -> Self {
+        let settings = SolanaSettings::new_solana();
+        Self {
+            rpc,
+            pool,
+            poll_interval: Duration::from_millis(settings.tx_poll_interval_ms),
+            max_resubmits: settings.tx_max_resubmits,
+            recover_after: Duration::from_secs(settings.tx_recover_after_secs),
+        }
+    }
+
+    /// Signs, records and sends the instructions, then waits until the transaction is
+    /// confirmed, failed or expired for good.
+    pub async fn submit(
+        &self,
+        purpose: &str,
+        reference: Option<&str>,
+        instructions: &[Instruction],
+        signer: &dyn TransactionSigner,
+    ) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        let payer = signer.solana_pubkey().ok_or(SignerError::NoSolanaKey)?;
+        let (blockhash, last_valid_block_height) = self.rpc.get_latest_blockhash_with_height().await?;
+        let message = Message::new_with_blockhash(instructions, Some(&payer), &blockhash);
+        let transaction = sign_solana_transaction(signer, message).await?;
+
+        // Recorded before the first broadcast, so a crash can never lose track of a transaction.
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO solana_transactions (purpose, reference, signature, message, last_valid_block_height, status, attempts)
+            VALUES ($1, $2, $3, $4, $5, $6, 1)
+            RETURNING id
+            "#,
+            purpose,
+            reference,
+            transaction.signatures[0].to_string(),
+            hex::encode(transaction.message.serialize()),
+            last_valid_block_height as i64,
+            SolanaTransactionStatus::Sent.to_string(),
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .id;
+
+        let submission = Submission { id, transaction, last_valid_block_height, attempts: 1 };
+        if let Err(e) = self.rpc.send_transaction(&submission.transaction).await {
+            if let Some(tx_error) = permanent_send_error(&e) {
+                return self.finish(&submission, SolanaTransactionStatus::Failed, None, Some(tx_error.to_string())).await;
+            }
+            warn!("Broadcast of transaction {} failed, will keep tracking it: {}", id, e);
+        }
+        self.track(submission, signer).await
+    }
+
+    /// Picks up transactions left in flight by a previous run and drives them to a final status.
+    ///
+    /// A tracked transaction is touched on every poll, so only those nobody polled for
+    /// `TX_RECOVER_AFTER_SECS` are taken over; claiming them touches them too, so another
+    /// instance recovering at the same time skips them.
+    pub async fn recover_pending(&self, signer: &dyn TransactionSigner) -> Result<Vec<TrackedTransaction>, Box<dyn Error + Send + Sync>> {
+        let rows = sqlx::query!(
+            r#"
+            UPDATE solana_transactions
+            SET updated_at = NOW()
+            WHERE status IN ('sent', 'processed') AND updated_at < NOW() - make_interval(secs => $1)
+            RETURNING id, message, last_valid_block_height, attempts
+            "#,
+            self.recover_after.as_secs_f64()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut submissions = Vec::with_capacity(rows.len());
+        for row in rows {
+            let message: Message = bincode::deserialize(&hex::decode(&row.message)?)?;
+            // Ed25519 signatures are deterministic, so this reproduces the recorded signature.
+            let transaction = sign_solana_transaction(signer, message).await?;
+            info!("Recovering transaction {} ({})", row.id, transaction.signatures[0]);
+
+            submissions.push(Submission {
+                id: row.id,
+                transaction,
+                last_valid_block_height: row.last_valid_block_height as u64,
+                attempts: row.attempts,
+            });
+        }
+
+        // Tracked side by side, so none of them goes stale while waiting for the others.
+        join_all(submissions.into_iter().map(|submission| self.track(submission, signer)))
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Status as last recorded.
+    pub async fn get_transaction(&self, id: i32) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        let row = sqlx::query!("SELECT signature, status, error FROM solana_transactions WHERE id = $1", id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(TrackedTransaction {
+            id,
+            signature: Signature::from_str(&row.signature)?,
+            status: SolanaTransactionStatus::from_str(&row.status)?,
+            error: row.error,
+        })
+    }
+
+    /// Re-reads the on-chain status of a recorded transaction, e.g. to see it finalize.
+    pub async fn refresh_status(&self, id: i32) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        let tracked = self.get_transaction(id).await?;
+        if !tracked.is_landed() {
+            return Ok(tracked);
+        }
+        match self.rpc.get_signature_status(&tracked.signature).await? {
+            Some(status) => {
+                let new_status = to_status(status.confirmation_status.as_ref());
+                if new_status != tracked.status {
+                    self.update_status(id, new_status, Some(status.slot), None).await?;
+                }
+                Ok(TrackedTransaction { status: new_status, ..tracked })
+            }
+            None => Ok(tracked),
+        }
+    }
+
+    /// Reads the on-chain outcome of a transaction recorded as in flight, e.g. after the call
+    /// tracking it errored. One whose blockhash expired without it landing is marked expired,
+    /// unless someone polled it within `TX_RECOVER_AFTER_SECS` and may still resubmit it.
+    pub async fn settle_from_chain(&self, id: i32) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        let row = sqlx::query!(
+            "SELECT signature, status, error, last_valid_block_height FROM solana_transactions WHERE id = $1",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let tracked = TrackedTransaction {
+            id,
+            signature: Signature::from_str(&row.signature)?,
+            status: SolanaTransactionStatus::from_str(&row.status)?,
+            error: row.error,
+        };
+        if tracked.status.is_settled() {
+            return Ok(tracked);
+        }
+
+        let (block_height, status) = self.rpc.get_block_height_and_signature_status(&tracked.signature).await?;
+        match status {
+            Some(status) => {
+                let error = status.err.as_ref().map(|e| e.to_string());
+                let new_status = if error.is_some() { SolanaTransactionStatus::Failed } else { to_status(status.confirmation_status.as_ref()) };
+                if new_status != tracked.status {
+                    self.update_status(id, new_status, Some(status.slot), error.clone()).await?;
+                }
+                Ok(TrackedTransaction { status: new_status, error, ..tracked })
+            }
+            None if block_height > row.last_valid_block_height as u64 => {
+                if self.rpc.get_signature_status(&tracked.signature).await?.is_some() {
+                    return Ok(tracked);
+                }
+                let error = "Blockhash expired before the transaction landed".to_string();
+                let expired = sqlx::query!(
+                    r#"
+                    UPDATE solana_transactions SET status = $2, error = $3, updated_at = NOW()
+                    WHERE id = $1 AND status IN ('sent', 'processed') AND updated_at < NOW() - make_interval(secs => $4)
+                    "#,
+                    id,
+                    SolanaTransactionStatus::Expired.to_string(),
+                    error,
+                    self.recover_after.as_secs_f64()
+                )
+                .execute(&self.pool)
+                .await?;
+                if expired.rows_affected() == 0 {
+                    return Ok(tracked);
+                }
+                Ok(TrackedTransaction { status: SolanaTransactionStatus::Expired, error: Some(error), ..tracked })
+            }
+            None => Ok(tracked),
+        }
+    }
+
+    async fn track(&self, mut submission: Submission, signer: &dyn TransactionSigner) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        let mut recorded = SolanaTransactionStatus::Sent;
+        loop {
+            let signature = submission.transaction.signatures[0];
+            let (block_height, status) = self.rpc.get_block_height_and_signature_status(&signature).await?;
+            match status {
+                Some(status) if status.err.is_some() => {
+                    let error = status.err.map(|e| e.to_string());
+                    return self.finish(&submission, SolanaTransactionStatus::Failed, Some(status.slot), error).await;
+                }
+                Some(status) => {
+                    let new_status = to_status(status.confirmation_status.as_ref());
+                    if new_status.is_landed() {
+                        return self.finish(&submission, new_status, Some(status.slot), None).await;
+                    }
+                    if new_status != recorded {
+                        self.update_status(submission.id, new_status, Some(status.slot), None).await?;
+                        recorded = new_status;
+                    }
+                }
+                None if block_height > submission.last_valid_block_height => {
+                    // The blockhash is gone, so this signature can never land any more, unless it
+                    // landed on a node ahead of the one asked; asked again before replacing it.
+                    if self.rpc.get_signature_status(&signature).await?.is_some() {
+                        continue;
+                    }
+                    if submission.attempts > self.max_resubmits {
+                        let error = Some("Blockhash expired before the transaction landed".to_string());
+                        return self.finish(&submission, SolanaTransactionStatus::Expired, None, error).await;
+                    }
+                    let (blockhash, last_valid_block_height) = self.rpc.get_latest_blockhash_with_height().await?;
+                    self.resubmit(&mut submission, blockhash, last_valid_block_height, signer).await?;
+                    recorded = SolanaTransactionStatus::Sent;
+                }
+                None => {
+                    // Rebroadcast while waiting; the cluster drops unconfirmed transactions freely.
+                    if let Err(e) = self.rpc.send_transaction(&submission.transaction).await {
+                        if let Some(tx_error) = permanent_send_error(&e) {
+                            return self.finish(&submission, SolanaTransactionStatus::Failed, None, Some(tx_error.to_string())).await;
+                        }
+                    }
+                }
+            }
+            self.heartbeat(submission.id).await?;
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Marks the transaction as actively tracked, keeping `recover_pending` off it.
+    async fn heartbeat(&self, id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+        sqlx::query!("UPDATE solana_transactions SET updated_at = NOW() WHERE id = $1", id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn resubmit(
+        &self,
+        submission: &mut Submission,
+        blockhash: Hash,
+        last_valid_block_height: u64,
+        signer: &dyn TransactionSigner,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut message = submission.transaction.message.clone();
+        message.recent_blockhash = blockhash;
+        let transaction = sign_solana_transaction(signer, message).await?;
+        let previous = submission.transaction.signatures[0];
+
+        sqlx::query!(
+            r#"
+            UPDATE solana_transactions
+            SET signature = $1, message = $2, last_valid_block_height = $3, status = $4,
+                attempts = attempts + 1, updated_at = NOW()
+            WHERE id = $5
+            "#,
+            transaction.signatures[0].to_string(),
+            hex::encode(transaction.message.serialize()),
+            last_valid_block_height as i64,
+            SolanaTransactionStatus::Sent.to_string(),
+            submission.id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        warn!("Transaction {} expired as {}, resubmitting as {}", submission.id, previous, transaction.signatures[0]);
+        submission.transaction = transaction;
+        submission.last_valid_block_height = last_valid_block_height;
+        submission.attempts += 1;
+
+        if let Err(e) = self.rpc.send_transaction(&submission.transaction).await {
+            warn!("Broadcast of transaction {} failed, will keep tracking it: {}", submission.id, e);
+        }
+        Ok(())
+    }
+
+    async fn update_status(
+        &self,
+        id: i32,
+        status: SolanaTransactionStatus,
+        slot: Option<u64>,
+        error: Option<String>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        sqlx::query!(
+            r#"
+            UPDATE solana_transactions
+            SET status = $1, slot = COALESCE($2, slot), error = $3, updated_at = NOW()
+            WHERE id = $4
+            "#,
+            status.to_string(),
+            slot.map(|s| s as i64),
+            error,
+            id,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        submission: &Submission,
+        status: SolanaTransactionStatus,
+        slot: Option<u64>,
+        error: Option<String>,
+    ) -> Result<TrackedTransaction, Box<dyn Error + Send + Sync>> {
+        self.update_status(submission.id, status, slot, error.clone()).await?;
+        Ok(TrackedTransaction {
+            id: submission.id,
+            signature: submission.transaction.signatures[0],
+            status,
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use solana_client::client_error::Result as ClientResult;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_client::rpc_client::RpcClientConfig;
+    use solana_client::rpc_request::RpcRequest;
+    use solana_client::rpc_response::{Response, RpcBlockhash, RpcResponseContext};
+    use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::system_instruction;
+    use solana_transaction_status::TransactionStatus;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Mutex;
+
+    use crate::utils::signer::{LimitPolicy, LocalSigner};
+
+    /// In-memory cluster answering the calls the manager makes.
+    #[derive(Default)]
+    struct Chain {
+        block_height: u64,
+        /// Validity of the next blockhashes handed out; `block_height + 150` once empty.
+        validity: VecDeque<u64>,
+        blockhashes: Vec<Hash>,
+        /// Broadcasts built on the blockhash at this index or a later one land.
+        lands_from: usize,
+        sent: Vec<Signature>,
+        landed: HashMap<Signature, TransactionConfirmationStatus>,
+    }
+
+    impl Chain {
+        fn new(block_height: u64, validity: &[u64], lands_from: usize) -> Arc<Mutex<Self>> {
+            Arc::new(Mutex::new(Chain { block_height, validity: validity.iter().copied().collect(), lands_from, ..Chain::default() }))
+        }
+    }
+
+    struct ChainSender(Arc<Mutex<Chain>>);
+
+    fn response<T: serde::Serialize>(value: T) -> Value {
+        json!(Response { context: RpcResponseContext { slot: 1, api_version: None }, value })
+    }
+
+    #[async_trait]
+    impl RpcSender for ChainSender {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            let mut chain = self.0.lock().unwrap();
+            Ok(match request {
+                RpcRequest::GetBlockHeight => json!(chain.block_height),
+                RpcRequest::GetLatestBlockhash => {
+                    let blockhash = Hash::new_unique();
+                    let last_valid_block_height = chain.validity.pop_front().unwrap_or(chain.block_height + 150);
+                    chain.blockhashes.push(blockhash);
+                    response(RpcBlockhash { blockhash: blockhash.to_string(), last_valid_block_height })
+                }
+                RpcRequest::SendTransaction => {
+                    let transaction: Transaction = bincode::deserialize(&decode_base64(params[0].as_str().unwrap())).unwrap();
+                    let signature = transaction.signatures[0];
+                    let index = chain.blockhashes.iter().position(|hash| *hash == transaction.message.recent_blockhash);
+                    if index.is_some_and(|index| index >= chain.lands_from) {
+                        chain.landed.insert(signature, TransactionConfirmationStatus::Confirmed);
+                    }
+                    chain.sent.push(signature);
+                    json!(signature.to_string())
+                }
+                RpcRequest::GetSignatureStatuses => {
+                    let statuses: Vec<Option<TransactionStatus>> = params[0]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|signature| {
+                            let signature = Signature::from_str(signature.as_str().unwrap()).unwrap();
+                            chain.landed.get(&signature).map(|confirmation| TransactionStatus {
+                                slot: 7,
+                                confirmations: None,
+                                status: Ok(()),
+                                err: None,
+                                confirmation_status: Some(confirmation.clone()),
+                            })
+                        })
+                        .collect();
+                    response(statuses)
+                }
+                other => panic!("unexpected RPC call {}", other),
+            })
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "chain".to_string()
+        }
+    }
+
+    fn decode_base64(input: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let (mut bits, mut count, mut bytes) = (0u32, 0u32, Vec::new());
+        for byte in input.bytes().filter(|byte| *byte != b'=') {
+            bits = (bits << 6) | ALPHABET.iter().position(|c| *c == byte).unwrap() as u32;
+            count += 6;
+            if count >= 8 {
+                count -= 8;
+                bytes.push((bits >> count) as u8);
+                bits &= (1 << count) - 1;
+            }
+        }
+        bytes
+    }
+
+    async fn setup(pool: &PgPool) {
+        sqlx::raw_sql(include_str!("../../migrations/20261018010000_solana_transactions.sql"))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    fn manager(pool: PgPool, chain: &Arc<Mutex<Chain>>, max_resubmits: i32) -> TransactionManager {
+        let client = RpcClient::new_sender(ChainSender(chain.clone()), RpcClientConfig::with_commitment(CommitmentConfig::confirmed()));
+        let settings = SolanaSettings { rpc_max_retries: 0, ..SolanaSettings::new_solana() };
+        TransactionManager {
+            rpc: Arc::new(SolanaRpc::from_clients(vec![("chain".to_string(), client)], &settings)),
+            pool,
+            poll_interval: Duration::from_millis(1),
+            max_resubmits,
+            recover_after: Duration::from_secs(60),
+        }
+    }
+
+    fn signer() -> LocalSigner {
+        LocalSigner::new_local_signer(Some(Keypair::new()), None, Arc::new(LimitPolicy::default()))
+    }
+
+    fn transfer(signer: &LocalSigner) -> Vec<Instruction> {
+        vec![system_instruction::transfer(&signer.solana_pubkey().unwrap(), &Pubkey::new_unique(), 1)]
+    }
+
+    /// Records a transaction the way `submit` does, as left behind by a previous run.
+    async fn insert_in_flight(pool: &PgPool, signer: &LocalSigner, blockhash: Hash, last_valid_block_height: u64, stale: bool) -> (i32, Signature) {
+        let message = Message::new_with_blockhash(&transfer(signer), signer.solana_pubkey().as_ref(), &blockhash);
+        let transaction = sign_solana_transaction(signer, message).await.unwrap();
+        let updated_at = if stale { "NOW() - INTERVAL '10 minutes'" } else { "NOW()" };
+        let id = sqlx::query_scalar::<_, i32>(&format!(
+            "INSERT INTO solana_transactions (purpose, signature, message, last_valid_block_height, status, attempts, updated_at)
+             VALUES ('swap', $1, $2, $3, 'sent', 1, {}) RETURNING id",
+            updated_at
+        ))
+        .bind(transaction.signatures[0].to_string())
+        .bind(hex::encode(transaction.message.serialize()))
+        .bind(last_valid_block_height as i64)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        (id, transaction.signatures[0])
+    }
+
+    async fn row(pool: &PgPool, id: i32) -> (String, String, i32) {
+        sqlx::query_as::<_, (String, String, i32)>("SELECT signature, status, attempts FROM solana_transactions WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn submit_records_and_confirms_a_landed_transaction(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(100, &[], 0);
+        let signer = signer();
+
+        let tracked = manager(pool.clone(), &chain, 3).submit("payout", Some("payout:1"), &transfer(&signer), &signer).await.unwrap();
+
+        assert_eq!(tracked.status, SolanaTransactionStatus::Confirmed);
+        assert_eq!(chain.lock().unwrap().sent, vec![tracked.signature]);
+        assert_eq!(row(&pool, tracked.id).await, (tracked.signature.to_string(), "confirmed".to_string(), 1));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn resubmits_with_a_fresh_blockhash_once_the_first_expired(pool: PgPool) {
+        setup(&pool).await;
+        // The first blockhash is already past its validity, the second one lands.
+        let chain = Chain::new(200, &[150, 350], 1);
+        let signer = signer();
+
+        let tracked = manager(pool.clone(), &chain, 3).submit("swap", None, &transfer(&signer), &signer).await.unwrap();
+
+        assert_eq!(tracked.status, SolanaTransactionStatus::Confirmed);
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 2);
+        assert_ne!(sent[0], sent[1]);
+        assert_eq!(tracked.signature, sent[1]);
+        assert_eq!(row(&pool, tracked.id).await, (sent[1].to_string(), "confirmed".to_string(), 2));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn gives_up_after_the_last_resubmit_expires(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(200, &[150, 150], usize::MAX);
+        let signer = signer();
+
+        let tracked = manager(pool.clone(), &chain, 1).submit("swap", None, &transfer(&signer), &signer).await.unwrap();
+
+        assert_eq!(tracked.status, SolanaTransactionStatus::Expired);
+        assert!(tracked.error.is_some());
+        assert_eq!(row(&pool, tracked.id).await, (tracked.signature.to_string(), "expired".to_string(), 2));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn recovers_a_transaction_that_landed_while_the_process_was_down(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(100, &[], 0);
+        let signer = signer();
+        let blockhash = Hash::new_unique();
+        let (id, signature) = insert_in_flight(&pool, &signer, blockhash, 250, true).await;
+        chain.lock().unwrap().landed.insert(signature, TransactionConfirmationStatus::Finalized);
+
+        let recovered = manager(pool.clone(), &chain, 3).recover_pending(&signer).await.unwrap();
+
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].signature, signature);
+        assert_eq!(recovered[0].status, SolanaTransactionStatus::Finalized);
+        assert!(chain.lock().unwrap().sent.is_empty());
+        assert_eq!(row(&pool, id).await, (signature.to_string(), "finalized".to_string(), 1));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn recovers_an_expired_transaction_by_resubmitting_it(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(200, &[], 0);
+        let signer = signer();
+        let (id, signature) = insert_in_flight(&pool, &signer, Hash::new_unique(), 150, true).await;
+        // Tracked by a live process, so recovery leaves it alone.
+        insert_in_flight(&pool, &signer, Hash::new_unique(), 150, false).await;
+
+        let recovered = manager(pool.clone(), &chain, 3).recover_pending(&signer).await.unwrap();
+
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].id, id);
+        assert_eq!(recovered[0].status, SolanaTransactionStatus::Confirmed);
+        assert_ne!(recovered[0].signature, signature);
+        assert_eq!(row(&pool, id).await, (recovered[0].signature.to_string(), "confirmed".to_string(), 2));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn settles_a_landed_transaction_from_chain(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(100, &[], 0);
+        let signer = signer();
+        let (id, signature) = insert_in_flight(&pool, &signer, Hash::new_unique(), 250, false).await;
+        chain.lock().unwrap().landed.insert(signature, TransactionConfirmationStatus::Confirmed);
+
+        let settled = manager(pool.clone(), &chain, 3).settle_from_chain(id).await.unwrap();
+
+        assert_eq!(settled.status, SolanaTransactionStatus::Confirmed);
+        assert_eq!(row(&pool, id).await.1, "confirmed");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn settles_an_abandoned_transaction_past_its_blockhash_as_expired(pool: PgPool) {
+        setup(&pool).await;
+        let chain = Chain::new(200, &[], 0);
+        let signer = signer();
+        let (abandoned, _) = insert_in_flight(&pool, &signer, Hash::new_unique(), 150, true).await;
+        let (polled, _) = insert_in_flight(&pool, &signer, Hash::new_unique(), 150, false).await;
+        let manager = manager(pool.clone(), &chain, 3);
+
+        let settled = manager.settle_from_chain(abandoned).await.unwrap();
+        assert_eq!(settled.status, SolanaTransactionStatus::Expired);
+        assert_eq!(row(&pool, abandoned).await.1, "expired");
+
+        // Someone polled this one recently and may still resubmit it.
+        let settled = manager.settle_from_chain(polled).await.unwrap();
+        assert_eq!(settled.status, SolanaTransactionStatus::Sent);
+        assert_eq!(row(&pool, polled).await.1, "sent");
+    }
+}
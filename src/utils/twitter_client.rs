@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::core::config::TwitterSettings;
+use crate::utils::error::TwitterError;
+
+/// `max_results` accepted by the timeline endpoint.
+const MIN_TIMELINE_RESULTS: usize = 5;
+const MAX_TIMELINE_RESULTS: usize = 100;
+/// Pages of retweeters read per post; the endpoint returns at most 100 per page.
+const MAX_RETWEETER_PAGES: usize = 10;
+/// Wait after a 429 without a reset time, the length of a rate limit window.
+const DEFAULT_RATE_LIMIT_WAIT_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwitterPost {
+    pub id: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+/// The part of Twitter the retweet credit pipeline needs.
+#[async_trait]
+pub trait TwitterClient: Send + Sync {
+    /// Latest original posts of the agent account, newest first.
+    async fn recent_posts(&self, limit: usize) -> Result<Vec<TwitterPost>, TwitterError>;
+
+    /// Twitter user ids of the accounts that retweeted a post.
+    async fn retweeters(&self, post_id: &str) -> Result<Vec<String>, TwitterError>;
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    id: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ApiMeta {
+    next_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    detail: String,
+}
+
+/// Response envelope of the v2 API; `data` is left out when there is nothing to return.
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    data: Option<Vec<T>>,
+    #[serde(default)]
+    meta: ApiMeta,
+    #[serde(default)]
+    errors: Vec<ApiError>,
+}
+
+/// Twitter API v2 with app-only bearer authentication.
+pub struct TwitterApiClient {
+    client: Client,
+    base_url: String,
+    bearer_token: String,
+    user_id: String,
+}
+
+impl TwitterApiClient {
+    pub fn new_twitter_api_client(base_url: &str, bearer_token: &str, user_id: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            bearer_token: bearer_token.to_string(),
+            user_id: user_id.to_string(),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<ApiResponse<T>, TwitterError> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .bearer_auth(&self.bearer_token)
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(TwitterError::RateLimited(rate_limit_wait_secs(response.headers())));
+        }
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(TwitterError::Api(format!("{} {}: {}", status, path, body)));
+        }
+
+        let response: ApiResponse<T> = serde_json::from_str(&body)?;
+        if response.data.is_none() {
+            if let Some(error) = response.errors.first() {
+                return Err(TwitterError::Api(format!("{}: {}", error.title, error.detail)));
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Seconds until the rate limit window resets, from `x-rate-limit-reset` (epoch seconds).
+fn rate_limit_wait_secs(headers: &HeaderMap) -> u64 {
+    headers
+        .get("x-rate-limit-reset")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .map(|reset| (reset - Utc::now().timestamp()).max(1) as u64)
+        .unwrap_or(DEFAULT_RATE_LIMIT_WAIT_SECS)
+}
+
+#[async_trait]
+impl TwitterClient for TwitterApiClient {
+    async fn recent_posts(&self, limit: usize) -> Result<Vec<TwitterPost>, TwitterError> {
+        let max_results = limit.clamp(MIN_TIMELINE_RESULTS, MAX_TIMELINE_RESULTS);
+        let response: ApiResponse<TwitterPost> = self
+            .get(
+                &format!("/2/users/{}/tweets", self.user_id),
+                &[("max_results", max_results.to_string()), ("exclude", "retweets,replies".to_string())],
+            )
+            .await?;
+        let mut posts = response.data.unwrap_or_default();
+        posts.truncate(limit);
+        Ok(posts)
+    }
+
+    async fn retweeters(&self, post_id: &str) -> Result<Vec<String>, TwitterError> {
+        let mut retweeters = Vec::new();
+        let mut next_token: Option<String> = None;
+        for _ in 0..MAX_RETWEETER_PAGES {
+            let mut query = vec![("max_results", "100".to_string())];
+            if let Some(token) = next_token.take() {
+                query.push(("pagination_token", token));
+            }
+            let response: ApiResponse<ApiUser> = self.get(&format!("/2/tweets/{}/retweeted_by", post_id), &query).await?;
+            retweeters.extend(response.data.unwrap_or_default().into_iter().map(|user| user.id));
+            next_token = response.meta.next_token;
+            if next_token.is_none() {
+                break;
+            }
+        }
+        Ok(retweeters)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixturePost {
+    #[serde(flatten)]
+    pub post: TwitterPost,
+    /// Twitter user ids that retweeted the post.
+    #[serde(default)]
+    pub retweeters: Vec<String>,
+}
+
+/// Fake client serving posts and retweeters from a fixture, for tests and local runs.
+///
+/// The fixture is a JSON object `{"posts": [{"id": "1", "text": "...", "retweeters": ["42"]}]}`
+/// with the newest post first.
+pub struct FixtureTwitterClient {
+    posts: Vec<FixturePost>,
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    posts: Vec<FixturePost>,
+}
+
+impl FixtureTwitterClient {
+    pub fn new_fixture_twitter_client(posts: Vec<FixturePost>) -> Self {
+        Self { posts }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, TwitterError> {
+        let fixture: Fixture = serde_json::from_str(json)?;
+        Ok(Self::new_fixture_twitter_client(fixture.posts))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, TwitterError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[async_trait]
+impl TwitterClient for FixtureTwitterClient {
+    async fn recent_posts(&self, limit: usize) -> Result<Vec<TwitterPost>, TwitterError> {
+        Ok(self.posts.iter().take(limit).map(|fixture| fixture.post.clone()).collect())
+    }
+
+    async fn retweeters(&self, post_id: &str) -> Result<Vec<String>, TwitterError> {
+        self.posts
+            .iter()
+            .find(|fixture| fixture.post.id == post_id)
+            .map(|fixture| fixture.retweeters.clone())
+            .ok_or_else(|| TwitterError::Api(format!("Post {} not found", post_id)))
+    }
+}
+
+/// Client selected by `TWITTER_CLIENT`.
+pub fn twitter_client_from_settings() -> Result<Arc<dyn TwitterClient>, TwitterError> {
+    let settings = TwitterSettings::new_twitter();
+    match settings.client.as_str() {
+        "api" => Ok(Arc::new(TwitterApiClient::new_twitter_api_client(
+            &settings.api_url,
+            &settings.bearer_token,
+            &settings.user_id,
+        ))),
+        "fixture" => Ok(Arc::new(FixtureTwitterClient::from_file(&settings.fixture_path)?)),
+        client => Err(TwitterError::UnknownClient(client.to_string())),
+    }
+}